@@ -0,0 +1,43 @@
+//! Compares header lookups by parsed name, by `HeaderName` constant, and by
+//! `StandardHeaderName`.
+//!
+//! Run with `cargo bench --bench headers`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rustnet::headers::names::{ACCEPT, StandardHeaderName};
+use rustnet::{Body, HeaderName, Headers};
+
+const ITERATIONS: u32 = 1_000_000;
+
+fn bench<F: FnMut() -> bool>(label: &str, mut lookup: F) {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        assert!(black_box(lookup()));
+    }
+
+    let elapsed = start.elapsed();
+    let per_lookup = elapsed / ITERATIONS;
+    println!("{label:<24} {elapsed:>12?} total {per_lookup:>10?} per lookup");
+}
+
+fn main() {
+    let mut headers = Headers::new();
+    headers.default_request_headers(&Body::Empty, None);
+    headers.insert(ACCEPT, "*/*".into());
+    headers.header("X-Custom", b"custom");
+
+    println!("{ITERATIONS} lookups of \"Accept\":");
+
+    bench("parsed HeaderName", || {
+        headers.contains(&HeaderName::from(black_box("accept")))
+    });
+
+    bench("HeaderName constant", || headers.contains(black_box(&ACCEPT)));
+
+    bench("StandardHeaderName", || {
+        headers.contains_standard(black_box(StandardHeaderName::Accept))
+    });
+}
@@ -0,0 +1,68 @@
+//! Compares writing a response piece by piece, as `send_response` used to,
+//! with `send_response`, which writes the head and body together.
+//!
+//! Bodies larger than the writer's buffer take two writes to the socket when
+//! written piece by piece and one write with `send_response`.
+//!
+//! Run with `cargo bench --bench send_response`.
+
+use std::io::{self, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Instant;
+
+use rustnet::{Body, Connection, NetResult, Response};
+
+const ITERATIONS: u32 = 50_000;
+
+fn bench<F>(label: &str, conn: &mut Connection, mut send: F)
+where
+    F: FnMut(&mut Connection) -> NetResult<()>,
+{
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        send(conn).unwrap();
+    }
+
+    let elapsed = start.elapsed();
+    let per_send = elapsed / ITERATIONS;
+    println!("{label:<16} {elapsed:>12?} total {per_send:>10?} per response");
+}
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Discard everything that is written to the connection.
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = io::copy(&mut stream, &mut io::sink());
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut conn = Connection::try_from(stream).unwrap();
+
+    // Bodies that fit within and exceed the writer's buffer.
+    for body_len in [16, 8 * 1024] {
+        let mut res = Response::builder()
+            .body(Body::Bytes(vec![b'a'; body_len].into()))
+            .build()
+            .unwrap();
+        res.headers.default_response_headers(&res.body);
+
+        println!("{ITERATIONS} responses with a {body_len} byte body:");
+
+        bench("piecewise", &mut conn, |conn| {
+            res.headers.default_response_headers(&res.body);
+            conn.write_status_line(&res.version, &res.status)?;
+            conn.write_headers(&res.headers)?;
+            conn.write_body(&res.body)?;
+            conn.writer.flush()?;
+            Ok(())
+        });
+
+        bench("send_response", &mut conn, |conn| conn.send_response(&mut res));
+    }
+}
@@ -1,7 +1,33 @@
 use std::collections::VecDeque;
 use std::env;
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+use std::time::Instant;
 
-use rustnet::ClientCli;
+use rustnet::{ClientCli, Progress};
+
+/// The time at which the first body bytes were received.
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Prints the download progress and throughput on a single line to stderr.
+fn print_progress(bytes_read: u64, total: Option<u64>) {
+    let started = STARTED.get_or_init(Instant::now);
+    let secs = started.elapsed().as_secs_f64().max(0.001);
+
+    #[allow(clippy::cast_precision_loss)]
+    let kib_per_sec = bytes_read as f64 / 1024.0 / secs;
+
+    match total {
+        Some(total) if total > 0 => {
+            let percent = bytes_read.saturating_mul(100) / total;
+            eprint!(
+                "\r{percent:>3}% {bytes_read}/{total} bytes \
+                ({kib_per_sec:.1} KiB/s)"
+            );
+        },
+        _ => eprint!("\r{bytes_read} bytes ({kib_per_sec:.1} KiB/s)"),
+    }
+}
 
 fn main() {
     let args = env::args().collect::<VecDeque<String>>();
@@ -21,17 +47,45 @@ fn main() {
         return;
     }
 
+    if client.dry_run {
+        client.print_wire();
+        return;
+    }
+
+    // Show download progress when stderr is a terminal.
+    let show_progress = client.download.is_some() && io::stderr().is_terminal();
+
+    if show_progress {
+        if let Some(conn) = client.conn.as_mut() {
+            conn.options.on_progress = Some(Progress::new(print_progress));
+        }
+    }
+
     if client.do_send {
         if let Err(ref e) = client.send_request() {
             eprintln!("Error while sending the request.\n{e}");
             return;
         }
 
-        if let Err(ref e) = client.recv_response() {
+        let received = client.recv_response();
+
+        if show_progress && STARTED.get().is_some() {
+            eprintln!();
+        }
+
+        if let Err(ref e) = received {
             eprintln!("Error while receiving the response.\n{e}");
             return;
         }
     } 
 
+    if client.download.is_some() {
+        if let Err(ref e) = client.save_body() {
+            eprintln!("Error while saving the response body.\n{e}");
+        }
+
+        return;
+    }
+
     client.print();
 }
@@ -74,7 +74,6 @@ fn main() -> NetResult<()> {
         return Ok(());
     }
 
-    // Start the HTTP server and wait for it to exit.
-    server.start()?.join()?;
-    Ok(())
+    // Run the HTTP server until it is shut down or interrupted.
+    server.run()
 }
@@ -1,12 +1,199 @@
 use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::hash::{Hash, Hasher};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str;
+use std::sync::{Arc, Mutex, PoisonError};
 
-use crate::{Method, NetError, NetResult};
+use crate::{
+    EventStream, Headers, Method, NetError, NetResult, Request, Response,
+    Uri, MAX_BUFFERED_BODY,
+};
+use crate::headers::names::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE};
 use crate::utils;
 
+/// A function that writes server-sent events to an `EventStream`.
+///
+/// Handlers are compared and hashed by address so that they can be used
+/// as `Router` targets.
+#[derive(Clone, Copy)]
+pub struct EventHandler(pub fn(&mut EventStream<'_>) -> NetResult<()>);
+
+impl Debug for EventHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "EventHandler({:#x})", self.addr())
+    }
+}
+
+impl PartialEq for EventHandler {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for EventHandler {}
+
+impl PartialOrd for EventHandler {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EventHandler {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl Hash for EventHandler {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+impl EventHandler {
+    /// Runs the handler on the given `EventStream`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error returned by the handler.
+    pub fn run(self, stream: &mut EventStream<'_>) -> NetResult<()> {
+        (self.0)(stream)
+    }
+
+    /// Returns the address of the handler function.
+    fn addr(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A function that fills in the `Response` to the `Request` that matched
+/// its route.
+///
+/// The response starts with status 200, or 201 for POST requests, and an
+/// empty body. The request's body has already been read. Like an
+/// `EventHandler`, a handler is compared and hashed by address.
+#[derive(Clone, Copy)]
+pub struct Handler(pub fn(&Request, &mut Response));
+
+impl Debug for Handler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Handler({:#x})", self.addr())
+    }
+}
+
+impl PartialEq for Handler {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for Handler {}
+
+impl PartialOrd for Handler {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Handler {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl Hash for Handler {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+impl Handler {
+    /// Runs the handler on the given `Request` and `Response`.
+    pub fn run(self, req: &Request, res: &mut Response) {
+        (self.0)(req, res);
+    }
+
+    /// Returns the address of the handler function.
+    fn addr(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A reader that supplies a message body as it is written, along with the
+/// body length if it is known.
+///
+/// Clones share the same reader, which can only be read once, so a streamed
+/// `Body` can only be sent once. Readers are compared and hashed by address.
+#[derive(Clone)]
+pub struct BodyReader {
+    pub len: Option<u64>,
+    pub reader: Arc<Mutex<Option<Box<dyn Read + Send>>>>,
+}
+
+impl Debug for BodyReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "BodyReader({:#x}, len: {:?})", self.addr(), self.len)
+    }
+}
+
+impl PartialEq for BodyReader {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for BodyReader {}
+
+impl PartialOrd for BodyReader {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BodyReader {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl Hash for BodyReader {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+impl BodyReader {
+    /// Returns a new `BodyReader` from `reader` and the body length, if
+    /// known.
+    #[must_use]
+    pub fn new<R>(reader: R, len: Option<u64>) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let reader: Box<dyn Read + Send> = Box::new(reader);
+        Self { len, reader: Arc::new(Mutex::new(Some(reader))) }
+    }
+
+    /// Takes the reader, leaving `None` in its place. Returns `None` if the
+    /// reader was already taken.
+    #[must_use]
+    pub fn take(&self) -> Option<Box<dyn Read + Send>> {
+        self.reader
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Returns the address of the shared reader.
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.reader).cast::<()>() as usize
+    }
+}
+
 /// A respresentation of the message body.
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Body {
@@ -17,6 +204,8 @@ pub enum Body {
     Text(Cow<'static, str>),
     Bytes(Cow<'static, [u8]>),
     Favicon(Cow<'static, [u8]>),
+    EventStream(EventHandler),
+    Stream(BodyReader),
 }
 
 impl Default for Body {
@@ -28,7 +217,11 @@ impl Default for Body {
 impl Display for Body {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
-            Self::Empty | Self::Bytes(_) | Self::Favicon(_) => Ok(()),
+            Self::Empty
+                | Self::Bytes(_)
+                | Self::Favicon(_)
+                | Self::EventStream(_)
+                | Self::Stream(_) => Ok(()),
             Self::Xml(ref s)
                 | Self::Html(ref s)
                 | Self::Json(ref s)
@@ -43,6 +236,8 @@ impl Debug for Body {
             Self::Empty => write!(f, "Body::Empty"),
             Self::Bytes(_) => write!(f, "Body::Bytes(...)"),
             Self::Favicon(_) => write!(f, "Body::Favicon(...)"),
+            Self::EventStream(_) => write!(f, "Body::EventStream(...)"),
+            Self::Stream(_) => write!(f, "Body::Stream(...)"),
             Self::Xml(ref s) => write!(f, "Body::Xml({:?})", s.trim_end()),
             Self::Html(ref s) => write!(f, "Body::Html({:?})", s.trim_end()),
             Self::Json(ref s) => write!(f, "Body::Json({:?})", s.trim_end()),
@@ -80,7 +275,11 @@ impl TryFrom<Target> for Body {
 
     fn try_from(target: Target) -> NetResult<Self> {
         match target {
-            Target::Empty | Target::NotFound => Ok(Self::Empty),
+            Target::Empty
+                | Target::NotFound
+                | Target::Handler(_)
+                | Target::Redirect(..)
+                | Target::Proxy(_) => Ok(Self::Empty),
             Target::Shutdown => Ok("Server is shutting down.".into()),
             Target::Xml(s) => Ok(Self::Xml(s)),
             Target::Html(s) => Ok(Self::Html(s)),
@@ -89,6 +288,7 @@ impl TryFrom<Target> for Body {
             Target::Bytes(b) => Ok(Self::Bytes(b)),
             Target::File(ref path) => Ok(Self::from_filepath(path)?),
             Target::Favicon(ref path) => Ok(Self::from_filepath(path)?),
+            Target::EventStream(handler) => Ok(Self::EventStream(handler)),
         }
     }
 }
@@ -106,6 +306,18 @@ impl Body {
         matches!(self, Self::Empty)
     }
 
+    /// Returns true if the body type is `Body::EventStream`.
+    #[must_use]
+    pub const fn is_event_stream(&self) -> bool {
+        matches!(self, Self::EventStream(_))
+    }
+
+    /// Returns true if the body type is `Body::Stream`.
+    #[must_use]
+    pub const fn is_stream(&self) -> bool {
+        matches!(self, Self::Stream(_))
+    }
+
     /// Returns true if the body type is `Body::Text`.
     #[must_use]
     pub const fn is_text(&self) -> bool {
@@ -153,7 +365,7 @@ impl Body {
     #[must_use]
     pub fn get_ref(&self) -> Option<&[u8]> {
         match self {
-            Self::Empty => None,
+            Self::Empty | Self::EventStream(_) | Self::Stream(_) => None,
             Self::Xml(s) | Self::Html(s) | Self::Json(s) | Self::Text(s) => {
                 let body: &str = s.borrow();
                 Some(body.as_bytes())
@@ -186,7 +398,48 @@ impl Body {
             Self::Json(_) => Some("application/json"),
             Self::Html(_) => Some("text/html; charset=utf-8"),
             Self::Text(_) => Some("text/plain; charset=utf-8"),
-            Self::Bytes(_) => Some("application/octet-stream"),
+            Self::Bytes(_) | Self::Stream(_) => {
+                Some("application/octet-stream")
+            },
+            Self::EventStream(_) => Some("text/event-stream"),
+        }
+    }
+
+    /// Returns a `Body` that supplies the bytes read from `reader`, where
+    /// `len` is the number of bytes to send, if known.
+    ///
+    /// A body with a known length of up to `MAX_BUFFERED_BODY` bytes is read
+    /// into memory now and returned as `Body::Bytes`, which can be sent more
+    /// than once and is written together with the message head. Otherwise,
+    /// a `Body::Stream` is returned. It holds only the reader, which is read
+    /// in blocks as the message is written, so memory use stays constant
+    /// however long the body is, but the body can only be sent once. A
+    /// streamed body is sent with a Content-Length header if `len` is known
+    /// and with chunked transfer encoding if it is not.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from `reader` fails, or
+    /// `NetError::IncompleteBody` if it ends before `len` bytes are read.
+    pub fn from_reader<R>(reader: R, len: Option<u64>) -> NetResult<Self>
+    where
+        R: Read + Send + 'static,
+    {
+        let Some(len) = len.filter(|&len| len <= MAX_BUFFERED_BODY) else {
+            return Ok(Self::Stream(BodyReader::new(reader, len)));
+        };
+
+        let mut buf = Vec::new();
+        reader.take(len).read_to_end(&mut buf)?;
+
+        if (buf.len() as u64) < len {
+            return Err(NetError::IncompleteBody);
+        }
+
+        if buf.is_empty() {
+            Ok(Self::Empty)
+        } else {
+            Ok(Self::Bytes(buf.into()))
         }
     }
 
@@ -197,7 +450,7 @@ impl Body {
     /// Returns an error if reading the file at `filepath` fails.
     pub fn from_filepath(filepath: &Path) -> NetResult<Self> {
         let data = fs::read(filepath)
-            .map_err(|e| NetError::IoError(e.kind()))?;
+            .map_err(|e| NetError::IoError(e.into()))?;
 
         match utils::get_extension(filepath) {
             Some("xml") => {
@@ -223,6 +476,56 @@ impl Body {
         }
     }
 
+    /// Parses a `Body` from the raw bytes of a message body and the message
+    /// `Headers`.
+    ///
+    /// Bodies with a Content-Encoding other than "identity" are kept as raw
+    /// bytes so that they can be decoded later.
+    #[must_use]
+    pub fn from_headers(buf: &[u8], headers: &Headers) -> Self {
+        let content_type = headers
+            .get(&CONTENT_TYPE)
+            .map_or(Cow::Borrowed(""), |value| value.as_str());
+
+        let is_encoded = headers
+            .get(&CONTENT_ENCODING)
+            .is_some_and(|value| {
+                !value.as_str().trim().eq_ignore_ascii_case("identity")
+            });
+
+        if is_encoded {
+            Self::Bytes(buf.to_vec().into())
+        } else {
+            Self::from_content_type(buf, &content_type)
+        }
+    }
+
+    /// Parses a `Body` from the bytes that follow a message head.
+    ///
+    /// With a Content-Length header, only that many bytes are used and any
+    /// bytes after them are ignored. Chunked bodies and bodies without a
+    /// Content-Length use all of `buf`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadHeaderValue` if the Content-Length header is
+    /// not a valid length, or `NetError::IncompleteBody` if `buf` holds
+    /// fewer bytes than the Content-Length.
+    pub fn from_message(buf: &[u8], headers: &Headers) -> NetResult<Self> {
+        let content_len = match headers.get(&CONTENT_LENGTH) {
+            Some(len) if !utils::is_chunked(headers) => len,
+            _ => return Ok(Self::from_headers(buf, headers)),
+        };
+
+        let content_len = content_len
+            .as_u64()
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or(NetError::BadHeaderValue)?;
+
+        let body = buf.get(..content_len).ok_or(NetError::IncompleteBody)?;
+        Ok(Self::from_headers(body, headers))
+    }
+
     /// Parses a `Body` from a bytes slice and a Content-Type header value.
     #[must_use]
     pub fn from_content_type(buf: &[u8], content_type: &str) -> Self {
@@ -283,6 +586,10 @@ pub enum Target {
     Bytes(Cow<'static, [u8]>),
     File(Cow<'static, Path>),
     Favicon(Cow<'static, Path>),
+    EventStream(EventHandler),
+    Handler(Handler),
+    Redirect(u16, Cow<'static, str>),
+    Proxy(Uri),
 }
 
 impl Default for Target {
@@ -306,6 +613,12 @@ impl Display for Target {
             Self::Favicon(ref p) => {
                 write!(f, "Target::Favicon({})", p.display())
             },
+            Self::EventStream(_) => write!(f, "Target::EventStream(...)"),
+            Self::Handler(_) => write!(f, "Target::Handler(...)"),
+            Self::Redirect(code, ref location) => {
+                write!(f, "Target::Redirect({code}, {location})")
+            },
+            Self::Proxy(ref upstream) => write!(f, "Target::Proxy({upstream})"),
         }
     }
 }
@@ -325,6 +638,14 @@ impl Debug for Target {
             Self::Favicon(ref p) => {
                 write!(f, "Target::Favicon({:?})", p.display())
             },
+            Self::EventStream(_) => write!(f, "Target::EventStream(...)"),
+            Self::Handler(_) => write!(f, "Target::Handler(...)"),
+            Self::Redirect(code, ref location) => {
+                write!(f, "Target::Redirect({code}, {location:?})")
+            },
+            Self::Proxy(ref upstream) => {
+                write!(f, "Target::Proxy({upstream:?})")
+            },
         }
     }
 }
@@ -432,11 +753,61 @@ impl Target {
         matches!(self, Self::Favicon(_))
     }
 
+    /// Returns true if the target type is `Target::EventStream`.
+    #[must_use]
+    pub const fn is_event_stream(&self) -> bool {
+        matches!(self, Self::EventStream(_))
+    }
+
+    /// Returns true if the target type is `Target::Handler`.
+    #[must_use]
+    pub const fn is_handler(&self) -> bool {
+        matches!(self, Self::Handler(_))
+    }
+
+    /// Returns true if the target type is `Target::Redirect`.
+    #[must_use]
+    pub const fn is_redirect(&self) -> bool {
+        matches!(self, Self::Redirect(..))
+    }
+
+    /// Returns true if the target type is `Target::Proxy`.
+    #[must_use]
+    pub const fn is_proxy(&self) -> bool {
+        matches!(self, Self::Proxy(_))
+    }
+
+    /// Returns the name of the target type, such as "File" for
+    /// `Target::File`.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Empty => "Empty",
+            Self::Shutdown => "Shutdown",
+            Self::NotFound => "NotFound",
+            Self::Xml(_) => "Xml",
+            Self::Html(_) => "Html",
+            Self::Json(_) => "Json",
+            Self::Text(_) => "Text",
+            Self::Bytes(_) => "Bytes",
+            Self::File(_) => "File",
+            Self::Favicon(_) => "Favicon",
+            Self::EventStream(_) => "EventStream",
+            Self::Handler(_) => "Handler",
+            Self::Redirect(..) => "Redirect",
+            Self::Proxy(_) => "Proxy",
+        }
+    }
+
     /// Returns the `Target` as a Content-Type header value, if possible.
     #[must_use]
     pub fn as_content_type(&self) -> Option<&str> {
         match self {
-            Self::Empty | Self::NotFound => None,
+            Self::Empty
+                | Self::NotFound
+                | Self::Handler(_)
+                | Self::Redirect(..)
+                | Self::Proxy(_) => None,
             Self::Xml(_) => Some("application/xml"),
             Self::Html(_) => Some("text/html; charset=utf-8"),
             Self::Json(_) => Some("application/json"),
@@ -444,6 +815,7 @@ impl Target {
                 Some("text/plain; charset=utf-8")
             },
             Self::Bytes(_) => Some("application/octet-stream"),
+            Self::EventStream(_) => Some("text/event-stream"),
             Self::File(ref path) | Self::Favicon(ref path) => {
                 utils::content_type_from_ext(path)
             },
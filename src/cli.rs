@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io::{self, Read};
 use std::path::PathBuf;
 use std::process::{self, Command, Stdio};
 use std::str::FromStr;
@@ -7,24 +9,31 @@ use std::str::FromStr;
 use crate::{
     Body, Client, Headers, Method, NetError, NetResult, Request, Route,
     Router, Server, Style, Target, Tui, UriPath, Version, WriteCliError,
-    CLIENT_NAME, SERVER_NAME, TEST_SERVER_ADDR, utils,
+    CLIENT_NAME, DEFAULT_NAME, SERVER_NAME, TEST_SERVER_ADDR, utils,
 };
+use crate::headers::names::CONTENT_TYPE;
 use crate::style::colors::{GREEN, RED, RESET};
 
 /// Contains the parsed client command line arguments.
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ClientCli {
     pub do_send: bool,
     pub do_debug: bool,
+    pub dry_run: bool,
     pub no_dates: bool,
+    pub timing: bool,
+    pub compressed: bool,
     pub addr: Option<String>,
+    pub user_agent: Option<String>,
+    pub download: Option<PathBuf>,
     pub style: Style,
     pub method: Method,
     pub path: UriPath,
     pub version: Version,
     pub headers: Headers,
     pub body: Body,
+    pub body_file: Option<PathBuf>,
 }
 
 impl Default for ClientCli {
@@ -32,14 +41,20 @@ impl Default for ClientCli {
         Self {
             do_send: true,
             do_debug: false,
+            dry_run: false,
             no_dates: false,
+            timing: false,
+            compressed: false,
             addr: None,
+            user_agent: Some(DEFAULT_NAME.to_string()),
+            download: None,
             style: Style::default(),
             method: Method::default(),
             path: UriPath::default(),
             version: Version::default(),
             headers: Headers::default(),
             body: Body::default(),
+            body_file: None,
         }
     }
 }
@@ -59,22 +74,48 @@ impl TryFrom<ClientCli> for Client {
             return Err(NetError::NotConnected);
         };
 
-        let req = Request {
+        let mut req = Request {
             method: cli.method,
             path: cli.path.clone(),
             version: cli.version,
             headers: cli.headers.clone(),
-            body: cli.body.clone()
+            body: cli.body.clone(),
+            authority: None
         };
 
-        Self::builder()
+        // Infer the Content-Type of a body read from a file.
+        if let Some(path) = cli.body_file.as_ref() {
+            if !req.headers.contains(&CONTENT_TYPE) {
+                if let Some(content_type) = utils::content_type_from_ext(path) {
+                    req.headers.insert(CONTENT_TYPE, content_type.into());
+                }
+            }
+        }
+
+        let mut builder = Self::builder();
+
+        let _ = builder
             .do_send(cli.do_send)
             .do_debug(cli.do_debug)
+            .dry_run(cli.dry_run)
             .no_dates(cli.no_dates)
+            .timing(cli.timing)
+            .auto_decompress(cli.compressed)
             .style(cli.style)
+            .user_agent(cli.user_agent.as_deref())
             .req(req)
-            .addr(addr)
-            .build()
+            .host(addr);
+
+        if let Some(path) = cli.download {
+            let _ = builder.download(path);
+        }
+
+        // A dry run only prints the request so it does not connect.
+        if !cli.dry_run {
+            let _ = builder.addr(addr);
+        }
+
+        builder.build()
     }
 }
 
@@ -95,22 +136,35 @@ impl ClientCli {
 {GREEN}ARGUMENT:{RESET}
     URI     An HTTP URI (e.g. \"httpbin.org/json\").\n
 {GREEN}OPTIONS:{RESET}
-    -B, --body TEXT         Add TEXT to the request body.
+    -B, --body TEXT         Add TEXT to the request body, or read the body
+                            from stdin if TEXT is \"@-\".
+        --body-file PATH    Read the request body from the file at PATH.
     -b, --builder           Build a request and send it.
+    -c, --compressed        Request a compressed response and decode it.
     -d, --debug             Print client debug information.
+        --download [PATH]   Save the response body to PATH and only print
+                            the status and the number of bytes saved. If
+                            PATH is a directory or is omitted, the filename
+                            comes from the response or the URI path.
+        --dry-run           Print the exact request bytes without connecting.
+                            CRLFs are shown as \\r\\n unless --plain is set.
     -H, --header HEADER     Add a header with the format NAME:VALUE to the request.
     -h, --help              Display this help message.
     -M, --method METHOD     Use METHOD as the request method (default: \"GET\").
     -m, --minimal           Only print the request line and status line.
     -n, --no-dates          Remove Date headers from the output (used during testing).
+        --no-user-agent     Do not send a User-Agent header.
     -O, --output FORMAT     Set the output style to FORMAT, see below
                             (default: --output \"shb\").
     -P, --path PATH         Use PATH as the URI path (default: \"/\").
     -p, --plain             Do not colorize the output.
+        --pretty            Pretty-print JSON bodies.
     -r, --request           Print the request without sending it.
     -s, --server            Start a server listening on {TEST_SERVER_ADDR}.
     -S, --shutdown          Shut down the server running on {TEST_SERVER_ADDR}.
+    -t, --timing            Print the time taken to receive the response.
     -T, --tui               Run the client TUI.
+        --user-agent VALUE  Send VALUE as the User-Agent header.
     -v, --verbose           Print both the request and the response.
     -V, --version           Set the protocol version (default: \"HTTP/1.1\").\n
 {GREEN}FORMAT OPTIONS:{RESET}
@@ -179,14 +233,25 @@ impl ClientCli {
             "-h" | "--help" => self.print_help(),
             // Do not colorize output.
             "-p" | "--plain" => self.style.to_plain(),
+            // Pretty-print JSON bodies.
+            "--pretty" => self.style.pretty = true,
             // Enable debug printing.
             "-d" | "--debug" => self.do_debug = true,
             // Remove Date headers before printing.
             "-n" | "--no-dates" => self.no_dates = true,
+            // Print the time taken to receive the response.
+            "-t" | "--timing" => self.timing = true,
+            // Request a compressed response and decode it.
+            "-c" | "--compressed" => self.compressed = true,
             // Only print the request line and status line.
             "-m" | "--minimal" => self.style.from_format_str("Rs"),
             // Set verbose output style.
             "-v" | "--verbose" => self.style.from_format_str("*"),
+            // Print the exact request bytes and do not connect.
+            "--dry-run" => {
+                self.do_send = false;
+                self.dry_run = true;
+            },
             // Set request output style and do not send.
             "-r" | "--request" => {
                 self.do_send = false;
@@ -223,9 +288,53 @@ impl ClientCli {
             },
             // Set the request body.
             "-B" | "--body" => match args.pop_front() {
-                Some(body) => self.body = String::from(body.trim()).into(),
+                Some("@-") => {
+                    let mut body = Vec::new();
+
+                    if io::stdin().lock().read_to_end(&mut body).is_err() {
+                        self.invalid_arg(opt, "@-");
+                    }
+
+                    self.body = Body::Bytes(body.into());
+                    self.body_file = None;
+                },
+                Some(body) => {
+                    self.body = String::from(body.trim()).into();
+                    self.body_file = None;
+                },
+                None => self.missing_arg(opt),
+            },
+            // Read the request body from a file.
+            "--body-file" => match args.pop_front() {
+                Some(path) => match fs::read(path) {
+                    Ok(body) => {
+                        self.body = Body::Bytes(body.into());
+                        self.body_file = Some(PathBuf::from(path));
+                    },
+                    Err(_) => self.invalid_arg(opt, path),
+                },
                 None => self.missing_arg(opt),
             },
+            // Save the response body to a file. The path is optional, so
+            // the URI at the end of the arguments is never taken as a path.
+            "--download" => {
+                let path = match args.front() {
+                    Some(next) if !next.starts_with('-') && args.len() > 1 => {
+                        args.pop_front()
+                    },
+                    _ => None,
+                };
+
+                let path = path.unwrap_or(".");
+                self.download = Some(PathBuf::from(path));
+            },
+            // Set a custom User-Agent header.
+            "--user-agent" => match args.pop_front() {
+                Some(value) => self.user_agent = Some(value.trim().to_string()),
+                None => self.missing_arg(opt),
+            },
+            // Do not send a User-Agent header.
+            "--no-user-agent" => self.user_agent = None,
             // Set the output style based on a format string.
             "-O" | "--output" => match args.pop_front() {
                 Some(format) => self.style.from_format_str(format.trim()),
@@ -288,7 +397,7 @@ impl ClientCli {
             process::exit(1);
         }
 
-        if utils::check_server_is_live(TEST_SERVER_ADDR) {
+        if utils::check_server_is_ready(TEST_SERVER_ADDR) {
             println!(
                 "{GREEN}Server is listening on {TEST_SERVER_ADDR}.{RESET}"
             );
@@ -485,10 +594,7 @@ impl ServerCli {
                     // Print help message.
                     "-h" | "--help" => cli.print_help(),
                     // Make the server a test server.
-                    "-t" | "--test" => {
-                        cli.is_test = true;
-                        let  _ = cli.router.shutdown();
-                    },
+                    "-t" | "--test" => cli.is_test = true,
                     // Set a local log file.
                     "-f" | "--log-file" => match args.pop_front() {
                         Some(arg) => {
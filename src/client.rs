@@ -1,24 +1,230 @@
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
-use std::net::{TcpStream, ToSocketAddrs};
+use std::mem;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::{
-    Body, Connection, Headers, Method, NetError, NetResult, Request,
-    Response, Style, UriPath,
+    Body, Connection, ConnectionOptions, HeaderValue, Headers, Method,
+    NetError, NetResult, Kind, Progress, Request, Response, RetryAfter,
+    Style, Uri, UriPath, Version, DEFAULT_NAME,
 };
-use crate::headers::names::DATE;
-use crate::style::colors::{GREEN, ORANGE, RESET, BLUE};
-use crate::utils;
+use crate::headers::names::{
+    ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_ENCODING, CONTENT_LENGTH,
+    CONTENT_TYPE, DATE, HOST, USER_AGENT,
+};
+use crate::style::colors::{BLUE, CYAN, GREEN, ORANGE, RESET};
+use crate::{encoding, style, utils};
+
+/// The delay between attempts when a request is retried.
+pub const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// The longest Retry-After delay that a client waits for before re-sending
+/// a request. Longer delays are not waited for, and the response that asked
+/// for them is returned instead.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How long to wait for a "100 Continue" response before sending the
+/// request body anyway.
+pub const CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The largest numeric suffix tried when a downloaded file would replace
+/// an existing one.
+pub const MAX_DOWNLOAD_SUFFIX: u32 = 999;
+
+/// Opens a `Connection` to `addr`, giving up with `NetError::Timeout` if
+/// connecting takes longer than `timeout`.
+fn connect<A: ToSocketAddrs>(
+    addr: A,
+    timeout: Option<Duration>
+) -> NetResult<Connection> {
+    let Some(timeout) = timeout else {
+        return utils::connect(addr).and_then(Connection::try_from);
+    };
+
+    if timeout.is_zero() {
+        return Err(NetError::Timeout);
+    }
+
+    let mut last_err = NetError::BadAddress;
+
+    for addr in utils::resolve(addr)? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Connection::try_from(stream),
+            Err(e) => last_err = e.into(),
+        }
+    }
+
+    Err(last_err)
+}
+
+/// Creates a new file named `name` in `dir`, adding a numeric suffix, such
+/// as "index.html.1", if a file with that name already exists.
+fn create_new_file(dir: &Path, name: &str) -> NetResult<(PathBuf, File)> {
+    let mut path = dir.join(name);
+
+    for suffix in 1..=MAX_DOWNLOAD_SUFFIX {
+        match File::create_new(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                path = dir.join(format!("{name}.{suffix}"));
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Err(NetError::Other("Too many files with the same name.".into()))
+}
+
+/// Sets the User-Agent header to `user_agent`, or removes it if `None`,
+/// unless the `Request` already has a custom User-Agent header.
+fn set_user_agent(req: &mut Request, user_agent: Option<&HeaderValue>) {
+    if req.headers.contains(&USER_AGENT)
+        && !utils::is_default_user_agent(&req.headers)
+    {
+        return;
+    }
+
+    match user_agent {
+        Some(value) => req.headers.insert(USER_AGENT, value.clone()),
+        None => req.headers.remove(&USER_AGENT),
+    }
+}
+
+/// Inserts an Accept-Encoding header listing the content codings that can
+/// be decoded, unless the `Request` already has one.
+fn set_accept_encoding(req: &mut Request) {
+    if !req.headers.contains(&ACCEPT_ENCODING) {
+        let encodings = encoding::SUPPORTED_ENCODINGS;
+        req.headers.insert(ACCEPT_ENCODING, encodings.into());
+    }
+}
+
+/// Returns true if every content coding applied to the `Response` was
+/// listed in the Accept-Encoding header of the `Request`. A request
+/// without an Accept-Encoding header, or with a "*" item, accepts any
+/// coding.
+fn accepts_encoding(req: Option<&Request>, res: &Response) -> bool {
+    // Quality values are ignored and "x-gzip" is an alias for "gzip".
+    let coding = |item: &String| {
+        let name = item.split(';').next().unwrap_or_default().trim();
+        let name = name.to_ascii_lowercase();
+        if name == "x-gzip" { String::from("gzip") } else { name }
+    };
+
+    let accepted = req.and_then(|req| req.headers.get(&ACCEPT_ENCODING));
+    let applied = res.headers.get(&CONTENT_ENCODING);
+
+    let (Some(accepted), Some(applied)) = (accepted, applied) else {
+        return true;
+    };
+
+    let accepted = accepted.split_list().iter().map(coding).collect::<Vec<_>>();
+
+    accepted.iter().any(|item| item == "*")
+        || applied.split_list().iter().map(coding).all(|applied| {
+            applied == "identity" || accepted.contains(&applied)
+        })
+}
+
+/// Returns a new client nonce for Digest authentication.
+///
+/// The nonce only needs to be unpredictable enough that responses cannot be
+/// replayed, so the current time, process ID, and a counter are hashed.
+fn new_cnonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    let seed = format!(
+        "{nanos}:{}:{}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    encoding::hex_encode(&encoding::sha256(seed.as_bytes())[..16])
+}
+
+/// Inserts each of the `defaults` into the `Request` headers unless a header
+/// with the same name is already present.
+fn merge_default_headers(req: &mut Request, defaults: &Headers) {
+    for (name, value) in defaults.iter_ordered() {
+        if !req.headers.contains(name) {
+            req.headers.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+/// A user name and password for HTTP authentication.
+///
+/// The password is left out of the `Debug` output so that printing a
+/// `Client` or `ClientBuilder` does not reveal it.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct Credentials {
+    pub user: String,
+    pub pass: String,
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Credentials")
+            .field("user", &self.user)
+            .field("pass", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Credentials {
+    /// Returns new `Credentials` from the given user name and password.
+    #[must_use]
+    pub fn new(user: &str, pass: &str) -> Self {
+        Self { user: user.to_string(), pass: pass.to_string() }
+    }
+}
 
 /// An HTTP client builder object.
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct ClientBuilder {
     pub do_send: bool,
     pub do_debug: bool,
+    pub dry_run: bool,
     pub no_dates: bool,
+    pub timing: bool,
+    pub auto_decompress: bool,
+    pub auto_accept: bool,
+    pub auto_host: bool,
+    pub expect_continue: bool,
+    pub ordered_headers: bool,
+    pub tcp_nodelay: bool,
+    pub respect_retry_after: bool,
+    pub retry_any_method: bool,
+    pub can_reconnect: bool,
+    pub retries: u8,
+    pub version: Option<Version>,
+    pub digest_auth: Option<Credentials>,
     pub style: Style,
+    pub on_progress: Option<Progress>,
+    pub host: Option<String>,
+    pub default_accept: HeaderValue,
+    pub default_headers: Headers,
+    pub user_agent: Option<HeaderValue>,
+    pub timeout: Option<Duration>,
+    pub started: Option<Instant>,
+    pub download: Option<PathBuf>,
+    pub resolve: BTreeMap<String, SocketAddr>,
     pub req: Option<Request>,
     pub conn: Option<NetResult<Connection>>,
 }
@@ -28,8 +234,31 @@ impl Default for ClientBuilder {
         Self {
             do_send: true,
             do_debug: false,
+            dry_run: false,
             no_dates: false,
+            timing: false,
+            auto_decompress: false,
+            auto_accept: true,
+            auto_host: true,
+            expect_continue: false,
+            ordered_headers: false,
+            tcp_nodelay: false,
+            respect_retry_after: false,
+            retry_any_method: false,
+            can_reconnect: true,
+            retries: 0,
+            version: None,
+            digest_auth: None,
             style: Style::default(),
+            on_progress: None,
+            host: None,
+            default_accept: "*/*".into(),
+            default_headers: Headers::new(),
+            user_agent: Some(DEFAULT_NAME.into()),
+            timeout: None,
+            started: None,
+            download: None,
+            resolve: BTreeMap::new(),
             req: None,
             conn: None
         }
@@ -43,6 +272,27 @@ impl ClientBuilder {
         Self::default()
     }
 
+    /// Returns a new `ClientBuilder` that uses an already connected
+    /// `stream` instead of opening a connection itself.
+    ///
+    /// No URI is parsed, so the Host header falls back to the remote IP
+    /// address and port of `stream` unless `host` is set or the request
+    /// includes one.
+    ///
+    /// The client cannot open a connection of its own to replace `stream`,
+    /// so requests are never re-sent: `retries` and `respect_retry_after`
+    /// have no effect, and answering a Digest challenge fails with
+    /// `NetError::NotConnected` if the server closes the connection.
+    #[must_use]
+    pub fn from_stream(stream: TcpStream) -> Self {
+        Self {
+            started: Some(Instant::now()),
+            can_reconnect: false,
+            conn: Some(Connection::try_from(stream)),
+            ..Self::default()
+        }
+    }
+
     /// Sets whether to send the request.
     pub fn do_send(&mut self, do_send: bool) -> &mut Self {
         self.do_send = do_send;
@@ -55,31 +305,283 @@ impl ClientBuilder {
         self
     }
 
+    /// Sets whether to only print the request bytes instead of sending
+    /// them. No connection is required to build a dry-run `Client`.
+    pub const fn dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Sets whether to print Date headers.
     pub fn no_dates(&mut self, no_dates: bool) -> &mut Self {
         self.no_dates = no_dates;
         self
     }
 
+    /// Sets whether to print the time taken to receive the response.
+    pub const fn timing(&mut self, timing: bool) -> &mut Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Sets whether to decode response bodies based on the Content-Encoding
+    /// header.
+    ///
+    /// When enabled, an Accept-Encoding header listing the supported
+    /// codings is sent unless the request already has one. Bodies with a
+    /// coding that was not accepted or is not supported are left encoded.
+    pub const fn auto_decompress(
+        &mut self,
+        auto_decompress: bool
+    ) -> &mut Self {
+        self.auto_decompress = auto_decompress;
+        self
+    }
+
+    /// Sets whether to send an `Expect: 100-continue` header with requests
+    /// that have a body and wait for the server's "100 Continue" response,
+    /// up to `CONTINUE_TIMEOUT`, before sending the body.
+    pub const fn expect_continue(
+        &mut self,
+        expect_continue: bool
+    ) -> &mut Self {
+        self.expect_continue = expect_continue;
+        self
+    }
+
+    /// Sets the `TCP_NODELAY` option on the connection so that requests are
+    /// sent without waiting to coalesce small writes (default: disabled).
+    pub const fn tcp_nodelay(&mut self, tcp_nodelay: bool) -> &mut Self {
+        self.tcp_nodelay = tcp_nodelay;
+        self
+    }
+
+    /// Sets whether to write request headers in the order they were
+    /// inserted instead of sorted by name.
+    pub const fn ordered_headers(
+        &mut self,
+        ordered_headers: bool
+    ) -> &mut Self {
+        self.ordered_headers = ordered_headers;
+        self
+    }
+
+    /// Sets a callback that is invoked as response body bytes arrive with
+    /// the number of bytes read so far and the total from the
+    /// Content-Length header, if known. No callback is set by default.
+    pub fn on_progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: FnMut(u64, Option<u64>) + Send + 'static,
+    {
+        self.on_progress = Some(Progress::new(progress));
+        self
+    }
+
+    /// Sets the number of times an idempotent request is re-sent on a new
+    /// connection if the connection is reset or closed before a response
+    /// arrives (default: 0).
+    pub const fn retries(&mut self, retries: u8) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets whether to wait for the time given by a Retry-After header and
+    /// re-send the request when the response is "429 Too Many Requests" or
+    /// "503 Service Unavailable" (default: disabled).
+    ///
+    /// These retries count toward `retries` and are only made for
+    /// idempotent requests unless `retry_any_method` is enabled. A request
+    /// is not retried if the wait would pass the `timeout` deadline or be
+    /// longer than `MAX_RETRY_AFTER`.
+    pub const fn respect_retry_after(
+        &mut self,
+        respect_retry_after: bool
+    ) -> &mut Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+
+    /// Sets whether requests with non-idempotent methods, such as POST, are
+    /// also re-sent after a Retry-After response (default: disabled).
+    pub const fn retry_any_method(
+        &mut self,
+        retry_any_method: bool
+    ) -> &mut Self {
+        self.retry_any_method = retry_any_method;
+        self
+    }
+
+    /// Sets the HTTP version of the request, overriding the version set on
+    /// the `Request` itself.
+    ///
+    /// With the `http2` feature enabled, `Version::TwoDotZero` sends the
+    /// request over cleartext HTTP/2 with prior knowledge (h2c), which
+    /// requires a server that accepts HTTP/2 without an upgrade. Only a
+    /// single request is sent on each HTTP/2 connection.
+    pub const fn version(&mut self, version: Version) -> &mut Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the credentials used to answer a Digest authentication
+    /// challenge.
+    ///
+    /// When a response is "401 Unauthorized" with a Digest challenge in its
+    /// WWW-Authenticate header, the request is re-sent once with an
+    /// Authorization header computed from the challenge. MD5 and SHA-256,
+    /// including their "-sess" variants, are supported.
+    pub fn digest_auth(&mut self, user: &str, pass: &str) -> &mut Self {
+        self.digest_auth = Some(Credentials::new(user, pass));
+        self
+    }
+
     /// Sets the output style.
     pub fn style(&mut self, style: Style) -> &mut Self {
         self.style = style;
         self
     }
 
+    /// Sets a file path that the response body is saved to instead of
+    /// being printed.
+    pub fn download<P: Into<PathBuf>>(&mut self, path: P) -> &mut Self {
+        self.download = Some(path.into());
+        self
+    }
+
+    /// Sets the Host header value from a "host:port" address. The port is
+    /// omitted if it is the default HTTP port.
+    ///
+    /// If unset, the Host header falls back to the remote IP address and
+    /// port of the connection.
+    pub fn host(&mut self, addr: &str) -> &mut Self {
+        self.host = Some(utils::host_from_addr(addr));
+        self
+    }
+
+    /// Sets whether to insert an Accept header into requests that do not
+    /// set one (default: enabled).
+    pub const fn auto_accept(&mut self, auto_accept: bool) -> &mut Self {
+        self.auto_accept = auto_accept;
+        self
+    }
+
+    /// Sets whether to insert a Host header into requests that do not set
+    /// one (default: enabled). Disabling this produces requests that are
+    /// invalid for HTTP/1.1, which is only useful for testing servers.
+    pub const fn auto_host(&mut self, auto_host: bool) -> &mut Self {
+        self.auto_host = auto_host;
+        self
+    }
+
+    /// Sets the Accept header value used for requests that do not set one
+    /// (default: "*/*").
+    pub fn default_accept(&mut self, accept: HeaderValue) -> &mut Self {
+        self.default_accept = accept;
+        self
+    }
+
+    /// Sets headers that are added to every request sent by the `Client`.
+    ///
+    /// A header that is set on the `Request` itself takes precedence over a
+    /// default header with the same name.
+    pub fn default_headers(&mut self, headers: Headers) -> &mut Self {
+        self.default_headers = headers;
+        self
+    }
+
+    /// Sets the User-Agent header value used for requests that do not set
+    /// one (default: `DEFAULT_NAME`). The header is left out entirely if
+    /// `user_agent` is `None`.
+    pub fn user_agent(&mut self, user_agent: Option<&str>) -> &mut Self {
+        self.user_agent = user_agent.map(HeaderValue::from);
+        self
+    }
+
+    /// Sets an overall deadline for the request, measured from when `addr`
+    /// is called, that bounds connecting, sending the request, and receiving
+    /// the whole response. Once the deadline passes, the request is aborted
+    /// with `NetError::Timeout`.
+    ///
+    /// Set this before calling `addr` so that it also limits how long
+    /// connecting may take. The deadline is enforced with the read and
+    /// write timeouts of the `Connection`, which are set to the time left
+    /// before each line and each body read, replacing any set there
+    /// before. `CONTINUE_TIMEOUT` still applies within the deadline. Any
+    /// error that occurs after the deadline has passed is reported as
+    /// `NetError::Timeout`.
+    pub const fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// Sets the HTTP `Request`.
     pub fn req(&mut self, req: Request) -> &mut Self {
         self.req = Some(req);
         self
     }
 
+    /// Connects to `addr` instead of resolving `host` when a URI with that
+    /// host is given to `uri`, similar to curl's `--resolve` option. The
+    /// host name is matched case-insensitively and regardless of port, and
+    /// an IPv6 host is given without brackets.
+    ///
+    /// The Host header still uses the URI's authority. This must be called
+    /// before `uri`.
+    pub fn resolve(&mut self, host: &str, addr: SocketAddr) -> &mut Self {
+        self.resolve.insert(host.to_ascii_lowercase(), addr);
+        self
+    }
+
     /// Opens a TCP connection to the provided address.
     pub fn addr<A: ToSocketAddrs>(&mut self, addr: A) -> &mut Self {
-        let conn_result = TcpStream::connect(addr)
-            .map_err(|e| NetError::IoError(e.kind()))
-            .and_then(Connection::try_from);
+        self.started = Some(Instant::now());
+        self.conn = Some(connect(addr, self.timeout));
+        self
+    }
+
+    /// Opens a TCP connection to the host and port of `uri`, or to the
+    /// address given to `resolve` for its host, uses its authority for the
+    /// Host header, and requests its path and query.
+    ///
+    /// The path replaces that of a request given to `req`, so this must be
+    /// called after `req`. Without a request, a GET request is created.
+    /// Building the client fails if the URI's scheme is "https".
+    pub fn uri(&mut self, uri: &Uri) -> &mut Self {
+        let path = UriPath::from(uri.path_and_query());
+
+        match self.req.as_mut() {
+            Some(req) => req.path = path,
+            None => self.req = Some(Request::builder().path(path).build()),
+        }
+
+        if uri.is_https() {
+            self.conn = Some(Err(NetError::HttpsNotImplemented));
+            return self;
+        }
+
+        self.host = Some(uri.authority());
+
+        match self.resolve.get(&uri.host) {
+            Some(&resolved) => self.addr(resolved),
+            None => self.addr(uri.addr()),
+        }
+    }
+
+    /// Opens a connection to the Unix domain socket at `path`.
+    ///
+    /// The Host header defaults to "localhost" unless `host` is also set.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.started = Some(Instant::now());
+
+        self.conn = Some(UnixStream::connect(path)
+            .map_err(NetError::from)
+            .and_then(Connection::try_from));
+
+        if self.host.is_none() {
+            self.host = Some(String::from("localhost"));
+        }
 
-        self.conn = Some(conn_result);
         self
     }
 
@@ -90,19 +592,80 @@ impl ClientBuilder {
     /// Returns an error if a TCP connection could not be established.
     pub fn build(&mut self) -> NetResult<Client> {
         let conn = match self.conn.take() {
-            Some(Ok(conn)) => conn,
+            Some(Ok(mut conn)) => {
+                if self.tcp_nodelay {
+                    conn.set_nodelay(true)?;
+                }
+
+                conn.ordered_headers = self.ordered_headers;
+                conn.options = ConnectionOptions {
+                    send_user_agent: self.user_agent.is_some(),
+                    send_accept: self.auto_accept,
+                    send_host: self.auto_host,
+                    on_progress: self.on_progress.take(),
+                    ..conn.options
+                };
+                Some(conn)
+            },
             Some(Err(e)) => Err(e)?,
+            None if self.dry_run => None,
             None => Err(NetError::NotConnected)?,
         };
 
+        let mut req = self.req.take();
+
+        if let Some(req) = req.as_mut() {
+            merge_default_headers(req, &self.default_headers);
+            set_user_agent(req, self.user_agent.as_ref());
+
+            if let Some(version) = self.version {
+                req.version = version;
+            }
+
+            if self.auto_decompress {
+                set_accept_encoding(req);
+            }
+        }
+
+        // Prefer the hostname over the remote IP address for the Host header.
+        if let (Some(req), Some(host)) = (req.as_mut(), self.host.as_ref()) {
+            if self.auto_host && !req.headers.contains(&HOST) {
+                req.headers.insert(HOST, host.as_str().into());
+            }
+        }
+
+        if let Some(req) = req.as_mut() {
+            if self.auto_accept && !req.headers.contains(&ACCEPT) {
+                req.headers.insert(ACCEPT, self.default_accept.clone());
+            }
+        }
+
         Ok(Client {
             do_send: self.do_send,
             do_debug: self.do_debug,
+            dry_run: self.dry_run,
             no_dates: self.no_dates,
+            timing: self.timing,
+            auto_decompress: self.auto_decompress,
+            expect_continue: self.expect_continue,
+            respect_retry_after: self.respect_retry_after,
+            retry_any_method: self.retry_any_method,
+            tcp_nodelay: self.tcp_nodelay,
+            can_reconnect: self.can_reconnect,
+            retries: self.retries,
+            digest_auth: self.digest_auth.take(),
             style: self.style,
-            req: self.req.take(),
+            default_headers: mem::take(&mut self.default_headers),
+            user_agent: self.user_agent.clone(),
+            deadline: self.timeout.map(|timeout| {
+                self.started.unwrap_or_else(Instant::now) + timeout
+            }),
+            download: self.download.take(),
+            sent_at: None,
+            elapsed: None,
+            req,
             res: None,
-            conn: Some(conn)
+            conn
         })
     }
 
@@ -117,15 +680,93 @@ impl ClientBuilder {
         client.send_request()?;
         Ok(client)
     }
+
+    /// Builds the `Client` and sends its `Request` with the file at
+    /// `file_path` streamed as the body, returning the `Client`.
+    ///
+    /// The request given to `req`, which must come before `uri`, is sent
+    /// with its own method, such as POST or PUT. If no request is given at
+    /// all, a POST request is sent. The Content-Length header
+    /// is set to the file size, and the Content-Type header is set from the
+    /// file extension unless the request already has one. The `progress`
+    /// callback is invoked as the file is sent with the number of bytes
+    /// sent so far and the total file size. The `timeout`, if set, limits
+    /// the whole upload. The `Request` stored in the returned `Client` does
+    /// not contain the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, if building the
+    /// `Client` fails, or if sending the request fails.
+    pub fn upload_file<F>(
+        &mut self,
+        file_path: &Path,
+        progress: F
+    ) -> NetResult<Client>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let mut file = File::open(file_path)?;
+        let total = file.metadata()?.len();
+
+        let mut req = self
+            .req
+            .take()
+            .unwrap_or_else(|| Request::builder().method(Method::Post).build());
+
+        let content_len = total.to_string();
+        req.headers.insert(CONTENT_LENGTH, content_len.as_str().into());
+
+        if !req.headers.contains(&CONTENT_TYPE) {
+            let content_type = utils::content_type_from_ext(file_path)
+                .unwrap_or("application/octet-stream");
+            req.headers.insert(CONTENT_TYPE, content_type.into());
+        }
+
+        self.req = Some(req);
+        let mut client = self.build()?;
+
+        client.with_deadline(|client| {
+            let req = client.req.as_mut().ok_or(NetError::NoRequest)?;
+            let conn = client.conn.as_mut().ok_or(NetError::NotConnected)?;
+            conn.stream_request(req, &mut file, Some(total), progress)
+        })?;
+
+        client.sent_at = Some(Instant::now());
+        Ok(client)
+    }
 }
 
 /// An HTTP client.
+///
+/// `elapsed` holds the time between writing the last `Request` byte, at
+/// `sent_at`, and receiving the full `Response`. If `tcp_nodelay` is set, it
+/// is applied again to each new connection that a retry opens. Unless
+/// `can_reconnect` is set, no new connection is opened and requests are
+/// not retried.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct Client {
     pub do_send: bool,
     pub do_debug: bool,
+    pub dry_run: bool,
     pub no_dates: bool,
+    pub timing: bool,
+    pub auto_decompress: bool,
+    pub expect_continue: bool,
+    pub respect_retry_after: bool,
+    pub retry_any_method: bool,
+    pub tcp_nodelay: bool,
+    pub can_reconnect: bool,
+    pub retries: u8,
+    pub digest_auth: Option<Credentials>,
     pub style: Style,
+    pub default_headers: Headers,
+    pub user_agent: Option<HeaderValue>,
+    pub deadline: Option<Instant>,
+    pub download: Option<PathBuf>,
+    pub sent_at: Option<Instant>,
+    pub elapsed: Option<Duration>,
     pub req: Option<Request>,
     pub res: Option<Response>,
     pub conn: Option<Connection>,
@@ -136,8 +777,24 @@ impl Default for Client {
         Self {
             do_send: true,
             do_debug: false,
+            dry_run: false,
             no_dates: false,
+            timing: false,
+            auto_decompress: false,
+            expect_continue: false,
+            respect_retry_after: false,
+            retry_any_method: false,
+            tcp_nodelay: false,
+            can_reconnect: true,
+            retries: 0,
+            digest_auth: None,
             style: Style::default(),
+            default_headers: Headers::new(),
+            user_agent: Some(DEFAULT_NAME.into()),
+            deadline: None,
+            download: None,
+            sent_at: None,
+            elapsed: None,
             req: None,
             res: None,
             conn: None
@@ -149,8 +806,24 @@ impl PartialEq for Client {
     fn eq(&self, other: &Self) -> bool {
         self.do_send == other.do_send
             && self.do_debug == other.do_debug
+            && self.dry_run == other.dry_run
             && self.no_dates == other.no_dates
+            && self.timing == other.timing
+            && self.auto_decompress == other.auto_decompress
+            && self.expect_continue == other.expect_continue
+            && self.respect_retry_after == other.respect_retry_after
+            && self.retry_any_method == other.retry_any_method
+            && self.tcp_nodelay == other.tcp_nodelay
+            && self.can_reconnect == other.can_reconnect
+            && self.retries == other.retries
+            && self.digest_auth == other.digest_auth
             && self.style == other.style
+            && self.default_headers == other.default_headers
+            && self.user_agent == other.user_agent
+            && self.deadline == other.deadline
+            && self.download == other.download
+            && self.sent_at == other.sent_at
+            && self.elapsed == other.elapsed
             && self.req == other.req
             && self.res == other.res
             && self.conn.is_some() == other.conn.is_some()
@@ -191,11 +864,10 @@ impl Client {
     /// Returns an error `TcpStream::connect` is unable to connect to the the
     /// given URI.
     pub fn new(method: Method, uri: &str) -> NetResult<Self> {
-        let (addr, path) = utils::parse_uri(uri)?;
+        let uri = uri.parse::<Uri>()?;
+        let req = Request::builder().method(method).build();
 
-        let req = Request::builder().method(method).path(path.into()).build();
-
-        Self::builder().addr(&addr).req(req).build()
+        Self::builder().req(req).uri(&uri).build()
     }
 
     /// Sends an HTTP request to the given URI using the provided HTTP method,
@@ -206,25 +878,273 @@ impl Client {
     /// Returns an error `TcpStream::connect` is unable to connect to the the
     /// given URI or if sending the request fails.
     pub fn send(method: Method, uri: &str) -> NetResult<Self> {
-        let (addr, path) = utils::parse_uri(uri)?;
+        let uri = uri.parse::<Uri>()?;
+        let req = Request::builder().method(method).build();
 
-        let req = Request::builder().method(method).path(path.into()).build();
+        Self::builder().req(req).uri(&uri).send()
+    }
 
-        Self::builder().addr(&addr).req(req).send()
+    /// Uploads the file at `file_path` to the given URI in a POST request,
+    /// streaming the file as the request body and returning the `Client`.
+    ///
+    /// The `progress` callback is invoked as the file is sent with the
+    /// number of bytes sent so far and the total file size. The
+    /// `Request` stored in the returned `Client` does not contain the file.
+    /// Use `ClientBuilder::upload_file` to set a timeout or other options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI is invalid, if the file cannot be opened,
+    /// if `TcpStream::connect` is unable to connect to the given URI, or if
+    /// sending the request fails.
+    pub fn upload_file<F>(
+        uri: &str,
+        file_path: &Path,
+        progress: F
+    ) -> NetResult<Self>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let uri = uri.parse::<Uri>()?;
+        let req = Request::builder().method(Method::Post).build();
+
+        Self::builder()
+            .req(req)
+            .uri(&uri)
+            .upload_file(file_path, progress)
     }
 
     /// Writes an HTTP `Request` to a `Connection`.
     ///
+    /// The `Client`'s default headers and User-Agent are added to the
+    /// `Request` first, except for any it already sets. Idempotent requests
+    /// are re-sent on a new connection, up to the configured number of
+    /// retries, if the connection is reset.
+    ///
     /// # Errors
     ///
-    /// An error is returned if `Connection::send_request` fails.
+    /// An error is returned if `Connection::send_request` fails on the
+    /// last attempt.
     pub fn send_request(&mut self) -> NetResult<()> {
+        if let Some(req) = self.req.as_mut() {
+            merge_default_headers(req, &self.default_headers);
+            set_user_agent(req, self.user_agent.as_ref());
+
+            if self.auto_decompress {
+                set_accept_encoding(req);
+            }
+        }
+
+        let mut attempts = 0;
+
+        loop {
+            match self.with_deadline(Self::write_request) {
+                Err(ref e) if self.should_retry(e, attempts) => {
+                    attempts += 1;
+                    self.reconnect()?;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// Writes the `Request` to the `Connection` once.
+    fn write_request(&mut self) -> NetResult<()> {
+        let continue_timeout = self
+            .remaining()?
+            .map_or(CONTINUE_TIMEOUT, |left| left.min(CONTINUE_TIMEOUT));
+
         let req = self.req.as_mut().ok_or(NetError::NoRequest)?;
+        let conn = self.conn.as_mut().ok_or(NetError::NotConnected)?;
 
-        self.conn
-            .as_mut()
-            .ok_or(NetError::NotConnected)
-            .and_then(|conn| conn.send_request(req))
+        #[cfg(feature = "http2")]
+        if req.version == Version::TwoDotZero {
+            conn.send_request_h2(req)?;
+            self.sent_at = Some(Instant::now());
+            return Ok(());
+        }
+
+        if self.expect_continue && !req.body.is_empty() {
+            conn.send_request_expect_continue(req, continue_timeout)?;
+        } else {
+            conn.send_request(req)?;
+        }
+
+        self.sent_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Waits until response bytes are available on the `Connection`.
+    fn wait_for_response(&mut self) -> NetResult<()> {
+        let conn = self.conn.as_mut().ok_or(NetError::NotConnected)?;
+
+        // A final response may have arrived while waiting to send the body.
+        if conn.pending_head.is_some() {
+            return Ok(());
+        }
+
+        match conn.fill_buf() {
+            Ok([]) => Err(NetError::UnexpectedEof),
+            Ok(_) => Ok(()),
+            Err(e) => Err(NetError::Read(e.into())),
+        }
+    }
+
+    /// Returns true if the request should be re-sent after the given error.
+    fn should_retry(&self, err: &NetError, attempts: u8) -> bool {
+        let is_idempotent = self
+            .req
+            .as_ref()
+            .is_some_and(|req| req.method.is_idempotent());
+
+        self.can_reconnect
+            && is_idempotent
+            && err.is_disconnect()
+            && attempts < self.retries
+    }
+
+    /// Replaces the `Connection` with a new one to the same remote address
+    /// after waiting for `RETRY_DELAY`.
+    fn reconnect(&mut self) -> NetResult<()> {
+        self.reconnect_after(RETRY_DELAY)
+    }
+
+    /// Replaces the `Connection` with a new one to the same remote address
+    /// after waiting for `delay`, unless `can_reconnect` is unset.
+    fn reconnect_after(&mut self, delay: Duration) -> NetResult<()> {
+        if !self.can_reconnect {
+            return Err(NetError::NotConnected);
+        }
+
+        let conn = self.conn.as_ref().ok_or(NetError::NotConnected)?;
+        let addr = conn.remote_addr;
+        let ordered_headers = conn.ordered_headers;
+        let unix_path = conn.writer.get_ref().unix_peer_path();
+
+        thread::sleep(delay);
+
+        let mut conn = match unix_path {
+            #[cfg(unix)]
+            Some(path) => Connection::try_from(UnixStream::connect(path)?)?,
+            _ => connect(addr, self.remaining()?)?,
+        };
+
+        conn.ordered_headers = ordered_headers;
+
+        // The old connection is replaced, so its options are moved over.
+        if let Some(old) = self.conn.as_mut() {
+            conn.options = mem::take(&mut old.options);
+        }
+
+        if self.tcp_nodelay {
+            conn.set_nodelay(true)?;
+        }
+
+        self.conn = Some(conn);
+        Ok(())
+    }
+
+    /// Returns how long to wait before re-sending the request if `res` asks
+    /// for a retry with a Retry-After header that should be honored.
+    fn retry_after_delay(
+        &self,
+        res: &Response,
+        attempts: u8
+    ) -> Option<Duration> {
+        if !self.can_reconnect
+            || !self.respect_retry_after
+            || attempts >= self.retries
+            || !matches!(res.status.code(), 429 | 503)
+        {
+            return None;
+        }
+
+        let method_allowed = self.req.as_ref().is_some_and(|req| {
+            self.retry_any_method || req.method.is_idempotent()
+        });
+
+        if !method_allowed {
+            return None;
+        }
+
+        let delay = match res.headers.retry_after()? {
+            RetryAfter::Seconds(secs) => Duration::from_secs(secs),
+            RetryAfter::Date(date) => date
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        };
+
+        if delay > MAX_RETRY_AFTER {
+            return None;
+        }
+
+        match self.remaining() {
+            Ok(Some(remaining)) if delay >= remaining => None,
+            Ok(_) => Some(delay),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the Authorization header value answering the Digest challenge
+    /// in `res`, if it is a 401 response and credentials are set.
+    fn digest_authorization(&self, res: &Response) -> Option<String> {
+        let creds = self.digest_auth.as_ref()?;
+        let req = self.req.as_ref()?;
+
+        if res.status.code() != 401 {
+            return None;
+        }
+
+        let challenge = res.headers.digest_challenge()?;
+        let uri = req.path.to_string();
+
+        Some(challenge.authorization(
+            &creds.user,
+            &creds.pass,
+            req.method,
+            &uri,
+            &new_cnonce(),
+            1
+        ))
+    }
+
+    /// Returns the time left until the deadline, if one is set.
+    fn remaining(&self) -> NetResult<Option<Duration>> {
+        let Some(deadline) = self.deadline else {
+            return Ok(None);
+        };
+
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .map(Some)
+            .ok_or(NetError::Timeout)
+    }
+
+    /// Runs `op` with the deadline set on the `Connection`, whose socket
+    /// timeouts are then armed before each read so that a blocked read or
+    /// write returns once the deadline passes.
+    ///
+    /// The result is replaced by `NetError::Timeout` if `op` fails with a
+    /// timed out read or write or fails after the deadline.
+    fn with_deadline<T, F>(&mut self, op: F) -> NetResult<T>
+    where
+        F: FnOnce(&mut Self) -> NetResult<T>,
+    {
+        if self.remaining()?.is_none() {
+            return op(self);
+        }
+
+        let conn = self.conn.as_mut().ok_or(NetError::NotConnected)?;
+        conn.options.deadline = self.deadline;
+        conn.arm_deadline()?;
+
+        match op(self) {
+            Err(e) if e.is_timeout() || self.remaining().is_err() => {
+                Err(NetError::Timeout)
+            },
+            result => result,
+        }
     }
 
     /// Writes an HTTP `Response` to a `Connection`.
@@ -258,15 +1178,89 @@ impl Client {
 
     /// Reads and parses an HTTP `Response` from the contained `Connection`.
     ///
+    /// If the connection is reset or closed before any response bytes
+    /// arrive, idempotent requests are re-sent on a new connection up to the
+    /// configured number of retries. With `respect_retry_after` enabled,
+    /// requests are also re-sent after the delay given by a Retry-After
+    /// header in a 429 or 503 response. With `digest_auth` credentials set,
+    /// a 401 response with a Digest challenge is answered once.
+    ///
     /// # Errors
     ///
-    /// An error is returned if `Connection::recv_response` fails.
+    /// An error is returned if `Connection::recv_response_to` fails or if
+    /// decoding the response body fails.
     pub fn recv_response(&mut self) -> NetResult<()> {
-        let res = self
-            .conn
-            .as_mut()
-            .ok_or(NetError::NotConnected)
-            .and_then(Connection::recv_response)?;
+        let mut attempts = 0;
+        let mut authorized = false;
+
+        let mut res = loop {
+            match self.with_deadline(Self::wait_for_response) {
+                Err(ref e) if self.should_retry(e, attempts) => {
+                    attempts += 1;
+                    self.reconnect()?;
+
+                    // A failed write is detected by the next wait.
+                    let _ = self.with_deadline(Self::write_request);
+                    continue;
+                },
+                Err(e) => return Err(e),
+                Ok(()) => {},
+            }
+
+            let method =
+                self.req.as_ref().map_or(Method::Get, |req| req.method);
+
+            let res = self.with_deadline(|client| {
+                let conn =
+                    client.conn.as_mut().ok_or(NetError::NotConnected)?;
+
+                #[cfg(feature = "http2")]
+                if client.req.as_ref().is_some_and(|req| {
+                    req.version == Version::TwoDotZero
+                }) {
+                    return conn.recv_response_h2(&method);
+                }
+
+                conn.recv_response_to(&method)
+            })?;
+
+            if let Some(delay) = self.retry_after_delay(&res, attempts) {
+                attempts += 1;
+                self.reconnect_after(delay)?;
+                self.with_deadline(Self::write_request)?;
+                continue;
+            }
+
+            let auth = if authorized {
+                None
+            } else {
+                self.digest_authorization(&res)
+            };
+
+            let (Some(auth), Some(req)) = (auth, self.req.as_mut()) else {
+                break res;
+            };
+
+            authorized = true;
+            req.headers.insert(AUTHORIZATION, auth.as_str().into());
+
+            // HTTP/2 connections only carry a single request.
+            if req.version == Version::TwoDotZero
+                || Connection::closes_after(res.version, &res.headers)
+            {
+                self.reconnect_after(Duration::ZERO)?;
+            }
+
+            self.with_deadline(Self::write_request)?;
+        };
+
+        self.elapsed = self.sent_at.map(|sent_at| sent_at.elapsed());
+
+        // Codings that were not asked for are left for the caller, along
+        // with the Content-Encoding header.
+        if self.auto_decompress && accepts_encoding(self.req.as_ref(), &res) {
+            res.decompress()?;
+        }
 
         self.res = Some(res);
         Ok(())
@@ -332,18 +1326,174 @@ impl Client {
 
     /// Prints the request `Body` if appropriate for the `Style`.
     pub fn print_req_body(&self, req: &Request) {
-        if self.style.req.is_body() && req.body.is_printable() {
-            println!("{}", req.body.to_string().trim_end());
-        }
+        self.print_body(&req.body, self.style.req);
     }
 
     /// Prints the response `Body` if appropriate for the `Style`.
     pub fn print_res_body(&self, res: &Response) {
-        if self.style.res.is_body() && res.body.is_printable() {
-            println!("{}", res.body.to_string().trim_end());
+        self.print_body(&res.body, self.style.res);
+    }
+
+    /// Prints a `Body` if appropriate for the `Kind`, highlighting JSON
+    /// bodies if the `Kind` is a `Color` variant.
+    pub fn print_body(&self, body: &Body, kind: Kind) {
+        if !kind.is_body() || !body.is_printable() {
+            return;
+        }
+
+        let text = self.body_to_string(body);
+
+        if kind.is_color() && body.is_json() {
+            println!("{}", style::highlight_json(text.trim_end()));
+        } else {
+            println!("{}", text.trim_end());
+        }
+    }
+
+    /// Returns the `Body` as a `String`, pretty-printing valid JSON if the
+    /// `Style` asks for it.
+    #[must_use]
+    pub fn body_to_string(&self, body: &Body) -> String {
+        let text = body.to_string();
+
+        if self.style.pretty && body.is_json() {
+            utils::pretty_json(&text).unwrap_or(text)
+        } else {
+            text
         }
     }
 
+    /// Returns the exact bytes that sending the `Request` would write,
+    /// including the default request headers.
+    ///
+    /// Date headers are removed if `no_dates` is set. Headers are in
+    /// insertion order only if the `Connection` has `ordered_headers` set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `Client` has no `Request`.
+    pub fn wire_bytes(&mut self) -> NetResult<Vec<u8>> {
+        let req = self.req.as_mut().ok_or(NetError::NoRequest)?;
+        let remote_addr = self.conn
+            .as_ref()
+            .filter(|conn| conn.options.send_host)
+            .map(|conn| conn.remote_addr);
+        req.headers.default_request_headers(&req.body, remote_addr);
+        set_user_agent(req, self.user_agent.as_ref());
+        req.sync_content_length();
+
+        if self.no_dates {
+            req.headers.remove(&DATE);
+        }
+
+        if self.conn.as_ref().is_some_and(|conn| conn.ordered_headers) {
+            Ok(req.to_ordered_wire_bytes())
+        } else {
+            Ok(req.to_wire_bytes())
+        }
+    }
+
+    /// Prints the exact request bytes to stdout.
+    ///
+    /// The bytes are written unchanged with a plain `Style`. Otherwise, each
+    /// CRLF is shown as a highlighted "\\r\\n" before the line break.
+    pub fn print_wire(&mut self) {
+        let bytes = match self.wire_bytes() {
+            Ok(bytes) => bytes,
+            Err(ref e) => return eprintln!("{e}"),
+        };
+
+        if self.style.req.is_plain() {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(&bytes).and_then(|()| stdout.flush());
+        } else {
+            let crlf = format!("{BLUE}\\r\\n{RESET}\n");
+            let wire = String::from_utf8_lossy(&bytes).replace("\r\n", &crlf);
+
+            // Keep the shell prompt off of the last line of the body.
+            if wire.ends_with('\n') {
+                print!("{wire}");
+            } else {
+                println!("{wire}");
+            }
+        }
+    }
+
+    /// Returns the filename to save the response body under when the
+    /// `download` path is a directory.
+    ///
+    /// The filename is taken from the response's Content-Disposition header,
+    /// then from the last segment of the request path, and finally falls
+    /// back to "index.html". Directory separators are always removed.
+    #[must_use]
+    pub fn download_filename(&self) -> String {
+        let from_header = self
+            .res
+            .as_ref()
+            .and_then(|res| res.headers.content_disposition_filename())
+            .and_then(|name| utils::sanitize_filename(&name));
+
+        let from_path = || {
+            let req = self.req.as_ref()?;
+            let path = req.path.as_str();
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            let segment = path.rsplit('/').next().unwrap_or_default();
+            let segment = utils::percent_decode(segment);
+            utils::sanitize_filename(&String::from_utf8_lossy(&segment))
+        };
+
+        from_header
+            .or_else(from_path)
+            .unwrap_or_else(|| String::from("index.html"))
+    }
+
+    /// Writes the raw response `Body` bytes to the `download` path and
+    /// prints a short summary of the status and the number of bytes written.
+    ///
+    /// If the `download` path is a directory, the body is saved in it under
+    /// the name returned by `download_filename`. Since that name comes from
+    /// the server, an existing file is never replaced: a numeric suffix,
+    /// such as ".1", is added to the name instead. A `download` path that
+    /// names a file is overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no download path is set, if there is no
+    /// `Response`, if writing the file fails, or if the name and every
+    /// suffix up to `MAX_DOWNLOAD_SUFFIX` are already taken.
+    pub fn save_body(&self) -> NetResult<()> {
+        let Some(path) = self.download.as_ref() else {
+            return Err(NetError::Other("No download path.".into()));
+        };
+
+        let res = self.res.as_ref().ok_or(NetError::NoResponse)?;
+
+        let path = if path.is_dir() {
+            let name = self.download_filename();
+            let (path, mut file) = create_new_file(path, &name)?;
+            file.write_all(res.body.as_bytes())?;
+            path
+        } else {
+            fs::write(path, res.body.as_bytes())?;
+            path.clone()
+        };
+
+        let status = if self.style.res.is_plain() {
+            res.status_line_to_plain_string()
+        } else {
+            res.status_line_to_color_string()
+        };
+
+        println!(
+            "{}\nSaved {} bytes to {}.",
+            status.trim_end(),
+            res.body.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
     /// Prints the request and the response to stdout based on the `Style`.
     pub fn print(&mut self) {
         let mut is_not_head = true;
@@ -376,9 +1526,30 @@ impl Client {
             }
         }
 
+        self.print_elapsed();
         println!();
     }
 
+    /// Prints the elapsed time if `timing` is set or the `Style` is the
+    /// "verbose" style. It is never part of the default output.
+    pub fn print_elapsed(&self) {
+        let Some(elapsed) = self.elapsed else {
+            return;
+        };
+
+        if !self.timing && !self.style.is_verbose() {
+            return;
+        }
+
+        let millis = elapsed.as_millis();
+
+        if self.style.res.is_plain() {
+            println!("Time: {millis}ms");
+        } else {
+            println!("{BLUE}Time{RESET}: {CYAN}{millis}ms{RESET}");
+        }
+    }
+
     /// Reads and parses a URI from stdin.
     ///
     /// # Errors
@@ -529,15 +1700,21 @@ impl Client {
         let headers = Self::get_headers(&mut line)?;
         let body = Self::get_body(&mut line)?;
 
-        self.req = Some(Request::builder()
+        let mut req = Request::builder()
             .method(method)
             .path(path)
             .headers(headers)
             .body(body)
-            .build());
+            .build();
+
+        if !req.headers.contains(&HOST) {
+            let host = utils::host_from_addr(&addr);
+            req.headers.insert(HOST, host.as_str().into());
+        }
+
+        self.req = Some(req);
 
-        self.conn = Some(TcpStream::connect(addr)
-            .map_err(|e| NetError::IoError(e.kind()))
+        self.conn = Some(utils::connect(addr)
             .and_then(Connection::try_from)?);
 
         Ok(())
@@ -0,0 +1,783 @@
+use std::fmt::Write as _;
+#[cfg(feature = "brotli")]
+use std::io::Read;
+
+use crate::{NetError, NetResult};
+
+/// Content codings that can be decoded.
+#[cfg(not(feature = "brotli"))]
+pub const SUPPORTED_ENCODINGS: &str = "gzip, deflate";
+
+/// Content codings that can be decoded.
+#[cfg(feature = "brotli")]
+pub const SUPPORTED_ENCODINGS: &str = "gzip, deflate, br";
+
+// Base lengths and extra bits for length codes 257 through 285.
+const LEN_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59,
+    67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LEN_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4,
+    5, 5, 5, 5, 0,
+];
+
+// Base distances and extra bits for distance codes 0 through 29.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+    513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10,
+    11, 11, 12, 12, 13, 13,
+];
+
+// Order in which code length code lengths are stored in dynamic blocks.
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const MAX_BITS: usize = 15;
+
+/// The default maximum length of decoded data, which limits how much memory
+/// a small compressed body can expand into.
+pub const MAX_DECODED_LEN: usize = 64 * 1024 * 1024;
+
+/// Decodes `data` according to a Content-Encoding header value.
+///
+/// Returns `Ok(None)` if the value contains a coding that is not supported
+/// so that callers can leave the data as is.
+///
+/// # Errors
+///
+/// Returns an error if the data is not valid for a supported coding, or
+/// `NetError::BodyTooLarge` if decoding produces more than `max_len` bytes.
+pub fn decode(
+    content_encoding: &str,
+    data: &[u8],
+    max_len: usize
+) -> NetResult<Option<Vec<u8>>> {
+    let mut decoded = data.to_vec();
+
+    // Codings are listed in the order they were applied.
+    for coding in content_encoding.rsplit(',') {
+        decoded = match coding.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => decoded,
+            "gzip" | "x-gzip" => gunzip(&decoded, max_len)?,
+            "deflate" => inflate(&decoded, max_len)?,
+            #[cfg(feature = "brotli")]
+            "br" => unbrotli(&decoded, max_len)?,
+            _ => return Ok(None),
+        };
+    }
+
+    Ok(Some(decoded))
+}
+
+/// Decompresses data in the gzip format (RFC 1952).
+///
+/// # Errors
+///
+/// Returns an error if the data is not valid gzip data or if the checksum
+/// does not match, or `NetError::BodyTooLarge` if the decompressed data is
+/// longer than `max_len` bytes.
+pub fn gunzip(data: &[u8], max_len: usize) -> NetResult<Vec<u8>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 18 || data[..3] != [0x1f, 0x8b, 0x08] {
+        return Err(NetError::BadEncoding);
+    }
+
+    let flags = data[3];
+    let mut pos = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = data.get(pos..pos + 2).ok_or(NetError::BadEncoding)?;
+        pos += 2 + usize::from(u16::from_le_bytes([len[0], len[1]]));
+    }
+
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = data.get(pos..).ok_or(NetError::BadEncoding)?;
+            let end = rest
+                .iter()
+                .position(|&b| b == 0)
+                .ok_or(NetError::BadEncoding)?;
+            pos += end + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    let deflated = data.get(pos..).ok_or(NetError::BadEncoding)?;
+    let (output, used) = inflate_raw(deflated, max_len)?;
+
+    let trailer = deflated
+        .get(used..used + 8)
+        .ok_or(NetError::BadEncoding)?;
+    let (crc, size) = trailer.split_at(4);
+    let crc = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]);
+
+    // The size is stored modulo 2^32.
+    #[allow(clippy::cast_possible_truncation)]
+    let out_size = output.len() as u32;
+
+    if crc != crc32(&output) || size != out_size {
+        return Err(NetError::BadEncoding);
+    }
+
+    Ok(output)
+}
+
+/// Decompresses data for the "deflate" content coding, which is data in the
+/// zlib format (RFC 1950). Raw deflate data is also accepted since some
+/// servers omit the zlib wrapper.
+///
+/// # Errors
+///
+/// Returns an error if the data is not valid deflate data or if the
+/// checksum does not match, or `NetError::BodyTooLarge` if the decompressed
+/// data is longer than `max_len` bytes.
+pub fn inflate(data: &[u8], max_len: usize) -> NetResult<Vec<u8>> {
+    let has_zlib_header = data.len() >= 2
+        && data[0] & 0x0f == 8
+        && (u16::from(data[0]) << 8 | u16::from(data[1])) % 31 == 0;
+
+    if !has_zlib_header {
+        return inflate_raw(data, max_len).map(|(output, _)| output);
+    }
+
+    // Preset dictionaries are not supported.
+    if data[1] & 0x20 != 0 {
+        return Err(NetError::BadEncoding);
+    }
+
+    let (output, used) = inflate_raw(&data[2..], max_len)?;
+
+    let trailer = data
+        .get(2 + used..6 + used)
+        .ok_or(NetError::BadEncoding)?;
+    let checksum =
+        u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+
+    if checksum != adler32(&output) {
+        return Err(NetError::BadEncoding);
+    }
+
+    Ok(output)
+}
+
+/// Decompresses data in the Brotli format (RFC 7932).
+///
+/// # Errors
+///
+/// Returns an error if the data is not valid Brotli data, or
+/// `NetError::BodyTooLarge` if the decompressed data is longer than
+/// `max_len` bytes.
+#[cfg(feature = "brotli")]
+pub fn unbrotli(data: &[u8], max_len: usize) -> NetResult<Vec<u8>> {
+    let mut output =
+        Vec::with_capacity(data.len().saturating_mul(4).min(max_len));
+
+    // Read one byte past the limit to tell a body that is exactly `max_len`
+    // bytes long from one that is longer.
+    let limit = u64::try_from(max_len).unwrap_or(u64::MAX).saturating_add(1);
+
+    brotli_decompressor::Decompressor::new(data, 4096)
+        .take(limit)
+        .read_to_end(&mut output)
+        .map_err(|_| NetError::BadEncoding)?;
+
+    if output.len() > max_len {
+        return Err(NetError::BodyTooLarge);
+    }
+
+    Ok(output)
+}
+
+/// Decompresses raw deflate data (RFC 1951), returning the output and the
+/// number of input bytes that were used.
+///
+/// # Errors
+///
+/// Returns an error if the data is not valid deflate data, or
+/// `NetError::BodyTooLarge` if the decompressed data is longer than
+/// `max_len` bytes.
+pub fn inflate_raw(
+    data: &[u8],
+    max_len: usize
+) -> NetResult<(Vec<u8>, usize)> {
+    let mut bits = BitReader::new(data);
+    let mut output =
+        Vec::with_capacity(data.len().saturating_mul(4).min(max_len));
+
+    loop {
+        let is_last = bits.read(1)? == 1;
+
+        match bits.read(2)? {
+            0 => bits.copy_stored(&mut output, max_len)?,
+            1 => {
+                let (lit, dist) = Huffman::fixed()?;
+                inflate_block(&mut bits, &mut output, max_len, &lit, &dist)?;
+            },
+            2 => {
+                let (lit, dist) = Huffman::dynamic(&mut bits)?;
+                inflate_block(&mut bits, &mut output, max_len, &lit, &dist)?;
+            },
+            _ => return Err(NetError::BadEncoding),
+        }
+
+        if is_last {
+            return Ok((output, bits.pos));
+        }
+    }
+}
+
+/// Decodes the compressed data of a single block.
+fn inflate_block(
+    bits: &mut BitReader<'_>,
+    output: &mut Vec<u8>,
+    max_len: usize,
+    lit: &Huffman,
+    dist: &Huffman
+) -> NetResult<()> {
+    loop {
+        let symbol = usize::from(lit.decode(bits)?);
+
+        match symbol {
+            0..=255 if output.len() >= max_len => {
+                return Err(NetError::BodyTooLarge);
+            },
+            0..=255 => output.push(u8::try_from(symbol).unwrap_or(0)),
+            256 => return Ok(()),
+            257..=285 => {
+                let idx = symbol - 257;
+                let len = usize::from(LEN_BASE[idx])
+                    + bits.read(LEN_EXTRA[idx])? as usize;
+
+                let idx = usize::from(dist.decode(bits)?);
+                let (Some(&base), Some(&extra)) =
+                    (DIST_BASE.get(idx), DIST_EXTRA.get(idx))
+                else {
+                    return Err(NetError::BadEncoding);
+                };
+                let distance = usize::from(base) + bits.read(extra)? as usize;
+
+                if distance > output.len() {
+                    return Err(NetError::BadEncoding);
+                }
+
+                if len > max_len - output.len() {
+                    return Err(NetError::BodyTooLarge);
+                }
+
+                // Copy byte by byte since the ranges may overlap.
+                let start = output.len() - distance;
+                for i in 0..len {
+                    output.push(output[start + i]);
+                }
+            },
+            _ => return Err(NetError::BadEncoding),
+        }
+    }
+}
+
+/// Reads a deflate stream one bit at a time, least significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u8,
+}
+
+impl<'a> BitReader<'a> {
+    const fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, buf: 0, count: 0 }
+    }
+
+    /// Reads `num` bits, where `num` is at most 16.
+    fn read(&mut self, num: u8) -> NetResult<u32> {
+        while self.count < num {
+            let byte = *self.data.get(self.pos).ok_or(NetError::BadEncoding)?;
+            self.pos += 1;
+            self.buf |= u32::from(byte) << self.count;
+            self.count += 8;
+        }
+
+        let value = self.buf & ((1 << num) - 1);
+        self.buf >>= num;
+        self.count -= num;
+        Ok(value)
+    }
+
+    /// Copies a stored (uncompressed) block to `output`.
+    fn copy_stored(
+        &mut self,
+        output: &mut Vec<u8>,
+        max_len: usize
+    ) -> NetResult<()> {
+        // Stored blocks start on a byte boundary.
+        self.buf = 0;
+        self.count = 0;
+
+        let header = self
+            .data
+            .get(self.pos..self.pos + 4)
+            .ok_or(NetError::BadEncoding)?;
+        let len = u16::from_le_bytes([header[0], header[1]]);
+        let nlen = u16::from_le_bytes([header[2], header[3]]);
+
+        if len != !nlen {
+            return Err(NetError::BadEncoding);
+        }
+
+        self.pos += 4;
+        let end = self.pos + usize::from(len);
+        let stored = self.data
+            .get(self.pos..end)
+            .ok_or(NetError::BadEncoding)?;
+
+        if stored.len() > max_len - output.len() {
+            return Err(NetError::BodyTooLarge);
+        }
+
+        output.extend_from_slice(stored);
+        self.pos = end;
+        Ok(())
+    }
+}
+
+/// A canonical Huffman code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds a Huffman code from the code length of each symbol.
+    fn new(lengths: &[u8]) -> NetResult<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+
+        // Reject over-subscribed codes. Incomplete codes are permitted.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+
+            if left < 0 {
+                return Err(NetError::BadEncoding);
+            }
+        }
+
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in (0u16..).zip(lengths) {
+            if len != 0 {
+                let offset = &mut offsets[usize::from(len)];
+                symbols[usize::from(*offset)] = symbol;
+                *offset += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    /// Returns the literal/length and distance codes for fixed blocks.
+    fn fixed() -> NetResult<(Self, Self)> {
+        let mut lengths = [0u8; 288];
+        lengths[..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..].fill(8);
+
+        Ok((Self::new(&lengths)?, Self::new(&[5; 30])?))
+    }
+
+    /// Reads the literal/length and distance codes for a dynamic block.
+    fn dynamic(bits: &mut BitReader<'_>) -> NetResult<(Self, Self)> {
+        let num_lit = bits.read(5)? as usize + 257;
+        let num_dist = bits.read(5)? as usize + 1;
+        let num_clen = bits.read(4)? as usize + 4;
+
+        if num_lit > 286 || num_dist > 30 {
+            return Err(NetError::BadEncoding);
+        }
+
+        let mut clen_lengths = [0u8; 19];
+        for &idx in &CLEN_ORDER[..num_clen] {
+            clen_lengths[idx] = u8::try_from(bits.read(3)?).unwrap_or(0);
+        }
+
+        let clen_code = Self::new(&clen_lengths)?;
+
+        let mut lengths = Vec::with_capacity(num_lit + num_dist);
+
+        while lengths.len() < num_lit + num_dist {
+            let (len, repeat) = match clen_code.decode(bits)? {
+                len @ 0..=15 => (u8::try_from(len).unwrap_or(0), 1),
+                16 => {
+                    let prev = *lengths.last().ok_or(NetError::BadEncoding)?;
+                    (prev, 3 + bits.read(2)?)
+                },
+                17 => (0, 3 + bits.read(3)?),
+                18 => (0, 11 + bits.read(7)?),
+                _ => return Err(NetError::BadEncoding),
+            };
+
+            for _ in 0..repeat {
+                lengths.push(len);
+            }
+        }
+
+        if lengths.len() != num_lit + num_dist || lengths[256] == 0 {
+            return Err(NetError::BadEncoding);
+        }
+
+        let lit = Self::new(&lengths[..num_lit])?;
+        let dist = Self::new(&lengths[num_lit..])?;
+        Ok((lit, dist))
+    }
+
+    /// Decodes a single symbol.
+    fn decode(&self, bits: &mut BitReader<'_>) -> NetResult<u16> {
+        let mut code = 0u32;
+        let mut first = 0u32;
+        let mut index = 0u32;
+
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)?;
+            let count = u32::from(count);
+
+            if code < first + count {
+                let idx = (index + code - first) as usize;
+                return self
+                    .symbols
+                    .get(idx)
+                    .copied()
+                    .ok_or(NetError::BadEncoding);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(NetError::BadEncoding)
+    }
+}
+
+/// Returns the CRC-32 checksum used by gzip.
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Returns the Adler-32 checksum used by zlib.
+#[must_use]
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65_521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for &byte in data {
+        a = (a + u32::from(byte)) % MOD;
+        b = (b + a) % MOD;
+    }
+
+    (b << 16) | a
+}
+
+/// Returns the SHA-1 digest of `data` (RFC 3174).
+#[must_use]
+#[allow(clippy::many_single_char_names)]
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0,
+    ];
+
+    for block in pad_message(data, true).chunks_exact(64) {
+        let mut w = [0u32; 80];
+
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in state.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+
+    for (chunk, h) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&h.to_be_bytes());
+    }
+
+    digest
+}
+
+/// Pads `data` to a multiple of 64 bytes as MD5 and SHA-2 both require: a 1
+/// bit, zeros, and the message length in bits in the final eight bytes.
+fn pad_message(data: &[u8], big_endian: bool) -> Vec<u8> {
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+
+    if big_endian {
+        msg.extend_from_slice(&bit_len.to_be_bytes());
+    } else {
+        msg.extend_from_slice(&bit_len.to_le_bytes());
+    }
+
+    msg
+}
+
+/// Returns the MD5 digest of `data` (RFC 1321).
+///
+/// MD5 is broken for collision resistance and is only provided for
+/// protocols, such as HTTP Digest authentication, that still call for it.
+#[must_use]
+#[allow(clippy::many_single_char_names)]
+pub fn md5(data: &[u8]) -> [u8; 16] {
+    const SHIFTS: [u32; 16] =
+        [7, 12, 17, 22, 5, 9, 14, 20, 4, 11, 16, 23, 6, 10, 15, 21];
+    const K: [u32; 64] = [
+        0xd76a_a478, 0xe8c7_b756, 0x2420_70db, 0xc1bd_ceee, 0xf57c_0faf,
+        0x4787_c62a, 0xa830_4613, 0xfd46_9501, 0x6980_98d8, 0x8b44_f7af,
+        0xffff_5bb1, 0x895c_d7be, 0x6b90_1122, 0xfd98_7193, 0xa679_438e,
+        0x49b4_0821, 0xf61e_2562, 0xc040_b340, 0x265e_5a51, 0xe9b6_c7aa,
+        0xd62f_105d, 0x0244_1453, 0xd8a1_e681, 0xe7d3_fbc8, 0x21e1_cde6,
+        0xc337_07d6, 0xf4d5_0d87, 0x455a_14ed, 0xa9e3_e905, 0xfcef_a3f8,
+        0x676f_02d9, 0x8d2a_4c8a, 0xfffa_3942, 0x8771_f681, 0x6d9d_6122,
+        0xfde5_380c, 0xa4be_ea44, 0x4bde_cfa9, 0xf6bb_4b60, 0xbebf_bc70,
+        0x289b_7ec6, 0xeaa1_27fa, 0xd4ef_3085, 0x0488_1d05, 0xd9d4_d039,
+        0xe6db_99e5, 0x1fa2_7cf8, 0xc4ac_5665, 0xf429_2244, 0x432a_ff97,
+        0xab94_23a7, 0xfc93_a039, 0x655b_59c3, 0x8f0c_cc92, 0xffef_f47d,
+        0x8584_5dd1, 0x6fa8_7e4f, 0xfe2c_e6e0, 0xa301_4314, 0x4e08_11a1,
+        0xf753_7e82, 0xbd3a_f235, 0x2ad7_d2bb, 0xeb86_d391,
+    ];
+
+    let mut state: [u32; 4] =
+        [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+
+    for block in pad_message(data, false).chunks_exact(64) {
+        let mut m = [0u32; 16];
+
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            m[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(K[i])
+                .wrapping_add(m[g])
+                .rotate_left(SHIFTS[(i / 16) * 4 + i % 4]);
+
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        for (h, v) in state.iter_mut().zip([a, b, c, d]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 16];
+
+    for (chunk, h) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&h.to_le_bytes());
+    }
+
+    digest
+}
+
+/// Returns the SHA-256 digest of `data` (FIPS 180-4).
+#[must_use]
+#[allow(clippy::many_single_char_names)]
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b,
+        0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01,
+        0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7,
+        0xc19b_f174, 0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc,
+        0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da, 0x983e_5152,
+        0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147,
+        0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc,
+        0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+        0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819,
+        0xd699_0624, 0xf40e_3585, 0x106a_a070, 0x19a4_c116, 0x1e37_6c08,
+        0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f,
+        0x682e_6ff3, 0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208,
+        0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+    ];
+
+    let mut state: [u32; 8] = [
+        0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f,
+        0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+    ];
+
+    for block in pad_message(data, true).chunks_exact(64) {
+        let mut w = [0u32; 64];
+
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7)
+                ^ w[i - 15].rotate_right(18)
+                ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17)
+                ^ w[i - 2].rotate_right(19)
+                ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+
+        for (&k, &word) in K.iter().zip(w.iter()) {
+            let s1 =
+                e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            let s0 =
+                a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+
+    for (chunk, h) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&h.to_be_bytes());
+    }
+
+    digest
+}
+
+/// Encodes `data` as a lowercase hex string.
+#[must_use]
+pub fn hex_encode(data: &[u8]) -> String {
+    data.iter().fold(String::with_capacity(data.len() * 2), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Encodes `data` as base64 with padding (RFC 4648).
+#[must_use]
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let n = u32::from_be_bytes([
+            0,
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3f;
+                out.push(char::from(ALPHABET[index as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
@@ -1,8 +1,10 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use std::result::Result;
+use std::sync::Arc;
 
 /// Result type that contains a `NetError` error variant.
 pub type NetResult<T> = Result<T, NetError>;
@@ -12,6 +14,8 @@ pub type NetResult<T> = Result<T, NetError>;
 pub enum NetError {
     BadAddress,
     BadBody,
+    BadEncoding,
+    BadFrame,
     BadHeader,
     BadHeaderName,
     BadHeaderValue,
@@ -23,26 +27,45 @@ pub enum NetError {
     BadStatusCode,
     BadUri,
     BadVersion,
+    BodyTooLarge,
+    ConnectionRefused,
+    Dns,
+    HeadersTooLarge,
+    Http2(u32),
     HttpsNotImplemented,
-    IoError(IoErrorKind),
+    IncompleteBody,
+    IoError(IoCause),
     JoinFailure,
     NotConnected,
     NoRequest,
     NoResponse,
     Other(Cow<'static, str>),
-    Read(IoErrorKind),
+    Read(IoCause),
+    Timeout,
     TooManyHeaders,
     UnexpectedEof,
-    Write(IoErrorKind),
+    UriTooLong,
+    Write(IoCause),
 }
 
-impl Error for NetError {}
+impl Error for NetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IoError(cause) | Self::Read(cause) | Self::Write(cause) => {
+                Some(cause.get_ref())
+            },
+            _ => None,
+        }
+    }
+}
 
 impl Display for NetError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self {
             Self::BadAddress => f.write_str("Address parsing failed"),
             Self::BadBody => f.write_str("Body parsing failed"),
+            Self::BadEncoding => f.write_str("Content decoding failed"),
+            Self::BadFrame => f.write_str("WebSocket frame parsing failed"),
             Self::BadHeader => f.write_str("Header parsing failed"),
             Self::BadHeaderName => f.write_str("Header name parsing failed"),
             Self::BadHeaderValue => f.write_str("Header value parsing failed"),
@@ -54,17 +77,114 @@ impl Display for NetError {
             Self::BadStatusCode => f.write_str("Status code parsing failed"),
             Self::BadUri => f.write_str("URI parsing failed"),
             Self::BadVersion => f.write_str("Version parsing failed"),
+            Self::BodyTooLarge => f.write_str("Body too large"),
+            Self::ConnectionRefused => f.write_str("Connection refused"),
+            Self::Dns => f.write_str("Address lookup failed"),
+            Self::HeadersTooLarge => f.write_str("Headers too large"),
+            Self::Http2(code) => write!(f, "HTTP/2 error (code {code:#x})"),
             Self::HttpsNotImplemented => f.write_str("HTTPS not implemented"),
-            Self::IoError(kind) => write!(f, "Received \"{kind}\" error"),
+            Self::IncompleteBody => {
+                f.write_str("Body is shorter than its Content-Length")
+            },
+            Self::IoError(cause) => {
+                write!(f, "Received \"{}\" error", cause.kind())
+            },
             Self::JoinFailure => f.write_str("Could not join server thread"),
             Self::NotConnected => f.write_str("No active TCP connection"),
             Self::NoRequest => f.write_str("No request found"),
             Self::NoResponse => f.write_str("No response found"),
             Self::Other(ref err_msg) => write!(f, "{err_msg}"),
-            Self::Read(kind) => write!(f, "Received \"{kind}\" read error"),
+            Self::Read(cause) => {
+                write!(f, "Received \"{}\" read error", cause.kind())
+            },
+            Self::Timeout => f.write_str("Operation timed out"),
             Self::TooManyHeaders => f.write_str("Too many headers"),
             Self::UnexpectedEof => f.write_str("Received unexpected EOF"),
-            Self::Write(kind) => write!(f, "Received \"{kind}\" write error"),
+            Self::UriTooLong => f.write_str("URI too long"),
+            Self::Write(cause) => {
+                write!(f, "Received \"{}\" write error", cause.kind())
+            },
+        }
+    }
+}
+
+impl NetError {
+    /// Returns the status code with which a server should respond when
+    /// receiving a request fails with this error.
+    ///
+    /// Malformed requests get a 400, oversized bodies get a 413, overly long
+    /// URIs get a 414, oversized header sections get a 431, and all other
+    /// errors get a 500.
+    #[must_use]
+    pub const fn status_code(&self) -> u16 {
+        match self {
+            Self::BadBody
+                | Self::BadEncoding
+                | Self::BadHeader
+                | Self::BadHeaderName
+                | Self::BadHeaderValue
+                | Self::BadMethod
+                | Self::BadPath
+                | Self::BadRequest
+                | Self::BadUri
+                | Self::BadVersion
+                | Self::IncompleteBody => 400,
+            Self::BodyTooLarge => 413,
+            Self::UriTooLong => 414,
+            Self::HeadersTooLarge | Self::TooManyHeaders => 431,
+            _ => 500,
+        }
+    }
+
+    /// Returns true if this error means that a read or write timed out or
+    /// that a request deadline passed.
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::IoError(cause) | Self::Read(cause) | Self::Write(cause) => {
+                matches!(
+                    cause.kind(),
+                    IoErrorKind::WouldBlock | IoErrorKind::TimedOut
+                )
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns true if this error means that the peer closed or reset the
+    /// connection.
+    #[must_use]
+    pub fn is_disconnect(&self) -> bool {
+        match self {
+            Self::UnexpectedEof => true,
+            Self::IoError(cause) | Self::Read(cause) | Self::Write(cause) => {
+                matches!(
+                    cause.kind(),
+                    IoErrorKind::BrokenPipe
+                        | IoErrorKind::ConnectionAborted
+                        | IoErrorKind::ConnectionReset
+                )
+            },
+            _ => false,
+        }
+    }
+
+    /// Returns the kind of the I/O error that caused this error, if any.
+    ///
+    /// The original `std::io::Error` of an `IoError`, `Read`, or `Write`
+    /// error is available from `Error::source`.
+    #[must_use]
+    pub fn io_kind(&self) -> Option<IoErrorKind> {
+        match self {
+            Self::IoError(cause) | Self::Read(cause) | Self::Write(cause) => {
+                Some(cause.kind())
+            },
+            Self::ConnectionRefused => Some(IoErrorKind::ConnectionRefused),
+            Self::NotConnected => Some(IoErrorKind::NotConnected),
+            Self::Timeout => Some(IoErrorKind::TimedOut),
+            Self::UnexpectedEof => Some(IoErrorKind::UnexpectedEof),
+            _ => None,
         }
     }
 }
@@ -72,39 +192,54 @@ impl Display for NetError {
 impl From<IoErrorKind> for NetError {
     fn from(kind: IoErrorKind) -> Self {
         match kind {
+            IoErrorKind::ConnectionRefused => Self::ConnectionRefused,
             IoErrorKind::NotConnected => Self::NotConnected,
+            IoErrorKind::TimedOut | IoErrorKind::WouldBlock => Self::Timeout,
             IoErrorKind::UnexpectedEof => Self::UnexpectedEof,
-            _ => Self::IoError(kind),
+            _ => Self::IoError(kind.into()),
         }
     }
 }
 
 impl From<IoError> for NetError {
     fn from(err: IoError) -> Self {
-        err.kind().into()
+        match err.kind() {
+            IoErrorKind::ConnectionRefused
+                | IoErrorKind::NotConnected
+                | IoErrorKind::TimedOut
+                | IoErrorKind::WouldBlock
+                | IoErrorKind::UnexpectedEof => err.kind().into(),
+            _ => Self::IoError(err.into()),
+        }
     }
 }
 
 impl From<NetError> for IoError {
     fn from(err: NetError) -> Self {
         match err {
-            NetError::IoError(kind)
-                | NetError::Read(kind)
-                | NetError::Write(kind) =>
+            NetError::IoError(cause)
+                | NetError::Read(cause)
+                | NetError::Write(cause) =>
             {
-                Self::from(kind)
+                cause.into_inner()
             },
             NetError::HttpsNotImplemented => {
                 Self::new(IoErrorKind::Unsupported, err)
             },
+            NetError::ConnectionRefused => {
+                Self::new(IoErrorKind::ConnectionRefused, err)
+            },
             NetError::NotConnected => {
                 Self::from(IoErrorKind::NotConnected)
             },
             NetError::UnexpectedEof => {
                 Self::from(IoErrorKind::UnexpectedEof)
             },
+            NetError::Timeout => Self::new(IoErrorKind::TimedOut, err),
             NetError::BadAddress
                 | NetError::BadBody
+                | NetError::BadEncoding
+                | NetError::BadFrame
                 | NetError::BadHeader
                 | NetError::BadHeaderName
                 | NetError::BadHeaderValue
@@ -116,10 +251,16 @@ impl From<NetError> for IoError {
                 | NetError::BadStatusCode
                 | NetError::BadUri
                 | NetError::BadVersion
+                | NetError::BodyTooLarge
+                | NetError::Dns
+                | NetError::HeadersTooLarge
+                | NetError::Http2(_)
+                | NetError::IncompleteBody
                 | NetError::JoinFailure
                 | NetError::NoRequest
                 | NetError::NoResponse
-                | NetError::TooManyHeaders =>
+                | NetError::TooManyHeaders
+                | NetError::UriTooLong =>
             {
                 Self::new(IoErrorKind::Other, err)
             },
@@ -127,3 +268,70 @@ impl From<NetError> for IoError {
         }
     }
 }
+
+/// The I/O error that caused an `IoError`, `Read`, or `Write` error.
+///
+/// The error is shared so that a `NetError` can still be cloned, and it is
+/// compared and ordered by its kind alone.
+#[derive(Clone, Debug)]
+pub struct IoCause(Arc<IoError>);
+
+impl PartialEq for IoCause {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind()
+    }
+}
+
+impl Eq for IoCause {}
+
+impl PartialOrd for IoCause {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IoCause {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.kind().cmp(&other.kind())
+    }
+}
+
+impl Display for IoCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl From<IoError> for IoCause {
+    fn from(err: IoError) -> Self {
+        Self(Arc::new(err))
+    }
+}
+
+impl From<IoErrorKind> for IoCause {
+    fn from(kind: IoErrorKind) -> Self {
+        Self(Arc::new(IoError::from(kind)))
+    }
+}
+
+impl IoCause {
+    /// Returns the kind of the underlying I/O error.
+    #[must_use]
+    pub fn kind(&self) -> IoErrorKind {
+        self.0.kind()
+    }
+
+    /// Returns a reference to the underlying I/O error.
+    #[must_use]
+    pub fn get_ref(&self) -> &IoError {
+        &self.0
+    }
+
+    /// Returns the underlying I/O error, or a new one of the same kind if
+    /// this error is still shared by a clone.
+    #[must_use]
+    pub fn into_inner(self) -> IoError {
+        Arc::try_unwrap(self.0)
+            .unwrap_or_else(|shared| IoError::from(shared.kind()))
+    }
+}
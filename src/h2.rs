@@ -0,0 +1,1007 @@
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+use crate::{
+    Body, Headers, Method, NetError, NetResult, Request, Response, Status,
+    Version,
+};
+use crate::headers::names::HOST;
+
+/// The connection preface that a client sends before its first frame.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// The largest frame payload, in bytes, that is sent or accepted.
+pub const MAX_FRAME_LEN: usize = 16_384;
+
+/// The initial flow-control window, in bytes, of connections and streams.
+pub const INITIAL_WINDOW: u32 = 65_535;
+
+/// The largest HPACK dynamic table size, in bytes, that the peer may use.
+pub const MAX_TABLE_SIZE: usize = 4096;
+
+/// The stream on which the request is sent.
+pub const STREAM_ID: u32 = 1;
+
+/// The error code that signals a graceful shutdown.
+pub const NO_ERROR: u32 = 0x0;
+
+/// The error code for a generic protocol violation.
+pub const PROTOCOL_ERROR: u32 = 0x1;
+
+/// The error code for a violation of the flow-control protocol.
+pub const FLOW_CONTROL_ERROR: u32 = 0x3;
+
+/// The error code for a frame with an invalid size.
+pub const FRAME_SIZE_ERROR: u32 = 0x6;
+
+/// The error code for a header block that cannot be decoded.
+pub const COMPRESSION_ERROR: u32 = 0x9;
+
+/// The flag that marks the last frame of a stream.
+pub const END_STREAM: u8 = 0x1;
+
+/// The flag that marks a SETTINGS or PING frame as an acknowledgement.
+pub const ACK: u8 = 0x1;
+
+/// The flag that marks the last frame of a header block.
+pub const END_HEADERS: u8 = 0x4;
+
+/// The flag that marks a frame as padded.
+pub const PADDED: u8 = 0x8;
+
+/// The flag that marks a HEADERS frame as carrying priority fields.
+pub const PRIORITY: u8 = 0x20;
+
+const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+
+/// The HPACK static table (RFC 7541, Appendix A).
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// Lengths of the HPACK Huffman codes for each byte value followed by the
+// end-of-string symbol (RFC 7541, Appendix B). The code is canonical, so
+// the codes themselves follow from their lengths.
+const HUFFMAN_LENS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28,
+    28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6,
+    5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10,
+    13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6,
+    15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6, 6, 5,
+    6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23,
+    24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23,
+    21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25,
+    19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23,
+    26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+const MAX_HUFFMAN_LEN: usize = 30;
+
+// Header fields that only apply to a single HTTP/1.x connection and must
+// not be sent over HTTP/2.
+const CONNECTION_SPECIFIC: [&str; 6] = [
+    "connection", "host", "keep-alive", "proxy-connection",
+    "transfer-encoding", "upgrade",
+];
+
+/// The type of an HTTP/2 frame.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FrameKind {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    Unknown(u8),
+}
+
+impl From<u8> for FrameKind {
+    fn from(kind: u8) -> Self {
+        match kind {
+            0x0 => Self::Data,
+            0x1 => Self::Headers,
+            0x2 => Self::Priority,
+            0x3 => Self::RstStream,
+            0x4 => Self::Settings,
+            0x5 => Self::PushPromise,
+            0x6 => Self::Ping,
+            0x7 => Self::GoAway,
+            0x8 => Self::WindowUpdate,
+            0x9 => Self::Continuation,
+            kind => Self::Unknown(kind),
+        }
+    }
+}
+
+impl FrameKind {
+    /// Returns the frame type as the value used on the wire.
+    #[must_use]
+    pub const fn as_u8(self) -> u8 {
+        match self {
+            Self::Data => 0x0,
+            Self::Headers => 0x1,
+            Self::Priority => 0x2,
+            Self::RstStream => 0x3,
+            Self::Settings => 0x4,
+            Self::PushPromise => 0x5,
+            Self::Ping => 0x6,
+            Self::GoAway => 0x7,
+            Self::WindowUpdate => 0x8,
+            Self::Continuation => 0x9,
+            Self::Unknown(kind) => kind,
+        }
+    }
+}
+
+/// A single HTTP/2 frame.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Returns a new `Frame`.
+    #[must_use]
+    pub const fn new(
+        kind: FrameKind,
+        flags: u8,
+        stream_id: u32,
+        payload: Vec<u8>
+    ) -> Self {
+        Self { kind, flags, stream_id, payload }
+    }
+
+    /// Returns a SETTINGS `Frame` with the given identifiers and values.
+    #[must_use]
+    pub fn settings(settings: &[(u16, u32)]) -> Self {
+        let payload = settings
+            .iter()
+            .flat_map(|(id, value)| {
+                id.to_be_bytes().into_iter().chain(value.to_be_bytes())
+            })
+            .collect();
+
+        Self::new(FrameKind::Settings, 0, 0, payload)
+    }
+
+    /// Returns a window update `Frame` that grows the window of a stream,
+    /// or of the connection if `stream_id` is zero.
+    #[must_use]
+    pub fn window_update(stream_id: u32, increment: u32) -> Self {
+        let payload = increment.to_be_bytes().to_vec();
+        Self::new(FrameKind::WindowUpdate, 0, stream_id, payload)
+    }
+
+    /// Returns true if the given flag is set.
+    #[must_use]
+    pub const fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Returns the payload with any padding and priority fields removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::Http2` if the padding is longer than the payload.
+    pub fn data(&self) -> NetResult<&[u8]> {
+        let mut data = &self.payload[..];
+
+        if self.has_flag(PADDED) {
+            let (&pad_len, rest) = data
+                .split_first()
+                .ok_or(NetError::Http2(PROTOCOL_ERROR))?;
+
+            data = rest
+                .len()
+                .checked_sub(usize::from(pad_len))
+                .map(|len| &rest[..len])
+                .ok_or(NetError::Http2(PROTOCOL_ERROR))?;
+        }
+
+        if self.kind == FrameKind::Headers && self.has_flag(PRIORITY) {
+            data = data.get(5..).ok_or(NetError::Http2(PROTOCOL_ERROR))?;
+        }
+
+        Ok(data)
+    }
+}
+
+/// Reads a single frame.
+///
+/// # Errors
+///
+/// Returns `NetError::Http2` with `FRAME_SIZE_ERROR` if the payload is
+/// longer than `MAX_FRAME_LEN`. An error is also returned if reading fails.
+pub fn read_frame<R: Read>(reader: &mut R) -> NetResult<Frame> {
+    let mut head = [0u8; 9];
+    reader.read_exact(&mut head)?;
+
+    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]);
+    let kind = FrameKind::from(head[3]);
+    let flags = head[4];
+    let stream_id = u32::from_be_bytes([head[5], head[6], head[7], head[8]])
+        & 0x7fff_ffff;
+
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|&len| len <= MAX_FRAME_LEN)
+        .ok_or(NetError::Http2(FRAME_SIZE_ERROR))?;
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+
+    Ok(Frame { kind, flags, stream_id, payload })
+}
+
+/// Writes a single frame and flushes the writer.
+///
+/// # Errors
+///
+/// Returns `NetError::Http2` with `FRAME_SIZE_ERROR` if the payload is
+/// longer than `MAX_FRAME_LEN`. An error is also returned if writing fails.
+pub fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> NetResult<()> {
+    if frame.payload.len() > MAX_FRAME_LEN {
+        return Err(NetError::Http2(FRAME_SIZE_ERROR));
+    }
+
+    let len = u32::try_from(frame.payload.len())
+        .map_err(|_| NetError::Http2(FRAME_SIZE_ERROR))?
+        .to_be_bytes();
+
+    let mut buf = Vec::with_capacity(frame.payload.len() + 9);
+    buf.extend_from_slice(&len[1..]);
+    buf.push(frame.kind.as_u8());
+    buf.push(frame.flags);
+    buf.extend_from_slice(&(frame.stream_id & 0x7fff_ffff).to_be_bytes());
+    buf.extend_from_slice(&frame.payload);
+
+    writer.write_all(&buf)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Encodes a list of header fields as an HPACK header block.
+///
+/// Fields that are in the static table are sent as an index. All others
+/// are sent as literals that are not added to the dynamic table, with the
+/// name indexed when possible. String literals are not Huffman-coded.
+#[must_use]
+pub fn encode_headers(fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = Vec::new();
+
+    for &(name, value) in fields {
+        let exact = STATIC_TABLE.iter().position(|&f| f == (name, value));
+
+        if let Some(idx) = exact {
+            encode_int(&mut block, idx + 1, 7, 0x80);
+            continue;
+        }
+
+        let indexed_name = STATIC_TABLE.iter().position(|&(n, _)| n == name);
+
+        if let Some(idx) = indexed_name {
+            encode_int(&mut block, idx + 1, 4, 0x00);
+        } else {
+            block.push(0x00);
+            encode_string(&mut block, name);
+        }
+
+        encode_string(&mut block, value);
+    }
+
+    block
+}
+
+/// Decodes HPACK header blocks.
+///
+/// The dynamic table is kept between blocks, so a single `HpackDecoder`
+/// must be used for all header blocks received on a connection.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct HpackDecoder {
+    pub table: VecDeque<(String, String)>,
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl Default for HpackDecoder {
+    fn default() -> Self {
+        Self { table: VecDeque::new(), size: 0, max_size: MAX_TABLE_SIZE }
+    }
+}
+
+impl HpackDecoder {
+    /// Returns a new `HpackDecoder` with an empty dynamic table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a complete header block into a list of header fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::Http2` with `COMPRESSION_ERROR` if the block is
+    /// truncated, refers to a missing table entry, or is otherwise invalid.
+    pub fn decode(&mut self, block: &[u8]) -> NetResult<Vec<(String, String)>> {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+
+        while let Some(&byte) = block.get(pos) {
+            if byte & 0x80 != 0 {
+                // Indexed field.
+                let idx = decode_int(block, &mut pos, 7)?;
+                fields.push(self.get(idx)?);
+            } else if byte & 0x40 != 0 {
+                // Literal field that is added to the dynamic table.
+                let field = self.decode_literal(block, &mut pos, 6)?;
+                self.insert(field.clone());
+                fields.push(field);
+            } else if byte & 0x20 != 0 {
+                // Dynamic table size update.
+                let max_size = decode_int(block, &mut pos, 5)?;
+
+                if max_size > MAX_TABLE_SIZE {
+                    return Err(NetError::Http2(COMPRESSION_ERROR));
+                }
+
+                self.max_size = max_size;
+                self.evict(0);
+            } else {
+                // Literal field that is not added to the dynamic table.
+                fields.push(self.decode_literal(block, &mut pos, 4)?);
+            }
+        }
+
+        Ok(fields)
+    }
+
+    /// Returns the field at `idx` in the combined static and dynamic table.
+    fn get(&self, idx: usize) -> NetResult<(String, String)> {
+        let field = match idx {
+            0 => None,
+            1..=61 => STATIC_TABLE
+                .get(idx - 1)
+                .map(|&(name, value)| (name.to_string(), value.to_string())),
+            _ => self.table.get(idx - 62).cloned(),
+        };
+
+        field.ok_or(NetError::Http2(COMPRESSION_ERROR))
+    }
+
+    /// Decodes a literal field whose name index has the given prefix size.
+    fn decode_literal(
+        &self,
+        block: &[u8],
+        pos: &mut usize,
+        prefix: u32
+    ) -> NetResult<(String, String)> {
+        let name = match decode_int(block, pos, prefix)? {
+            0 => decode_string(block, pos)?,
+            idx => self.get(idx)?.0,
+        };
+
+        let value = decode_string(block, pos)?;
+        Ok((name, value))
+    }
+
+    /// Adds a field to the dynamic table, evicting the oldest entries as
+    /// needed to stay within `max_size`.
+    fn insert(&mut self, field: (String, String)) {
+        let field_size = field.0.len() + field.1.len() + 32;
+        self.evict(field_size);
+
+        // A field that is larger than the table empties it.
+        if field_size <= self.max_size {
+            self.size += field_size;
+            self.table.push_front(field);
+        }
+    }
+
+    /// Evicts the oldest entries until `extra` more bytes fit in the table.
+    fn evict(&mut self, extra: usize) {
+        while self.size + extra > self.max_size {
+            let Some((name, value)) = self.table.pop_back() else {
+                self.size = 0;
+                break;
+            };
+
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+}
+
+/// Decodes a string that was encoded with the HPACK Huffman code.
+///
+/// # Errors
+///
+/// Returns `NetError::Http2` with `COMPRESSION_ERROR` if the data contains
+/// the end-of-string symbol or is not correctly padded.
+pub fn huffman_decode(data: &[u8]) -> NetResult<Vec<u8>> {
+    // Order the symbols by code length to rebuild the canonical code.
+    let mut counts = [0usize; MAX_HUFFMAN_LEN + 1];
+
+    for &len in &HUFFMAN_LENS {
+        counts[usize::from(len)] += 1;
+    }
+
+    let mut offsets = [0usize; MAX_HUFFMAN_LEN + 1];
+
+    for len in 1..=MAX_HUFFMAN_LEN {
+        offsets[len] = offsets[len - 1] + counts[len - 1];
+    }
+
+    let mut symbols = [0usize; 257];
+
+    for (symbol, &len) in HUFFMAN_LENS.iter().enumerate() {
+        let slot = &mut offsets[usize::from(len)];
+        symbols[*slot] = symbol;
+        *slot += 1;
+    }
+
+    let mut decoded = Vec::with_capacity(data.len() * 8 / 5);
+    let (mut code, mut first, mut index, mut len) = (0, 0, 0, 0);
+    let mut all_ones = true;
+
+    for byte in data {
+        for shift in (0..8).rev() {
+            let bit = usize::from((byte >> shift) & 1);
+            code |= bit;
+            all_ones &= bit == 1;
+            len += 1;
+
+            let count = counts[len];
+
+            if code < first + count {
+                let symbol = u8::try_from(symbols[index + code - first])
+                    .map_err(|_| NetError::Http2(COMPRESSION_ERROR))?;
+                decoded.push(symbol);
+                (code, first, index, len) = (0, 0, 0, 0);
+                all_ones = true;
+            } else if len == MAX_HUFFMAN_LEN {
+                return Err(NetError::Http2(COMPRESSION_ERROR));
+            } else {
+                index += count;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+        }
+    }
+
+    // Padding is the most significant bits of the end-of-string symbol.
+    if len > 7 || !all_ones {
+        return Err(NetError::Http2(COMPRESSION_ERROR));
+    }
+
+    Ok(decoded)
+}
+
+/// Writes a request as the first stream of a new HTTP/2 connection with
+/// prior knowledge, preceded by the connection preface and a SETTINGS frame
+/// that disables server push.
+///
+/// The Host header is sent as the ":authority" pseudo-header, and headers
+/// that are specific to HTTP/1.x connections are left out. While the body
+/// is blocked by flow control, settings, ping, and window update frames
+/// from the server are handled.
+///
+/// # Errors
+///
+/// Returns `NetError::Http2` if the server resets the stream, closes the
+/// connection, or sends any other frame before the body is sent. An error
+/// is also returned if reading or writing fails.
+pub fn write_request<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    req: &Request
+) -> NetResult<()>
+where
+    R: Read,
+    W: Write,
+{
+    writer.write_all(PREFACE)?;
+    write_frame(writer, &Frame::settings(&[(SETTINGS_ENABLE_PUSH, 0)]))?;
+
+    let authority = req.headers.get(&HOST).map(|host| host.as_str());
+    let mut fields = vec![
+        (":method".to_string(), req.method.as_str().to_string()),
+        (":scheme".to_string(), "http".to_string()),
+        (":path".to_string(), req.path.as_str().to_string()),
+    ];
+
+    if let Some(authority) = authority {
+        fields.insert(2, (":authority".to_string(), authority.into_owned()));
+    }
+
+    for (name, value) in req.headers.iter() {
+        let name = name.as_str().to_ascii_lowercase();
+
+        if !CONNECTION_SPECIFIC.contains(&name.as_str()) {
+            fields.push((name, value.as_str().into_owned()));
+        }
+    }
+
+    let fields = fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect::<Vec<_>>();
+
+    let body = req.body.as_bytes();
+    let block = encode_headers(&fields);
+    let mut chunks = block.chunks(MAX_FRAME_LEN).peekable();
+    let mut kind = FrameKind::Headers;
+
+    while let Some(chunk) = chunks.next() {
+        let mut flags = 0;
+
+        if chunks.peek().is_none() {
+            flags |= END_HEADERS;
+        }
+
+        if kind == FrameKind::Headers && body.is_empty() {
+            flags |= END_STREAM;
+        }
+
+        write_frame(writer, &Frame::new(kind, flags, STREAM_ID, chunk.into()))?;
+        kind = FrameKind::Continuation;
+    }
+
+    let mut window = SendWindow::default();
+    let mut sent = 0;
+
+    while sent < body.len() {
+        let len = window.available().min(body.len() - sent);
+
+        if len == 0 {
+            window.update(reader, writer)?;
+            continue;
+        }
+
+        let flags = if sent + len == body.len() { END_STREAM } else { 0 };
+        let chunk = body[sent..(sent + len)].to_vec();
+        let frame = Frame::new(FrameKind::Data, flags, STREAM_ID, chunk);
+        write_frame(writer, &frame)?;
+
+        window.consume(len);
+        sent += len;
+    }
+
+    Ok(())
+}
+
+/// Reads the response to the request on `STREAM_ID`, acknowledging the
+/// server's SETTINGS and PING frames and replenishing its flow-control
+/// windows as DATA frames arrive.
+///
+/// Interim 1xx responses are skipped, and trailers are added to the
+/// response headers.
+///
+/// # Errors
+///
+/// Returns `NetError::Http2` if the server resets the stream, closes the
+/// connection before responding, or violates the protocol, and
+/// `NetError::BadResponse` if the response has no valid ":status". An error
+/// is also returned if reading or writing fails.
+pub fn read_response<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    method: &Method
+) -> NetResult<Response>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = HpackDecoder::new();
+    let mut status = None;
+    let mut headers = Headers::new();
+    let mut body = Vec::new();
+
+    loop {
+        let frame = read_frame(reader)?;
+
+        if control_frame(writer, &frame)? {
+            continue;
+        }
+
+        match frame.kind {
+            FrameKind::Headers if frame.stream_id == STREAM_ID => {
+                let fields = read_header_block(reader, &mut decoder, &frame)?;
+                let is_trailer = status.is_some();
+
+                for (name, value) in fields {
+                    if name == ":status" && !is_trailer {
+                        status = Some(Status::try_from(value.as_bytes())?);
+                    } else if !name.starts_with(':') {
+                        headers.header(&name, value.as_bytes());
+                    } else {
+                        return Err(NetError::Http2(PROTOCOL_ERROR));
+                    }
+                }
+
+                // Interim responses are followed by the final response.
+                if status.is_some_and(|status| status.code() < 200) {
+                    status = None;
+                    headers = Headers::new();
+                }
+            },
+            FrameKind::Headers => {
+                // Keep the dynamic table in sync with the server.
+                let _ = read_header_block(reader, &mut decoder, &frame)?;
+            },
+            FrameKind::Data if frame.stream_id == STREAM_ID => {
+                if status.is_none() {
+                    return Err(NetError::Http2(PROTOCOL_ERROR));
+                }
+
+                body.extend_from_slice(frame.data()?);
+                replenish(writer, &frame, frame.has_flag(END_STREAM))?;
+            },
+            FrameKind::Data => replenish(writer, &frame, true)?,
+            FrameKind::RstStream if frame.stream_id == STREAM_ID => {
+                return Err(NetError::Http2(read_u32(&frame.payload)));
+            },
+            FrameKind::PushPromise | FrameKind::Continuation => {
+                return Err(NetError::Http2(PROTOCOL_ERROR));
+            },
+            _ => {},
+        }
+
+        let is_stream_frame = matches!(
+            frame.kind,
+            FrameKind::Headers | FrameKind::Data
+        ) && frame.stream_id == STREAM_ID;
+
+        if is_stream_frame && frame.has_flag(END_STREAM) {
+            break;
+        }
+    }
+
+    let status = status.ok_or(NetError::BadResponse)?;
+
+    let body = if Body::should_be_empty(status.code(), method) {
+        Body::Empty
+    } else {
+        Body::from_headers(&body, &headers)
+    };
+
+    let version = Version::TwoDotZero;
+    Ok(Response { version, status, reason: None, headers, body })
+}
+
+/// The flow-control windows that limit how much request body may be sent.
+#[derive(Debug)]
+struct SendWindow {
+    connection: i64,
+    stream: i64,
+    initial: i64,
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        let initial = i64::from(INITIAL_WINDOW);
+        Self { connection: initial, stream: initial, initial }
+    }
+}
+
+impl SendWindow {
+    /// Returns the number of bytes that may be sent in the next DATA frame.
+    fn available(&self) -> usize {
+        let available = self.connection.min(self.stream).max(0);
+        usize::try_from(available).unwrap_or(0).min(MAX_FRAME_LEN)
+    }
+
+    /// Shrinks both windows after `len` bytes are sent.
+    fn consume(&mut self, len: usize) {
+        let len = i64::try_from(len).unwrap_or(i64::MAX);
+        self.connection -= len;
+        self.stream -= len;
+    }
+
+    /// Reads one frame from the server and applies it to the windows.
+    fn update<R: Read, W: Write>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W
+    ) -> NetResult<()> {
+        let frame = read_frame(reader)?;
+
+        match frame.kind {
+            FrameKind::WindowUpdate => {
+                let increment = i64::from(read_u32(&frame.payload));
+
+                match frame.stream_id {
+                    0 => self.connection += increment,
+                    STREAM_ID => self.stream += increment,
+                    _ => {},
+                }
+
+                if self.connection > i64::from(i32::MAX)
+                    || self.stream > i64::from(i32::MAX)
+                {
+                    return Err(NetError::Http2(FLOW_CONTROL_ERROR));
+                }
+            },
+            FrameKind::Settings if !frame.has_flag(ACK) => {
+                // A new initial window size also applies to open streams.
+                for setting in frame.payload.chunks_exact(6) {
+                    let id = u16::from_be_bytes([setting[0], setting[1]]);
+                    let value = u32::from_be_bytes([
+                        setting[2], setting[3], setting[4], setting[5],
+                    ]);
+
+                    if id == SETTINGS_INITIAL_WINDOW_SIZE {
+                        self.stream += i64::from(value) - self.initial;
+                        self.initial = i64::from(value);
+                    }
+                }
+
+                let _ = control_frame(writer, &frame)?;
+            },
+            _ if control_frame(writer, &frame)? => {},
+            _ => return Err(NetError::Http2(PROTOCOL_ERROR)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Handles a frame that applies to the whole connection, returning true if
+/// the frame needs no further handling.
+fn control_frame<W: Write>(writer: &mut W, frame: &Frame) -> NetResult<bool> {
+    match frame.kind {
+        FrameKind::Settings => {
+            if frame.stream_id != 0 || frame.payload.len() % 6 != 0 {
+                return Err(NetError::Http2(PROTOCOL_ERROR));
+            }
+
+            if !frame.has_flag(ACK) {
+                let ack = Frame::new(FrameKind::Settings, ACK, 0, Vec::new());
+                write_frame(writer, &ack)?;
+            }
+
+            Ok(true)
+        },
+        FrameKind::Ping => {
+            if !frame.has_flag(ACK) {
+                let data = frame.payload.clone();
+                let pong = Frame::new(FrameKind::Ping, ACK, 0, data);
+                write_frame(writer, &pong)?;
+            }
+
+            Ok(true)
+        },
+        FrameKind::GoAway => {
+            // The server may still finish the streams it has started.
+            let last_stream = read_u32(&frame.payload) & 0x7fff_ffff;
+            let code = read_u32(frame.payload.get(4..).unwrap_or(&[]));
+
+            if last_stream < STREAM_ID || code != NO_ERROR {
+                return Err(NetError::Http2(code));
+            }
+
+            Ok(true)
+        },
+        FrameKind::WindowUpdate
+            | FrameKind::Priority
+            | FrameKind::Unknown(_) => Ok(true),
+        _ => Ok(false),
+    }
+}
+
+/// Reads the rest of a header block that starts with `frame` and decodes
+/// it.
+fn read_header_block<R: Read>(
+    reader: &mut R,
+    decoder: &mut HpackDecoder,
+    frame: &Frame
+) -> NetResult<Vec<(String, String)>> {
+    let mut block = frame.data()?.to_vec();
+    let mut end_headers = frame.has_flag(END_HEADERS);
+
+    while !end_headers {
+        let next = read_frame(reader)?;
+
+        if next.kind != FrameKind::Continuation
+            || next.stream_id != frame.stream_id
+        {
+            return Err(NetError::Http2(PROTOCOL_ERROR));
+        }
+
+        block.extend_from_slice(&next.payload);
+        end_headers = next.has_flag(END_HEADERS);
+    }
+
+    decoder.decode(&block)
+}
+
+/// Returns the flow-control credit used by a DATA frame to the connection
+/// and, unless the stream has ended, to its stream.
+fn replenish<W: Write>(
+    writer: &mut W,
+    frame: &Frame,
+    stream_ended: bool
+) -> NetResult<()> {
+    let Ok(len @ 1..) = u32::try_from(frame.payload.len()) else {
+        return Ok(());
+    };
+
+    write_frame(writer, &Frame::window_update(0, len))?;
+
+    if !stream_ended {
+        write_frame(writer, &Frame::window_update(frame.stream_id, len))?;
+    }
+
+    Ok(())
+}
+
+/// Returns the big-endian `u32` at the start of a payload, such as an error
+/// code or a window increment, or zero if the payload is too short.
+fn read_u32(payload: &[u8]) -> u32 {
+    payload
+        .get(..4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u32::from_be_bytes)
+}
+
+/// Appends an HPACK integer with a prefix of `prefix` bits to `buf`, with
+/// `flags` set in the high bits of the first byte.
+fn encode_int(buf: &mut Vec<u8>, value: usize, prefix: u32, flags: u8) {
+    let max = (1 << prefix) - 1;
+
+    if value < max {
+        buf.push(flags | u8::try_from(value).unwrap_or(u8::MAX));
+        return;
+    }
+
+    buf.push(flags | u8::try_from(max).unwrap_or(u8::MAX));
+    let mut rest = value - max;
+
+    while rest >= 0x80 {
+        buf.push(0x80 | u8::try_from(rest & 0x7f).unwrap_or(0x7f));
+        rest >>= 7;
+    }
+
+    buf.push(u8::try_from(rest).unwrap_or(0x7f));
+}
+
+/// Decodes an HPACK integer with a prefix of `prefix` bits.
+fn decode_int(block: &[u8], pos: &mut usize, prefix: u32) -> NetResult<usize> {
+    let max = (1 << prefix) - 1;
+    let mut next = || {
+        let byte = block.get(*pos).copied();
+        *pos += 1;
+        byte.ok_or(NetError::Http2(COMPRESSION_ERROR))
+    };
+
+    let value = usize::from(next()?) & max;
+
+    if value < max {
+        return Ok(value);
+    }
+
+    let mut value = value;
+    let mut shift = 0;
+
+    loop {
+        let byte = next()?;
+        value += usize::from(byte & 0x7f) << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+
+        shift += 7;
+
+        // Larger values are not needed for any valid header block.
+        if shift > 28 {
+            return Err(NetError::Http2(COMPRESSION_ERROR));
+        }
+    }
+}
+
+/// Appends an HPACK string literal without Huffman coding to `buf`.
+fn encode_string(buf: &mut Vec<u8>, s: &str) {
+    encode_int(buf, s.len(), 7, 0x00);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Decodes an HPACK string literal, which may be Huffman-coded.
+fn decode_string(block: &[u8], pos: &mut usize) -> NetResult<String> {
+    let is_huffman = block
+        .get(*pos)
+        .ok_or(NetError::Http2(COMPRESSION_ERROR))?
+        & 0x80
+        != 0;
+
+    let len = decode_int(block, pos, 7)?;
+    let end = pos
+        .checked_add(len)
+        .filter(|&end| end <= block.len())
+        .ok_or(NetError::Http2(COMPRESSION_ERROR))?;
+
+    let bytes = &block[*pos..end];
+    *pos = end;
+
+    let bytes = if is_huffman {
+        huffman_decode(bytes)?
+    } else {
+        bytes.to_vec()
+    };
+
+    String::from_utf8(bytes).map_err(|_| NetError::Http2(COMPRESSION_ERROR))
+}
@@ -1,18 +1,35 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult, Write as _};
+use std::hash::{Hash, Hasher};
+use std::mem;
 use std::net::SocketAddr;
 use std::str::{self, FromStr};
+use std::time::SystemTime;
 
-use crate::{Body, NetError, NetResult, DEFAULT_NAME};
+use crate::{
+    Body, Method, NetError, NetResult, DEFAULT_NAME, MAX_HEADERS,
+};
 use crate::style::colors::{BLUE, CYAN, RESET};
-use crate::utils;
+use crate::{encoding, utils};
 
 pub mod names;
 pub mod values;
 
-pub use names::HeaderName;
+pub use names::{HeaderName, StandardHeaderName};
 pub use values::HeaderValue;
 
+// Commonly used header name constants. Constants for every standard header
+// name are in the `names` module.
+pub use names::{
+    ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, ALLOW, AUTHORIZATION,
+    CACHE_CONTROL, CONNECTION, CONTENT_DISPOSITION, CONTENT_ENCODING,
+    CONTENT_LENGTH, CONTENT_TYPE, COOKIE, DATE, ETAG, EXPECT, HOST,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, ORIGIN, RANGE,
+    REFERER, RETRY_AFTER, SERVER, SET_COOKIE, TRANSFER_ENCODING, UPGRADE,
+    USER_AGENT, VARY, WWW_AUTHENTICATE, X_FORWARDED_FOR,
+};
+
 /// A convenience type containing a single header's name and value.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Header(pub HeaderName, pub HeaderValue);
@@ -30,7 +47,8 @@ impl TryFrom<&[u8]> for Header {
 
     fn try_from(input: &[u8]) -> NetResult<Self> {
         // Split header into name and value slices at the ':'.
-        let (name, value) = utils::trim(input)
+        let input = utils::trim(input);
+        let (name, value) = input
             .iter()
             .position(|&b| b == b':')
             .ok_or(NetError::BadHeader)
@@ -38,11 +56,17 @@ impl TryFrom<&[u8]> for Header {
 
         // Remove the colon used above by `split_at` and trim whitespace.
         let value = match value.strip_prefix(b":") {
-            Some(strip) => utils::trim_start(strip),
+            Some(strip) => utils::trim(strip),
             None if value.is_empty() => &[][..],
-            None => utils::trim_start(value),
+            None => utils::trim(value),
         };
 
+        // Tolerate whitespace between the name and the colon.
+        let name = utils::trim_end(name);
+        if name.is_empty() {
+            return Err(NetError::BadHeaderName);
+        }
+
         let name = HeaderName::try_from(name)?;
         let value = HeaderValue::from(value);
 
@@ -70,13 +94,230 @@ impl Header {
     }
 }
 
+/// The value of a Retry-After header, given either as a delay in seconds or
+/// as an HTTP date.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RetryAfter {
+    Seconds(u64),
+    Date(SystemTime),
+}
+
+impl Display for RetryAfter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Seconds(secs) => write!(f, "{secs}"),
+            Self::Date(time) => {
+                write!(f, "{}", utils::format_http_date(*time))
+            },
+        }
+    }
+}
+
+impl FromStr for RetryAfter {
+    type Err = NetError;
+
+    fn from_str(s: &str) -> NetResult<Self> {
+        let s = s.trim();
+
+        if let Ok(secs) = s.parse::<u64>() {
+            return Ok(Self::Seconds(secs));
+        }
+
+        utils::parse_http_date(s)
+            .map(Self::Date)
+            .ok_or(NetError::BadHeader)
+    }
+}
+
+/// A Digest authentication challenge from a WWW-Authenticate header
+/// (RFC 7616).
+///
+/// Only the "auth" quality of protection is supported. A challenge without
+/// a qop parameter is answered in the older RFC 2069 form.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: String,
+    pub qop_auth: bool,
+}
+
+impl FromStr for DigestChallenge {
+    type Err = NetError;
+
+    /// Parses the Digest challenge from a WWW-Authenticate value, which may
+    /// also list challenges for other schemes.
+    fn from_str(s: &str) -> NetResult<Self> {
+        let mut params = BTreeMap::new();
+        let mut in_digest = false;
+
+        for item in split_params(s, ',') {
+            let mut item = item.trim();
+
+            // An item that starts with a bare token begins a new challenge.
+            if let Some((token, rest)) = item.split_once(' ') {
+                if !token.contains('=') && !rest.trim().starts_with('=') {
+                    in_digest = token.eq_ignore_ascii_case("digest");
+                    item = rest.trim();
+                }
+            } else if !item.is_empty() && !item.contains('=') {
+                in_digest = item.eq_ignore_ascii_case("digest");
+                continue;
+            }
+
+            if let (true, Some((name, value))) =
+                (in_digest, item.split_once('='))
+            {
+                let name = name.trim().to_ascii_lowercase();
+                params.entry(name).or_insert_with(|| unquote(value.trim()));
+            }
+        }
+
+        let algorithm = params
+            .remove("algorithm")
+            .unwrap_or_else(|| String::from("MD5"));
+
+        let supported = ["MD5", "MD5-sess", "SHA-256", "SHA-256-sess"]
+            .into_iter()
+            .find(|name| name.eq_ignore_ascii_case(&algorithm))
+            .ok_or(NetError::BadHeader)?;
+
+        let qop_auth = params.get("qop").is_some_and(|qop| {
+            qop.split(',').any(|qop| qop.trim().eq_ignore_ascii_case("auth"))
+        });
+
+        if params.contains_key("qop") && !qop_auth {
+            return Err(NetError::BadHeader);
+        }
+
+        Ok(Self {
+            realm: params.remove("realm").ok_or(NetError::BadHeader)?,
+            nonce: params.remove("nonce").ok_or(NetError::BadHeader)?,
+            opaque: params.remove("opaque"),
+            algorithm: supported.to_string(),
+            qop_auth
+        })
+    }
+}
+
+impl DigestChallenge {
+    /// Returns the Authorization header value that answers this challenge
+    /// for a request with the given `method` and request target `uri`.
+    ///
+    /// `cnonce` and the nonce count `nc` are only used when the challenge
+    /// offers the "auth" quality of protection.
+    #[must_use]
+    pub fn authorization(
+        &self,
+        username: &str,
+        password: &str,
+        method: Method,
+        uri: &str,
+        cnonce: &str,
+        nc: u32
+    ) -> String {
+        let nc = format!("{nc:08x}");
+
+        let mut ha1 =
+            self.hash(&format!("{username}:{}:{password}", self.realm));
+
+        if self.algorithm.ends_with("-sess") {
+            ha1 = self.hash(&format!("{ha1}:{}:{cnonce}", self.nonce));
+        }
+
+        let ha2 = self.hash(&format!("{method}:{uri}"));
+
+        let response = if self.qop_auth {
+            self.hash(&format!("{ha1}:{}:{nc}:{cnonce}:auth:{ha2}", self.nonce))
+        } else {
+            self.hash(&format!("{ha1}:{}:{ha2}", self.nonce))
+        };
+
+        let mut value = format!(
+            "Digest username={}, realm={}, nonce={}, uri={}, \
+            algorithm={}, response=\"{response}\"",
+            quote(username),
+            quote(&self.realm),
+            quote(&self.nonce),
+            quote(uri),
+            self.algorithm
+        );
+
+        if self.qop_auth {
+            let cnonce = quote(cnonce);
+            let _ = write!(value, ", qop=auth, nc={nc}, cnonce={cnonce}");
+        }
+
+        if let Some(opaque) = self.opaque.as_ref() {
+            let _ = write!(value, ", opaque={}", quote(opaque));
+        }
+
+        value
+    }
+
+    /// Returns the lowercase hex digest of `data` using the challenge's
+    /// hash algorithm.
+    fn hash(&self, data: &str) -> String {
+        let digest = if self.algorithm.starts_with("SHA-256") {
+            encoding::sha256(data.as_bytes()).to_vec()
+        } else {
+            encoding::md5(data.as_bytes()).to_vec()
+        };
+
+        encoding::hex_encode(&digest)
+    }
+}
+
 /// A mapping of `HeaderNames` to `HeaderValues`.
-#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Headers(pub BTreeMap<HeaderName, HeaderValue>);
+///
+/// The entries are sorted by name. Each entry also records the position
+/// at which its name was first inserted so that headers can be written in
+/// insertion order. Equality and ordering only consider the names and
+/// values.
+#[derive(Clone, Default)]
+pub struct Headers {
+    entries: BTreeMap<HeaderName, (usize, HeaderValue)>,
+    next_pos: usize,
+}
+
+impl PartialEq for Headers {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Headers {}
+
+impl PartialOrd for Headers {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Headers {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl Hash for Headers {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+impl Debug for Headers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
 
 impl Display for Headers {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        for (name, value) in &self.0 {
+        for (name, value) in self.iter() {
             writeln!(f, "{name}: {value}")?;
         }
 
@@ -96,11 +337,7 @@ impl TryFrom<&[u8]> for Headers {
     type Error = NetError;
 
     fn try_from(input: &[u8]) -> NetResult<Self> {
-        input.split_inclusive(|&b| b == b'\n')
-            .map(utils::trim)
-            .take_while(|line| !line.is_empty())
-            .map(Header::try_from)
-            .collect::<NetResult<Self>>()
+        Self::parse_lines(input.split_inclusive(|&b| b == b'\n'))
     }
 }
 
@@ -109,12 +346,13 @@ impl FromIterator<Header> for Headers {
     where
         I: IntoIterator<Item = Header>,
     {
-        let map = iter
-            .into_iter()
-            .map(Header::into_tuple)
-            .collect::<BTreeMap<HeaderName, HeaderValue>>();
+        let mut headers = Self::new();
 
-        Self(map)
+        for Header(name, value) in iter {
+            headers.insert(name, value);
+        }
+
+        headers
     }
 }
 
@@ -125,39 +363,211 @@ impl Headers {
         Self::default()
     }
 
+    /// Parses header lines from `lines` until the blank line that ends the
+    /// header section, which is also consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::TooManyHeaders` if there are more than
+    /// `MAX_HEADERS` header lines, or an error if a line cannot be parsed as
+    /// a `Header`.
+    pub fn parse_lines<'a, I>(lines: I) -> NetResult<Self>
+    where
+        I: Iterator<Item = &'a [u8]>,
+    {
+        let mut headers = Self::new();
+
+        let lines = lines
+            .map(utils::trim)
+            .take_while(|line| !line.is_empty());
+
+        for (num_headers, line) in lines.enumerate() {
+            if num_headers >= usize::from(MAX_HEADERS) {
+                return Err(NetError::TooManyHeaders);
+            }
+
+            let (name, value) = Header::try_from(line)?.into_tuple();
+            headers.insert(name, value);
+        }
+
+        Ok(headers)
+    }
+
     /// Returns the `HeaderValue` that is mapped to the given `HeaderName`,
     /// if present.
+    ///
+    /// Standard header names can be looked up by constant, which matches
+    /// the name in any letter case.
+    ///
+    /// ```
+    /// use rustnet::Headers;
+    /// use rustnet::headers::{CONTENT_TYPE, HOST};
+    ///
+    /// let headers = "Host: example.com\r\ncontent-type: text/plain\r\n\r\n"
+    ///     .parse::<Headers>()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(headers.get(&HOST).unwrap().as_str(), "example.com");
+    /// assert_eq!(headers.get(&CONTENT_TYPE).unwrap().as_str(), "text/plain");
+    /// assert!(headers.get(&rustnet::headers::ACCEPT).is_none());
+    /// ```
     #[must_use]
     pub fn get(&self, name: &HeaderName) -> Option<&HeaderValue> {
-        self.0.get(name)
+        self.entries.get(name).map(|(_, value)| value)
+    }
+
+    /// Returns the `HeaderValue` that is mapped to the given standard header
+    /// name, if present.
+    ///
+    /// This avoids the parsing and title-casing done when a `HeaderName` is
+    /// built from a string.
+    #[must_use]
+    pub fn get_standard(
+        &self,
+        name: StandardHeaderName
+    ) -> Option<&HeaderValue> {
+        self.get(&HeaderName::standard(name))
     }
 
     /// Returns the number of header field entries.
     #[must_use]
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.entries.len()
     }
 
     /// Returns true if there are no header entries.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.entries.is_empty()
     }
 
     /// Removes a header field entry from the `Headers` map.
     pub fn remove(&mut self, name: &HeaderName) {
-        self.0.remove(name);
+        self.entries.remove(name);
     }
 
     /// Returns true if the header name represented by `HeaderName` is present.
     #[must_use]
     pub fn contains(&self, name: &HeaderName) -> bool {
-        self.0.contains_key(name)
+        self.entries.contains_key(name)
+    }
+
+    /// Returns true if the given standard header name is present.
+    #[must_use]
+    pub fn contains_standard(&self, name: StandardHeaderName) -> bool {
+        self.entries.contains_key(&HeaderName::standard(name))
+    }
+
+    /// Removes the hop-by-hop headers, which only apply to a single
+    /// connection, along with any headers named in the Connection header.
+    pub fn remove_hop_by_hop(&mut self) {
+        use crate::headers::names::{
+            KEEP_ALIVE, PROXY_AUTHENTICATE, PROXY_AUTHORIZATION, TE, TRAILER,
+        };
+
+        if let Some(connection) = self.get(&CONNECTION) {
+            for name in connection.split_list() {
+                self.remove(&HeaderName::from(name.as_str()));
+            }
+        }
+
+        for name in [
+            CONNECTION,
+            KEEP_ALIVE,
+            PROXY_AUTHENTICATE,
+            PROXY_AUTHORIZATION,
+            TE,
+            TRAILER,
+            TRANSFER_ENCODING,
+            UPGRADE,
+        ] {
+            self.remove(&name);
+        }
+    }
+
+    /// Returns the parsed value of the Retry-After header, if present and
+    /// given as either a number of seconds or an HTTP date.
+    #[must_use]
+    pub fn retry_after(&self) -> Option<RetryAfter> {
+        self.get_standard(StandardHeaderName::RetryAfter)
+            .and_then(|value| value.as_str().parse::<RetryAfter>().ok())
+    }
+
+    /// Returns the Digest challenge from the WWW-Authenticate header, if
+    /// present and supported.
+    #[must_use]
+    pub fn digest_challenge(&self) -> Option<DigestChallenge> {
+        self.get_standard(StandardHeaderName::WwwAuthenticate)
+            .and_then(|value| value.as_str().parse().ok())
+    }
+
+    /// Returns the filename given by the Content-Disposition header, if
+    /// present.
+    ///
+    /// An RFC 5987 encoded `filename*` parameter is preferred over a plain
+    /// `filename` parameter. The filename is returned as sent, so callers
+    /// that write to disk should sanitize it first.
+    #[must_use]
+    pub fn content_disposition_filename(&self) -> Option<String> {
+        let value = self
+            .get_standard(StandardHeaderName::ContentDisposition)?
+            .as_str();
+
+        let mut filename = None;
+
+        for param in split_params(&value, ';').into_iter().skip(1) {
+            let Some((name, value)) = param.split_once('=') else {
+                continue;
+            };
+
+            match name.trim().to_ascii_lowercase().as_str() {
+                "filename*" => {
+                    if let Some(decoded) = decode_ext_value(value.trim()) {
+                        return Some(decoded);
+                    }
+                },
+                "filename" if filename.is_none() => {
+                    filename = Some(unquote(value.trim()));
+                },
+                _ => {},
+            }
+        }
+
+        filename
+    }
+
+    /// Returns an iterator over the header entries, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        self.entries.iter().map(|(name, (_, value))| (name, value))
+    }
+
+    /// Returns an iterator over the header entries in the order in which
+    /// their names were first inserted.
+    pub fn iter_ordered(
+        &self
+    ) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        let mut ordered = self.entries
+            .iter()
+            .map(|(name, (pos, value))| (*pos, name, value))
+            .collect::<Vec<_>>();
+
+        ordered.sort_unstable_by_key(|(pos, _, _)| *pos);
+        ordered.into_iter().map(|(_, name, value)| (name, value))
     }
 
     /// Appends the entries from another `Headers` collection to this one.
     pub fn append(&mut self, other: &mut Self) {
-        self.0.append(&mut other.0);
+        let mut entries = mem::take(&mut other.entries)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        entries.sort_unstable_by_key(|(_, (pos, _))| *pos);
+
+        for (name, (_, value)) in entries {
+            self.insert(name, value);
+        }
+
+        other.next_pos = 0;
     }
 
     /// Inserts a new header entry from the given name and value or updates
@@ -168,24 +578,86 @@ impl Headers {
 
     /// Inserts a new header entry from the given `HeaderName` and
     /// `HeaderValue` or updates the value if the key was already present.
-    pub fn insert(&mut self, name: HeaderName, value: HeaderValue) {
-        self.0.insert(name, value);
+    ///
+    /// Any CR, LF, and NUL bytes are removed from the value, and an entry
+    /// whose name is not a valid token is ignored, so that neither can
+    /// inject headers into a message. Use `try_insert` to get an error
+    /// instead.
+    pub fn insert(&mut self, name: HeaderName, mut value: HeaderValue) {
+        if !name.is_valid() {
+            return;
+        }
+
+        if !value.is_valid() {
+            value.sanitize();
+        }
+
+        if let Some((_, existing)) = self.entries.get_mut(&name) {
+            *existing = value;
+        } else {
+            self.entries.insert(name, (self.next_pos, value));
+            self.next_pos += 1;
+        }
+    }
+
+    /// Inserts a new header entry like `insert`, but rejects an invalid
+    /// name or value instead of ignoring or sanitizing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadHeaderName` if the name is not a valid token,
+    /// or `NetError::BadHeaderValue` if the value contains a CR, LF, or NUL
+    /// byte.
+    pub fn try_insert(
+        &mut self,
+        name: HeaderName,
+        value: HeaderValue
+    ) -> NetResult<()> {
+        if !name.is_valid() {
+            return Err(NetError::BadHeaderName);
+        }
+
+        if !value.is_valid() {
+            return Err(NetError::BadHeaderValue);
+        }
+
+        self.insert(name, value);
+        Ok(())
+    }
+
+    /// Checks that every entry would be accepted by `try_insert`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadHeaderName` or `NetError::BadHeaderValue` for
+    /// the first invalid entry.
+    pub fn validate(&self) -> NetResult<()> {
+        for (name, value) in self.iter() {
+            if !name.is_valid() {
+                return Err(NetError::BadHeaderName);
+            }
+
+            if !value.is_valid() {
+                return Err(NetError::BadHeaderValue);
+            }
+        }
+
+        Ok(())
     }
 
     /// Inserts a sensible set default of request headers.
+    ///
+    /// The Accept header is not included so that a `Client` can apply its
+    /// own default. `Connection` falls back to "*/*" when sending.
     pub fn default_request_headers(
         &mut self,
         body: &Body,
         remote_addr: Option<SocketAddr>
     ) {
         use crate::headers::names::{
-            ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, DATE, HOST, USER_AGENT,
+            CONTENT_LENGTH, CONTENT_TYPE, DATE, HOST, USER_AGENT,
         };
 
-        if !self.contains(&ACCEPT) {
-            self.insert(ACCEPT, "*/*".into());
-        }
-
         if !self.contains(&CONTENT_LENGTH) && !body.is_empty() {
             self.insert(CONTENT_LENGTH, body.len().into());
         }
@@ -218,6 +690,7 @@ impl Headers {
     pub fn default_response_headers(&mut self, body: &Body) {
         use crate::headers::names::{
             CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, DATE, SERVER,
+            TRANSFER_ENCODING,
         };
 
         if !self.contains(&CACHE_CONTROL) {
@@ -229,7 +702,13 @@ impl Headers {
             }
         }
 
-        if !self.contains(&CONTENT_LENGTH) && !body.is_empty() {
+        // Chunked messages and event streams must not have a Content-Length
+        // header.
+        if !self.contains(&CONTENT_LENGTH)
+            && !self.contains(&TRANSFER_ENCODING)
+            && !body.is_empty()
+            && !body.is_event_stream()
+        {
             self.insert(CONTENT_LENGTH, body.len().into());
         }
 
@@ -255,7 +734,7 @@ impl Headers {
     pub fn to_color_string(&self) -> String {
         let mut headers = String::new();
 
-        for (name, value) in &self.0 {
+        for (name, value) in self.iter() {
             let header = format!(
                 "{BLUE}{name}{RESET}: {CYAN}{value}{RESET}\n"
             );
@@ -266,3 +745,88 @@ impl Headers {
         headers
     }
 }
+
+/// Splits a header value into its `sep`-separated parameters, ignoring
+/// separators within quoted strings.
+fn split_params(value: &str, sep: char) -> Vec<&str> {
+    let mut params = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ if c == sep && !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+
+    params.push(&value[start..]);
+    params
+}
+
+/// Returns `value` as a quoted string, escaping quotes and backslashes.
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Removes the quotes and backslash escapes from a quoted string. Other
+/// values are returned unchanged.
+fn unquote(value: &str) -> String {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+
+    let mut unquoted = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unquoted.extend(chars.next()),
+            _ => unquoted.push(c),
+        }
+    }
+
+    unquoted
+}
+
+/// Decodes an RFC 5987 extended value with the form
+/// "charset'language'percent-encoded-value".
+///
+/// Only the UTF-8 and ISO-8859-1 charsets are supported.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) =
+        (parts.next()?, parts.next()?, parts.next()?);
+
+    let bytes = utils::percent_decode(encoded);
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
@@ -33,13 +33,32 @@ impl TryFrom<&[u8]> for HeaderName {
     type Error = NetError;
 
     fn try_from(name: &[u8]) -> NetResult<Self> {
-        str::from_utf8(name)
+        let name: Self = str::from_utf8(name)
             .map_err(|_| NetError::BadHeaderName)
-            .map(Into::into)
+            .map(Into::into)?;
+
+        if name.is_valid() {
+            Ok(name)
+        } else {
+            Err(NetError::BadHeaderName)
+        }
+    }
+}
+
+impl From<StandardHeaderName> for HeaderName {
+    fn from(name: StandardHeaderName) -> Self {
+        Self::standard(name)
     }
 }
 
 impl HeaderName {
+    /// Returns the `HeaderName` for a `StandardHeaderName` without any string
+    /// parsing. The result is equal to the same name parsed from a string.
+    #[must_use]
+    pub const fn standard(name: StandardHeaderName) -> Self {
+        Self { inner: HeaderNameInner::Standard(name) }
+    }
+
     /// Returns the `HeaderName` as a string slice.
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -51,9 +70,25 @@ impl HeaderName {
     pub fn as_bytes(&self) -> &[u8] {
         self.inner.as_bytes()
     }
+
+    /// Returns true if the name is a token, so that it cannot end the
+    /// header line early or be mistaken for part of the value.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        match self.inner {
+            HeaderNameInner::Standard(_) => true,
+            HeaderNameInner::Custom(ref name) => {
+                utils::is_token(name.as_bytes())
+            },
+        }
+    }
 }
 
 /// A representation of header names as either standard or custom.
+///
+/// Custom names keep the letter case they were given, but header names are
+/// case-insensitive (RFC 9110), so equality, ordering, and hashing ignore
+/// ASCII letter case.
 #[derive(Clone, Debug)]
 pub enum HeaderNameInner {
     Standard(StandardHeaderName),
@@ -62,7 +97,7 @@ pub enum HeaderNameInner {
 
 impl PartialEq for HeaderNameInner {
     fn eq(&self, other: &Self) -> bool {
-        self.as_str().eq(other.as_str())
+        self.as_str().eq_ignore_ascii_case(other.as_str())
     }
 }
 
@@ -76,13 +111,21 @@ impl PartialOrd for HeaderNameInner {
 
 impl Ord for HeaderNameInner {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.as_str().cmp(other.as_str())
+        // Compare byte by byte rather than allocating lowercase copies,
+        // since names are compared on every map lookup.
+        let lhs = self.as_bytes().iter().map(u8::to_ascii_lowercase);
+        let rhs = other.as_bytes().iter().map(u8::to_ascii_lowercase);
+        lhs.cmp(rhs)
     }
 }
 
 impl Hash for HeaderNameInner {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.as_str().hash(state);
+        for byte in self.as_bytes() {
+            state.write_u8(byte.to_ascii_lowercase());
+        }
+
+        state.write_u8(0xff);
     }
 }
 
@@ -95,11 +138,7 @@ impl Display for HeaderNameInner {
 impl From<&str> for HeaderNameInner {
     fn from(inner: &str) -> Self {
         StandardHeaderName::from_str(inner).map_or_else(
-            |_| {
-                // Store custom header names in titlecase.
-                let inner = utils::to_titlecase(inner.trim());
-                Self::Custom(inner)
-            },
+            |_| Self::Custom(inner.trim().to_string()),
             Self::Standard
         )
     }
@@ -188,8 +227,8 @@ macro_rules! impl_standard_header_names {
             }
         }
 
-        /// `HeaderName` constants for each `StandardHeaderName` variant.
         $(
+            #[doc = concat!("The \"", $text, "\" header name.")]
             pub const $constant: HeaderName = HeaderName {
                 inner: HeaderNameInner::Standard(
                     StandardHeaderName::$variant
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::str;
 
 use crate::utils;
 
@@ -49,4 +50,42 @@ impl HeaderValue {
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_slice()
     }
+
+    /// Returns true if the value contains no CR, LF, or NUL bytes, any of
+    /// which could end the header line early and inject new headers or a
+    /// new message.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        !self.0.iter().any(|&b| matches!(b, b'\r' | b'\n' | b'\0'))
+    }
+
+    /// Removes any CR, LF, and NUL bytes from the value.
+    pub fn sanitize(&mut self) {
+        self.0.retain(|&b| !matches!(b, b'\r' | b'\n' | b'\0'));
+    }
+
+    /// Returns the `HeaderValue` parsed as an unsigned integer, if it
+    /// consists only of ASCII digits after trimming whitespace.
+    #[must_use]
+    pub fn as_u64(&self) -> Option<u64> {
+        let digits = utils::trim(self.as_bytes());
+
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+
+        str::from_utf8(digits).ok()?.parse::<u64>().ok()
+    }
+
+    /// Returns the items of a comma-separated list header value with
+    /// surrounding whitespace trimmed and empty items removed.
+    #[must_use]
+    pub fn split_list(&self) -> Vec<String> {
+        self.as_str()
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    }
 }
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::num::NonZeroU16;
 use std::str::{self, FromStr};
@@ -6,7 +7,12 @@ use std::str::{self, FromStr};
 use crate::{NetError, NetResult, utils};
 
 /// The HTTP method.
-#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+///
+/// Methods are ordered independently of their declaration order so that
+/// `Route` sets and route tables are sorted the same way across releases:
+/// GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS, TRACE, and CONNECT come
+/// first, followed by SHUTDOWN and then the ANY wildcard.
+#[derive(Clone, Copy, Hash, Eq, PartialEq)]
 pub enum Method {
     /// Wildcard variant which represents any method value.
     Any,
@@ -50,6 +56,18 @@ impl Debug for Method {
     }
 }
 
+impl PartialOrd for Method {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Method {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_rank().cmp(&other.sort_rank())
+    }
+}
+
 impl FromStr for Method {
     type Err = NetError;
 
@@ -111,6 +129,23 @@ impl Method {
         }
     }
 
+    /// Returns the position of this `Method` in the documented sort order.
+    const fn sort_rank(self) -> u8 {
+        match self {
+            Self::Get => 0,
+            Self::Head => 1,
+            Self::Post => 2,
+            Self::Put => 3,
+            Self::Patch => 4,
+            Self::Delete => 5,
+            Self::Options => 6,
+            Self::Trace => 7,
+            Self::Connect => 8,
+            Self::Shutdown => 9,
+            Self::Any => 10,
+        }
+    }
+
     /// Returns the `Method` as a bytes slice.
     #[must_use]
     pub const fn as_bytes(&self) -> &'static [u8] {
@@ -249,6 +284,15 @@ impl TryFrom<u16> for Status {
     }
 }
 
+/// Returns the reason phrase of a status line such as "404 Not Found" by
+/// skipping the three-digit code and the space that follows it.
+const fn strip_code(text: &'static str) -> &'static str {
+    match str::from_utf8(text.as_bytes().split_at(4).1) {
+        Ok(reason) => reason,
+        Err(_) => text,
+    }
+}
+
 macro_rules! impl_status_methods {
     ($( $num:literal, $text:literal, $bytes:literal; )+) => {
         impl Status {
@@ -270,11 +314,12 @@ macro_rules! impl_status_methods {
                 }
             }
 
-            /// Returns a reason phrase for this `Status`, if possible.
+            /// Returns the reason phrase for this `Status` without the status
+            /// code (e.g. "Not Found" for 404), if the code is a known one.
             #[must_use]
             pub const fn msg(&self) -> Option<&'static str> {
                 match self.code() {
-                    $( $num => Some($text), )+
+                    $( $num => Some(strip_code($text)), )+
                     _ => None,
                 }
             }
@@ -285,6 +330,16 @@ macro_rules! impl_status_methods {
                 self.0.get()
             }
 
+            /// Returns the class of the status code, which is its leading digit
+            /// (e.g. 4 for "404 Not Found").
+            #[must_use]
+            pub const fn class(&self) -> u8 {
+                // Status codes are at most 999, so the class is at most 9.
+                #[allow(clippy::cast_possible_truncation)]
+                let class = (self.code() / 100) as u8;
+                class
+            }
+
             /// Returns true if the status code is greater than or equal to 100 and
             /// less than 200.
             #[must_use]
@@ -302,10 +357,18 @@ macro_rules! impl_status_methods {
             /// Returns true if the status code is greater than or equal to 300 and
             /// less than 400.
             #[must_use]
-            pub const fn is_redirection(&self) -> bool {
+            pub const fn is_redirect(&self) -> bool {
                 matches!(self.code(), 300..=399)
             }
 
+            /// Returns true if the status code is greater than or equal to
+            /// 300 and less than 400.
+            #[must_use]
+            #[deprecated(note = "use `Status::is_redirect` instead")]
+            pub const fn is_redirection(&self) -> bool {
+                self.is_redirect()
+            }
+
             /// Returns true if the status code is greater than or equal to 400 and
             /// less than 500.
             #[must_use]
@@ -1,18 +1,45 @@
-use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::io::{
-    BufRead, BufReader, BufWriter, Read, Result as IoResult, Write,
+    self, BufRead, BufReader, BufWriter, ErrorKind, IoSlice, Read,
+    Result as IoResult, Write, sink,
 };
-use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::{
-    Body, Headers, Method, NetError, NetResult, Request, Response, Status,
-    UriPath, Version, MAX_HEADERS, READER_BUFSIZE, WRITER_BUFSIZE, utils,
+    Body, BodyReader, HeaderName, HeaderValue, Headers, Method, NetError,
+    NetResult, Request, Response, Status, UriPath, Version, MAX_HEADERS,
+    MAX_URI_LEN, READER_BUFSIZE, WRITER_BUFSIZE, utils,
 };
-use crate::headers::names::{CONNECTION, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::headers::names::{
+    ACCEPT, CONNECTION, CONTENT_LENGTH, DATE, EXPECT, HOST,
+    SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION,
+    TRANSFER_ENCODING, UPGRADE, USER_AGENT,
+};
+use crate::response::parse_reason;
 use crate::style::colors::{RED, RESET};
+#[cfg(feature = "http2")]
+use crate::h2;
+
+/// The number of bytes allowed in a request line in addition to the URI,
+/// which covers the method, the version, and the separators.
+const REQUEST_LINE_OVERHEAD: usize = 32;
+
+/// How long to wait in total for unread request bytes after sending an
+/// error.
+const LINGER_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The maximum number of unread request bytes to discard after sending an
+/// error.
+const LINGER_MAX_BYTES: u64 = 64 * 1024;
 
 /// A trait for printing CLI argument errors to the terminal.
 pub trait WriteCliError {
@@ -41,13 +68,409 @@ pub trait WriteCliError {
     }
 }
 
-/// Represents the TCP connection between a client and a server.
+/// A stream of server-sent events that is written to a `Connection`.
+///
+/// An `EventStream` is handed to the `EventHandler` of a
+/// `Target::EventStream` route after the response head has been sent. The
+/// stream ends when the handler returns, so serving it occupies a worker
+/// thread for the stream's whole lifetime.
+#[derive(Debug)]
+pub struct EventStream<'a> {
+    pub conn: &'a mut Connection,
+}
+
+impl EventStream<'_> {
+    /// Writes an event with the given name and data, followed by a blank
+    /// line, and flushes it to the client.
+    ///
+    /// The "event" line is omitted if `event` is empty, and each line of
+    /// `data` is written as its own "data" line. Lines of `data` may end
+    /// with CRLF, LF, or a lone CR, as in the event stream format.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `event` contains a CR or LF, which would end
+    /// the "event" line early, in which case nothing is written. An error
+    /// is also returned if writing to the underlying `TcpStream` fails,
+    /// such as when the client has disconnected.
+    pub fn send(&mut self, event: &str, data: &str) -> NetResult<()> {
+        if event.contains(['\r', '\n']) {
+            return Err(NetError::Other("Invalid event name.".into()));
+        }
+
+        let mut buf = String::with_capacity(event.len() + data.len() + 16);
+
+        if !event.is_empty() {
+            buf.push_str("event: ");
+            buf.push_str(event);
+            buf.push('\n');
+        }
+
+        let lines = data.split('\n').flat_map(|line| {
+            line.strip_suffix('\r').unwrap_or(line).split('\r')
+        });
+
+        for line in lines {
+            buf.push_str("data: ");
+            buf.push_str(line);
+            buf.push('\n');
+        }
+
+        buf.push('\n');
+
+        self.conn.writer.write_all(buf.as_bytes())?;
+        self.conn.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The socket underlying a `Connection`.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Self {
+        Self::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Stream {
+    fn from(stream: UnixStream) -> Self {
+        Self::Unix(stream)
+    }
+}
+
+impl TryFrom<Stream> for TcpStream {
+    type Error = Stream;
+
+    /// Returns the `TcpStream` of a TCP `Stream`, or the `Stream` itself if
+    /// it is not a TCP socket.
+    fn try_from(stream: Stream) -> Result<Self, Stream> {
+        match stream {
+            Stream::Tcp(stream) => Ok(stream),
+            #[cfg(unix)]
+            Stream::Unix(_) => Err(stream),
+        }
+    }
+}
+
+impl Stream {
+    /// Returns a new handle to the same socket.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if cloning the socket fails.
+    pub fn try_clone(&self) -> IoResult<Self> {
+        match self {
+            Self::Tcp(stream) => stream.try_clone().map(Self::Tcp),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.try_clone().map(Self::Unix),
+        }
+    }
+
+    /// Returns the read timeout of the socket.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading the socket option fails.
+    pub fn read_timeout(&self) -> IoResult<Option<Duration>> {
+        match self {
+            Self::Tcp(stream) => stream.read_timeout(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read_timeout(),
+        }
+    }
+
+    /// Sets the read timeout of the socket.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `timeout` is zero.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    /// Sets the write timeout of the socket.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `timeout` is zero.
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    /// Sets the `TCP_NODELAY` option of a TCP socket, which sends small
+    /// writes immediately instead of coalescing them. Unix domain sockets
+    /// are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if setting the option fails.
+    pub fn set_nodelay(&self, nodelay: bool) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_nodelay(nodelay),
+            #[cfg(unix)]
+            Self::Unix(_) => Ok(()),
+        }
+    }
+
+    /// Shuts down the read half, the write half, or both halves of the
+    /// socket.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the shutdown call fails.
+    pub fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.shutdown(how),
+        }
+    }
+
+    /// Returns the socket address of the remote peer.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `ErrorKind::Unsupported` is returned for a Unix
+    /// domain socket, which has no IP address.
+    pub fn peer_addr(&self) -> IoResult<SocketAddr> {
+        match self {
+            Self::Tcp(stream) => stream.peer_addr(),
+            #[cfg(unix)]
+            Self::Unix(_) => Err(ErrorKind::Unsupported.into()),
+        }
+    }
+
+    /// Returns the path of the remote end of a Unix domain socket, if it is
+    /// bound to one.
+    #[must_use]
+    pub fn unix_peer_path(&self) -> Option<PathBuf> {
+        match self {
+            Self::Tcp(_) => None,
+            #[cfg(unix)]
+            Self::Unix(stream) => stream
+                .peer_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(Path::to_path_buf)),
+        }
+    }
+
+    /// Returns true if this is a Unix domain socket.
+    #[must_use]
+    pub const fn is_unix(&self) -> bool {
+        match self {
+            Self::Tcp(_) => false,
+            #[cfg(unix)]
+            Self::Unix(_) => true,
+        }
+    }
+}
+
+/// A callback that receives the number of body bytes read so far and the
+/// expected total, if known.
+pub type ProgressFn = Box<dyn FnMut(u64, Option<u64>) + Send>;
+
+/// Holds the `ProgressFn` of a `Connection`.
+///
+/// The callback is kept behind a `Mutex` so that a `Connection` stays
+/// `Sync`, but it is only called through `&mut self`, so it is never
+/// locked.
+pub struct Progress(pub Mutex<ProgressFn>);
+
+impl Debug for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Progress({:#x})", self.addr())
+    }
+}
+
+impl Progress {
+    /// Returns a new `Progress` from the callback `f`.
+    #[must_use]
+    pub fn new<F>(f: F) -> Self
+    where
+        F: FnMut(u64, Option<u64>) + Send + 'static,
+    {
+        let f: ProgressFn = Box::new(f);
+        Self(Mutex::new(f))
+    }
+
+    /// Calls the callback with the number of bytes read and the total.
+    pub fn call(&mut self, bytes_read: u64, total: Option<u64>) {
+        let f = self.0.get_mut().unwrap_or_else(PoisonError::into_inner);
+        f(bytes_read, total);
+    }
+
+    /// Returns the address of the callback slot.
+    fn addr(&self) -> usize {
+        std::ptr::from_ref(&self.0).cast::<()>() as usize
+    }
+}
+
+/// A function that returns the current time, used in place of
+/// `SystemTime::now` for the Date header of responses.
+pub type ClockFn = Arc<dyn Fn() -> SystemTime + Send + Sync>;
+
+/// A shared `ClockFn`.
+///
+/// Clones share the same function, so a clock can be handed to each
+/// connection cheaply. Clocks are compared by address.
+#[derive(Clone)]
+pub struct Clock(pub ClockFn);
+
+impl Debug for Clock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Clock({:#x})", self.addr())
+    }
+}
+
+impl PartialEq for Clock {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl Eq for Clock {}
+
+impl Clock {
+    /// Returns a new `Clock` from the function `f`.
+    #[must_use]
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    /// Returns the current time according to this clock.
+    #[must_use]
+    pub fn now(&self) -> SystemTime {
+        (self.0)()
+    }
+
+    /// Returns the address of the shared function.
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.0).cast::<()>() as usize
+    }
+}
+
+/// The settings of a `Connection` that apply to only one side of it.
+///
+/// If `send_user_agent` is unset, the default User-Agent header is left out
+/// of requests, and likewise for the default Accept and Host headers with
+/// `send_accept` and `send_host`. If `on_progress` is set, it is invoked as
+/// message body bytes are read. If `deadline` is set, the socket timeouts
+/// are set to the time left before it ahead of each line and each body
+/// read, so that reads and writes fail once it passes. These are used by
+/// clients. If `clock` is set, it supplies the Date header of the responses
+/// that are sent, replacing any Date header they already have. It is used
+/// by servers.
+#[derive(Debug)]
+pub struct ConnectionOptions {
+    pub send_user_agent: bool,
+    pub send_accept: bool,
+    pub send_host: bool,
+    pub on_progress: Option<Progress>,
+    pub deadline: Option<Instant>,
+    pub clock: Option<Clock>,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self {
+            send_user_agent: true,
+            send_accept: true,
+            send_host: true,
+            on_progress: None,
+            deadline: None,
+            clock: None,
+        }
+    }
+}
+
+/// Sets the read and write timeouts of `stream` to the time left before
+/// `deadline`, if it is set, or returns `NetError::Timeout` if it has passed.
+fn arm_deadline(stream: &Stream, deadline: Option<Instant>) -> NetResult<()> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+
+    let remaining = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or(NetError::Timeout)?;
+
+    stream.set_read_timeout(Some(remaining))?;
+    stream.set_write_timeout(Some(remaining))?;
+    Ok(())
+}
+
+/// Represents the connection between a client and a server.
+///
+/// The settings that only a client or only a server uses are kept together
+/// in `options`, so that they can be copied to a new `Connection` at once.
+/// `body_pending` is set while the body of the last request head that was
+/// read is still unread, and `req_method` holds the method of that request.
+/// `close_after_response` is set when the server decides that the
+/// connection must be closed after the response to that request, and it is
+/// cleared when the next request head is read. `pending_head` holds the
+/// head of a final response that arrived while a client waited for
+/// "100 Continue", until it is returned by `recv_response_head`.
+/// Both addresses of a connection over a Unix domain socket are
+/// `UNIX_SOCKET_ADDR`, since such sockets have no IP address.
 #[derive(Debug)]
 pub struct Connection {
     pub local_addr: SocketAddr,
     pub remote_addr: SocketAddr,
-    pub reader: BufReader<TcpStream>,
-    pub writer: BufWriter<TcpStream>,
+    pub reader: BufReader<Stream>,
+    pub writer: BufWriter<Stream>,
+    pub ordered_headers: bool,
+    pub body_pending: bool,
+    pub req_method: Option<Method>,
+    pub close_after_response: bool,
+    pub pending_head: Option<Response>,
+    pub options: ConnectionOptions,
 }
 
 impl Display for Connection {
@@ -55,8 +478,18 @@ impl Display for Connection {
         writeln!(f, "Connection {{")?;
         writeln!(f, "    local_addr: {},", self.local_addr)?;
         writeln!(f, "    remote_addr: {},", self.remote_addr)?;
-        writeln!(f, "    reader: BufReader {{ TcpStream {{ ... }} }},")?;
-        writeln!(f, "    writer: BufWriter {{ TcpStream {{ ... }} }},")?;
+        writeln!(f, "    reader: BufReader {{ Stream {{ ... }} }},")?;
+        writeln!(f, "    writer: BufWriter {{ Stream {{ ... }} }},")?;
+        writeln!(f, "    ordered_headers: {},", self.ordered_headers)?;
+        writeln!(f, "    body_pending: {},", self.body_pending)?;
+        writeln!(f, "    req_method: {:?},", self.req_method)?;
+        writeln!(
+            f,
+            "    close_after_response: {},",
+            self.close_after_response
+        )?;
+        writeln!(f, "    pending_head: {:?},", self.pending_head)?;
+        writeln!(f, "    options: {:?},", self.options)?;
         write!(f, "}}")?;
         Ok(())
     }
@@ -96,9 +529,7 @@ impl TryFrom<&str> for Connection {
     type Error = NetError;
 
     fn try_from(addr: &str) -> NetResult<Self> {
-        TcpStream::connect(addr)
-            .map_err(|e| NetError::IoError(e.kind()))
-            .and_then(Self::try_from)
+        utils::connect(addr).and_then(Self::try_from)
     }
 }
 
@@ -118,16 +549,117 @@ impl TryFrom<(TcpStream, SocketAddr)> for Connection {
         (stream, remote_addr): (TcpStream, SocketAddr)
     ) -> NetResult<Self> {
         let local_addr = stream.local_addr()?;
+        Self::from_stream(Stream::Tcp(stream), local_addr, remote_addr)
+    }
+}
+
+#[cfg(unix)]
+impl TryFrom<UnixStream> for Connection {
+    type Error = NetError;
+
+    fn try_from(stream: UnixStream) -> NetResult<Self> {
+        let stream = Stream::Unix(stream);
+        Self::from_stream(stream, UNIX_SOCKET_ADDR, UNIX_SOCKET_ADDR)
+    }
+}
+
+/// The read half of a `Connection`, returned by `Connection::split`.
+#[derive(Debug)]
+pub struct ConnectionReader {
+    pub local_addr: SocketAddr,
+    pub remote_addr: SocketAddr,
+    pub reader: BufReader<Stream>,
+}
+
+impl Read for ConnectionReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl BufRead for ConnectionReader {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.reader.consume(amt);
+    }
+}
+
+/// The write half of a `Connection`, returned by `Connection::split`.
+#[derive(Debug)]
+pub struct ConnectionWriter {
+    pub local_addr: SocketAddr,
+    pub remote_addr: SocketAddr,
+    pub writer: BufWriter<Stream>,
+}
+
+impl Write for ConnectionWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.writer.write_all(buf)
+    }
+}
+
+impl ConnectionWriter {
+    /// Flushes any buffered bytes and then shuts down the write half of
+    /// the socket, so the peer reads an EOF.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if flushing or the shutdown call fails.
+    pub fn shutdown(&mut self) -> NetResult<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().shutdown(Shutdown::Write)?;
+        Ok(())
+    }
+}
+
+/// The placeholder address of both ends of a Unix domain socket connection.
+///
+/// Every Unix domain socket peer has this address, so it does not tell
+/// clients apart. Servers do not apply their rate limit to such connections
+/// unless a trusted X-Forwarded-For header names the client.
+pub const UNIX_SOCKET_ADDR: SocketAddr =
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
 
+impl Connection {
+    /// Returns a new `Connection` that reads from and writes to `stream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if cloning the `Stream` fails.
+    pub fn from_stream(
+        stream: Stream,
+        local_addr: SocketAddr,
+        remote_addr: SocketAddr
+    ) -> NetResult<Self> {
         let clone = stream.try_clone()?;
         let reader = BufReader::with_capacity(READER_BUFSIZE, clone);
         let writer = BufWriter::with_capacity(WRITER_BUFSIZE, stream);
 
-        Ok(Self { local_addr, remote_addr, reader, writer })
+        Ok(Self {
+            local_addr,
+            remote_addr,
+            reader,
+            writer,
+            ordered_headers: false,
+            body_pending: false,
+            req_method: None,
+            close_after_response: false,
+            pending_head: None,
+            options: ConnectionOptions::default(),
+        })
     }
-}
 
-impl Connection {
     /// Returns the IP address for the remote half of the `TcpStream`.
     #[must_use]
     pub const fn remote_ip(&self) -> IpAddr {
@@ -152,14 +684,144 @@ impl Connection {
         self.local_addr.port()
     }
 
+    /// Sets the read timeout of the underlying `TcpStream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `timeout` is zero.
+    pub fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>
+    ) -> NetResult<()> {
+        self.reader.get_ref().set_read_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Sets the write timeout of the underlying `TcpStream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `timeout` is zero.
+    pub fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>
+    ) -> NetResult<()> {
+        self.writer.get_ref().set_write_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Sets the read and write timeouts of the underlying `TcpStream` to the
+    /// time left before `options.deadline`, if it is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::Timeout` if the deadline has passed, or an error
+    /// if setting the timeouts fails.
+    pub fn arm_deadline(&self) -> NetResult<()> {
+        arm_deadline(self.reader.get_ref(), self.options.deadline)
+    }
+
+    /// Sets the `TCP_NODELAY` option of the underlying `TcpStream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if setting the option fails.
+    pub fn set_nodelay(&self, nodelay: bool) -> NetResult<()> {
+        self.reader.get_ref().set_nodelay(nodelay)?;
+        Ok(())
+    }
+
+    /// Flushes any buffered output and returns the underlying `Stream`.
+    ///
+    /// Input that has already been read into the buffer is discarded. Use
+    /// `TcpStream::try_from` to get the socket of a TCP connection.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if flushing the buffered output fails.
+    pub fn into_inner(self) -> NetResult<Stream> {
+        self.writer
+            .into_inner()
+            .map_err(|e| NetError::Write(e.into_error().into()))
+    }
+
+    /// Completes a WebSocket opening handshake (RFC 6455) for the given
+    /// upgrade `Request` and returns the underlying `Stream` so that the
+    /// caller can exchange WebSocket frames over it.
+    ///
+    /// A "101 Switching Protocols" response is sent with the
+    /// Sec-WebSocket-Accept value for the request's Sec-WebSocket-Key.
+    /// Clients do not send frames until they receive this response, so no
+    /// buffered input is lost when the stream is returned. Use
+    /// `TcpStream::try_from` to get the socket of a TCP connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadRequest` if the request is not a WebSocket
+    /// upgrade request, which must be a GET request with an Upgrade header
+    /// of "websocket", a Sec-WebSocket-Key header, and a
+    /// Sec-WebSocket-Version of 13. An error is also returned if writing the
+    /// response fails.
+    pub fn accept_websocket(mut self, req: &Request) -> NetResult<Stream> {
+        let has_token = |name, token: &str| {
+            req.headers.get(name).is_some_and(|value| {
+                value
+                    .split_list()
+                    .iter()
+                    .any(|item| item.eq_ignore_ascii_case(token))
+            })
+        };
+
+        let key = req.headers
+            .get(&SEC_WEBSOCKET_KEY)
+            .map(|key| key.as_str().trim().to_string())
+            .filter(|key| !key.is_empty());
+
+        let is_upgrade = req.method == Method::Get
+            && has_token(&UPGRADE, "websocket")
+            && has_token(&CONNECTION, "upgrade")
+            && has_token(&SEC_WEBSOCKET_VERSION, "13");
+
+        let Some(key) = key.filter(|_| is_upgrade) else {
+            return Err(NetError::BadRequest);
+        };
+
+        let mut headers = Headers::new();
+        headers.insert(UPGRADE, "websocket".into());
+        headers.insert(CONNECTION, "Upgrade".into());
+        headers.insert(
+            SEC_WEBSOCKET_ACCEPT,
+            utils::websocket_accept(&key).as_str().into()
+        );
+
+        let status = Status::try_from(101)?;
+        self.write_status_line(&Version::OneDotOne, &status)?;
+        self.write_headers(&headers)?;
+        self.into_inner()
+    }
+
     /// Returns a clone of this `Connection`.
     ///
+    /// Bytes already buffered by this `Connection`'s reader are not copied
+    /// to the clone. Use `split` to read and write from separate threads
+    /// without losing them. The `pending_head` and the `on_progress`
+    /// callback are not copied either.
+    ///
     /// # Errors
     ///
     /// An error is returned if cloning of the contained `TcpStream` fails.
     pub fn try_clone(&self) -> NetResult<Self> {
         let local_addr = self.local_addr;
         let remote_addr = self.remote_addr;
+        let ordered_headers = self.ordered_headers;
+        let body_pending = self.body_pending;
+        let req_method = self.req_method;
+        let close_after_response = self.close_after_response;
+        let options = ConnectionOptions {
+            on_progress: None,
+            clock: self.options.clock.clone(),
+            ..self.options
+        };
 
         let reader = self
             .reader
@@ -173,7 +835,42 @@ impl Connection {
             .try_clone()
             .map(|stream| BufWriter::with_capacity(WRITER_BUFSIZE, stream))?;
 
-        Ok(Self { local_addr, remote_addr, reader, writer })
+        Ok(Self {
+            local_addr,
+            remote_addr,
+            reader,
+            writer,
+            ordered_headers,
+            body_pending,
+            req_method,
+            close_after_response,
+            pending_head: None,
+            options,
+        })
+    }
+
+    /// Splits this `Connection` into independent read and write halves for
+    /// full-duplex protocols, such as WebSocket or tunnels, where one
+    /// thread reads while another writes.
+    ///
+    /// Each half owns its own handle to the socket, which was duplicated
+    /// when the `Connection` was created, and any bytes that were already
+    /// buffered stay with the half that buffered them.
+    #[must_use]
+    pub fn split(self) -> (ConnectionReader, ConnectionWriter) {
+        let reader = ConnectionReader {
+            local_addr: self.local_addr,
+            remote_addr: self.remote_addr,
+            reader: self.reader,
+        };
+
+        let writer = ConnectionWriter {
+            local_addr: self.local_addr,
+            remote_addr: self.remote_addr,
+            writer: self.writer,
+        };
+
+        (reader, writer)
     }
 
     /// Reads a single line from the underlying `TcpStream`.
@@ -183,11 +880,12 @@ impl Connection {
     /// An error of kind `NetError::UnexpectedEof` is returned if an attempt
     /// to read from the underlying `TcpStream` returns `Ok(0)`.
     pub fn recv_line(&mut self, buf: &mut Vec<u8>) -> NetResult<()> {
+        self.arm_deadline()?;
         let max_bytes = u64::try_from(READER_BUFSIZE).unwrap_or(4000);
         let mut reader = self.reader.by_ref().take(max_bytes);
 
         match reader.read_until(b'\n', buf) {
-            Err(e) => Err(NetError::Read(e.kind())),
+            Err(e) => Err(NetError::Read(e.into())),
             Ok(0) => Err(NetError::UnexpectedEof),
             Ok(_) => Ok(()),
         }
@@ -199,22 +897,30 @@ impl Connection {
     ///
     /// As with the other readers, an error of kind `NetError::UnexpectedEof`
     /// is returned if `Ok(0)` is received while reading from the underlying
-    /// `TcpStream`.
+    /// `TcpStream`. An error of kind `NetError::TooManyHeaders` or
+    /// `NetError::HeadersTooLarge` is returned if there are more than
+    /// `MAX_HEADERS` headers or if they exceed `READER_BUFSIZE` bytes.
     pub fn recv_headers(&mut self, buf: &mut Vec<u8>) -> NetResult<()> {
+        let deadline = self.options.deadline;
         let max_bytes = u64::try_from(READER_BUFSIZE).unwrap_or(4000);
         let mut reader = self.reader.by_ref().take(max_bytes);
 
         let mut num_headers = 0;
 
         loop {
-            if num_headers >= MAX_HEADERS {
-                return Err(NetError::TooManyHeaders);
-            }
+            arm_deadline(reader.get_ref().get_ref(), deadline)?;
 
             match reader.read_until(b'\n', buf) {
-                Err(e) => return Err(NetError::Read(e.kind())),
+                Err(e) => return Err(NetError::Read(e.into())),
                 Ok(0) => return Err(NetError::UnexpectedEof),
                 Ok(1 | 2) => return Ok(()),
+                // The read limit was reached in the middle of a line.
+                Ok(_) if reader.limit() == 0 && !buf.ends_with(b"\n") => {
+                    return Err(NetError::HeadersTooLarge);
+                },
+                Ok(_) if num_headers >= MAX_HEADERS => {
+                    return Err(NetError::TooManyHeaders);
+                },
                 Ok(_) => num_headers += 1,
             }
         }
@@ -222,30 +928,196 @@ impl Connection {
 
     /// Reads and parses the message body from the underlying `TcpStream`.
     ///
+    /// A chunked body is read until its last chunk. Otherwise, exactly
+    /// Content-Length bytes are read, and the body is empty without a
+    /// Content-Length or if it is zero.
+    ///
     /// # Errors
     ///
     /// An error of kind `NetError::UnexpectedEof` is returned if an attempt
-    /// to read the underlying `TcpStream` returns `Ok(0)`.
+    /// to read the underlying `TcpStream` returns `Ok(0)`. An error of kind
+    /// `NetError::BadHeaderValue` is returned if the Content-Length is not a
+    /// valid length, and `NetError::IncompleteBody` if the stream ends
+    /// before Content-Length bytes are read.
     pub fn recv_body(
         &mut self,
         buf: &mut Vec<u8>,
         headers: &Headers
     ) -> NetResult<Body> {
-        let content_len = headers
-            .get(&CONTENT_LENGTH)
-            .and_then(|value| value.as_str().parse::<u64>().ok())
-            .unwrap_or(0);
+        if utils::is_chunked(headers) {
+            return self.recv_chunked_body(buf, headers);
+        }
 
-        let content_type = headers
-            .get(&CONTENT_TYPE)
-            .map_or(Cow::Borrowed(""), |value| value.as_str());
+        let content_len = match headers.get(&CONTENT_LENGTH) {
+            Some(len) => len.as_u64().ok_or(NetError::BadHeaderValue)?,
+            None => 0,
+        };
 
         if content_len == 0 {
-            Ok(Body::Empty)
-        } else {
-            self.reader.by_ref().take(content_len).read_to_end(buf)?;
-            Ok(Body::from_content_type(buf, &content_type))
+            return Ok(Body::Empty);
+        }
+
+        let start = buf.len();
+        self.read_body_bytes(buf, Some(content_len), Some(content_len))?;
+
+        if ((buf.len() - start) as u64) < content_len {
+            return Err(NetError::IncompleteBody);
+        }
+
+        Ok(Body::from_headers(buf, headers))
+    }
+
+    /// Appends up to `len` body bytes to `buf`, or reads until EOF if `len`
+    /// is `None`.
+    ///
+    /// If `on_progress` is set, it is called after each read with the length
+    /// of `buf` and the expected `total`. If `deadline` is set, it is armed
+    /// again before each read.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading from the underlying `TcpStream`
+    /// fails.
+    pub fn read_body_bytes(
+        &mut self,
+        buf: &mut Vec<u8>,
+        len: Option<u64>,
+        total: Option<u64>
+    ) -> NetResult<()> {
+        if self.options.on_progress.is_none()
+            && self.options.deadline.is_none()
+        {
+            match len {
+                Some(len) => self.reader.by_ref().take(len).read_to_end(buf)?,
+                None => self.reader.read_to_end(buf)?,
+            };
+
+            return Ok(());
+        }
+
+        let mut remaining = len.unwrap_or(u64::MAX);
+
+        while remaining > 0 {
+            self.arm_deadline()?;
+
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            if available.is_empty() {
+                break;
+            }
+
+            let num_read = usize::try_from(remaining)
+                .map_or(available.len(), |rem| rem.min(available.len()));
+
+            buf.extend_from_slice(&available[..num_read]);
+            self.reader.consume(num_read);
+
+            remaining -= num_read as u64;
+
+            if let Some(progress) = self.options.on_progress.as_mut() {
+                progress.call(buf.len() as u64, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads and parses a message body that uses chunked transfer encoding.
+    ///
+    /// Chunk extensions and trailer fields are discarded.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if a chunk size line is invalid or if reading
+    /// from the underlying `TcpStream` fails.
+    pub fn recv_chunked_body(
+        &mut self,
+        buf: &mut Vec<u8>,
+        headers: &Headers
+    ) -> NetResult<Body> {
+        self.recv_chunked_body_with_limit(buf, headers, usize::MAX)
+    }
+
+    /// Reads and parses a message body that uses chunked transfer encoding,
+    /// reading no more than `max_len` bytes of body data.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `NetError::BodyTooLarge` is returned if the chunks
+    /// add up to more than `max_len` bytes. Other errors are returned as
+    /// for `recv_chunked_body`.
+    pub fn recv_chunked_body_with_limit(
+        &mut self,
+        buf: &mut Vec<u8>,
+        headers: &Headers,
+        max_len: usize
+    ) -> NetResult<Body> {
+        let mut line = Vec::with_capacity(64);
+        let body_start = buf.len();
+
+        loop {
+            line.clear();
+            self.recv_line(&mut line)?;
+
+            let size_line = str::from_utf8(utils::trim(&line))
+                .map_err(|_| NetError::BadBody)?;
+
+            let size = size_line
+                .split(';')
+                .next()
+                .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+                .ok_or(NetError::BadBody)?;
+
+            if size == 0 {
+                break;
+            }
+
+            if (buf.len() - body_start).saturating_add(size) > max_len {
+                return Err(NetError::BodyTooLarge);
+            }
+
+            let start = buf.len();
+            self.read_body_bytes(buf, Some(size as u64), None)?;
+
+            if buf.len() - start < size {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+
+            // Consume the CRLF that follows the chunk data.
+            line.clear();
+            self.recv_line(&mut line)?;
         }
+
+        // Discard any trailer fields.
+        loop {
+            line.clear();
+            self.recv_line(&mut line)?;
+
+            if utils::trim(&line).is_empty() {
+                break;
+            }
+        }
+
+        Ok(Body::from_headers(buf, headers))
+    }
+
+    /// Reads the message body until the underlying `TcpStream` is closed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading from the underlying `TcpStream`
+    /// fails.
+    pub fn recv_body_to_eof(
+        &mut self,
+        buf: &mut Vec<u8>,
+        headers: &Headers
+    ) -> NetResult<Body> {
+        self.read_body_bytes(buf, None, None)?;
+        Ok(Body::from_headers(buf, headers))
     }
 
     /// Reads and parses a `Request` from a `TcpStream`.
@@ -255,37 +1127,206 @@ impl Connection {
     /// An error is returned if there is a failure to read or parse the
     /// individual components of the `Request`.
     pub fn recv_request(&mut self) -> NetResult<Request> {
+        let mut req = self.recv_request_head()?;
+        let _ = req.read_body(self)?;
+        Ok(req)
+    }
+
+    /// Reads and parses the request line and headers of a `Request` from a
+    /// `TcpStream`, leaving the message body unread.
+    ///
+    /// Sets `body_pending` if the headers announce a body, which can then be
+    /// read with `Request::read_body` or skipped with `discard_body`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if there is a failure to read or parse the
+    /// request line or the headers, or if the URI is longer than
+    /// `MAX_URI_LEN` bytes.
+    pub fn recv_request_head(&mut self) -> NetResult<Request> {
+        self.recv_request_head_with_limit(MAX_URI_LEN)
+    }
+
+    /// Reads and parses the request line and headers of a `Request` from a
+    /// `TcpStream`, leaving the message body unread.
+    ///
+    /// No more of the request line is read than a URI of `max_uri_len`
+    /// bytes requires. Sets `body_pending` if the headers announce a body.
+    ///
+    /// # Errors
+    ///
+    /// An error of kind `NetError::UriTooLong` is returned if the URI is
+    /// longer than `max_uri_len` bytes. Other errors are returned if there
+    /// is a failure to read or parse the request line or the headers.
+    pub fn recv_request_head_with_limit(
+        &mut self,
+        max_uri_len: usize
+    ) -> NetResult<Request> {
         let mut buf = Vec::with_capacity(READER_BUFSIZE);
 
-        self.recv_line(&mut buf)?;
+        let max_line = max_uri_len.saturating_add(REQUEST_LINE_OVERHEAD);
+        let max_line = u64::try_from(max_line).unwrap_or(u64::MAX);
+        let mut reader = self.reader.by_ref().take(max_line);
+
+        match reader.read_until(b'\n', &mut buf) {
+            Err(e) => return Err(NetError::Read(e.into())),
+            Ok(0) => return Err(NetError::UnexpectedEof),
+            // The read limit was reached in the middle of the line.
+            Ok(_) if reader.limit() == 0 && !buf.ends_with(b"\n") => {
+                return Err(NetError::UriTooLong);
+            },
+            Ok(_) => {},
+        }
 
         let mut parts = utils::trim_start(&buf[..])
             .splitn(3, |&b| b == b' ');
 
         let method = Method::try_from(parts.next())?;
-        let path = UriPath::try_from(parts.next())?;
+        let (path, authority) = UriPath::parse_target(parts.next())?;
         let version = Version::try_from(parts.next())?;
         buf.clear();
 
+        if path.as_str().len() > max_uri_len {
+            return Err(NetError::UriTooLong);
+        }
+
         self.recv_headers(&mut buf)?;
-        let headers = Headers::try_from(&buf[..])?;
-        buf.clear();
+        let mut headers = Headers::try_from(&buf[..])?;
+        let body = Body::Empty;
+
+        // The authority of an absolute-form target replaces the Host header.
+        if let Some(authority) = authority.as_deref() {
+            headers.insert(HOST, authority.into());
+        }
+
+        // Only a chunked body or a valid Content-Length announces a body.
+        // An invalid Content-Length leaves the body's end unknown.
+        let bad_len = headers
+            .get(&CONTENT_LENGTH)
+            .is_some_and(|len| len.as_u64().is_none());
+
+        if bad_len && !utils::is_chunked(&headers) {
+            return Err(NetError::BadHeaderValue);
+        }
+
+        let req = Request { method, path, version, headers, body, authority };
+        self.body_pending = req.has_body();
+        self.req_method = Some(req.method);
+        self.close_after_response = false;
+        Ok(req)
+    }
+
+    /// Reads and discards the pending body of the last request head that
+    /// was read, so that the next request can be read from the connection.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading the body fails.
+    pub fn discard_body(&mut self, headers: &Headers) -> NetResult<()> {
+        if !self.body_pending {
+            return Ok(());
+        }
+
+        self.body_pending = false;
+
+        if utils::is_chunked(headers) {
+            let mut buf = Vec::with_capacity(READER_BUFSIZE);
+            let _ = self.recv_chunked_body(&mut buf, headers)?;
+            return Ok(());
+        }
+
+        let len = headers
+            .get(&CONTENT_LENGTH)
+            .and_then(HeaderValue::as_u64)
+            .unwrap_or(0);
+
+        let mut body = self.reader.by_ref().take(len);
+        let copied = io::copy(&mut body, &mut sink())?;
 
-        let body = self.recv_body(&mut buf, &headers)?;
+        if copied < len {
+            return Err(NetError::UnexpectedEof);
+        }
 
-        Ok(Request {method, path, version, headers, body })
+        Ok(())
     }
 
-    /// Reads and parses a `Response` from a `TcpStream`.
+    /// Reads and parses a `Response` from a `TcpStream`, as if it answers a
+    /// GET request.
     ///
     /// # Errors
     ///
     /// An error is returned if there is a failure to read or parse the
     /// individual components of the `Response`.
+    #[deprecated(note = "use `Connection::recv_response_to` instead")]
     pub fn recv_response(&mut self) -> NetResult<Response> {
+        self.recv_response_to(&Method::Get)
+    }
+
+    /// Reads and parses a `Response` to a request with the given `Method`
+    /// from a `TcpStream`.
+    ///
+    /// If the response has a body but no Content-Length or Transfer-Encoding
+    /// header, the body is read until the server closes the connection, as
+    /// long as the connection is not being kept alive. Interim responses,
+    /// other than "101 Switching Protocols", are skipped.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if there is a failure to read or parse the
+    /// individual components of the `Response`.
+    pub fn recv_response_to(
+        &mut self,
+        method: &Method
+    ) -> NetResult<Response> {
+        let mut res = self.recv_response_head()?;
         let mut buf = Vec::with_capacity(READER_BUFSIZE);
 
-        self.recv_line(&mut buf)?;
+        res.body = if Body::should_be_empty(res.status.code(), method) {
+            Body::Empty
+        } else if res.headers.contains(&CONTENT_LENGTH)
+            || res.headers.contains(&TRANSFER_ENCODING)
+            || !Self::closes_after(res.version, &res.headers)
+        {
+            self.recv_body(&mut buf, &res.headers)?
+        } else {
+            self.recv_body_to_eof(&mut buf, &res.headers)?
+        };
+
+        Ok(res)
+    }
+
+    /// Reads and parses the status line and headers of a `Response` from a
+    /// `TcpStream`, leaving the body unread. Interim responses, other than
+    /// "101 Switching Protocols", are skipped.
+    ///
+    /// The `pending_head` is returned instead, if one is set.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if there is a failure to read or parse the
+    /// status line or the headers.
+    pub fn recv_response_head(&mut self) -> NetResult<Response> {
+        if let Some(head) = self.pending_head.take() {
+            return Ok(head);
+        }
+
+        let mut buf = Vec::with_capacity(READER_BUFSIZE);
+
+        loop {
+            let res = self.recv_status_head(&mut buf)?;
+
+            // Skip interim responses, such as "100 Continue".
+            if !matches!(res.status.code(), 100 | 102..=199) {
+                return Ok(res);
+            }
+        }
+    }
+
+    /// Reads and parses a single status line and its headers, which may
+    /// belong to an interim response, using `buf` for the raw bytes.
+    fn recv_status_head(&mut self, buf: &mut Vec<u8>) -> NetResult<Response> {
+        buf.clear();
+        self.recv_line(buf)?;
 
         let start = buf
             .iter()
@@ -295,16 +1336,44 @@ impl Connection {
         let mut parts = buf[start..].splitn(2, |&b| b == b' ');
 
         let version = Version::try_from(parts.next())?;
-        let status = Status::try_from(parts.next())?;
+        let rest = parts.next();
+        let status = Status::try_from(rest)?;
+        let reason = rest.and_then(|rest| parse_reason(status, rest));
         buf.clear();
 
-        self.recv_headers(&mut buf)?;
+        self.recv_headers(buf)?;
         let headers = Headers::try_from(&buf[..])?;
         buf.clear();
 
-        let body = self.recv_body(&mut buf, &headers)?;
+        let body = Body::Empty;
+        Ok(Response { version, status, reason, headers, body })
+    }
 
-        Ok(Response { version, status, headers, body })
+    /// Reads and parses the HTTP/2 `Response` to a request that was sent
+    /// with `send_request_h2`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `h2::read_response` fails.
+    #[cfg(feature = "http2")]
+    pub fn recv_response_h2(&mut self, method: &Method) -> NetResult<Response> {
+        h2::read_response(&mut self.reader, &mut self.writer, method)
+    }
+
+    /// Returns true if the connection should be closed after a message with
+    /// the given `Version` and `Headers`.
+    #[must_use]
+    pub fn closes_after(version: Version, headers: &Headers) -> bool {
+        let connection = headers
+            .get(&CONNECTION)
+            .map(|value| value.as_str().to_ascii_lowercase());
+
+        match connection.as_deref() {
+            Some(value) if value.contains("close") => true,
+            Some(value) if value.contains("keep-alive") => false,
+            // Persistent connections are the default starting in HTTP/1.1.
+            _ => matches!(version, Version::ZeroDotNine | Version::OneDotZero),
+        }
     }
 
     /// Writes the request line to the underlying `TcpStream`.
@@ -348,71 +1417,469 @@ impl Connection {
 
     /// Writes a `Headers` map to the underlying `TcpStream`.
     ///
+    /// Headers are written in sorted order unless `ordered_headers` is set,
+    /// in which case they are written in the order they were inserted.
+    ///
     /// # Errors
     ///
-    /// An error is returned if a problem was encountered while writing the
-    /// `Headers` to the underlying `TcpStream`.
+    /// An error is returned if `Headers::validate` finds an invalid entry,
+    /// in which case nothing is written, or if a problem was encountered
+    /// while writing the `Headers` to the underlying `TcpStream`.
     pub fn write_headers(&mut self, headers: &Headers) -> NetResult<()> {
-        for (name, value) in &headers.0 {
-            self.writer.write_all(name.as_bytes())?;
-            self.writer.write_all(b": ")?;
-            self.writer.write_all(value.as_bytes())?;
-            self.writer.write_all(b"\r\n")?;
+        headers.validate()?;
+
+        if self.ordered_headers {
+            for (name, value) in headers.iter_ordered() {
+                self.write_header(name, value)?;
+            }
+        } else {
+            for (name, value) in headers.iter() {
+                self.write_header(name, value)?;
+            }
         }
 
         self.writer.write_all(b"\r\n")?;
         Ok(())
     }
 
-    /// Writes a message `Body` to the underlying `TcpStream`.
+    /// Writes a single header line to the underlying `TcpStream`.
+    fn write_header(
+        &mut self,
+        name: &HeaderName,
+        value: &HeaderValue
+    ) -> NetResult<()> {
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_all(b": ")?;
+        self.writer.write_all(value.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    /// Writes a message `Body` to the underlying `TcpStream` using chunked
+    /// transfer encoding.
     ///
     /// # Errors
     ///
     /// An error is returned if the `Body` could not be written
     /// to the underlying `TcpStream` successfully.
+    pub fn write_chunked_body(&mut self, body: &Body) -> NetResult<()> {
+        if let Body::Stream(stream) = body {
+            let mut reader = Self::take_reader(stream)?;
+            let mut buf = [0u8; WRITER_BUFSIZE];
+
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(num_read) => self.write_chunk(&buf[..num_read])?,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        } else {
+            for chunk in body.as_bytes().chunks(WRITER_BUFSIZE) {
+                self.write_chunk(chunk)?;
+            }
+        }
+
+        self.finish_chunked()
+    }
+
+    /// Writes a single chunk of a body that uses chunked transfer encoding,
+    /// prefixed with its size in hexadecimal.
+    ///
+    /// An empty `chunk` is skipped, since a chunk of size zero would end the
+    /// body. Use `finish_chunked` to write the last chunk.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the chunk could not be written to the
+    /// underlying `TcpStream` successfully.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> NetResult<()> {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+
+        write!(self.writer, "{:x}\r\n", chunk.len())?;
+        self.writer.write_all(chunk)?;
+        self.writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    /// Writes the last chunk of a body that uses chunked transfer encoding
+    /// and flushes the underlying `TcpStream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the last chunk could not be written to the
+    /// underlying `TcpStream` successfully.
+    pub fn finish_chunked(&mut self) -> NetResult<()> {
+        self.writer.write_all(b"0\r\n\r\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes a message `Body` to the underlying `TcpStream`.
+    ///
+    /// A `Body::Stream` is copied from its reader in blocks, stopping after
+    /// its length, if known, or else at the end of the reader.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the `Body` could not be written
+    /// to the underlying `TcpStream` successfully, or
+    /// `NetError::IncompleteBody` if a `Body::Stream` ends before its length.
     pub fn write_body(&mut self, body: &Body) -> NetResult<()> {
-        if !body.is_empty() {
+        if let Body::Stream(stream) = body {
+            let reader = Self::take_reader(stream)?;
+            let limit = stream.len.unwrap_or(u64::MAX);
+            let copied = io::copy(&mut reader.take(limit), &mut self.writer)?;
+
+            if stream.len.is_some_and(|len| copied < len) {
+                return Err(NetError::IncompleteBody);
+            }
+        } else if !body.is_empty() {
             self.writer.write_all(body.as_bytes())?;
         }
 
         Ok(())
     }
 
+    /// Takes the reader from a streamed body.
+    ///
+    /// Returns an error if the body was already sent.
+    fn take_reader(stream: &BodyReader) -> NetResult<Box<dyn Read + Send>> {
+        stream.take().ok_or_else(|| {
+            NetError::Other("Streamed body was already sent.".into())
+        })
+    }
+
+    /// Inserts the default request headers, including an Accept header of
+    /// "*/*" if the `Request` does not set one.
+    ///
+    /// The Accept and Host headers are not inserted if `send_accept` or
+    /// `send_host` is unset. The default User-Agent header is removed if
+    /// `send_user_agent` is unset, but a custom one is kept.
+    fn set_default_headers(&self, req: &mut Request) {
+        let host_addr = self.options.send_host.then_some(self.remote_addr);
+        req.headers.default_request_headers(&req.body, host_addr);
+
+        if self.options.send_accept && !req.headers.contains(&ACCEPT) {
+            req.headers.insert(ACCEPT, "*/*".into());
+        }
+
+        let is_default = utils::is_default_user_agent(&req.headers);
+
+        if !self.options.send_user_agent && is_default {
+            req.headers.remove(&USER_AGENT);
+        }
+    }
+
     /// Writes a `Request` to a `TcpStream`.
     ///
+    /// The Content-Length header is set from the body length before writing.
+    ///
     /// # Errors
     ///
-    /// An error is returned if there is a failure to write any of the
-    /// individual components of the `Request` to the `TcpStream`.
+    /// An error is returned if the headers contain an invalid entry, as
+    /// checked by `Headers::validate`, or if there is a failure to write any
+    /// of the individual components of the `Request` to the `TcpStream`.
     pub fn send_request(&mut self, req: &mut Request) -> NetResult<()> {
         // Ensure default request headers are set.
-        req.headers.default_request_headers(&req.body, Some(self.remote_addr));
+        self.set_default_headers(req);
+        req.sync_content_length();
 
-        self.write_request_line(&req.method, &req.path, &req.version)?;
-        self.write_headers(&req.headers)?;
-        self.write_body(&req.body)?;
+        self.write_request_head(req)?;
+        self.write_request_body(req)?;
         self.writer.flush()?;
         Ok(())
     }
 
-    /// Writes a `Response` to a `TcpStream`.
+    /// Writes the request line and headers of a `Request`, with the headers
+    /// in the order they were inserted if `ordered_headers` is set.
+    fn write_request_head(&mut self, req: &Request) -> NetResult<()> {
+        req.headers.validate()?;
+
+        let head = if self.ordered_headers {
+            req.ordered_head_bytes()
+        } else {
+            req.head_bytes()
+        };
+
+        self.writer.write_all(&head)?;
+        Ok(())
+    }
+
+    /// Writes a request body on its own, after the head. A `Body::Stream`
+    /// uses chunked transfer encoding if the headers call for it.
+    fn write_request_body(&mut self, req: &Request) -> NetResult<()> {
+        if req.body.is_stream() && utils::is_chunked(&req.headers) {
+            self.write_chunked_body(&req.body)
+        } else {
+            self.write_body(&req.body)
+        }
+    }
+
+    /// Writes a `Request` over HTTP/2 with prior knowledge, opening the
+    /// HTTP/2 connection and sending the request on its first stream.
+    ///
+    /// A `Body::Stream` is read into memory before the request is sent.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading a streamed body or
+    /// `h2::write_request` fails.
+    #[cfg(feature = "http2")]
+    pub fn send_request_h2(&mut self, req: &mut Request) -> NetResult<()> {
+        if let Body::Stream(ref stream) = req.body {
+            let mut buf = Vec::new();
+            Self::take_reader(stream)?.read_to_end(&mut buf)?;
+            req.body = Body::from(buf);
+        }
+
+        // Ensure default request headers are set.
+        self.set_default_headers(req);
+        req.sync_content_length();
+
+        h2::write_request(&mut self.reader, &mut self.writer, req)
+    }
+
+    /// Writes a `Request` with an `Expect: 100-continue` header to a
+    /// `TcpStream`, sending the message body only after the server responds
+    /// with "100 Continue" or `timeout` elapses.
+    ///
+    /// The body is not sent if the server responds with a final status
+    /// instead.
     ///
     /// # Errors
     ///
     /// An error is returned if there is a failure to write any of the
+    /// individual components of the `Request` to the `TcpStream` or if the
+    /// connection is closed while waiting.
+    pub fn send_request_expect_continue(
+        &mut self,
+        req: &mut Request,
+        timeout: Duration
+    ) -> NetResult<()> {
+        // Ensure default request headers are set.
+        self.set_default_headers(req);
+        req.sync_content_length();
+        req.headers.insert(EXPECT, "100-continue".into());
+
+        self.write_request_head(req)?;
+        self.writer.flush()?;
+
+        if self.recv_continue(timeout)? {
+            self.write_request_body(req)?;
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for an interim "100 Continue" response.
+    ///
+    /// Returns true if the interim response was received, and consumed, or
+    /// if no response arrived in time. Returns false if a final response
+    /// arrived instead, which is kept as the `pending_head`. Other interim
+    /// responses are skipped. The socket's read timeout is restored
+    /// afterwards.
+    fn recv_continue(&mut self, timeout: Duration) -> NetResult<bool> {
+        let prev_timeout = self.reader.get_ref().read_timeout()?;
+        let mut buf = Vec::with_capacity(64);
+
+        loop {
+            // Only the wait for the response to start is limited by
+            // `timeout`. Once it has started, its head is read in full.
+            self.reader.get_ref().set_read_timeout(Some(timeout))?;
+            let waited = self.reader.fill_buf().map(<[u8]>::len);
+            self.reader.get_ref().set_read_timeout(prev_timeout)?;
+
+            match waited {
+                Ok(0) => return Err(NetError::UnexpectedEof),
+                Ok(_) => {},
+                Err(e) if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut
+                ) => return Ok(true),
+                Err(e) => return Err(NetError::Read(e.into())),
+            }
+
+            let head = self.recv_status_head(&mut buf)?;
+
+            match head.status.code() {
+                100 => return Ok(true),
+                102..=199 => {},
+                _ => {
+                    self.pending_head = Some(head);
+                    return Ok(false);
+                },
+            }
+        }
+    }
+
+    /// Writes a `Request` to a `TcpStream`, streaming the message body from
+    /// `reader` rather than from the `Request` body.
+    ///
+    /// The `progress` callback is invoked after each block is written with
+    /// the number of body bytes sent so far and the expected `total`, if
+    /// known. Returns the total number of body bytes sent. If `deadline` is
+    /// set, it is armed again before each block.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the headers contain an invalid entry, if
+    /// reading from `reader` fails, or if there is a failure to write to the
+    /// `TcpStream`. `NetError::IncompleteBody` is returned if `reader` ends
+    /// before `total` bytes are sent. No more than `total` bytes are sent.
+    pub fn stream_request<R, F>(
+        &mut self,
+        req: &mut Request,
+        reader: &mut R,
+        total: Option<u64>,
+        mut progress: F
+    ) -> NetResult<u64>
+    where
+        R: Read,
+        F: FnMut(u64, Option<u64>),
+    {
+        // Ensure default request headers are set.
+        self.set_default_headers(req);
+        self.write_request_head(req)?;
+
+        let mut reader = reader.take(total.unwrap_or(u64::MAX));
+        let mut buf = [0u8; WRITER_BUFSIZE];
+        let mut sent = 0u64;
+
+        loop {
+            self.arm_deadline()?;
+
+            let num_read = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(num_read) => num_read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            self.writer.write_all(&buf[..num_read])?;
+            sent += num_read as u64;
+            progress(sent, total);
+        }
+
+        self.writer.flush()?;
+
+        if total.is_some_and(|total| sent != total) {
+            return Err(NetError::IncompleteBody);
+        }
+
+        Ok(sent)
+    }
+
+    /// Writes a `Response` to a `TcpStream`.
+    ///
+    /// The Content-Length header is set from the body length before writing,
+    /// as described in `Response::sync_content_length`. It is left out of
+    /// responses to HEAD requests and of successful responses to CONNECT
+    /// requests, based on `req_method`.
+    ///
+    /// For a `Body::EventStream`, the head is written and then the body's
+    /// `EventHandler` is run until it returns.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the reason phrase or the headers contain an
+    /// invalid entry, as checked by `Response::validate_reason` and
+    /// `Headers::validate`, or if there is a failure to write any of the
     /// individual components of the `Response` to the `TcpStream`.
     pub fn send_response(&mut self, res: &mut Response) -> NetResult<()> {
         // Ensure default response headers are set.
         res.headers.default_response_headers(&res.body);
+        self.set_date(&mut res.headers);
+        res.sync_content_length();
+
+        // Responses to HEAD requests and successful responses to CONNECT
+        // requests have no body to frame.
+        let no_length = match self.req_method {
+            Some(Method::Head) => res.body.is_empty(),
+            Some(Method::Connect) => res.status.is_success(),
+            _ => false,
+        };
+
+        if no_length {
+            res.headers.remove(&CONTENT_LENGTH);
+        }
+
+        res.validate_reason()?;
+        res.headers.validate()?;
+
+        let head = if self.ordered_headers {
+            res.ordered_head_bytes()
+        } else {
+            res.head_bytes()
+        };
+
+        if let Body::EventStream(handler) = res.body {
+            self.writer.write_all(&head)?;
+            self.writer.flush()?;
+            return handler.run(&mut EventStream { conn: self });
+        }
+
+        if res.is_chunked() {
+            self.writer.write_all(&head)?;
+            self.write_chunked_body(&res.body)?;
+        } else if res.body.is_stream() {
+            self.writer.write_all(&head)?;
+            self.write_body(&res.body)?;
+        } else {
+            // Write the head and the body together to avoid small writes.
+            let mut bufs = [
+                IoSlice::new(&head),
+                IoSlice::new(res.body.as_bytes()),
+            ];
+            self.write_all_vectored(&mut bufs)?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes all of the given buffers to the underlying `TcpStream` using
+    /// as few vectored writes as possible.
+    fn write_all_vectored(
+        &mut self,
+        mut bufs: &mut [IoSlice<'_>]
+    ) -> IoResult<()> {
+        // Skip any empty buffers.
+        IoSlice::advance_slices(&mut bufs, 0);
+
+        while !bufs.is_empty() {
+            match self.writer.write_vectored(bufs) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(num) => IoSlice::advance_slices(&mut bufs, num),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
 
-        self.write_status_line(&res.version, &res.status)?;
-        self.write_headers(&res.headers)?;
-        self.write_body(&res.body)?;
+    /// Writes an interim "100 Continue" response to the underlying
+    /// `TcpStream`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if writing to the underlying `TcpStream` fails.
+    pub fn send_continue(&mut self) -> NetResult<()> {
+        let status = Status::try_from(100)?;
+
+        self.write_status_line(&Version::OneDotOne, &status)?;
+        self.writer.write_all(b"\r\n")?;
         self.writer.flush()?;
         Ok(())
     }
 
-    /// Writes an error `Response` to the underlying `TcpStream`.
+    /// Writes an error `Response` to the underlying `TcpStream` and closes
+    /// the writing half of the connection.
     ///
     /// # Errors
     ///
@@ -420,18 +1887,187 @@ impl Connection {
     /// or if the provided status `code` is not in the range from 100 to 999,
     /// inclusive.
     pub fn send_error(&mut self, code: u16, msg: String) -> NetResult<()> {
-        let body = Body::from(msg);
+        self.send_error_body(code, &Body::from(msg))
+    }
+
+    /// Sets the Date header to the time given by `clock`, if one is set.
+    fn set_date(&self, headers: &mut Headers) {
+        if let Some(clock) = self.options.clock.as_ref() {
+            let date = utils::format_http_date(clock.now());
+            headers.insert(DATE, date.as_str().into());
+        }
+    }
+
+    /// Writes an error `Response` with the given `Body` to the underlying
+    /// `TcpStream` and closes the writing half of the connection.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if writing to the underlying `TcpStream` fails
+    /// or if the provided status `code` is not in the range from 100 to 999,
+    /// inclusive.
+    pub fn send_error_body(&mut self, code: u16, body: &Body) -> NetResult<()> {
         let version = Version::default();
         let status = Status::try_from(code)?;
 
         let mut headers = Headers::new();
-        headers.default_response_headers(&body);
+        headers.default_response_headers(body);
         headers.insert(CONNECTION, "close".into());
+        self.set_date(&mut headers);
 
         self.write_status_line(&version, &status)?;
         self.write_headers(&headers)?;
-        self.write_body(&body)?;
+        self.write_body(body)?;
         self.writer.flush()?;
+
+        // Discard any unread request bytes so that closing the connection
+        // does not reset it before the client reads the response. The
+        // deadline bounds the wait even if the client keeps sending slowly.
+        let stream = self.writer.get_ref();
+        stream.shutdown(Shutdown::Write)?;
+
+        let deadline = Instant::now() + LINGER_TIMEOUT;
+        let mut discarded = 0;
+        let mut buf = [0; 4096];
+
+        while discarded < LINGER_MAX_BYTES {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                break;
+            }
+
+            stream.set_read_timeout(Some(remaining))?;
+
+            match self.reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => discarded += n as u64,
+            }
+        }
+
         Ok(())
     }
+
+    /// Replies to a CONNECT request with "200 Connection Established" and
+    /// then copies bytes in both directions between this `Connection` and
+    /// the `upstream` stream.
+    ///
+    /// The client closing its side is passed on to `upstream`, and the
+    /// tunnel is torn down once `upstream` closes or once no bytes have
+    /// been copied in either direction for `idle_timeout`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the reply could not be written or if copying
+    /// between the two streams fails. The idle timeout ends the tunnel with
+    /// `NetError::Timeout`.
+    pub fn tunnel(
+        &mut self,
+        upstream: TcpStream,
+        idle_timeout: Duration
+    ) -> NetResult<()> {
+        self.writer
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")?;
+        self.writer.flush()?;
+
+        let client = self.reader.get_ref();
+        client.set_read_timeout(Some(idle_timeout))?;
+        upstream.set_read_timeout(Some(idle_timeout))?;
+
+        let mut to_upstream = upstream.try_clone()?;
+        let mut from_client = client.try_clone()?;
+
+        // Bytes the client sent after the request head are already buffered.
+        to_upstream.write_all(self.reader.buffer())?;
+        self.reader.consume(self.reader.buffer().len());
+
+        let activity = Activity::new(idle_timeout);
+        let client_activity = activity.clone();
+
+        let handle = thread::spawn(move || -> IoResult<u64> {
+            let copied =
+                client_activity.copy(&mut from_client, &mut to_upstream);
+
+            // An idle client should not keep the upstream side open.
+            let how = if copied.is_ok() {
+                Shutdown::Write
+            } else {
+                Shutdown::Both
+            };
+            let _ = to_upstream.shutdown(how);
+            copied
+        });
+
+        let mut from_upstream = upstream;
+        let copied = activity.copy(&mut from_upstream, self.writer.get_mut());
+
+        // Once upstream is done, stop waiting on the client as well.
+        let _ = self.writer.get_ref().shutdown(Shutdown::Both);
+
+        let sent = handle
+            .join()
+            .map_err(|_| NetError::Other("Tunnel thread panicked.".into()))?;
+        copied?;
+        sent?;
+        Ok(())
+    }
+}
+
+/// The time of the last copy in either direction of a tunnel.
+#[derive(Clone)]
+struct Activity {
+    start: Instant,
+    last_millis: Arc<AtomicU64>,
+    idle_timeout: Duration,
+}
+
+impl Activity {
+    fn new(idle_timeout: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            last_millis: Arc::new(AtomicU64::new(0)),
+            idle_timeout,
+        }
+    }
+
+    fn elapsed_millis(&self) -> u64 {
+        u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX)
+    }
+
+    /// Copies from `reader` to `writer` until EOF. A read that times out
+    /// only ends the copy if the other direction has also been idle.
+    fn copy<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W
+    ) -> IoResult<u64> {
+        let mut buf = [0; 8192];
+        let mut copied = 0;
+
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(copied),
+                Ok(n) => {
+                    writer.write_all(&buf[..n])?;
+                    writer.flush()?;
+                    copied += n as u64;
+                    let now = self.elapsed_millis();
+                    self.last_millis.store(now, Ordering::Relaxed);
+                },
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut
+                ) => {
+                    let last = self.last_millis.load(Ordering::Relaxed);
+                    let idle = self.elapsed_millis().saturating_sub(last);
+
+                    if u128::from(idle) >= self.idle_timeout.as_millis() {
+                        return Err(ErrorKind::TimedOut.into());
+                    }
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
 }
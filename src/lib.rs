@@ -15,37 +15,57 @@
 pub mod body;
 pub mod cli;
 pub mod client;
+pub mod encoding;
 pub mod errors;
+#[cfg(feature = "http2")]
+pub mod h2;
 pub mod headers;
 pub mod http;
 pub mod io;
+pub mod middleware;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod server;
+pub mod signal;
 pub mod style;
 pub mod tui;
 pub mod utils;
 pub mod workers;
+pub mod ws;
 
-pub use body::{Body, Target};
+pub use body::{Body, BodyReader, EventHandler, Handler, Target};
 pub use cli::{ClientCli, ServerCli};
-pub use client::{Client, ClientBuilder};
-pub use errors::{NetError, NetResult};
-pub use headers::{Header, Headers, HeaderName, HeaderValue};
-pub use io::{Connection, WriteCliError};
+pub use client::{Client, ClientBuilder, Credentials};
+pub use errors::{IoCause, NetError, NetResult};
+pub use headers::{
+    DigestChallenge, Header, Headers, HeaderName, HeaderValue, RetryAfter,
+};
+pub use io::{
+    Clock, ClockFn, Connection, ConnectionOptions, ConnectionReader,
+    ConnectionWriter, EventStream, Progress, ProgressFn, Stream, WriteCliError,
+    UNIX_SOCKET_ADDR,
+};
 pub use http::{Method, Status, Version};
-pub use request::{Request, RequestBuilder, UriPath};
+pub use middleware::{
+    AllowOrigin, CacheConfig, Cors, CorsConfig, RateLimit, ResponseCache,
+};
+pub use request::{Request, RequestBuilder, Uri, UriPath};
 pub use response::{Response, ResponseBuilder};
-pub use router::{Route, RouteBuilder, Router};
-pub use server::{Listener, Server, ServerBuilder, NetHandle};
+pub use router::{NotFoundFor, PathMatch, Route, RouteBuilder, Router};
+pub use server::{
+    Listener, ListenerSocket, LogEntry, LogFormat, LogRotate, OnReady, ReadyFn,
+    Server, ServerBuilder, NetHandle,
+};
 pub use style::{Style, Kind, Parts};
 pub use tui::Tui;
-pub use workers::{ThreadPool, Worker};
+pub use workers::{JobSender, PoolStats, ThreadPool, Worker};
 
 pub const MAX_HEADERS: u16 = 1024;
+pub const MAX_URI_LEN: usize = 8192;
 pub const READER_BUFSIZE: usize = 2048;
 pub const WRITER_BUFSIZE: usize = 2048;
+pub const MAX_BUFFERED_BODY: u64 = 64 * 1024;
 pub const TEST_SERVER_ADDR: &str = "127.0.0.1:7878";
 pub const CLIENT_NAME: &str = "http_client";
 pub const SERVER_NAME: &str = "http_server";
@@ -0,0 +1,580 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{Method, NetResult, Request, Response, Version, utils};
+use crate::headers::names::{
+    ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+    ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+    ACCESS_CONTROL_REQUEST_METHOD, AGE, AUTHORIZATION, CACHE_CONTROL, COOKIE,
+    DATE, IF_MODIFIED_SINCE, IF_NONE_MATCH, ORIGIN, SET_COOKIE, VARY,
+};
+
+/// The request count for a single client within the current window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub start: Instant,
+    pub count: u32,
+}
+
+/// Limits the number of requests each client may make within a fixed
+/// window of time.
+///
+/// Clients are keyed by the IP address of the remote socket, so requests
+/// that arrive through a proxy will all count against the proxy's address
+/// unless the server is configured to trust X-Forwarded-For headers.
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub per: Duration,
+    pub clients: Mutex<HashMap<IpAddr, Window>>,
+    pub last_prune: Mutex<Instant>,
+}
+
+impl Debug for RateLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("RateLimit")
+            .field("max_requests", &self.max_requests)
+            .field("per", &self.per)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for RateLimit {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_requests == other.max_requests && self.per == other.per
+    }
+}
+
+impl Eq for RateLimit {}
+
+impl RateLimit {
+    /// Returns a new `RateLimit` that allows up to `max_requests` requests
+    /// from each client every `per` duration.
+    #[must_use]
+    pub fn new(max_requests: u32, per: Duration) -> Self {
+        Self {
+            max_requests,
+            per,
+            clients: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Records a request from the client at `ip`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the time remaining until the client's window resets if the
+    /// client has exceeded the limit.
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+
+        self.prune(now);
+
+        let Ok(mut clients) = self.clients.lock() else {
+            // Fail open if another worker panicked while holding the lock.
+            return Ok(());
+        };
+
+        let window = clients
+            .entry(ip)
+            .or_insert(Window { start: now, count: 0 });
+
+        let elapsed = now.duration_since(window.start);
+
+        if elapsed >= self.per {
+            // Start a new window.
+            *window = Window { start: now, count: 0 };
+        }
+
+        if window.count >= self.max_requests {
+            let elapsed = now.duration_since(window.start);
+            return Err(self.per.saturating_sub(elapsed));
+        }
+
+        window.count += 1;
+        Ok(())
+    }
+
+    /// Removes the entries for clients whose windows have expired. This
+    /// runs at most once per window length.
+    pub fn prune(&self, now: Instant) {
+        let Ok(mut last_prune) = self.last_prune.lock() else {
+            return;
+        };
+
+        if now.duration_since(*last_prune) < self.per {
+            return;
+        }
+
+        *last_prune = now;
+
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain(|_, window| {
+                now.duration_since(window.start) < self.per
+            });
+        }
+    }
+
+    /// Returns the number of clients currently being tracked.
+    #[must_use]
+    pub fn num_clients(&self) -> usize {
+        self.clients.lock().map_or(0, |clients| clients.len())
+    }
+}
+
+/// The origins that a `CorsConfig` allows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AllowOrigin {
+    /// Any origin. This is always sent as "*", which browsers do not accept
+    /// for requests with credentials.
+    #[default]
+    Any,
+    /// Only the listed origins, such as `https://example.com`.
+    List(Vec<String>),
+}
+
+/// Configures the cross-origin requests that the `Cors` middleware allows.
+///
+/// An empty list of `headers` allows any request headers that a preflight
+/// request asks for. Credentials are only allowed for the origins in an
+/// `AllowOrigin::List`, since allowing them for any origin would let every
+/// site make authenticated requests.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorsConfig {
+    pub origins: AllowOrigin,
+    pub methods: Vec<Method>,
+    pub headers: Vec<String>,
+    pub credentials: bool,
+    pub max_age: Option<u32>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            origins: AllowOrigin::Any,
+            methods: vec![
+                Method::Get,
+                Method::Head,
+                Method::Post,
+                Method::Put,
+                Method::Patch,
+                Method::Delete,
+            ],
+            headers: Vec::new(),
+            credentials: false,
+            max_age: None
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Returns a new `CorsConfig` that allows any origin to use the common
+    /// request methods.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows requests from `origin`. Once an origin has been added, only
+    /// the listed origins are allowed.
+    pub fn allow_origin(&mut self, origin: &str) -> &mut Self {
+        let origin = origin.trim().to_string();
+
+        match self.origins {
+            AllowOrigin::Any => self.origins = AllowOrigin::List(vec![origin]),
+            AllowOrigin::List(ref mut list) => list.push(origin),
+        }
+
+        self
+    }
+
+    /// Allows requests from any origin (the default).
+    pub fn allow_any_origin(&mut self) -> &mut Self {
+        self.origins = AllowOrigin::Any;
+        self
+    }
+
+    /// Sets the request methods that cross-origin requests may use.
+    pub fn allow_methods(&mut self, methods: &[Method]) -> &mut Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// Sets the request headers that cross-origin requests may send.
+    pub fn allow_headers(&mut self, headers: &[&str]) -> &mut Self {
+        self.headers = headers.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Sets whether cross-origin requests may include credentials, such as
+    /// cookies. This has no effect unless the allowed origins are listed.
+    pub const fn allow_credentials(&mut self, credentials: bool) -> &mut Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Sets the number of seconds that a browser may cache the result of a
+    /// preflight request.
+    pub const fn max_age(&mut self, max_age: u32) -> &mut Self {
+        self.max_age = Some(max_age);
+        self
+    }
+}
+
+/// Adds Cross-Origin Resource Sharing (CORS) headers to responses and
+/// answers preflight requests.
+///
+/// Requests without an Origin header are not cross-origin requests and are
+/// left unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cors {
+    pub config: CorsConfig,
+}
+
+impl From<CorsConfig> for Cors {
+    fn from(config: CorsConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Cors {
+    /// Returns a new `Cors` middleware with the given configuration.
+    #[must_use]
+    pub const fn new(config: CorsConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns true if the `Request` is a CORS preflight request, which is
+    /// an OPTIONS request with an Origin header and an
+    /// Access-Control-Request-Method header.
+    #[must_use]
+    pub fn is_preflight(req: &Request) -> bool {
+        req.method == Method::Options
+            && req.headers.contains(&ORIGIN)
+            && req.headers.contains(&ACCESS_CONTROL_REQUEST_METHOD)
+    }
+
+    /// Returns the Access-Control-Allow-Origin value for the `Request`, or
+    /// `None` if it has no Origin header or its origin is not allowed.
+    #[must_use]
+    pub fn allowed_origin(&self, req: &Request) -> Option<String> {
+        let origin = req.headers.get(&ORIGIN)?.as_str();
+
+        match self.config.origins {
+            AllowOrigin::Any => Some(String::from("*")),
+            AllowOrigin::List(ref list) => list
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&origin))
+                .then(|| origin.to_string()),
+        }
+    }
+
+    /// Returns a 204 response to a preflight `Request`.
+    ///
+    /// The response only includes the Access-Control-Allow-* headers if the
+    /// origin and the requested method are allowed, which tells the browser
+    /// to block the actual request otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `Response` cannot be built.
+    pub fn preflight(&self, req: &Request) -> NetResult<Response> {
+        let mut res = Response::builder().status_code(204).build()?;
+
+        let method_allowed = req.headers
+            .get(&ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| method.as_str().trim().parse::<Method>().ok())
+            .is_some_and(|method| self.config.methods.contains(&method));
+
+        if let Some(origin) = self.allowed_origin(req) {
+            if method_allowed {
+                self.insert_origin(&mut res, &origin);
+
+                let methods = self.config.methods
+                    .iter()
+                    .map(Method::as_str)
+                    .collect::<Vec<&str>>()
+                    .join(", ");
+                res.headers.insert(
+                    ACCESS_CONTROL_ALLOW_METHODS,
+                    methods.as_str().into()
+                );
+
+                let headers = if self.config.headers.is_empty() {
+                    req.headers
+                        .get(&ACCESS_CONTROL_REQUEST_HEADERS)
+                        .map(|headers| headers.as_str().to_string())
+                } else {
+                    Some(self.config.headers.join(", "))
+                };
+
+                if let Some(headers) = headers {
+                    res.headers.insert(
+                        ACCESS_CONTROL_ALLOW_HEADERS,
+                        headers.as_str().into()
+                    );
+                }
+
+                if let Some(max_age) = self.config.max_age {
+                    let max_age = max_age.to_string();
+                    let max_age = max_age.as_str().into();
+                    res.headers.insert(ACCESS_CONTROL_MAX_AGE, max_age);
+                }
+            }
+        }
+
+        res.match_version(req);
+        Ok(res)
+    }
+
+    /// Adds the Access-Control-Allow-Origin header, and the
+    /// Access-Control-Allow-Credentials header if enabled, to the response
+    /// to an allowed cross-origin `Request`.
+    pub fn apply(&self, req: &Request, res: &mut Response) {
+        if let Some(origin) = self.allowed_origin(req) {
+            self.insert_origin(res, &origin);
+        }
+    }
+
+    /// Inserts the allowed origin and, for a listed origin, the credentials
+    /// flag. A response that echoes a specific origin also gets "Origin"
+    /// added to its Vary header so that caches keep the responses for each
+    /// origin apart.
+    fn insert_origin(&self, res: &mut Response, origin: &str) {
+        if origin == "*" {
+            res.headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.into());
+            return;
+        }
+
+        let vary = match res.headers.get(&VARY) {
+            Some(vary) if Self::varies_by_origin(&vary.split_list()) => None,
+            Some(vary) => Some(format!("{vary}, Origin")),
+            None => Some(String::from("Origin")),
+        };
+
+        if let Some(vary) = vary {
+            res.headers.insert(VARY, vary.as_str().into());
+        }
+
+        res.headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.into());
+
+        if self.config.credentials {
+            res.headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, "true".into());
+        }
+    }
+
+    /// Returns true if the Vary header's fields already cover the Origin
+    /// header, either by name or with "*".
+    fn varies_by_origin(fields: &[String]) -> bool {
+        fields.iter().any(|field| {
+            field == "*" || field.eq_ignore_ascii_case("origin")
+        })
+    }
+}
+
+/// Configures the `ResponseCache` middleware.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub ttl: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { ttl: Duration::from_secs(60), max_entries: 256 }
+    }
+}
+
+/// A stored response along with when it was stored and when it was last
+/// used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub res: Response,
+    pub stored: Instant,
+    pub last_used: u64,
+}
+
+/// The entries of a `ResponseCache`, keyed by request method and path, and
+/// a counter that orders their uses.
+#[derive(Debug, Default)]
+pub struct CacheEntries {
+    pub map: HashMap<(Method, String), CacheEntry>,
+    pub uses: u64,
+}
+
+/// Stores the successful responses to GET and HEAD requests in memory for
+/// a fixed time, keyed by the request method and path.
+///
+/// Responses with a `Cache-Control: no-store` header, or to requests with
+/// one, are never stored. Since the cache is shared by all clients, neither
+/// are responses that are private, set cookies, or vary by request header,
+/// nor responses to requests with credentials. Streamed bodies, which can
+/// only be sent once, and responses to HTTP/1.0 requests, which are changed
+/// to fit the request's version, are not stored either. Conditional and
+/// credentialed requests bypass the cache. When more than `max_entries`
+/// responses are stored, the least recently used one is evicted.
+pub struct ResponseCache {
+    pub config: CacheConfig,
+    pub entries: Mutex<CacheEntries>,
+}
+
+impl Debug for ResponseCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ResponseCache")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for ResponseCache {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+    }
+}
+
+impl Eq for ResponseCache {}
+
+impl From<CacheConfig> for ResponseCache {
+    fn from(config: CacheConfig) -> Self {
+        Self::new(config)
+    }
+}
+
+impl ResponseCache {
+    /// Returns a new, empty `ResponseCache` with the given configuration.
+    #[must_use]
+    pub fn new(config: CacheConfig) -> Self {
+        Self { config, entries: Mutex::new(CacheEntries::default()) }
+    }
+
+    /// Returns a copy of the stored response to the `Request`, if one was
+    /// stored less than `ttl` ago. The copy has an Age header giving the
+    /// number of seconds since it was stored and a current Date header.
+    #[must_use]
+    pub fn get(&self, req: &Request) -> Option<Response> {
+        if !Self::is_cacheable_request(req) {
+            return None;
+        }
+
+        let mut entries = self.entries.lock().ok()?;
+        let key = (req.method, req.path.to_string());
+        let age = entries.map.get(&key)?.stored.elapsed();
+
+        if age >= self.config.ttl {
+            entries.map.remove(&key);
+            return None;
+        }
+
+        entries.uses += 1;
+        let uses = entries.uses;
+
+        let entry = entries.map.get_mut(&key)?;
+        entry.last_used = uses;
+
+        let mut res = entry.res.clone();
+        drop(entries);
+
+        let age = age.as_secs().to_string();
+        let date = utils::format_http_date(SystemTime::now());
+        res.headers.insert(AGE, age.as_str().into());
+        res.headers.insert(DATE, date.as_str().into());
+        Some(res)
+    }
+
+    /// Stores a copy of the `Response` to the `Request` if it may be
+    /// cached, evicting the least recently used response if the cache is
+    /// full.
+    pub fn store(&self, req: &Request, res: &Response) {
+        let is_cacheable = Self::is_cacheable_request(req)
+            && req.version != Version::OneDotZero
+            && res.status.code() == 200
+            && !res.body.is_stream()
+            && !res.body.is_event_stream()
+            && !res.headers.contains(&SET_COOKIE)
+            && !res.headers.contains(&VARY)
+            && !res.headers.get(&CACHE_CONTROL).is_some_and(|value| {
+                Self::is_private(&value.split_list())
+            });
+
+        if !is_cacheable || self.config.max_entries == 0 {
+            return;
+        }
+
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        entries.uses += 1;
+
+        let entry = CacheEntry {
+            res: res.clone(),
+            stored: Instant::now(),
+            last_used: entries.uses,
+        };
+
+        entries.map.insert((req.method, req.path.to_string()), entry);
+
+        while entries.map.len() > self.config.max_entries {
+            let oldest = entries.map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+
+            match oldest {
+                Some(key) => entries.map.remove(&key),
+                None => break,
+            };
+        }
+    }
+
+    /// Returns the number of stored responses, including expired ones that
+    /// have not been looked up since they expired.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.map.len())
+    }
+
+    /// Returns true if no responses are stored.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the response to the `Request` may be looked up in or
+    /// stored in the cache.
+    ///
+    /// Requests with credentials may get a response meant only for their
+    /// user, and conditional requests may need a 304 or 412 response, so
+    /// both are left to the router.
+    fn is_cacheable_request(req: &Request) -> bool {
+        matches!(req.method, Method::Get | Method::Head)
+            && !req.headers.contains(&AUTHORIZATION)
+            && !req.headers.contains(&COOKIE)
+            && !req.headers.contains(&IF_NONE_MATCH)
+            && !req.headers.contains(&IF_MODIFIED_SINCE)
+            && !req.headers.get(&CACHE_CONTROL).is_some_and(|value| {
+                Self::is_no_store(&value.split_list())
+            })
+    }
+
+    /// Returns true if the Cache-Control directives include "no-store".
+    fn is_no_store(directives: &[String]) -> bool {
+        directives
+            .iter()
+            .any(|directive| directive.eq_ignore_ascii_case("no-store"))
+    }
+
+    /// Returns true if the Cache-Control directives forbid a shared cache
+    /// from storing the response, with "no-store" or "private".
+    fn is_private(directives: &[String]) -> bool {
+        Self::is_no_store(directives)
+            || directives.iter().any(|directive| {
+                let name = directive.split('=').next().unwrap_or_default();
+                name.trim().eq_ignore_ascii_case("private")
+            })
+    }
+}
@@ -1,11 +1,18 @@
 use std::borrow::{Borrow, Cow};
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::Path;
 use std::str::{self, FromStr};
 
 use crate::{
-    Body, Header, Headers, Method, NetError, NetResult, Version, utils,
+    Body, BodyReader, Connection, HeaderName, HeaderValue, Headers, Method,
+    NetError, NetResult, Version, READER_BUFSIZE, utils,
+};
+use crate::headers::names::{
+    ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, EXPECT, HOST, TRANSFER_ENCODING,
+    USER_AGENT, X_FORWARDED_FOR,
 };
-use crate::headers::names::CONTENT_TYPE;
 use crate::style::colors::{ORANGE, RESET};
 
 /// An HTTP request builder object.
@@ -38,6 +45,27 @@ impl RequestBuilder {
         self
     }
 
+    /// Appends the percent-encoded key-value pairs to the query string of
+    /// the URI path, merging with any query that is already present.
+    pub fn query(&mut self, params: &[(&str, &str)]) -> &mut Self {
+        let mut path = self.path.to_string();
+
+        for (key, value) in params {
+            if !path.contains('?') {
+                path.push('?');
+            } else if !path.ends_with(['?', '&']) {
+                path.push('&');
+            }
+
+            path.push_str(&utils::percent_encode(key));
+            path.push('=');
+            path.push_str(&utils::percent_encode(value));
+        }
+
+        self.path = path.into();
+        self
+    }
+
     /// Sets the HTTP protocol version.
     pub fn version(&mut self, version: Version) -> &mut Self {
         self.version = version;
@@ -62,6 +90,38 @@ impl RequestBuilder {
         self
     }
 
+    /// Sets the request body to stream the file at `path`.
+    ///
+    /// The Content-Length header is set from the file's metadata and the
+    /// Content-Type header is set from the file extension, if not already
+    /// present. The file is read in blocks while the request is sent rather
+    /// than being buffered in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or if its metadata
+    /// cannot be read.
+    pub fn body_file<P: AsRef<Path>>(
+        &mut self,
+        path: P
+    ) -> NetResult<&mut Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+
+        let content_len = len.to_string();
+        self.headers.insert(CONTENT_LENGTH, content_len.as_str().into());
+
+        if !self.headers.contains(&CONTENT_TYPE) {
+            let content_type = utils::content_type_from_ext(path)
+                .unwrap_or("application/octet-stream");
+            self.headers.insert(CONTENT_TYPE, content_type.into());
+        }
+
+        self.body = Body::Stream(BodyReader::new(file, Some(len)));
+        Ok(self)
+    }
+
     /// Builds and returns a new `Request` instance.
     pub fn build(&mut self) -> Request {
         // Ensure the default request headers are set.
@@ -72,7 +132,8 @@ impl RequestBuilder {
             path: self.path.clone(),
             version: self.version,
             headers: self.headers.clone(),
-            body: self.body.clone()
+            body: self.body.clone(),
+            authority: None
         }
     }
 }
@@ -140,6 +201,50 @@ impl TryFrom<Option<&[u8]>> for UriPath {
 }
 
 impl UriPath {
+    /// Parses a request target into a `UriPath` and, for an absolute-form
+    /// target such as `http://example.com/path?q=1`, its authority.
+    ///
+    /// The path of an absolute-form target is "/" if it is empty. Other
+    /// targets, including the asterisk-form "*", are used as the path
+    /// unchanged and have no authority.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadPath` if the target is missing or is not valid
+    /// UTF-8, or if an absolute-form target has an empty authority or one
+    /// with user information.
+    pub fn parse_target(
+        target: Option<&[u8]>
+    ) -> NetResult<(Self, Option<String>)> {
+        let path = Self::try_from(target)?;
+
+        let rest = ["http://", "https://"].iter().find_map(|scheme| {
+            let prefix = path.as_str().get(..scheme.len())?;
+            prefix
+                .eq_ignore_ascii_case(scheme)
+                .then(|| &path.as_str()[scheme.len()..])
+        });
+
+        let Some(rest) = rest else {
+            return Ok((path, None));
+        };
+
+        let end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+
+        if authority.is_empty() || authority.contains('@') {
+            return Err(NetError::BadPath);
+        }
+
+        let path = match path {
+            "" => Self::default(),
+            path if path.starts_with('?') => format!("/{path}").into(),
+            path => path.into(),
+        };
+
+        Ok((path, Some(authority.to_string())))
+    }
+
     /// Returns the URI path as a string slice.
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -151,9 +256,222 @@ impl UriPath {
     pub fn as_bytes(&self) -> &[u8] {
         self.as_str().as_bytes()
     }
+
+    /// Returns a copy of the URI path with "." and ".." segments removed
+    /// as described in RFC 3986 and with duplicate slashes collapsed.
+    ///
+    /// Any query string is kept as is. Paths that do not begin with a
+    /// slash, such as "*", are returned unchanged.
+    #[must_use]
+    pub fn normalize(&self) -> Self {
+        let (path, query) = match self.as_str().split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (self.as_str(), None),
+        };
+
+        if !path.starts_with('/') {
+            return self.clone();
+        }
+
+        let mut segments = Vec::new();
+
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {},
+                ".." => {
+                    segments.pop();
+                },
+                segment => segments.push(segment),
+            }
+        }
+
+        let mut normalized = format!("/{}", segments.join("/"));
+
+        // A trailing slash or dot segment leaves the path ending in a slash.
+        let ends_in_dir = path
+            .rsplit('/')
+            .next()
+            .is_some_and(|last| matches!(last, "" | "." | ".."));
+
+        if ends_in_dir && !segments.is_empty() {
+            normalized.push('/');
+        }
+
+        if let Some(query) = query {
+            normalized.push('?');
+            normalized.push_str(query);
+        }
+
+        normalized.into()
+    }
+}
+
+/// A URI split into its scheme, host, port, path, and query components.
+///
+/// The scheme defaults to "http" and the port to the scheme's default port
+/// when they are missing from the parsed string. IPv6 hosts are stored
+/// without their brackets and any fragment is discarded. `Display` writes
+/// the URI back out in a form that parses to an equal `Uri`.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Uri {
+    pub scheme: String,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+    pub query: Option<String>,
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}://{}{}", self.scheme, self.authority(), self.path)?;
+
+        if let Some(query) = self.query.as_deref() {
+            write!(f, "?{query}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Uri {
+    type Err = NetError;
+
+    fn from_str(uri: &str) -> NetResult<Self> {
+        let uri = uri.trim();
+
+        let (scheme, rest) = match uri.split_once("://") {
+            Some((scheme, rest)) if scheme.is_empty() || rest.is_empty() => {
+                return Err(NetError::BadUri);
+            },
+            Some((scheme, rest)) => (Some(scheme), rest),
+            None => (None, uri),
+        };
+
+        let has_scheme = scheme.is_some();
+
+        let scheme = match scheme {
+            None => "http",
+            Some(scheme) if scheme.eq_ignore_ascii_case("http") => "http",
+            Some(scheme) if scheme.eq_ignore_ascii_case("https") => "https",
+            Some(_) => return Err(NetError::BadScheme),
+        };
+
+        let rest = rest.split_once('#').map_or(rest, |(rest, _)| rest);
+        let end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(end);
+
+        // Without a scheme or a path, the URI must at least look like an
+        // address.
+        if !has_scheme && path.is_empty() && !authority.contains([':', '.']) {
+            return Err(NetError::BadUri);
+        }
+
+        let default_port = if scheme == "https" { 443 } else { 80 };
+        let (host, port) = Self::parse_authority(authority, default_port)?;
+
+        let (path, query) = match path.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (path, None),
+        };
+
+        Ok(Self {
+            scheme: scheme.to_string(),
+            host,
+            port,
+            path: if path.is_empty() { "/".into() } else { path.into() },
+            query
+        })
+    }
+}
+
+impl Uri {
+    /// Splits an authority such as `example.com:8080` or `[::1]` into a
+    /// lowercase host and a port, using `default_port` if none is given.
+    fn parse_authority(
+        authority: &str,
+        default_port: u16
+    ) -> NetResult<(String, u16)> {
+        let (host, port) = match authority.strip_prefix('[') {
+            // [::1] or [2001:db8::1]:443
+            Some(bracketed) => {
+                let (ip, port) = bracketed
+                    .split_once(']')
+                    .ok_or(NetError::BadAddress)?;
+
+                if ip.parse::<Ipv6Addr>().is_err() {
+                    return Err(NetError::BadAddress);
+                }
+
+                (ip, port)
+            },
+            None => authority
+                .find(':')
+                .map_or((authority, ""), |idx| authority.split_at(idx)),
+        };
+
+        if host.is_empty() || host.contains('@') {
+            return Err(NetError::BadAddress);
+        }
+
+        let port = match port.strip_prefix(':') {
+            None if port.is_empty() => default_port,
+            Some(port) => port.parse().map_err(|_| NetError::BadAddress)?,
+            None => return Err(NetError::BadAddress),
+        };
+
+        Ok((host.to_ascii_lowercase(), port))
+    }
+
+    /// Returns true if the URI's scheme is "https".
+    #[must_use]
+    pub fn is_https(&self) -> bool {
+        self.scheme == "https"
+    }
+
+    /// Returns the "host:port" address to connect to, with brackets around
+    /// an IPv6 host.
+    #[must_use]
+    pub fn addr(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// Returns the host and, if it is not the scheme's default port, the
+    /// port, as used for a Host header.
+    #[must_use]
+    pub fn authority(&self) -> String {
+        let default_port = if self.is_https() { 443 } else { 80 };
+
+        match (self.host.contains(':'), self.port == default_port) {
+            (true, true) => format!("[{}]", self.host),
+            (false, true) => self.host.clone(),
+            (_, false) => self.addr(),
+        }
+    }
+
+    /// Returns the path followed by the query, if any, as used for the
+    /// target of a request.
+    #[must_use]
+    pub fn path_and_query(&self) -> String {
+        self.query.as_deref().map_or_else(
+            || self.path.clone(),
+            |query| format!("{}?{query}", self.path),
+        )
+    }
 }
 
 /// Contains the components of an HTTP request.
+///
+/// `authority` holds the host and optional port of an absolute-form request
+/// target, such as `GET http://example.com/path HTTP/1.1` sent to a forward
+/// proxy, and is `None` for all other request targets.
+///
+/// Like a `Response`, a `Request` holds no connection, so a clone is a full
+/// copy that compares equal to the original, except that a `Body::Stream`
+/// shares its reader with the clone.
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Request {
     pub method: Method,
@@ -161,6 +479,7 @@ pub struct Request {
     pub version: Version,
     pub headers: Headers,
     pub body: Body,
+    pub authority: Option<String>,
 }
 
 impl Display for Request {
@@ -197,31 +516,24 @@ impl TryFrom<&[u8]> for Request {
         let mut tokens = first_line.splitn(3, |&b| b == b' ');
 
         let method = Method::try_from(tokens.next())?;
-        let path = UriPath::try_from(tokens.next())?;
+        let (path, authority) = UriPath::parse_target(tokens.next())?;
         let version = Version::try_from(tokens.next())?;
 
-        let headers = lines
-            .by_ref()
-            .map(utils::trim)
-            .take_while(|line| !line.is_empty())
-            .map(Header::try_from)
-            .collect::<NetResult<Headers>>()?;
+        let mut headers = Headers::parse_lines(lines.by_ref())?;
+
+        // The authority of an absolute-form target replaces the Host header.
+        if let Some(authority) = authority.as_deref() {
+            headers.insert(HOST, authority.into());
+        }
 
         let body = lines
             .flatten()
             .copied()
             .collect::<Vec<u8>>();
 
-        let body = headers
-            .get(&CONTENT_TYPE)
-            .map_or(
-                Body::Empty,
-                |content_type| {
-                    let content_type = content_type.as_str();
-                    Body::from_content_type(&body, &content_type)
-                });
+        let body = Body::from_message(&body, &headers)?;
 
-        Ok(Self { method, path, version, headers, body })
+        Ok(Self { method, path, version, headers, body, authority })
     }
 }
 
@@ -256,6 +568,107 @@ impl Request {
         self.path.as_str()
     }
 
+    /// Returns the exact bytes that are written to a `TcpStream` when this
+    /// `Request` is sent: the request line and headers, each terminated by
+    /// CRLF, an empty line, and then the body.
+    #[must_use]
+    pub fn to_wire_bytes(&self) -> Vec<u8> {
+        self.wire_bytes_from(self.headers.iter())
+    }
+
+    /// Returns the bytes written to a `TcpStream` when this `Request` is sent
+    /// with the headers in the order they were inserted.
+    #[must_use]
+    pub fn to_ordered_wire_bytes(&self) -> Vec<u8> {
+        self.wire_bytes_from(self.headers.iter_ordered())
+    }
+
+    /// Returns the bytes of the request head: the request line and the
+    /// headers, followed by the blank line that precedes the body.
+    #[must_use]
+    pub fn head_bytes(&self) -> Vec<u8> {
+        self.head_bytes_from(self.headers.iter())
+    }
+
+    /// Returns the bytes of the request head with the headers in the order
+    /// they were inserted.
+    #[must_use]
+    pub fn ordered_head_bytes(&self) -> Vec<u8> {
+        self.head_bytes_from(self.headers.iter_ordered())
+    }
+
+    /// Returns the wire bytes of this `Request` with the given headers.
+    fn wire_bytes_from<'a, I>(&self, headers: I) -> Vec<u8>
+    where
+        I: Iterator<Item = (&'a HeaderName, &'a HeaderValue)>,
+    {
+        let mut bytes = self.head_bytes_from(headers);
+
+        if !self.body.is_empty() {
+            bytes.extend_from_slice(self.body.as_bytes());
+        }
+
+        bytes
+    }
+
+    /// Returns the bytes of the request head with the given headers.
+    fn head_bytes_from<'a, I>(&self, headers: I) -> Vec<u8>
+    where
+        I: Iterator<Item = (&'a HeaderName, &'a HeaderValue)>,
+    {
+        let mut bytes = Vec::with_capacity(self.body.len() + 256);
+
+        bytes.extend_from_slice(self.method.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.path.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.version.as_bytes());
+        bytes.extend_from_slice(b"\r\n");
+
+        for (name, value) in headers {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(b": ");
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes.extend_from_slice(b"\r\n");
+        bytes
+    }
+
+    /// Returns the request line and headers as they would be echoed back in
+    /// the body of a response to a TRACE request.
+    ///
+    /// Headers that may carry credentials (Authorization,
+    /// Proxy-Authorization, and Cookie) are omitted.
+    #[must_use]
+    pub fn trace_bytes(&self) -> Vec<u8> {
+        use crate::headers::names::{AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION};
+
+        let mut bytes = Vec::with_capacity(256);
+
+        bytes.extend_from_slice(self.method.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.path.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.version.as_bytes());
+        bytes.extend_from_slice(b"\r\n");
+
+        for (name, value) in self.headers.iter() {
+            if [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION].contains(name) {
+                continue;
+            }
+
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(b": ");
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes.extend_from_slice(b"\r\n");
+        bytes
+    }
+
     /// Returns the request line as a `String` with plain formatting.
     #[must_use]
     pub fn request_line_to_plain_string(&self) -> String {
@@ -284,4 +697,183 @@ impl Request {
     pub const fn body(&self) -> &Body {
         &self.body
     }
+
+    /// Returns the request body, reading it from the `Connection` first if
+    /// it has not been read yet.
+    ///
+    /// A `Request` whose head was read with `Connection::recv_request_head`
+    /// has an empty body until this is called, so a server only pays for
+    /// reading bodies that are actually used.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading the body fails.
+    pub fn read_body(&mut self, conn: &mut Connection) -> NetResult<&Body> {
+        if conn.body_pending {
+            conn.body_pending = false;
+
+            let mut buf = Vec::with_capacity(READER_BUFSIZE);
+            self.body = conn.recv_body(&mut buf, &self.headers)?;
+        }
+
+        Ok(&self.body)
+    }
+
+    /// Returns the value of the Content-Length header, if present and valid.
+    #[must_use]
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers.get(&CONTENT_LENGTH).and_then(HeaderValue::as_u64)
+    }
+
+    /// Returns the value of the Content-Type header, if present.
+    #[must_use]
+    pub fn content_type(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&CONTENT_TYPE).map(HeaderValue::as_str)
+    }
+
+    /// Returns the value of the Host header, if present.
+    #[must_use]
+    pub fn host(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&HOST).map(HeaderValue::as_str)
+    }
+
+    /// Returns the value of the User-Agent header, if present.
+    #[must_use]
+    pub fn user_agent(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&USER_AGENT).map(HeaderValue::as_str)
+    }
+
+    /// Returns the media type from `offered` that best matches the Accept
+    /// header, or the first offered type if there is no Accept header.
+    ///
+    /// Each offered type takes the quality value of the most specific media
+    /// range that matches it, so "text/html" is preferred over "text/*",
+    /// which is preferred over "*/*". Types with a quality value of zero are
+    /// not acceptable, and ties go to the type listed first in `offered`.
+    #[must_use]
+    pub fn preferred_content_type<'a>(
+        &self,
+        offered: &[&'a str]
+    ) -> Option<&'a str> {
+        let Some(accept) = self.headers.get(&ACCEPT) else {
+            return offered.first().copied();
+        };
+
+        let accept = accept.as_str();
+
+        let ranges = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let (kind, subtype) = params.next()?.trim().split_once('/')?;
+                let quality = params
+                    .filter_map(|param| param.split_once('='))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                    .map_or(Some(1.0), |(_, q)| q.trim().parse::<f32>().ok())?;
+                Some((kind.trim(), subtype.trim(), quality.clamp(0.0, 1.0)))
+            })
+            .collect::<Vec<(&str, &str, f32)>>();
+
+        // The quality of the most specific range matching `offer`, if any.
+        let quality = |offer: &str| {
+            let offer = offer.split(';').next().unwrap_or_default().trim();
+            let (kind, subtype) = offer.split_once('/')?;
+
+            ranges
+                .iter()
+                .filter_map(|&(range_kind, range_subtype, quality)| {
+                    let specificity = match (range_kind, range_subtype) {
+                        ("*", "*") => 0,
+                        _ if !range_kind.eq_ignore_ascii_case(kind) => {
+                            return None;
+                        },
+                        (_, "*") => 1,
+                        _ if range_subtype.eq_ignore_ascii_case(subtype) => 2,
+                        _ => return None,
+                    };
+                    Some((specificity, quality))
+                })
+                .max_by_key(|&(specificity, _)| specificity)
+                .map(|(_, quality)| quality)
+        };
+
+        let mut best: Option<(&'a str, f32)> = None;
+
+        for &offer in offered {
+            let Some(quality) = quality(offer) else {
+                continue;
+            };
+
+            if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+                best = Some((offer, quality));
+            }
+        }
+
+        best.map(|(offer, _)| offer)
+    }
+
+    /// Returns true if the headers announce a message body, either with a
+    /// non-zero Content-Length or with chunked transfer encoding.
+    #[must_use]
+    pub fn has_body(&self) -> bool {
+        utils::is_chunked(&self.headers)
+            || self.content_length().is_some_and(|len| len > 0)
+    }
+
+    /// Returns the request body as text, replacing any invalid UTF-8
+    /// sequences with the replacement character.
+    #[must_use]
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.body.as_bytes())
+    }
+
+    /// Returns the request body as a bytes slice.
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        self.body.as_bytes()
+    }
+
+    /// Sets the Content-Length header to the length of the body, replacing
+    /// any stale value.
+    ///
+    /// The header is removed if the body is empty or is sent using chunked
+    /// transfer encoding. A `Body::Stream` uses its length if known and is
+    /// otherwise sent with chunked transfer encoding.
+    pub fn sync_content_length(&mut self) {
+        if self.body.is_empty() || utils::is_chunked(&self.headers) {
+            self.headers.remove(&CONTENT_LENGTH);
+        } else if let Body::Stream(ref stream) = self.body {
+            if let Some(len) = stream.len {
+                let len = len.to_string();
+                self.headers.insert(CONTENT_LENGTH, len.as_str().into());
+            } else {
+                self.headers.remove(&CONTENT_LENGTH);
+                self.headers.insert(TRANSFER_ENCODING, "chunked".into());
+            }
+        } else {
+            self.headers.insert(CONTENT_LENGTH, self.body.len().into());
+        }
+    }
+
+    /// Returns true if the client is waiting for a "100 Continue" response
+    /// before sending the message body.
+    #[must_use]
+    pub fn expects_continue(&self) -> bool {
+        self.headers.get(&EXPECT).is_some_and(|value| {
+            value.as_str().trim().eq_ignore_ascii_case("100-continue")
+        })
+    }
+
+    /// Returns the originating client's IP address from the first entry of
+    /// the X-Forwarded-For header, if present and valid.
+    #[must_use]
+    pub fn forwarded_for(&self) -> Option<IpAddr> {
+        let value = self.headers.get(&X_FORWARDED_FOR)?.as_str();
+        let first = value.split(',').next()?.trim();
+
+        first.parse::<IpAddr>().ok().or_else(|| {
+            // Some proxies include the client's port.
+            first.parse::<SocketAddr>().ok().map(|addr| addr.ip())
+        })
+    }
 }
@@ -1,11 +1,16 @@
+use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::str::{self, FromStr};
 
 use crate::{
-    Body, Header, Headers, NetError, NetResult, Status, Target, Version,
-    utils,
+    Body, HeaderName, HeaderValue, Headers, NetError, NetResult, Request,
+    RetryAfter, Status, Target, Version, utils,
+};
+use crate::encoding;
+use crate::headers::names::{
+    CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION,
+    RETRY_AFTER, TRANSFER_ENCODING,
 };
-use crate::headers::names::CONTENT_TYPE;
 use crate::style::colors::{MAGENTA, RESET};
 
 /// An HTTP response builder object.
@@ -14,6 +19,7 @@ use crate::style::colors::{MAGENTA, RESET};
 pub struct ResponseBuilder {
     pub version: Version,
     pub status: Option<NetResult<Status>>,
+    pub reason: Option<String>,
     pub headers: Headers,
     pub body: Option<NetResult<Body>>,
 }
@@ -39,6 +45,17 @@ impl ResponseBuilder {
         self
     }
 
+    /// Sets a reason phrase to send in the status line in place of the
+    /// canonical one for the status code.
+    ///
+    /// Control characters other than horizontal tabs are removed, since
+    /// they are not allowed in a reason phrase.
+    #[must_use]
+    pub fn reason(&mut self, reason: &str) -> &mut Self {
+        self.reason = Some(strip_controls(reason));
+        self
+    }
+
     /// Inserts a header entry from the given name and value.
     #[must_use]
     pub fn header(&mut self, name: &str, value: &[u8]) -> &mut Self {
@@ -53,6 +70,33 @@ impl ResponseBuilder {
         self
     }
 
+    /// Sets a redirect status code and a Location header of `location`,
+    /// leaving the body empty.
+    ///
+    /// Building the `Response` fails with `NetError::BadStatusCode` if
+    /// `code` is not a 3xx status code.
+    #[must_use]
+    pub fn redirect(&mut self, code: u16, location: &str) -> &mut Self {
+        self.status = Some(match code {
+            300..=399 => Status::try_from(code),
+            _ => Err(NetError::BadStatusCode),
+        });
+        self.headers.insert(LOCATION, location.into());
+        self.body = Some(Ok(Body::Empty));
+        self
+    }
+
+    /// Sends the response body using chunked transfer encoding.
+    ///
+    /// This sets the Transfer-Encoding header to "chunked" and removes any
+    /// Content-Length header.
+    #[must_use]
+    pub fn chunked(&mut self) -> &mut Self {
+        self.headers.remove(&CONTENT_LENGTH);
+        self.headers.insert(TRANSFER_ENCODING, "chunked".into());
+        self
+    }
+
     /// Sets the response body based on the given `Target`.
     #[must_use]
     pub fn target(&mut self, target: Target) -> &mut Self {
@@ -92,6 +136,7 @@ impl ResponseBuilder {
         Ok(Response {
             version: self.version,
             status,
+            reason: self.reason.take(),
             headers: self.headers.clone(),
             body
         })
@@ -99,17 +144,30 @@ impl ResponseBuilder {
 }
 
 /// An HTTP response.
+///
+/// A `Response` does not hold the connection it was read from or is written
+/// to, so a clone copies the status, headers, and body and compares equal to
+/// the original. The exception is a `Body::Stream`, whose clone shares the
+/// same reader, so only one of the two bodies can be sent.
+///
+/// If `reason` is set, it replaces the canonical reason phrase for `status`
+/// in the status line. The status code is always taken from `status`. A
+/// `Response` whose `reason` contains a control character other than a
+/// horizontal tab is refused by `Connection::send_response`. A received
+/// reason phrase is kept in `reason` only if it differs from the canonical
+/// one.
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Response {
     pub version: Version,
     pub status: Status,
+    pub reason: Option<String>,
     pub headers: Headers,
     pub body: Body,
 }
 
 impl Display for Response {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        writeln!(f, "{} {}", &self.version, &self.status)?;
+        writeln!(f, "{} {}", &self.version, self.status_text())?;
 
         writeln!(f, "{}", &self.headers)?;
 
@@ -146,30 +204,45 @@ impl TryFrom<&[u8]> for Response {
         let mut tokens = first_line.splitn(2, |&b| b == b' ');
 
         let version = Version::try_from(tokens.next())?;
-        let status = Status::try_from(tokens.next())?;
+        let rest = tokens.next();
+        let status = Status::try_from(rest)?;
+        let reason = rest.and_then(|rest| parse_reason(status, rest));
 
-        let headers = lines
-            .by_ref()
-            .map(utils::trim)
-            .take_while(|line| !line.is_empty())
-            .map(Header::try_from)
-            .collect::<NetResult<Headers>>()?;
+        let headers = Headers::parse_lines(lines.by_ref())?;
 
         let body = lines
             .flatten()
             .copied()
             .collect::<Vec<u8>>();
 
-        let body = headers
-            .get(&CONTENT_TYPE)
-            .map_or(
-                Body::Empty,
-                |content_type| {
-                    let content_type = content_type.as_str();
-                    Body::from_content_type(&body, &content_type)
-                });
+        // Responses to HEAD requests have a Content-Length but no body.
+        let body = if body.is_empty() {
+            Body::Empty
+        } else {
+            Body::from_message(&body, &headers)?
+        };
 
-        Ok(Self { version, status, headers, body })
+        Ok(Self { version, status, reason, headers, body })
+    }
+}
+
+/// Returns the string with control characters other than horizontal tabs
+/// removed.
+fn strip_controls(s: &str) -> String {
+    s.chars().filter(|&c| c == '\t' || !c.is_control()).collect()
+}
+
+/// Returns the reason phrase from the rest of a received status line, which
+/// starts with the status code, if it differs from the canonical one for
+/// `status`. Control characters other than horizontal tabs are removed.
+pub(crate) fn parse_reason(status: Status, rest: &[u8]) -> Option<String> {
+    let reason = rest.get(4..)?.trim_ascii();
+    let reason = strip_controls(&String::from_utf8_lossy(reason));
+
+    if reason.is_empty() || status.msg() == Some(reason.as_str()) {
+        None
+    } else {
+        Some(reason)
     }
 }
 
@@ -198,16 +271,85 @@ impl Response {
         &self.status
     }
 
+    /// Returns the reason phrase of the status line, which is the one set
+    /// in `reason`, if any, or else the canonical one for the status code.
+    #[must_use]
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref().or_else(|| self.status.msg())
+    }
+
+    /// Returns an error if `reason` contains a control character other than
+    /// a horizontal tab, which could end the status line early.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NetError::BadResponse` if the reason phrase is invalid.
+    pub fn validate_reason(&self) -> NetResult<()> {
+        let is_bad = |c: char| c != '\t' && c.is_control();
+
+        match self.reason.as_deref() {
+            Some(reason) if reason.contains(is_bad) => {
+                Err(NetError::BadResponse)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the status code and reason phrase of the status line.
+    fn status_text(&self) -> Cow<'static, str> {
+        self.reason.as_deref().map_or_else(
+            || self.status.as_str(),
+            |reason| format!("{} {reason}", self.status.code()).into(),
+        )
+    }
+
     /// Returns the status line as a `String` with plain formatting.
     #[must_use]
     pub fn status_line_to_plain_string(&self) -> String {
-        format!("{} {}", &self.version, &self.status)
+        format!("{} {}", &self.version, self.status_text())
     }
 
     /// Returns the status line as a `String` with color formatting.
     #[must_use]
     pub fn status_line_to_color_string(&self) -> String {
-        format!("{MAGENTA}{} {}{RESET}", &self.version, &self.status)
+        format!("{MAGENTA}{} {}{RESET}", &self.version, self.status_text())
+    }
+
+    /// Returns the bytes of the response head: the status line and the
+    /// headers, followed by the blank line that precedes the body.
+    #[must_use]
+    pub fn head_bytes(&self) -> Vec<u8> {
+        self.head_bytes_from(self.headers.iter())
+    }
+
+    /// Returns the bytes of the response head with the headers in the order
+    /// they were inserted.
+    #[must_use]
+    pub fn ordered_head_bytes(&self) -> Vec<u8> {
+        self.head_bytes_from(self.headers.iter_ordered())
+    }
+
+    /// Returns the bytes of the response head with the given headers.
+    fn head_bytes_from<'a, I>(&self, headers: I) -> Vec<u8>
+    where
+        I: Iterator<Item = (&'a HeaderName, &'a HeaderValue)>,
+    {
+        let mut bytes = Vec::with_capacity(256);
+
+        bytes.extend_from_slice(self.version.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.status_text().as_bytes());
+        bytes.extend_from_slice(b"\r\n");
+
+        for (name, value) in headers {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(b": ");
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+
+        bytes.extend_from_slice(b"\r\n");
+        bytes
     }
 
     /// Returns a reference to the response `Headers`.
@@ -221,4 +363,163 @@ impl Response {
     pub const fn body(&self) -> &Body {
         &self.body
     }
+
+    /// Returns the response body as text, replacing any invalid UTF-8
+    /// sequences with the replacement character.
+    #[must_use]
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.body.as_bytes())
+    }
+
+    /// Returns the response body as a bytes slice.
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        self.body.as_bytes()
+    }
+
+    /// Returns the value of the Content-Type header, if present.
+    #[must_use]
+    pub fn content_type(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&CONTENT_TYPE).map(HeaderValue::as_str)
+    }
+
+    /// Returns the value of the Content-Length header, if present and valid.
+    #[must_use]
+    pub fn content_length(&self) -> Option<u64> {
+        self.headers.get(&CONTENT_LENGTH).and_then(HeaderValue::as_u64)
+    }
+
+    /// Returns the value of the Content-Encoding header, if present.
+    #[must_use]
+    pub fn content_encoding(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&CONTENT_ENCODING).map(HeaderValue::as_str)
+    }
+
+    /// Returns the value of the Location header, if present.
+    #[must_use]
+    pub fn location(&self) -> Option<Cow<'_, str>> {
+        self.headers.get(&LOCATION).map(HeaderValue::as_str)
+    }
+
+    /// Returns true if the response body uses chunked transfer encoding.
+    #[must_use]
+    pub fn is_chunked(&self) -> bool {
+        utils::is_chunked(&self.headers)
+    }
+
+    /// Sets the Content-Length header to the length of the body, replacing
+    /// any stale value.
+    ///
+    /// The header is removed for status codes that never have a body (1xx,
+    /// 204, and 304) and for chunked responses and event streams. An empty
+    /// body gets a Content-Length of 0, so that a kept-alive client knows
+    /// where the response ends.
+    ///
+    /// A `Body::Stream` uses its length if known. Otherwise, it is sent with
+    /// chunked transfer encoding, or, for HTTP/1.0, by closing the
+    /// connection after the body.
+    pub fn sync_content_length(&mut self) {
+        let has_no_body = matches!(self.status.code(), 100..=199 | 204 | 304)
+            || self.is_chunked()
+            || self.body.is_event_stream();
+
+        if has_no_body {
+            self.headers.remove(&CONTENT_LENGTH);
+            return;
+        }
+
+        let Body::Stream(ref stream) = self.body else {
+            self.headers.insert(CONTENT_LENGTH, self.body.len().into());
+            return;
+        };
+
+        if let Some(len) = stream.len {
+            let len = len.to_string();
+            self.headers.insert(CONTENT_LENGTH, len.as_str().into());
+        } else if self.version == Version::OneDotZero {
+            // HTTP/1.0 has no chunked encoding, so the end of the body is
+            // marked by closing the connection.
+            self.headers.remove(&CONTENT_LENGTH);
+            self.headers.insert(CONNECTION, "close".into());
+        } else {
+            self.headers.remove(&CONTENT_LENGTH);
+            self.headers.insert(TRANSFER_ENCODING, "chunked".into());
+        }
+    }
+
+    /// Matches the response to the HTTP version of the `Request`.
+    ///
+    /// HTTP/1.0 requests get an HTTP/1.0 status line and a Connection
+    /// header of "close", unless the client asked for "keep-alive".
+    /// Responses to other versions are left unchanged.
+    pub fn match_version(&mut self, req: &Request) {
+        if req.version != Version::OneDotZero {
+            return;
+        }
+
+        let keep_alive = req.headers.get(&CONNECTION).is_some_and(|value| {
+            value.as_str().to_ascii_lowercase().contains("keep-alive")
+        });
+
+        self.version = Version::OneDotZero;
+
+        if keep_alive {
+            self.headers.insert(CONNECTION, "keep-alive".into());
+        } else {
+            self.headers.insert(CONNECTION, "close".into());
+        }
+    }
+
+    /// Sets the Retry-After header to the given delay or date.
+    pub fn retry_after(&mut self, retry: RetryAfter) {
+        self.headers.insert(RETRY_AFTER, retry.to_string().as_str().into());
+    }
+
+    /// Decodes the response body according to its Content-Encoding header.
+    ///
+    /// On success, the Content-Encoding header is removed and the
+    /// Content-Length header is updated to match the decoded body. Bodies
+    /// with an unsupported encoding are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body is not valid for its encoding, or
+    /// `NetError::BodyTooLarge` if the decoded body would be longer than
+    /// `encoding::MAX_DECODED_LEN` bytes.
+    pub fn decompress(&mut self) -> NetResult<()> {
+        let Some(content_encoding) = self.headers.get(&CONTENT_ENCODING) else {
+            return Ok(());
+        };
+
+        // Responses to HEAD requests, for example, have no body to decode.
+        if self.body.is_empty() {
+            return Ok(());
+        }
+
+        let content_encoding = content_encoding.as_str();
+        let encoded = self.body.as_bytes();
+
+        let max_len = encoding::MAX_DECODED_LEN;
+        let Some(decoded) =
+            encoding::decode(&content_encoding, encoded, max_len)?
+        else {
+            return Ok(());
+        };
+
+        let content_type = self.headers
+            .get(&CONTENT_TYPE)
+            .map(|value| value.as_str().into_owned())
+            .unwrap_or_default();
+
+        let decoded_len = decoded.len();
+
+        self.body = if content_type.is_empty() {
+            Body::Bytes(decoded.into())
+        } else {
+            Body::from_content_type(&decoded, &content_type)
+        };
+        self.headers.remove(&CONTENT_ENCODING);
+        self.headers.insert(CONTENT_LENGTH, decoded_len.into());
+        Ok(())
+    }
 }
@@ -1,11 +1,32 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
-use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{
+    Debug, Display, Formatter, Result as FmtResult, Write as _,
+};
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::io::Read;
 use std::path::Path;
-
-use crate::{Body, Method, NetResult, Request, Response, Target, UriPath};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{
+    Body, Client, Connection, Handler, HeaderValue, Method, NetError,
+    NetResult, Request, Response, Status, Target, Uri, UriPath, Version,
+    READER_BUFSIZE, utils,
+};
+use crate::headers::names::{
+    ACCEPT, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, EXPECT, HOST,
+    IF_MATCH, IF_UNMODIFIED_SINCE,
+};
+
+/// How long a proxy route waits to connect to its upstream server, and for
+/// each read from or write to it, before responding with a 502 status.
+pub const PROXY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum length of a chunked upstream response body, which a proxy
+/// route reads into memory before responding.
+pub const MAX_PROXY_BODY: usize = 16 * 1024 * 1024;
 
 /// Represents a server end-point and the target resource to serve.
 #[derive(Clone, Default)]
@@ -13,6 +34,8 @@ pub struct Route {
     pub method: Method,
     pub path: Option<UriPath>,
     pub target: Target,
+    pub max_age: Option<u32>,
+    pub accepts: Vec<&'static str>,
 }
 
 impl Display for Route {
@@ -24,13 +47,25 @@ impl Display for Route {
 impl Debug for Route {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match self.path.as_ref() {
-            Some(path) => write!(
-                f,
-                "{} {} -> {:?}",
-                self.method.as_str(),
-                path.as_str(),
-                &self.target
-            ),
+            Some(path) => {
+                write!(
+                    f,
+                    "{} {} -> {:?}",
+                    self.method.as_str(),
+                    path.as_str(),
+                    &self.target
+                )?;
+
+                if let Some(max_age) = self.max_age {
+                    write!(f, " (max-age={max_age})")?;
+                }
+
+                if !self.accepts.is_empty() {
+                    write!(f, " (accepts={})", self.accepts.join(", "))?;
+                }
+
+                Ok(())
+            },
             None if self.is_not_found() => {
                 write!(f, "ANY -> {:?}", &self.target)
             },
@@ -81,7 +116,7 @@ impl Route {
     #[must_use]
     pub const fn new(method: Method, uri_path: UriPath, target: Target) -> Self {
         let path = Some(uri_path);
-        Self { method, path, target }
+        Self { method, path, target, max_age: None, accepts: Vec::new() }
     }
 
     /// Returns this route's HTTP method.
@@ -96,6 +131,43 @@ impl Route {
         self.path.as_ref()
     }
 
+    /// Returns the number of seconds that responses from this route may be
+    /// cached, if set.
+    #[must_use]
+    pub const fn max_age(&self) -> Option<u32> {
+        self.max_age
+    }
+
+    /// Returns the request content types this route accepts. An empty slice
+    /// means that any content type is accepted.
+    #[must_use]
+    pub fn accepts(&self) -> &[&'static str] {
+        &self.accepts
+    }
+
+    /// Returns true if this route accepts the Content-Type of the given
+    /// `Request`.
+    ///
+    /// Media type parameters, such as "charset", are ignored. Requests
+    /// without a body are always accepted. A body that has not been read
+    /// yet counts if the headers announce it.
+    #[must_use]
+    pub fn accepts_content_type(&self, req: &Request) -> bool {
+        if self.accepts.is_empty() || (req.body.is_empty() && !req.has_body())
+        {
+            return true;
+        }
+
+        req.headers.get(&CONTENT_TYPE).is_some_and(|value| {
+            let value = value.as_str();
+            let media_type = value.split(';').next().unwrap_or_default();
+
+            self.accepts.iter().any(|accepted| {
+                accepted.eq_ignore_ascii_case(media_type.trim())
+            })
+        })
+    }
+
     /// Returns true if the `Route` is a GET route.
     #[must_use]
     pub const fn is_get(&self) -> bool {
@@ -163,9 +235,96 @@ impl Route {
     }
 }
 
+/// Options that relax how request paths are compared to route paths.
+///
+/// Both options are disabled by default, so paths must match exactly.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PathMatch {
+    pub ignore_trailing_slash: bool,
+    pub case_insensitive: bool,
+}
+
+impl PathMatch {
+    /// Returns true if paths are only matched exactly.
+    #[must_use]
+    pub const fn is_exact(self) -> bool {
+        !self.ignore_trailing_slash && !self.case_insensitive
+    }
+
+    /// Returns true if the request path matches the route path under these
+    /// options.
+    ///
+    /// When trailing slashes are ignored, "/about" and "/about/" are
+    /// equivalent, but the root path "/" is left as is.
+    #[must_use]
+    pub fn matches(self, route_path: &str, req_path: &str) -> bool {
+        let route_path = self.trim(route_path);
+        let req_path = self.trim(req_path);
+
+        if self.case_insensitive {
+            route_path.eq_ignore_ascii_case(req_path)
+        } else {
+            route_path == req_path
+        }
+    }
+
+    /// Removes a trailing slash from a non-root path if trailing slashes are
+    /// ignored.
+    fn trim(self, path: &str) -> &str {
+        if self.ignore_trailing_slash && path.len() > 1 {
+            path.strip_suffix('/').unwrap_or(path)
+        } else {
+            path
+        }
+    }
+}
+
+/// Selects the requests to which a not-found `Target` applies.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NotFoundFor {
+    /// Requests with the given method.
+    Method(Method),
+    /// Requests whose Accept header lists the given media type.
+    Accept(&'static str),
+}
+
+impl Display for NotFoundFor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Method(method) => write!(f, "{}", method.as_str()),
+            Self::Accept(media_type) => write!(f, "Accept: {media_type}"),
+        }
+    }
+}
+
+impl From<Method> for NotFoundFor {
+    fn from(method: Method) -> Self {
+        Self::Method(method)
+    }
+}
+
+impl From<&'static str> for NotFoundFor {
+    fn from(media_type: &'static str) -> Self {
+        Self::Accept(media_type)
+    }
+}
+
 /// The server router.
 #[derive(Clone, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Router(pub BTreeSet<Route>);
+pub struct Router {
+    routes: BTreeSet<Route>,
+    // Not-found targets that are specific to a request method or an
+    // accepted media type.
+    not_found_for: BTreeMap<NotFoundFor, Target>,
+    // Error pages keyed by status code.
+    error_pages: BTreeMap<u16, Target>,
+}
+
+impl From<BTreeSet<Route>> for Router {
+    fn from(routes: BTreeSet<Route>) -> Self {
+        Self { routes, ..Self::default() }
+    }
+}
 
 impl Display for Router {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
@@ -174,8 +333,8 @@ impl Display for Router {
         } else {
             writeln!(f, "Router(")?;
 
-            for route in &self.0 {
-                writeln!(f, "    {route},")?;
+            for line in self.describe().lines() {
+                writeln!(f, "    {line},")?;
             }
 
             write!(f, ")")
@@ -190,8 +349,16 @@ impl Debug for Router {
         } else {
             writeln!(f, "Router(")?;
 
-            for route in &self.0 {
-                writeln!(f, "    {route:?},")?;
+            for route in &self.routes {
+                writeln!(f, "    {route},")?;
+            }
+
+            for (key, target) in &self.not_found_for {
+                writeln!(f, "    NOT FOUND ({key}) -> {target:?},")?;
+            }
+
+            for (code, target) in &self.error_pages {
+                writeln!(f, "    ERROR PAGE ({code}) -> {target:?},")?;
             }
 
             write!(f, ")")
@@ -209,23 +376,83 @@ impl Router {
     /// Returns true if this `Router` contains the provided `Route`.
     #[must_use]
     pub fn contains(&self, route: &Route) -> bool {
-        self.0.contains(route)
+        self.routes.contains(route)
     }
 
     /// Returns true if the `Router` contains no entries.
     #[must_use]
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.routes.is_empty() && self.not_found_for.is_empty() && self.error_pages.is_empty()
     }
 
     /// Appends another `Router` collection to this one.
     pub fn append(&mut self, other: &mut Self) {
-        self.0.append(&mut other.0);
+        self.routes.append(&mut other.routes);
+        self.not_found_for.append(&mut other.not_found_for);
+        self.error_pages.append(&mut other.error_pages);
     }
 
     /// Mount a new `Route` to the `Router`.
     pub fn mount(&mut self, route: Route) {
-        self.0.insert(route);
+        self.routes.insert(route);
+    }
+
+    /// Mounts a `Route`, replacing any equal `Route` that is already
+    /// mounted, and returns the replaced `Route`.
+    pub fn replace(&mut self, route: Route) -> Option<Route> {
+        self.routes.replace(route)
+    }
+
+    /// Returns an iterator over the mounted routes, sorted by method and
+    /// then by path.
+    pub fn routes(&self) -> impl Iterator<Item = &Route> {
+        self.routes.iter()
+    }
+
+    /// Returns an iterator over the not-found targets that were configured
+    /// with `Router::not_found_for`, sorted by key.
+    pub fn not_found_targets(
+        &self
+    ) -> impl Iterator<Item = (&NotFoundFor, &Target)> {
+        self.not_found_for.iter()
+    }
+
+    /// Returns the error page registered for the given status code, if any.
+    #[must_use]
+    pub fn error_page_for(&self, status_code: u16) -> Option<&Target> {
+        self.error_pages.get(&status_code)
+    }
+
+    /// Returns a listing of the routes, one per line, with the method, the
+    /// path, and the kind of target, such as "GET /about -> File". Lines
+    /// for not-found targets and error pages follow the routes.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let mut listing = String::new();
+
+        for route in self.routes() {
+            let method = route.method.as_str();
+            let kind = route.target.kind();
+
+            let _ = match route.path.as_ref() {
+                Some(path) => {
+                    writeln!(listing, "{method} {} -> {kind}", path.as_str())
+                },
+                None => writeln!(listing, "{method} -> {kind}"),
+            };
+        }
+
+        for (key, target) in &self.not_found_for {
+            let kind = target.kind();
+            let _ = writeln!(listing, "NOT FOUND ({key}) -> {kind}");
+        }
+
+        for (code, target) in &self.error_pages {
+            let kind = target.kind();
+            let _ = writeln!(listing, "ERROR PAGE ({code}) -> {kind}");
+        }
+
+        listing
     }
 
     /// Returns the `Target` for the given `Request` if a corresponding
@@ -233,10 +460,36 @@ impl Router {
     /// does not exist.
     #[must_use]
     pub fn get_target(&self, req: &Request) -> Target {
-        let path = if matches!(req.method, Method::Any | Method::Shutdown) {
+        self.get_route(req)
+            .map_or(Target::NotFound, |route| route.target.clone())
+    }
+
+    /// Returns the `Route` that corresponds to the given `Request`, if one
+    /// exists in this `Router`.
+    ///
+    /// The request path is normalized with `UriPath::normalize` before it is
+    /// matched.
+    #[must_use]
+    pub fn get_route(&self, req: &Request) -> Option<&Route> {
+        self.get_route_with(req, PathMatch::default())
+    }
+
+    /// Returns the `Route` that corresponds to the given `Request`, if one
+    /// exists in this `Router`, comparing paths according to `opts`.
+    ///
+    /// An exact match is always preferred over a relaxed one, and both are
+    /// preferred over a proxy route whose path is a prefix of the request
+    /// path.
+    #[must_use]
+    pub fn get_route_with(
+        &self,
+        req: &Request,
+        opts: PathMatch
+    ) -> Option<&Route> {
+        let path = if matches!(req.method, Method::Any) {
             None
         } else {
-            Some(req.path.clone())
+            Some(req.path.normalize())
         };
 
         let mut query_route = Route {
@@ -245,20 +498,72 @@ impl Router {
             ..Route::default()
         };
 
-        match self.0.get(&query_route) {
+        let found = match self.routes.get(&query_route) {
             // Route was found.
-            Some(route) => route.target.clone(),
+            Some(route) => Some(route),
             // Allow HEAD requests for all configured GET routes.
             None if query_route.is_head() => {
                 query_route.method = Method::Get;
-
-                self.0.get(&query_route).map_or(
-                    Target::NotFound,
-                    |route| route.target.clone())
+                self.routes.get(&query_route)
             },
             // Route was not found.
-            None => Target::NotFound,
+            None => None,
+        };
+
+        if found.is_some() {
+            return found;
+        }
+
+        let req_path = query_route.path.as_ref()?;
+
+        if opts.is_exact() {
+            return self.get_proxy_route(req_path);
         }
+
+        let find = |method: Method| {
+            self.routes.iter().find(|route| {
+                route.method == method
+                    && route.path.as_ref().is_some_and(|path| {
+                        opts.matches(path.as_str(), req_path.as_str())
+                    })
+            })
+        };
+
+        find(req.method)
+            .or_else(|| {
+                // Allow HEAD requests for all configured GET routes.
+                if matches!(req.method, Method::Head) {
+                    find(Method::Get)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| self.get_proxy_route(req_path))
+    }
+
+    /// Returns the proxy `Route` with the longest path that is a prefix of
+    /// `req_path`, if any.
+    ///
+    /// The prefix must end at a segment boundary, so a proxy route for
+    /// "/api" matches "/api" and "/api/users" but not "/apis".
+    fn get_proxy_route(&self, req_path: &UriPath) -> Option<&Route> {
+        let req_path = req_path.as_str();
+
+        self.routes
+            .iter()
+            .filter(|route| route.target.is_proxy())
+            .filter_map(|route| {
+                let prefix = route.path.as_ref()?.as_str();
+                let rest = req_path.strip_prefix(prefix)?;
+
+                let at_boundary = rest.is_empty()
+                    || prefix.ends_with('/')
+                    || rest.starts_with(['/', '?']);
+
+                at_boundary.then_some((prefix.len(), route))
+            })
+            .max_by_key(|&(len, _)| len)
+            .map(|(_, route)| route)
     }
 
     /// Resolves the given `Request` into a `Response`.
@@ -267,24 +572,74 @@ impl Router {
     ///
     /// Returns an error if `ResponseBuilder::build` is unable to construct a
     /// `Response`.
-    #[allow(clippy::similar_names)]
     pub fn resolve(&self, req: &Request) -> NetResult<Response> {
-        let mut res = match self.get_target(req) {
+        self.resolve_with(req, PathMatch::default())
+    }
+
+    /// Resolves the given `Request` into a `Response`, comparing paths
+    /// according to `opts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ResponseBuilder::build` is unable to construct a
+    /// `Response`.
+    #[allow(clippy::similar_names)]
+    pub fn resolve_with(
+        &self,
+        req: &Request,
+        opts: PathMatch
+    ) -> NetResult<Response> {
+        let route = self.get_route_with(req, opts);
+        let max_age = route.and_then(Route::max_age);
+        let target = route
+            .map_or(Target::NotFound, |route| route.target.clone());
+
+        // Reject request bodies that the route does not accept.
+        if route.is_some_and(|route| !route.accepts_content_type(req)) {
+            return self.error_response(415, req);
+        }
+
+        // Evaluate preconditions before modifying a file-backed resource.
+        if let Target::File(ref path) = target {
+            if !req.method.is_safe() && !Self::preconditions_met(req, path) {
+                return self.error_response(412, req);
+            }
+        }
+
+        let mut res = match target {
             // Route not found.
             Target::NotFound => {
-                let not_found_route = Route {
-                    method: Method::Any,
-                    path: None,
-                    ..Route::default()
+                let target = match self.not_found_target(req) {
+                    Target::NotFound => self.error_pages
+                        .get(&404)
+                        .cloned()
+                        .unwrap_or(Target::NotFound),
+                    target => target,
                 };
 
-                // Check for a configured "route not found" target.
-                let target = self.0.get(&not_found_route).map_or(
-                    Target::NotFound,
-                    |route| route.target.clone());
-
                 Response::builder().status_code(404).target(target).build()?
             },
+            // Redirect route found.
+            Target::Redirect(code, ref location) => {
+                Response::builder().redirect(code, location).build()?
+            },
+            // Proxy route found.
+            Target::Proxy(ref upstream) => match Self::forward(req, upstream) {
+                Ok(res) => res,
+                Err(_) => return self.error_response(502, req),
+            },
+            // Handler route found.
+            Target::Handler(handler) => {
+                let code = if matches!(req.method, Method::Post) {
+                    201
+                } else {
+                    200
+                };
+
+                let mut res = Response::builder().status_code(code).build()?;
+                handler.run(req, &mut res);
+                res
+            },
             // POST route found.
             target if matches!(req.method, Method::Post) => {
                 Response::builder().status_code(201).target(target).build()?
@@ -295,14 +650,211 @@ impl Router {
             },
         };
 
+        // Allow caching of routes configured with a max-age.
+        if let Some(max_age) = max_age {
+            let cache_control = format!("public, max-age={max_age}");
+            res.headers.insert(CACHE_CONTROL, cache_control.as_str().into());
+        }
+
         // Remove the response body, if appropriate.
         if Body::should_be_empty(res.status.code(), &req.method) {
             res.body = Body::Empty;
         }
 
+        res.match_version(req);
+        Ok(res)
+    }
+
+    /// Forwards the `Request` to the `upstream` server and returns its
+    /// response.
+    ///
+    /// The request is sent with its path appended to the path of the
+    /// `upstream` URI, a Host header for the upstream server, and without
+    /// hop-by-hop headers. The hop-by-hop headers are also removed from the
+    /// response. A response body with a Content-Length is streamed from the
+    /// upstream connection, as is one that ends when the upstream server
+    /// closes the connection. Chunked response bodies of up to
+    /// `MAX_PROXY_BODY` bytes are read in full first.
+    ///
+    /// Connecting and each read from or write to the upstream server time
+    /// out after `PROXY_TIMEOUT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if connecting to the upstream server, sending the
+    /// request, or reading the response head fails, or if a chunked
+    /// response body is longer than `MAX_PROXY_BODY`.
+    fn forward(req: &Request, upstream: &Uri) -> NetResult<Response> {
+        let addr = upstream.addr();
+        let base_path = upstream.path_and_query();
+        let path = format!("{}{}", base_path.trim_end_matches('/'), req.path);
+
+        let mut upstream_req = req.clone();
+        upstream_req.path = path.into();
+        upstream_req.version = Version::OneDotOne;
+        upstream_req.authority = None;
+        upstream_req.headers.remove_hop_by_hop();
+        upstream_req.headers.remove(&EXPECT);
+        upstream_req.headers.insert(HOST, upstream.authority().as_str().into());
+
+        let mut client = Client::builder()
+            .auto_accept(false)
+            .user_agent(None)
+            .timeout(PROXY_TIMEOUT)
+            .addr(&addr)
+            .req(upstream_req)
+            .build()?;
+
+        if let Some(conn) = client.conn.as_ref() {
+            conn.set_read_timeout(Some(PROXY_TIMEOUT))?;
+            conn.set_write_timeout(Some(PROXY_TIMEOUT))?;
+        }
+
+        client.send_request()?;
+
+        let mut conn = client.conn.take().ok_or(NetError::NotConnected)?;
+        let mut res = conn.recv_response_head()?;
+
+        let content_len = res
+            .headers
+            .get(&CONTENT_LENGTH)
+            .and_then(HeaderValue::as_u64);
+
+        res.body = if Body::should_be_empty(res.status.code(), &req.method) {
+            Body::Empty
+        } else if res.is_chunked() {
+            let mut buf = Vec::with_capacity(READER_BUFSIZE);
+            conn.recv_chunked_body_with_limit(
+                &mut buf,
+                &res.headers,
+                MAX_PROXY_BODY
+            )?
+        } else if let Some(len) = content_len {
+            Body::from_reader(conn.take(len), Some(len))?
+        } else if Connection::closes_after(res.version, &res.headers) {
+            Body::from_reader(conn, None)?
+        } else {
+            Body::Empty
+        };
+
+        res.version = Version::OneDotOne;
+        res.headers.remove_hop_by_hop();
+        Ok(res)
+    }
+
+    /// Returns a `Response` with the given error status code and the error
+    /// page registered for that code, if any.
+    fn error_response(&self, code: u16, req: &Request) -> NetResult<Response> {
+        let mut builder = Response::builder();
+        let _ = builder.status_code(code);
+
+        if let Some(target) = self.error_pages.get(&code) {
+            let _ = builder.target(target.clone());
+        }
+
+        let mut res = builder.build()?;
+
+        if Body::should_be_empty(code, &req.method) {
+            res.body = Body::Empty;
+        }
+
+        res.match_version(req);
         Ok(res)
     }
 
+    /// Returns the `Target` to serve for a `Request` whose route was not
+    /// found.
+    ///
+    /// A target for a media type in the request's Accept header is preferred,
+    /// in order of the media types' quality values, followed by a target for
+    /// the request method and then the target set by `Router::not_found`.
+    /// Wildcard media ranges, such as "*/*", are not matched.
+    #[must_use]
+    pub fn not_found_target(&self, req: &Request) -> Target {
+        let by_accept = || {
+            let accept = req.headers.get(&ACCEPT)?.as_str();
+
+            let mut media_types = accept
+                .split(',')
+                .filter_map(|range| {
+                    let mut params = range.split(';');
+                    let media_type = params.next()?.trim();
+                    let quality = params
+                        .filter_map(|param| param.trim().strip_prefix("q="))
+                        .find_map(|q| q.parse::<f32>().ok())
+                        .unwrap_or(1.0);
+                    (quality > 0.0).then_some((media_type, quality))
+                })
+                .collect::<Vec<(&str, f32)>>();
+
+            media_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            media_types.iter().find_map(|(media_type, _)| {
+                self.not_found_for.iter().find_map(|(key, target)| match key {
+                    NotFoundFor::Accept(accepted)
+                        if accepted.eq_ignore_ascii_case(media_type) =>
+                    {
+                        Some(target.clone())
+                    },
+                    _ => None,
+                })
+            })
+        };
+
+        let by_method = || {
+            self.not_found_for.get(&NotFoundFor::Method(req.method)).cloned()
+        };
+
+        let by_default = || {
+            let not_found_route = Route {
+                method: Method::Any,
+                path: None,
+                ..Route::default()
+            };
+
+            self.routes.get(&not_found_route).map(|route| route.target.clone())
+        };
+
+        by_accept()
+            .or_else(by_method)
+            .or_else(by_default)
+            .unwrap_or(Target::NotFound)
+    }
+
+    /// Returns true if the `Request` satisfies any If-Match or
+    /// If-Unmodified-Since precondition against the file at `path`.
+    ///
+    /// If-Match entries are compared to the tag returned by
+    /// `utils::file_etag`. If-Unmodified-Since is only evaluated when
+    /// If-Match is absent and is ignored if the date is invalid.
+    #[must_use]
+    pub fn preconditions_met(req: &Request, path: &Path) -> bool {
+        if let Some(if_match) = req.headers.get(&IF_MATCH) {
+            if if_match.as_str().trim() == "*" {
+                return path.is_file();
+            }
+
+            // Weak tags never match under the strong comparison.
+            return utils::file_etag(path).is_some_and(|etag| {
+                if_match.split_list().contains(&etag)
+            });
+        }
+
+        let Some(since) = req.headers
+            .get(&IF_UNMODIFIED_SINCE)
+            .and_then(|date| utils::parse_http_date(&date.as_str()))
+        else {
+            return true;
+        };
+
+        fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+            .zip(since.duration_since(UNIX_EPOCH).ok())
+            .is_none_or(|(mtime, since)| mtime.as_secs() <= since.as_secs())
+    }
+
     /// Configures a GET route that serves a file.
     #[must_use]
     pub fn get<P, F>(&mut self, uri_path: P, file_path: F) -> &mut Self
@@ -313,7 +865,30 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Get, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
+        self
+    }
+
+    /// Configures a GET route that serves a file with a
+    /// "Cache-Control: public, max-age=`max_age`" header.
+    #[must_use]
+    pub fn get_cached<P, F>(
+        &mut self,
+        uri_path: P,
+        file_path: F,
+        max_age: u32
+    ) -> &mut Self
+    where
+        P: Into<UriPath>,
+        F: Into<Cow<'static, Path>>,
+    {
+        let uri_path = uri_path.into();
+        let file_target = Target::File(file_path.into());
+        let route = Route {
+            max_age: Some(max_age),
+            ..Route::new(Method::Get, uri_path, file_target)
+        };
+        self.routes.insert(route);
         self
     }
 
@@ -327,7 +902,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Head, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -341,7 +916,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Post, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -355,7 +930,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Put, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -369,7 +944,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Patch, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -383,7 +958,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Delete, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -397,7 +972,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Trace, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -411,7 +986,7 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Options, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -425,10 +1000,140 @@ impl Router {
         let uri_path = uri_path.into();
         let file_target = Target::File(file_path.into());
         let route = Route::new(Method::Connect, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
+    /// Configures a GET route that redirects to `location` with a 301 status
+    /// if `permanent` is set, or with a 302 status otherwise.
+    #[must_use]
+    pub fn redirect<P, L>(
+        &mut self,
+        uri_path: P,
+        location: L,
+        permanent: bool
+    ) -> &mut Self
+    where
+        P: Into<UriPath>,
+        L: Into<Cow<'static, str>>,
+    {
+        let code = if permanent { 301 } else { 302 };
+        let uri_path = uri_path.into();
+        let target = Target::Redirect(code, location.into());
+        let route = Route::new(Method::Get, uri_path, target);
+        self.routes.insert(route);
+        self
+    }
+
+    /// Configures a route that forwards requests with any method to the
+    /// `upstream` server, such as `http://127.0.0.1:8080`, and sends its
+    /// responses back to the client.
+    ///
+    /// The route matches `uri_prefix` and every path below it. A status 502
+    /// response is sent if the upstream server cannot be reached or does
+    /// not respond within `PROXY_TIMEOUT`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `upstream` is not a valid URI, or
+    /// `NetError::HttpsNotImplemented` if its scheme is "https".
+    pub fn proxy<P>(
+        &mut self,
+        uri_prefix: P,
+        upstream: &str
+    ) -> NetResult<&mut Self>
+    where
+        P: Into<UriPath>,
+    {
+        let upstream = upstream.parse::<Uri>()?;
+
+        if upstream.is_https() {
+            return Err(NetError::HttpsNotImplemented);
+        }
+
+        let uri_path = uri_prefix.into();
+        let target = Target::Proxy(upstream);
+        let route = Route::new(Method::Any, uri_path, target);
+        self.routes.insert(route);
+        Ok(self)
+    }
+
+    /// Configures routes for testing HTTP clients without an external
+    /// service, similar to httpbin:
+    ///
+    /// - POST, PUT, and PATCH "/echo" respond with the request body.
+    /// - GET "/headers" responds with the request headers as a JSON object.
+    /// - GET "/status/{code}" responds with that status for each known
+    ///   status code from 200 through 599.
+    #[must_use]
+    pub fn add_testing_routes(&mut self) -> &mut Self {
+        for method in [Method::Post, Method::Put, Method::Patch] {
+            let target = Target::Handler(Handler(Self::echo_body));
+            self.routes.insert(Route::new(method, "/echo".into(), target));
+        }
+
+        let target = Target::Handler(Handler(Self::echo_headers));
+        self.routes.insert(Route::new(Method::Get, "/headers".into(), target));
+
+        let codes = (200..=599).filter(|&code| {
+            Status::try_from(code).is_ok_and(|status| status.msg().is_some())
+        });
+
+        for code in codes {
+            let uri_path = format!("/status/{code}").into();
+            let target = Target::Handler(Handler(Self::echo_status));
+            self.routes.insert(Route::new(Method::Get, uri_path, target));
+        }
+
+        self
+    }
+
+    /// Responds with the body and Content-Type of the `Request`.
+    fn echo_body(req: &Request, res: &mut Response) {
+        if let Some(content_type) = req.headers.get(&CONTENT_TYPE) {
+            res.headers.insert(CONTENT_TYPE, content_type.clone());
+        }
+
+        res.body = req.body.clone();
+    }
+
+    /// Responds with the headers of the `Request` as a JSON object.
+    fn echo_headers(req: &Request, res: &mut Response) {
+        let mut json = String::from("{\"headers\":{");
+
+        for (idx, (name, value)) in req.headers.iter_ordered().enumerate() {
+            if idx != 0 {
+                json.push(',');
+            }
+
+            let _ = write!(
+                json,
+                "\"{}\":\"{}\"",
+                utils::escape_json(&name.to_string()),
+                utils::escape_json(&value.as_str())
+            );
+        }
+
+        json.push_str("}}");
+        res.headers.insert(CONTENT_TYPE, "application/json".into());
+        res.body = Body::Json(json.into());
+    }
+
+    /// Responds with the status code in the last segment of the `Request`
+    /// path.
+    fn echo_status(req: &Request, res: &mut Response) {
+        let path = req.path.as_str();
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+        if let Some(status) = path
+            .rsplit('/')
+            .next()
+            .and_then(|code| code.parse::<Status>().ok())
+        {
+            res.status = status;
+        }
+    }
+
     /// Configures a route that serves a favicon image file.
     #[must_use]
     pub fn favicon<F>(&mut self, file_path: F) -> &mut Self
@@ -438,7 +1143,7 @@ impl Router {
         let uri_path = "/favicon.ico".into();
         let file_target = Target::Favicon(file_path.into());
         let route = Route::new(Method::Get, uri_path, file_target);
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
@@ -452,20 +1157,49 @@ impl Router {
         let route = Route {
             method: Method::Any,
             path: None,
-            target: Target::File(file_path.into())
+            target: Target::File(file_path.into()),
+            max_age: None,
+            accepts: Vec::new()
         };
-        self.0.insert(route);
+        self.routes.insert(route);
         self
     }
 
-    /// Mount a shutdown `Route` to the `Router`.
-    pub fn shutdown(&mut self) -> &mut Self {
-        let route = Route {
-            method: Method::Shutdown,
-            path: None,
-            target: Target::Shutdown
-        };
-        self.0.insert(route);
+    /// Configures a `Target` to be served in response to requests for
+    /// routes that are not found when the request matches `key`, which is
+    /// either a `Method` or a media type in the request's Accept header.
+    ///
+    /// See `Router::not_found_target` for how the target is chosen.
+    #[must_use]
+    pub fn not_found_for<K, T>(&mut self, key: K, target: T) -> &mut Self
+    where
+        K: Into<NotFoundFor>,
+        T: Into<Target>,
+    {
+        self.not_found_for.insert(key.into(), target.into());
+        self
+    }
+
+    /// Configures a file to be served as the body of responses with the
+    /// given error status code, such as 403 or 500.
+    ///
+    /// For a 404, a target set by `Router::not_found` or
+    /// `Router::not_found_for` is preferred over the error page.
+    #[must_use]
+    pub fn error_page<F>(&mut self, code: u16, file_path: F) -> &mut Self
+    where
+        F: Into<Cow<'static, Path>>,
+    {
+        self.error_pages.insert(code, Target::File(file_path.into()));
+        self
+    }
+
+    /// Formerly mounted the route that shuts down a test server.
+    ///
+    /// Test servers now answer SHUTDOWN requests before routing, so this
+    /// leaves the `Router` unchanged.
+    #[deprecated(note = "test servers handle shutdown without a route")]
+    pub const fn shutdown(&mut self) -> &mut Self {
         self
     }
 
@@ -482,13 +1216,34 @@ impl Router {
 pub struct RouteBuilder {
     pub router: Router,
     pub uri_path: &'static str,
+    pub accepts: Vec<&'static str>,
 }
 
 impl RouteBuilder {
     /// Returns a new `RouteBuilder` instance.
     #[must_use]
     pub const fn new(router: Router, uri_path: &'static str) -> Self {
-        Self { router, uri_path }
+        Self { router, uri_path, accepts: Vec::new() }
+    }
+
+    /// Restricts the routes configured after this call to requests whose
+    /// Content-Type is one of `content_types`. Other requests with a body
+    /// receive a 415 Unsupported Media Type response.
+    #[must_use]
+    pub fn accepts(&mut self, content_types: &[&'static str]) -> &mut Self {
+        self.accepts = content_types.to_vec();
+        self
+    }
+
+    /// Mounts a route for the given `Method` and `Target` at this URI path.
+    fn mount(&mut self, method: Method, target: Target) -> &mut Self {
+        let uri_path = self.uri_path.into();
+        let route = Route {
+            accepts: self.accepts.clone(),
+            ..Route::new(method, uri_path, target)
+        };
+        self.router.mount(route);
+        self
     }
 
     /// Configures a GET route that serves the given `Target`.
@@ -497,11 +1252,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Get, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Get, target.into())
     }
 
     /// Configures a HEAD route that serves the given `Target`.
@@ -510,11 +1261,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Head, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Head, target.into())
     }
 
     /// Configures a POST route that serves the given `Target`.
@@ -523,11 +1270,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Post, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Post, target.into())
     }
 
     /// Configures a PUT route that serves the given `Target`.
@@ -536,11 +1279,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Put, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Put, target.into())
     }
 
     /// Configures a PATCH route that serves the given `Target`.
@@ -549,11 +1288,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Patch, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Patch, target.into())
     }
 
     /// Configures a DELETE route that serves the given `Target`.
@@ -562,11 +1297,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Delete, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Delete, target.into())
     }
 
     /// Configures a TRACE route that serves the given `Target`.
@@ -575,11 +1306,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Trace, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Trace, target.into())
     }
 
     /// Configures an OPTIONS route that serves the given `Target`.
@@ -588,11 +1315,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Options, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Options, target.into())
     }
 
     /// Configures a CONNECT route that serves the given `Target`.
@@ -601,11 +1324,7 @@ impl RouteBuilder {
     where
         T: Into<Target>,
     {
-        let uri_path = self.uri_path.into();
-        let target = target.into();
-        let route = Route::new(Method::Connect, uri_path, target);
-        self.router.mount(route);
-        self
+        self.mount(Method::Connect, target.into())
     }
 
     /// Returns the inner `Router` instance.
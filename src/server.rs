@@ -1,29 +1,256 @@
+use std::collections::BTreeSet;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::net::{
-    Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs,
-};
-use std::path::PathBuf;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Result as IoResult, Write};
+use std::mem;
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
 use std::thread::{self, spawn, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::{Connection, NetError, NetResult, Router, ThreadPool};
+use crate::{
+    Body, CacheConfig, Clock, Connection, Cors, CorsConfig, Method,
+    NetError, NetResult, PathMatch, RateLimit, Request, Response,
+    ResponseCache, RetryAfter, Route, Router, Target, ThreadPool, Version,
+    MAX_URI_LEN, signal, utils,
+};
 
 pub const NUM_WORKERS: usize = 4;
 
+/// How long a kept-alive connection may sit idle before it is closed.
+pub const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest unread request body, in bytes, that is read and discarded
+/// to keep a connection alive. Connections with larger bodies are closed.
+pub const MAX_DISCARD_LEN: u64 = 64 * 1024;
+
+/// How long a CONNECT tunnel may go without copying any bytes before it is
+/// closed.
+pub const TUNNEL_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long the listener thread may block writing a 503 response to a
+/// connection that the busy worker pool cannot take.
+pub const BUSY_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long the listener thread waits between checks for a new connection,
+/// and so the longest it takes to notice a shutdown or termination signal.
+pub const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// A callback that receives the bound address of a `Server` once it is
+/// accepting connections.
+pub type ReadyFn = Box<dyn FnOnce(SocketAddr) + Send>;
+
+/// Holds the optional `ReadyFn` of a `Server`.
+///
+/// The callback is taken out when it is called, so it runs at most once.
+#[derive(Default)]
+pub struct OnReady(pub Mutex<Option<ReadyFn>>);
+
+impl Debug for OnReady {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "OnReady({:#x})", self.addr())
+    }
+}
+
+impl OnReady {
+    /// Returns a new `OnReady` holding the callback `f`.
+    #[must_use]
+    pub fn new<F>(f: F) -> Self
+    where
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        let f: ReadyFn = Box::new(f);
+        Self(Mutex::new(Some(f)))
+    }
+
+    /// Calls the callback with `addr` unless it has already been called.
+    pub fn call(&self, addr: SocketAddr) {
+        let f = self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+
+        if let Some(f) = f {
+            f(addr);
+        }
+    }
+
+    /// Returns the address of the callback slot.
+    fn addr(&self) -> usize {
+        std::ptr::from_ref(&self.0).cast::<()>() as usize
+    }
+}
+
+/// The URI path of the readiness probe route that test servers register.
+pub const PING_PATH: &str = "/__ping__";
+
+/// The URI path of the route listing that `ServerBuilder::expose_routes`
+/// registers.
+pub const ROUTES_PATH: &str = "/__routes";
+
+/// The format of the access log line that is written for each request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The client IP address and status code in brackets followed by the
+    /// method, path, byte count, and elapsed time.
+    #[default]
+    Simple,
+    /// The Apache Common Log Format. This format has no field for the
+    /// elapsed time.
+    Common,
+    /// A JSON object with one member per field.
+    Json,
+}
+
+impl LogFormat {
+    /// Formats a `LogEntry` as a single log line.
+    #[must_use]
+    pub fn format(&self, entry: &LogEntry) -> String {
+        let elapsed_ms = entry.elapsed.as_secs_f64() * 1000.0;
+
+        match self {
+            Self::Simple => format!(
+                "[{}|{}] {} {} ({} bytes, {elapsed_ms:.3} ms)",
+                entry.client_ip,
+                entry.status,
+                entry.method,
+                &entry.path,
+                entry.bytes
+            ),
+            Self::Common => {
+                let bytes = if entry.bytes == 0 {
+                    "-".to_string()
+                } else {
+                    entry.bytes.to_string()
+                };
+
+                format!(
+                    "{} - - [{}] \"{} {} {}\" {} {bytes}",
+                    entry.client_ip,
+                    utils::format_clf_date(entry.time),
+                    entry.method,
+                    &entry.path,
+                    entry.version,
+                    entry.status
+                )
+            },
+            Self::Json => format!(
+                "{{\"time\":\"{}\",\"client_ip\":\"{}\",\"method\":\"{}\",\
+                \"path\":\"{}\",\"version\":\"{}\",\"status\":{},\
+                \"bytes\":{},\"elapsed_ms\":{elapsed_ms:.3}}}",
+                utils::format_rfc3339(entry.time),
+                entry.client_ip,
+                entry.method,
+                utils::escape_json(&entry.path),
+                entry.version,
+                entry.status,
+                entry.bytes
+            ),
+        }
+    }
+}
+
+/// Size-based rotation settings for the server log file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogRotate {
+    pub max_bytes: u64,
+    pub keep: usize,
+}
+
+impl LogRotate {
+    /// Returns a new `LogRotate` instance.
+    #[must_use]
+    pub const fn new(max_bytes: u64, keep: usize) -> Self {
+        Self { max_bytes, keep }
+    }
+
+    /// Rotates the log file at `path` if it has reached `max_bytes`.
+    ///
+    /// The active file is renamed to "`path`.1", older rotated files are
+    /// shifted up by one, and any file beyond "`path`.`keep`" is removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if removing or renaming a log file fails.
+    pub fn rotate(&self, path: &Path) -> IoResult<()> {
+        let Ok(meta) = fs::metadata(path) else {
+            // Nothing to rotate until the log file is created.
+            return Ok(());
+        };
+
+        if meta.len() < self.max_bytes {
+            return Ok(());
+        }
+
+        if self.keep == 0 {
+            return fs::remove_file(path);
+        }
+
+        let rotated = |num: usize| {
+            let mut name = path.as_os_str().to_owned();
+            name.push(format!(".{num}"));
+            PathBuf::from(name)
+        };
+
+        let oldest = rotated(self.keep);
+        if oldest.exists() {
+            fs::remove_file(oldest)?;
+        }
+
+        for num in (1..self.keep).rev() {
+            let from = rotated(num);
+
+            if from.exists() {
+                fs::rename(from, rotated(num + 1))?;
+            }
+        }
+
+        fs::rename(path, rotated(1))
+    }
+}
+
+/// The details of a handled request that are written to the access log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    pub client_ip: IpAddr,
+    pub method: Method,
+    pub path: String,
+    pub version: Version,
+    pub status: u16,
+    pub bytes: usize,
+    pub elapsed: Duration,
+    pub time: SystemTime,
+}
+
 /// Configures the socket address and the router for a `Server`.
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::struct_excessive_bools)]
 #[derive(Debug, Default)]
 pub struct ServerBuilder {
     pub do_log: bool,
     pub do_debug: bool,
     pub is_test_server: bool,
+    pub expose_routes: bool,
+    pub trust_forwarded: bool,
+    pub keep_alive: bool,
+    pub trace_echo: bool,
+    pub tunnel_targets: BTreeSet<String>,
+    pub tunnel_idle_timeout: Option<Duration>,
+    pub ordered_headers: bool,
+    pub tcp_nodelay: bool,
+    pub path_match: PathMatch,
+    pub max_uri_len: Option<usize>,
+    pub max_queued: Option<usize>,
     pub listener: Option<NetResult<Listener>>,
     pub router: Router,
     pub log_file: Option<PathBuf>,
+    pub log_format: LogFormat,
+    pub log_rotate: Option<LogRotate>,
+    pub rate_limit: Option<RateLimit>,
+    pub cors: Option<Cors>,
+    pub cache: Option<ResponseCache>,
+    pub clock: Option<Clock>,
+    pub on_ready: OnReady,
 }
 
 impl ServerBuilder {
@@ -40,6 +267,16 @@ impl ServerBuilder {
         self
     }
 
+    /// Listens on a Unix domain socket at `path` instead of a TCP address.
+    ///
+    /// Binding fails if a file already exists at `path`.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn unix<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.listener = Some(Listener::bind_unix(path));
+        self
+    }
+
     /// Adds the given `Router` to the server.
     #[must_use]
     pub fn router(&mut self, router: &mut Router) -> &mut Self {
@@ -68,13 +305,208 @@ impl ServerBuilder {
         self
     }
 
-    /// Enables test server features for this server.
+    /// Rotates the log file once it reaches `max_bytes`, keeping at most
+    /// `keep` rotated files. This has no effect when logging to stdout.
+    #[must_use]
+    pub const fn log_rotate(
+        &mut self,
+        max_bytes: u64,
+        keep: usize
+    ) -> &mut Self {
+        self.log_rotate = Some(LogRotate::new(max_bytes, keep));
+        self
+    }
+
+    /// Sets the format of the access log (default: `LogFormat::Simple`).
+    #[must_use]
+    pub const fn log_format(&mut self, format: LogFormat) -> &mut Self {
+        self.log_format = format;
+        self
+    }
+
+    /// Enables test server features for this server: the SHUTDOWN method
+    /// and a GET `PING_PATH` route that responds with "pong".
     #[must_use]
     pub fn is_test_server(&mut self, is_test: bool) -> &mut Self {
         self.is_test_server = is_test;
         self
     }
 
+    /// Adds a GET `ROUTES_PATH` route that responds with the listing from
+    /// `Router::describe`, which is useful for debugging (default:
+    /// disabled). A route already mounted at that path takes precedence.
+    #[must_use]
+    pub const fn expose_routes(&mut self, expose: bool) -> &mut Self {
+        self.expose_routes = expose;
+        self
+    }
+
+    /// Uses the first address in a request's X-Forwarded-For header as the
+    /// client IP address for logging and rate limiting (default: disabled).
+    ///
+    /// Only enable this when the server is reachable solely through a
+    /// trusted proxy, since clients can otherwise spoof the header.
+    #[must_use]
+    pub const fn trust_forwarded(&mut self, trust: bool) -> &mut Self {
+        self.trust_forwarded = trust;
+        self
+    }
+
+    /// Keeps connections open to serve further requests until the client
+    /// asks to close the connection or it is idle for `KEEP_ALIVE_TIMEOUT`
+    /// (default: disabled). HTTP/1.0 clients must ask for "keep-alive".
+    #[must_use]
+    pub const fn keep_alive(&mut self, keep_alive: bool) -> &mut Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Answers TRACE requests by echoing the received request line and
+    /// headers back as a "message/http" body (default: disabled).
+    /// Credential headers such as Authorization and Cookie are omitted.
+    #[must_use]
+    pub const fn enable_trace_echo(&mut self, enable: bool) -> &mut Self {
+        self.trace_echo = enable;
+        self
+    }
+
+    /// Answers CONNECT requests by opening a TCP tunnel to the requested
+    /// "host:port" instead of routing them, but only for the given targets,
+    /// which are matched case-insensitively (default: disabled).
+    ///
+    /// CONNECT requests for other targets get a 403 response. Tunnel
+    /// requests count toward the rate limit and are logged like other
+    /// requests.
+    #[must_use]
+    pub fn connect_tunnel(&mut self, targets: &[&str]) -> &mut Self {
+        self.tunnel_targets
+            .extend(targets.iter().map(|target| target.to_ascii_lowercase()));
+        self
+    }
+
+    /// Sets how long a CONNECT tunnel may go without copying any bytes in
+    /// either direction before it is closed (default: `TUNNEL_IDLE_TIMEOUT`).
+    /// The timeout must not be zero.
+    #[must_use]
+    pub const fn tunnel_idle_timeout(
+        &mut self,
+        timeout: Duration
+    ) -> &mut Self {
+        self.tunnel_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Writes response headers in the order they were inserted instead of
+    /// sorted by name (default: disabled).
+    #[must_use]
+    pub const fn ordered_headers(&mut self, enable: bool) -> &mut Self {
+        self.ordered_headers = enable;
+        self
+    }
+
+    /// Sets the `TCP_NODELAY` option on accepted connections so that
+    /// responses are sent without waiting to coalesce small writes
+    /// (default: disabled).
+    #[must_use]
+    pub const fn tcp_nodelay(&mut self, enable: bool) -> &mut Self {
+        self.tcp_nodelay = enable;
+        self
+    }
+
+    /// Treats request paths with and without a trailing slash, such as
+    /// "/about" and "/about/", as equivalent when matching routes (default:
+    /// disabled). The root path is unaffected.
+    #[must_use]
+    pub const fn ignore_trailing_slash(&mut self, ignore: bool) -> &mut Self {
+        self.path_match.ignore_trailing_slash = ignore;
+        self
+    }
+
+    /// Compares request paths to route paths without regard to ASCII case
+    /// (default: disabled).
+    #[must_use]
+    pub const fn case_insensitive_paths(
+        &mut self,
+        case_insensitive: bool
+    ) -> &mut Self {
+        self.path_match.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets the maximum length in bytes of a request URI (default:
+    /// `MAX_URI_LEN`). Requests with longer URIs receive a 414 URI Too Long
+    /// response.
+    #[must_use]
+    pub const fn max_uri_len(&mut self, max_uri_len: usize) -> &mut Self {
+        self.max_uri_len = Some(max_uri_len);
+        self
+    }
+
+    /// Limits the number of accepted connections that may wait for a free
+    /// worker thread (default: unlimited). Once that many are waiting, new
+    /// connections receive a 503 Service Unavailable response.
+    #[must_use]
+    pub const fn max_queued(&mut self, max_queued: usize) -> &mut Self {
+        self.max_queued = Some(max_queued);
+        self
+    }
+
+    /// Limits each client IP address to `max_requests` requests every `per`
+    /// duration. Clients that exceed the limit receive a 429 response.
+    ///
+    /// The limit is keyed on the IP address of the remote socket, unless
+    /// `trust_forwarded` is enabled.
+    #[must_use]
+    pub fn rate_limit(
+        &mut self,
+        max_requests: u32,
+        per: Duration
+    ) -> &mut Self {
+        self.rate_limit = Some(RateLimit::new(max_requests, per));
+        self
+    }
+
+    /// Adds CORS headers to the responses to cross-origin requests and
+    /// answers CORS preflight requests according to `config`.
+    #[must_use]
+    pub fn cors(&mut self, config: CorsConfig) -> &mut Self {
+        self.cors = Some(Cors::from(config));
+        self
+    }
+
+    /// Stores the successful responses to GET and HEAD requests in memory
+    /// and serves repeated requests for the same path from the stored copy
+    /// for up to `config.ttl`, as described in `ResponseCache`.
+    #[must_use]
+    pub fn cache(&mut self, config: CacheConfig) -> &mut Self {
+        self.cache = Some(ResponseCache::from(config));
+        self
+    }
+
+    /// Sets the function that supplies the time for the Date header of each
+    /// response (default: `SystemTime::now`).
+    ///
+    /// A fixed clock makes the Date header predictable in tests.
+    #[must_use]
+    pub fn clock<F>(&mut self, clock: F) -> &mut Self
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        self.clock = Some(Clock::new(clock));
+        self
+    }
+
+    /// Sets a callback that is invoked with the server's bound address once
+    /// it is accepting connections, just before `Server::start` returns.
+    #[must_use]
+    pub fn on_ready<F>(&mut self, on_ready: F) -> &mut Self
+    where
+        F: FnOnce(SocketAddr) + Send + 'static,
+    {
+        self.on_ready = OnReady::new(on_ready);
+        self
+    }
+
     /// Builds and returns a `Server` instance.
     ///
     /// # Errors
@@ -83,11 +515,6 @@ impl ServerBuilder {
     /// address. If logging to a local file is enabled, an error will be
     /// returned if the provided file path is invalid.
     pub fn build(&mut self) -> NetResult<Server> {
-        if self.is_test_server {
-            // Mount a shutdown route if this is a test server.
-            let _ = self.router.shutdown();
-        }
-
         let log_file = self.log_file.take().map(|path| {
             self.do_log = true;
             Arc::new(path)
@@ -99,14 +526,61 @@ impl ServerBuilder {
             None => None,
         };
 
+        let unix_path = listener
+            .as_ref()
+            .and_then(Listener::unix_path)
+            .map(Path::to_path_buf);
+
+        let mut router = self.router.clone();
+
+        // Test servers answer a readiness probe unless the path is taken.
+        if self.is_test_server {
+            let target = Target::Text("pong".into());
+            router.mount(Route::new(Method::Get, PING_PATH.into(), target));
+        }
+
+        // The listing includes its own route unless the path is taken.
+        if self.expose_routes {
+            let target = Target::Text("".into());
+            let route = Route::new(Method::Get, ROUTES_PATH.into(), target);
+
+            if !router.contains(&route) {
+                router.mount(route);
+
+                let target = Target::Text(router.describe().into());
+                let route = Route::new(Method::Get, ROUTES_PATH.into(), target);
+                let _ = router.replace(route);
+            }
+        }
+
         let server = Server {
             do_log: self.do_log,
             do_debug: self.do_debug,
             is_test_server: self.is_test_server,
-            keep_listening: AtomicBool::new(false),
+            trust_forwarded: self.trust_forwarded,
+            keep_alive: self.keep_alive,
+            trace_echo: self.trace_echo,
+            tunnel_targets: self.tunnel_targets.clone(),
+            tunnel_idle_timeout: self.tunnel_idle_timeout
+                .unwrap_or(TUNNEL_IDLE_TIMEOUT),
+            ordered_headers: self.ordered_headers,
+            tcp_nodelay: self.tcp_nodelay,
+            path_match: self.path_match,
+            max_uri_len: self.max_uri_len.unwrap_or(MAX_URI_LEN),
+            max_queued: self.max_queued,
+            keep_listening: Arc::new(AtomicBool::new(false)),
             listener,
+            unix_path,
             log_file,
-            router: Arc::new(self.router.clone())
+            log_format: self.log_format,
+            log_rotate: self.log_rotate,
+            log_lock: Mutex::new(()),
+            router: Arc::new(router),
+            rate_limit: self.rate_limit.take(),
+            cors: self.cors.take(),
+            cache: self.cache.take(),
+            clock: self.clock.take(),
+            on_ready: mem::take(&mut self.on_ready)
         };
 
         Ok(server)
@@ -124,15 +598,35 @@ impl ServerBuilder {
 }
 
 /// A `Server` contains an active `Listener` and the server configuration.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct Server {
     pub do_log: bool,
     pub do_debug: bool,
     pub is_test_server: bool,
-    pub keep_listening: AtomicBool,
+    pub trust_forwarded: bool,
+    pub keep_alive: bool,
+    pub trace_echo: bool,
+    pub tunnel_targets: BTreeSet<String>,
+    pub tunnel_idle_timeout: Duration,
+    pub ordered_headers: bool,
+    pub tcp_nodelay: bool,
+    pub path_match: PathMatch,
+    pub max_uri_len: usize,
+    pub max_queued: Option<usize>,
+    pub keep_listening: Arc<AtomicBool>,
     pub listener: Option<Listener>,
+    pub unix_path: Option<PathBuf>,
     pub log_file: Option<Arc<PathBuf>>,
+    pub log_format: LogFormat,
+    pub log_rotate: Option<LogRotate>,
+    pub log_lock: Mutex<()>,
     pub router: Arc<Router>,
+    pub rate_limit: Option<RateLimit>,
+    pub cors: Option<Cors>,
+    pub cache: Option<ResponseCache>,
+    pub clock: Option<Clock>,
+    pub on_ready: OnReady,
 }
 
 impl Default for Server {
@@ -141,10 +635,29 @@ impl Default for Server {
             do_log: false,
             do_debug: false,
             is_test_server: false,
-            keep_listening: AtomicBool::new(false),
+            trust_forwarded: false,
+            keep_alive: false,
+            trace_echo: false,
+            tunnel_targets: BTreeSet::new(),
+            tunnel_idle_timeout: TUNNEL_IDLE_TIMEOUT,
+            ordered_headers: false,
+            tcp_nodelay: false,
+            path_match: PathMatch::default(),
+            max_uri_len: MAX_URI_LEN,
+            max_queued: None,
+            keep_listening: Arc::new(AtomicBool::new(false)),
             listener: None,
+            unix_path: None,
             log_file: None,
-            router: Arc::new(Router::default())
+            log_format: LogFormat::default(),
+            log_rotate: None,
+            log_lock: Mutex::new(()),
+            router: Arc::new(Router::default()),
+            rate_limit: None,
+            cors: None,
+            cache: None,
+            clock: None,
+            on_ready: OnReady::default()
         }
     }
 }
@@ -157,10 +670,26 @@ impl PartialEq for Server {
         self.do_log == other.do_log
             && self.do_debug == other.do_debug
             && self.is_test_server == other.is_test_server
+            && self.trust_forwarded == other.trust_forwarded
+            && self.keep_alive == other.keep_alive
+            && self.trace_echo == other.trace_echo
+            && self.tunnel_targets == other.tunnel_targets
+            && self.tunnel_idle_timeout == other.tunnel_idle_timeout
+            && self.ordered_headers == other.ordered_headers
+            && self.tcp_nodelay == other.tcp_nodelay
+            && self.path_match == other.path_match
+            && self.max_uri_len == other.max_uri_len
+            && self.max_queued == other.max_queued
             && self.router == other.router
             && self.listener.is_some() == other.listener.is_some()
+            && self.unix_path == other.unix_path
             && keep_listening1 == keep_listening2
             && self.log_file == other.log_file
+            && self.log_format == other.log_format
+            && self.log_rotate == other.log_rotate
+            && self.rate_limit == other.rate_limit
+            && self.cors == other.cors
+            && self.cache == other.cache
     }
 }
 
@@ -181,7 +710,27 @@ impl Server {
         builder
     }
 
+    /// Returns a `ServerBuilder` object that listens on a Unix domain
+    /// socket at `path`.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn unix<P: AsRef<Path>>(path: P) -> ServerBuilder {
+        let mut builder = ServerBuilder::new();
+        let _ = builder.unix(path);
+        builder
+    }
+
+    /// Logs a server message if debug printing is enabled.
+    pub fn log_debug(&self, msg: &str) {
+        if self.do_debug {
+            self.log(msg);
+        }
+    }
+
     /// Logs a server message to the terminal or to a log file.
+    ///
+    /// If log rotation is configured, the log file is rotated before the
+    /// message is written once it reaches the maximum size.
     pub fn log(&self, msg: &str) {
         if self.do_log {
             let Some(path) = self.log_file.as_ref() else {
@@ -189,6 +738,17 @@ impl Server {
                 return println!("{msg}");
             };
 
+            // Keep other workers from writing while the file is rotated.
+            let _guard = self.log_lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            if let Some(rotate) = self.log_rotate.as_ref() {
+                if let Err(ref err) = rotate.rotate(path) {
+                    eprintln!("Log rotation error: {err}");
+                }
+            }
+
             match OpenOptions::new()
                 .create(true)
                 .append(true)
@@ -202,7 +762,304 @@ impl Server {
         }
     }
 
-    /// Writes a status 500 server error response to the given `Connection`.
+    /// Writes an access log line for a handled request in the configured
+    /// `LogFormat`.
+    pub fn log_request(
+        &self,
+        req: &Request,
+        res: &Response,
+        conn: &Connection,
+        elapsed: Duration
+    ) {
+        if !self.do_log {
+            return;
+        }
+
+        let entry = LogEntry {
+            client_ip: self.client_ip(req, conn),
+            method: req.method,
+            path: req.path.to_string(),
+            version: req.version,
+            status: res.status.code(),
+            bytes: res.body.len(),
+            elapsed,
+            time: SystemTime::now(),
+        };
+
+        self.log(&self.log_format.format(&entry));
+    }
+
+    /// Reads the head of a `Request` from the `Connection`.
+    ///
+    /// The body is left unread, with `Connection::body_pending` set, since
+    /// routes do not use it. It can be read with `Request::read_body`, and
+    /// any unread body is discarded after the response has been sent. The
+    /// body of a request to a proxy route is read here, since it is forwarded
+    /// to the upstream server, as is the body of a request to a `Handler`
+    /// route, so that the handler can use it.
+    ///
+    /// If the client sent `Expect: 100-continue` with a body and a route
+    /// exists for the request, an interim "100 Continue" response is sent.
+    /// Otherwise, the client may or may not send the body, so it is not
+    /// treated as pending and `Connection::close_after_response` is set so
+    /// that the connection is closed after the response.
+    /// This keeps a body that arrives later from being parsed as the next
+    /// pipelined request. The expectation is ignored for a request without
+    /// a body, such as one with `Content-Length: 0`.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if reading the `Request` head or writing the
+    /// interim response fails.
+    pub fn recv_request(&self, conn: &mut Connection) -> NetResult<Request> {
+        let mut req = conn.recv_request_head_with_limit(self.max_uri_len)?;
+        let route = self.router.get_route_with(&req, self.path_match);
+
+        if req.expects_continue() && conn.body_pending {
+            if route.is_none() {
+                conn.body_pending = false;
+                conn.close_after_response = true;
+                return Ok(req);
+            }
+
+            conn.send_continue()?;
+        }
+
+        let reads_body = |route: &Route| {
+            route.target.is_proxy() || route.target.is_handler()
+        };
+
+        if route.is_some_and(reads_body) {
+            req.read_body(conn)?;
+        }
+
+        Ok(req)
+    }
+
+    /// Returns true if the unread body of the `Request` is small enough to
+    /// be discarded so that the `Connection` can be kept alive.
+    ///
+    /// Bodies with chunked transfer encoding or with a Content-Length of
+    /// more than `MAX_DISCARD_LEN` bytes are not read at all. The
+    /// connection is closed after the response instead.
+    #[must_use]
+    pub fn can_discard_body(req: &Request, conn: &Connection) -> bool {
+        !conn.body_pending
+            || (!utils::is_chunked(&req.headers)
+                && req.content_length().is_some_and(|len| {
+                    len <= MAX_DISCARD_LEN
+                }))
+    }
+
+    /// Returns true if the `Request` should shut down this test server.
+    #[must_use]
+    pub const fn is_shutdown_request(&self, req: &Request) -> bool {
+        self.is_test_server && matches!(req.method, Method::Shutdown)
+    }
+
+    /// Returns the IP address of the client that sent the `Request`.
+    ///
+    /// This is the remote address of the `Connection` unless the server
+    /// trusts forwarding headers and the request has an X-Forwarded-For
+    /// header.
+    #[must_use]
+    pub fn client_ip(&self, req: &Request, conn: &Connection) -> IpAddr {
+        if self.trust_forwarded {
+            if let Some(ip) = req.forwarded_for() {
+                return ip;
+            }
+        }
+
+        conn.remote_ip()
+    }
+
+    /// Resolves a `Request` received on the given `Connection` into a
+    /// `Response`, applying any configured rate limit before routing or
+    /// answering a preflight request.
+    ///
+    /// Test servers answer SHUTDOWN requests directly rather than through
+    /// the `Router`, so the shutdown route never competes with user routes.
+    ///
+    /// With trace echo enabled, TRACE requests are also answered directly.
+    /// With CORS enabled, preflight requests are answered directly and CORS
+    /// headers are added to the responses to other cross-origin requests.
+    /// With a response cache, a fresh stored response is returned instead
+    /// of routing the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `Router::resolve` fails or if the 429, TRACE, or
+    /// preflight response cannot be built.
+    pub fn resolve(
+        &self,
+        req: &Request,
+        conn: &Connection
+    ) -> NetResult<Response> {
+        if self.is_shutdown_request(req) {
+            return Response::builder()
+                .status_code(200)
+                .target(Target::Shutdown)
+                .build();
+        }
+
+        if let Some(mut res) = self.check_rate_limit(req, conn)? {
+            if let Some(cors) = self.cors.as_ref() {
+                cors.apply(req, &mut res);
+            }
+
+            return Ok(res);
+        }
+
+        if self.is_tunnel_request(req) {
+            return self.tunnel_response(req);
+        }
+
+        let Some(cors) = self.cors.as_ref() else {
+            return self.route(req);
+        };
+
+        if Cors::is_preflight(req) {
+            return cors.preflight(req);
+        }
+
+        let mut res = self.route(req)?;
+        cors.apply(req, &mut res);
+        Ok(res)
+    }
+
+    /// Counts the `Request` against the rate limit, if one is configured,
+    /// and returns a 429 response if the client has exceeded it.
+    fn check_rate_limit(
+        &self,
+        req: &Request,
+        conn: &Connection
+    ) -> NetResult<Option<Response>> {
+        let Some(limit) = self.rate_limit.as_ref() else {
+            return Ok(None);
+        };
+
+        // All Unix domain socket peers share one placeholder address, so
+        // only a forwarded address can tell their clients apart.
+        let is_forwarded =
+            self.trust_forwarded && req.forwarded_for().is_some();
+
+        if conn.writer.get_ref().is_unix() && !is_forwarded {
+            return Ok(None);
+        }
+
+        let Err(reset) = limit.check(self.client_ip(req, conn)) else {
+            return Ok(None);
+        };
+
+        let mut res = Response::builder()
+            .status_code(429)
+            .body("Too many requests.".into())
+            .build()?;
+
+        // Round up so clients never retry before the window resets.
+        let secs = reset.as_secs() + u64::from(reset.subsec_nanos() > 0);
+        res.retry_after(RetryAfter::Seconds(secs.max(1)));
+        res.match_version(req);
+        Ok(Some(res))
+    }
+
+    /// Resolves a `Request` that is not a shutdown request and is within
+    /// the rate limit into a `Response`.
+    fn route(&self, req: &Request) -> NetResult<Response> {
+        if self.trace_echo && req.method == Method::Trace {
+            let mut res = Response::builder()
+                .status_code(200)
+                .header("Content-Type", b"message/http")
+                .body(req.trace_bytes().into())
+                .build()?;
+            res.match_version(req);
+            return Ok(res);
+        }
+
+        let Some(cache) = self.cache.as_ref() else {
+            return self.router.resolve_with(req, self.path_match);
+        };
+
+        // The stored response may have been made for another HTTP version.
+        if let Some(mut res) = cache.get(req) {
+            res.match_version(req);
+            return Ok(res);
+        }
+
+        let res = self.router.resolve_with(req, self.path_match)?;
+        cache.store(req, &res);
+        Ok(res)
+    }
+
+    /// Returns true if the `Request` should be answered with a tunnel.
+    #[must_use]
+    pub fn is_tunnel_request(&self, req: &Request) -> bool {
+        !self.tunnel_targets.is_empty() && req.method == Method::Connect
+    }
+
+    /// Returns the response to a CONNECT request that is answered with a
+    /// tunnel: a 200 if its target is allowed, a 400 if the target is not in
+    /// authority form, and a 403 otherwise.
+    ///
+    /// The 200 response is not sent. It is passed to `Server::tunnel`.
+    fn tunnel_response(&self, req: &Request) -> NetResult<Response> {
+        let target = req.path.as_str();
+
+        let (code, msg) = if target.is_empty() || target.contains('/') {
+            (400, "Invalid CONNECT target.")
+        } else if self.tunnel_targets.contains(&target.to_ascii_lowercase()) {
+            (200, "")
+        } else {
+            (403, "CONNECT target not allowed.")
+        };
+
+        let mut res = Response::builder()
+            .status_code(code)
+            .body(msg.into())
+            .build()?;
+        res.match_version(req);
+        Ok(res)
+    }
+
+    /// Tunnels the given `Connection` to the "host:port" target of a
+    /// CONNECT request until either side closes or the tunnel is idle for
+    /// `tunnel_idle_timeout`.
+    ///
+    /// `res` is the 200 response that `Server::resolve` returned for the
+    /// request, which is logged once the target is reached. A status 502
+    /// response is sent instead if the target cannot be reached.
+    pub fn tunnel(
+        &self,
+        req: &Request,
+        res: &Response,
+        conn: &mut Connection,
+        start: Instant
+    ) {
+        let target = req.path.as_str();
+
+        let upstream = match TcpStream::connect(target) {
+            Ok(stream) => stream,
+            Err(err) => {
+                let msg = format!("Unable to connect to {target}: {err}");
+                self.send_error(502, msg, conn);
+                return;
+            },
+        };
+
+        self.log_request(req, res, conn, start.elapsed());
+        self.log_debug(&format!("[SERVER] Tunnel opened to {target}"));
+
+        match conn.tunnel(upstream, self.tunnel_idle_timeout) {
+            Err(ref err) if !err.is_disconnect() && !err.is_timeout() => {
+                self.log(&format!("[SERVER] Tunnel error: {err}"));
+            },
+            _ => self.log_debug(&format!("[SERVER] Tunnel closed to {target}")),
+        }
+    }
+
+    /// Writes an error response with the given status code to the given
+    /// `Connection`, using the `Router`'s error page for the code if one is
+    /// registered.
     pub fn send_error(
         &self,
         status_code: u16,
@@ -210,8 +1067,19 @@ impl Server {
         conn: &mut Connection
     ) {
         self.log(&format!("[SERVER] Error: {}", &err_msg));
+        conn.options.clock.clone_from(&self.clock);
+
+        // Prefer an error page that is registered for the status code.
+        let page = self.router
+            .error_page_for(status_code)
+            .and_then(|target| Body::try_from(target.clone()).ok());
 
-        if let Err(ref err) = conn.send_error(status_code, err_msg) {
+        let sent = match page {
+            Some(body) => conn.send_error_body(status_code, &body),
+            None => conn.send_error(status_code, err_msg),
+        };
+
+        if let Err(ref err) = sent {
             self.log(&format!("[SERVER] Error: {err}"));
         }
     }
@@ -233,54 +1101,130 @@ impl Server {
 
         self.keep_listening.store(false, Ordering::Relaxed);
 
-        let addr = conn.local_addr;
-        let timeout = Duration::from_millis(200);
-
-        // Briefly connect to ourselves to unblock the listener thread.
-        if let Err(ref err) = TcpStream::connect_timeout(&addr, timeout)
-            .and_then(|stream| stream.shutdown(Shutdown::Both))
-        {
-            self.log(&format!("[SERVER] Error: {err}"));
-        }
-
         // Give the worker threads some time to shutdown.
-        thread::sleep(timeout);
+        thread::sleep(Duration::from_millis(200));
     }
 
     /// Activates the server to begin listening on its bound address.
     ///
+    /// This returns once the listener thread is accepting connections, after
+    /// calling the `on_ready` callback, if set, so clients can connect right
+    /// away without polling. The returned `NetHandle` reports the address
+    /// that the `Listener` is bound to, which includes the port chosen by the
+    /// OS when binding to port 0. The `Listener` is polled every
+    /// `ACCEPT_POLL_INTERVAL` rather than blocking in `accept`, so a shutdown
+    /// is noticed promptly even when no connections arrive.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the `Listener` is not active.
+    /// Returns an error if the `Listener` is not active or cannot be made
+    /// non-blocking.
     pub fn start(mut self) -> NetResult<NetHandle<()>> {
         let listener = self.listener.take().ok_or(NetError::NotConnected)?;
+        let local_addr = listener.local_addr;
 
+        listener.set_nonblocking(true)?;
         self.keep_listening.store(true, Ordering::Relaxed);
 
+        let keep_listening = Arc::clone(&self.keep_listening);
         let server = Arc::new(self);
+        let (ready_tx, ready_rx) = mpsc::channel::<()>();
 
         // Spawn listener thread.
         let handle = spawn(move || {
-            let addr = listener.local_addr;
-            server.log(&format!("[SERVER] Listening on {addr}"));
+            server.log(&format!("[SERVER] Listening on {listener}"));
 
             // Create a thread pool of workers to handle incoming requests.
             let pool = ThreadPool::new(NUM_WORKERS, &server);
 
+            server.on_ready.call(local_addr);
+
+            drop(ready_tx);
+
             while server.do_listen() {
-                match listener.accept() {
-                    // Since this thread blocks on `accept`, check if server
-                    // shutdown has been triggered.
+                let accepted = listener.accept().and_then(|conn| {
+                    if server.tcp_nodelay {
+                        conn.set_nodelay(true)?;
+                    }
+
+                    Ok(conn)
+                });
+
+                match accepted {
+                    Err(ref err)
+                        if err.io_kind() == Some(ErrorKind::WouldBlock) =>
+                    {
+                        thread::sleep(ACCEPT_POLL_INTERVAL);
+                    },
+                    // Check again in case shutdown was triggered while this
+                    // connection was being accepted.
                     Ok(_) if server.do_shutdown() => break,
-                    Ok(conn) => pool.handle_connection(conn),
+                    // The response is written on the listener thread, so a
+                    // client that does not read it must not block accepting.
+                    Ok(conn) => match pool.handle_connection(conn) {
+                        None => server.log_debug(&format!(
+                            "[SERVER] Workers active: {}, queued: {}",
+                            pool.active_count(),
+                            pool.queued_count()
+                        )),
+                        Some(mut conn) => {
+                            let timeout = Some(BUSY_WRITE_TIMEOUT);
+
+                            if conn.set_write_timeout(timeout).is_ok() {
+                                let msg = String::from("Server is busy.");
+                                server.send_error(503, msg, &mut conn);
+                            }
+                        },
+                    },
+                    // Errors such as running out of file descriptors tend to
+                    // repeat, so wait before trying again.
                     Err(ref err) => {
                         server.log(&format!("[SERVER] Error: {err}"));
+                        thread::sleep(ACCEPT_POLL_INTERVAL);
                     },
                 }
             }
         });
 
-        Ok(NetHandle { handle })
+        // The sender is dropped once the workers are running, or if the
+        // listener thread panics before then.
+        let _ = ready_rx.recv();
+
+        Ok(NetHandle { handle, local_addr, keep_listening })
+    }
+
+    /// Starts the server and blocks until it has shut down, either after a
+    /// request to the shutdown route or once the process receives a
+    /// termination signal.
+    ///
+    /// On Unix platforms, SIGINT and SIGTERM trigger the shutdown. On
+    /// Windows, only Ctrl-C does, since there is no SIGTERM. Other platforms
+    /// rely on the shutdown route alone. In each case, the listener stops
+    /// accepting connections and the worker threads finish the connections
+    /// they are handling before this returns.
+    ///
+    /// A signal only shuts down the server that `run` was called on, and
+    /// is cleared once it has been handled. Servers started with `start`
+    /// keep running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `Listener` is not active or if the server's
+    /// listener thread panics.
+    pub fn run(self) -> NetResult<()> {
+        signal::install();
+
+        let handle = self.start()?;
+
+        while !handle.handle.is_finished() {
+            if signal::take() {
+                handle.shutdown();
+            }
+
+            thread::sleep(ACCEPT_POLL_INTERVAL);
+        }
+
+        handle.join()
     }
 }
 
@@ -288,9 +1232,28 @@ impl Server {
 #[derive(Debug)]
 pub struct NetHandle<T> {
     pub handle: JoinHandle<T>,
+    pub local_addr: SocketAddr,
+    pub keep_listening: Arc<AtomicBool>,
 }
 
 impl<T> NetHandle<T> {
+    /// Returns the local address that the server is listening on.
+    ///
+    /// This is `UNIX_SOCKET_ADDR` for a server on a Unix domain socket.
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Tells the server to stop accepting connections.
+    ///
+    /// The listener thread exits within about `ACCEPT_POLL_INTERVAL`, after
+    /// the worker threads finish the connections they are handling. Use
+    /// `join` to wait for it.
+    pub fn shutdown(&self) {
+        self.keep_listening.store(false, Ordering::Relaxed);
+    }
+
     /// Waits until the server thread is finished.
     ///
     /// # Errors
@@ -301,15 +1264,42 @@ impl<T> NetHandle<T> {
     }
 }
 
-/// A wrapper around a `TcpListener` instance.
+/// The socket on which a `Listener` accepts connections.
+#[derive(Debug)]
+pub enum ListenerSocket {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+/// A wrapper around a `TcpListener` or, on Unix platforms, a `UnixListener`.
+///
+/// The `local_addr` of a Unix domain socket listener is `UNIX_SOCKET_ADDR`.
+/// The socket file of a Unix domain socket listener is removed when the
+/// `Listener` is dropped, such as when its server shuts down.
 pub struct Listener {
-    pub inner: TcpListener,
+    pub inner: ListenerSocket,
     pub local_addr: SocketAddr,
 }
 
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let ListenerSocket::Unix(_, ref path) = self.inner {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
 impl Display for Listener {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{self:?}")
+        match self.inner {
+            ListenerSocket::Tcp(_) => write!(f, "{}", self.local_addr),
+            #[cfg(unix)]
+            ListenerSocket::Unix(_, ref path) => {
+                write!(f, "{}", path.display())
+            },
+        }
     }
 }
 
@@ -324,6 +1314,7 @@ impl TryFrom<TcpListener> for Listener {
 
     fn try_from(inner: TcpListener) -> NetResult<Self> {
         let local_addr = inner.local_addr()?;
+        let inner = ListenerSocket::Tcp(inner);
         Ok(Self { inner, local_addr })
     }
 }
@@ -343,17 +1334,82 @@ impl Listener {
         Self::try_from(listener)
     }
 
+    /// Binds a `Listener` to a Unix domain socket at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when `UnixListener::bind` returns an error, such as
+    /// when a file already exists at `path`.
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P) -> NetResult<Self> {
+        let path = path.as_ref();
+        let listener = UnixListener::bind(path)?;
+
+        Ok(Self {
+            inner: ListenerSocket::Unix(listener, path.to_path_buf()),
+            local_addr: crate::UNIX_SOCKET_ADDR,
+        })
+    }
+
+    /// Returns the path of a Unix domain socket listener.
+    #[must_use]
+    pub fn unix_path(&self) -> Option<&Path> {
+        match self.inner {
+            ListenerSocket::Tcp(_) => None,
+            #[cfg(unix)]
+            ListenerSocket::Unix(_, ref path) => Some(path),
+        }
+    }
+
+
+    /// Moves the socket into or out of non-blocking mode.
+    ///
+    /// A non-blocking `Listener` returns `NetError::Read` with an error kind
+    /// of `ErrorKind::WouldBlock` from `accept` when no connection is
+    /// waiting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if changing the mode of the socket fails.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> NetResult<()> {
+        match self.inner {
+            ListenerSocket::Tcp(ref inner) => {
+                inner.set_nonblocking(nonblocking)?;
+            },
+            #[cfg(unix)]
+            ListenerSocket::Unix(ref inner, _) => {
+                inner.set_nonblocking(nonblocking)?;
+            },
+        }
+
+        Ok(())
+    }
+
     /// Returns a `Connection` instance for each incoming connection.
     ///
+    /// Accepted connections are always in blocking mode, even when the
+    /// `Listener` is not.
+    ///
     /// # Errors
     ///
     /// Returns an error if `Connection::try_clone` fails.
     pub fn accept(&self) -> NetResult<Connection> {
-        self.inner
-            .accept()
-            .map_err(|err| NetError::Read(err.kind()))
-            .and_then(|(stream, remote_addr)| {
-                Connection::try_from((stream, remote_addr))
-            })
+        match self.inner {
+            ListenerSocket::Tcp(ref inner) => inner
+                .accept()
+                .map_err(|err| NetError::Read(err.into()))
+                .and_then(|(stream, remote_addr)| {
+                    stream.set_nonblocking(false)?;
+                    Connection::try_from((stream, remote_addr))
+                }),
+            #[cfg(unix)]
+            ListenerSocket::Unix(ref inner, _) => inner
+                .accept()
+                .map_err(|err| NetError::Read(err.into()))
+                .and_then(|(stream, _)| {
+                    stream.set_nonblocking(false)?;
+                    Connection::try_from(stream)
+                }),
+        }
     }
 }
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once a termination signal has been received, until it is taken.
+static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if a termination signal has been received since `install`
+/// was called and has not yet been taken.
+#[must_use]
+pub fn received() -> bool {
+    RECEIVED.load(Ordering::SeqCst)
+}
+
+/// Returns true if a termination signal has been received since `install`
+/// was called or since the last call to `take`, and clears it.
+pub fn take() -> bool {
+    RECEIVED.swap(false, Ordering::SeqCst)
+}
+
+/// Records that a termination signal has been received.
+fn raise() {
+    RECEIVED.store(true, Ordering::SeqCst);
+}
+
+/// Replaces the default handlers for SIGINT and SIGTERM with one that only
+/// records the signal, so that `received` returns true afterwards. Any
+/// signal recorded before this call is cleared.
+#[cfg(unix)]
+pub fn install() {
+    use std::ffi::c_int;
+
+    // POSIX fixes these numbers, which `kill -2` and `kill -15` also rely
+    // on, so they are the same on every Unix platform.
+    const SIGINT: c_int = 2;
+    const SIGTERM: c_int = 15;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+
+    extern "C" fn on_signal(_signum: c_int) {
+        raise();
+    }
+
+    RECEIVED.store(false, Ordering::SeqCst);
+
+    // SAFETY: the handler only stores to an atomic, which is
+    // async-signal-safe.
+    unsafe {
+        let _ = signal(SIGINT, on_signal);
+        let _ = signal(SIGTERM, on_signal);
+    }
+}
+
+/// Installs a console control handler that records Ctrl-C, so that
+/// `received` returns true afterwards. Other console events, such as
+/// closing the console window, keep their default behavior. Any Ctrl-C
+/// recorded before this call is cleared.
+#[cfg(windows)]
+pub fn install() {
+    const CTRL_C_EVENT: u32 = 0;
+
+    extern "system" {
+        fn SetConsoleCtrlHandler(
+            handler: extern "system" fn(u32) -> i32,
+            add: i32
+        ) -> i32;
+    }
+
+    extern "system" fn on_ctrl(ctrl_type: u32) -> i32 {
+        if ctrl_type == CTRL_C_EVENT {
+            raise();
+            1
+        } else {
+            0
+        }
+    }
+
+    RECEIVED.store(false, Ordering::SeqCst);
+
+    // SAFETY: the handler only stores to an atomic and runs on a thread
+    // that the system creates for it.
+    unsafe {
+        let _ = SetConsoleCtrlHandler(on_ctrl, 1);
+    }
+}
+
+/// Signal handling is not supported on this platform.
+#[cfg(not(any(unix, windows)))]
+pub const fn install() {}
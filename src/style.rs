@@ -1,4 +1,7 @@
+use std::fmt::Write as _;
+
 use crate::WriteCliError;
+use colors::{BLUE, GREEN, MAGENTA, ORANGE, RESET};
 
 /// ANSI colors.
 pub mod colors {
@@ -12,6 +15,66 @@ pub mod colors {
     pub const RESET: &str = "\x1b[0m";
 }
 
+/// Returns the JSON text with ANSI colors applied to its tokens.
+///
+/// Object keys are blue, strings are green, numbers are orange, and the
+/// literals `true`, `false`, and `null` are magenta. Punctuation and
+/// whitespace are left unchanged, so the layout of the text is preserved.
+/// The text is not validated.
+#[must_use]
+pub fn highlight_json(json: &str) -> String {
+    let mut chars = json.chars().peekable();
+    let mut colored = String::with_capacity(json.len() * 2);
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut string = String::from('"');
+                let mut escaped = false;
+
+                for c in chars.by_ref() {
+                    string.push(c);
+
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {},
+                    }
+                }
+
+                // A string followed by a colon is an object key.
+                let is_key = chars
+                    .clone()
+                    .find(|c| !c.is_ascii_whitespace())
+                    .is_some_and(|c| c == ':');
+
+                let color = if is_key { BLUE } else { GREEN };
+                let _ = write!(colored, "{color}{string}{RESET}");
+            },
+            '-' | '0'..='9' | 't' | 'f' | 'n' => {
+                let mut token = String::from(c);
+
+                while let Some(c) = chars.next_if(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-')
+                }) {
+                    token.push(c);
+                }
+
+                let color = if c.is_ascii_alphabetic() {
+                    MAGENTA
+                } else {
+                    ORANGE
+                };
+                let _ = write!(colored, "{color}{token}{RESET}");
+            },
+            _ => colored.push(c),
+        }
+    }
+
+    colored
+}
+
 /// Controls which components are printed.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Parts {
@@ -212,17 +275,21 @@ impl Kind {
 }
 
 /// The output style settings.
+///
+/// If `pretty` is set, JSON bodies are printed with indentation.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Style {
     pub req: Kind,
     pub res: Kind,
+    pub pretty: bool,
 }
 
 impl Default for Style {
     fn default() -> Self {
         Self {
             req: Kind::Color(Parts::None),
-            res: Kind::Color(Parts::All)
+            res: Kind::Color(Parts::All),
+            pretty: false,
         }
     }
 }
@@ -249,8 +316,16 @@ impl Style {
 
         let mut req_num = 0;
         let mut res_num = 0;
+        let mut seen = String::with_capacity(7);
 
         for c in format_str.chars().filter(|&c| is_good_char(c)) {
+            // Ignore repeated characters (e.g. "RRs").
+            if seen.contains(c) {
+                continue;
+            }
+
+            seen.push(c);
+
             match c {
                 // "Verbose" style.
                 '*' => {
@@ -1,5 +1,6 @@
 use std::collections::{BTreeSet, VecDeque};
 use std::error::Error;
+use std::net::SocketAddr;
 use std::num::NonZeroU16;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -7,13 +8,15 @@ use std::str::FromStr;
 use crate::{
     Body, Client, ClientCli, Connection, Headers, HeaderName, Kind, Method,
     NetHandle, NetError, Parts, Request, Response, Route, Router, Server,
-    ServerCli, Status, Style, Target, UriPath, Version,
+    ServerBuilder, ServerCli, Status, Style, Target, UriPath, Version,
 };
 use crate::headers::names::{
     ACCEPT, ACCEPT_ENCODING, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH,
     CONTENT_TYPE, HeaderNameInner, HOST, SERVER, STD_HEADER_NAMES, USER_AGENT,
 };
-use crate::utils::{parse_uri, to_titlecase, trim};
+use crate::utils::{
+    host_from_addr, parse_http_date, parse_uri, to_titlecase, trim,
+};
 
 macro_rules! test_parsing_from_str {
     (
@@ -57,6 +60,58 @@ macro_rules! test_parsing_from_int {
     };
 }
 
+/// Binds a server to an ephemeral local port, starts it, and returns the
+/// address on which it is listening.
+fn start_server(builder: &mut ServerBuilder) -> SocketAddr {
+    builder.addr("127.0.0.1:0").start().unwrap().local_addr()
+}
+
+/// Writes `res` with `Connection::send_response` from a server thread and
+/// returns the bytes that the client receives, along with the response as
+/// it was sent.
+fn send_response_raw(mut res: Response) -> (Vec<u8>, Response) {
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        conn.send_response(&mut res).unwrap();
+        res
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut raw = Vec::new();
+    let res = handle.join().unwrap();
+    stream.read_to_end(&mut raw).unwrap();
+    (raw, res)
+}
+
+/// Reads the response in `raw` with `Connection::recv_response_to`, as a
+/// client that sent a request with `method` would, after the server writes
+/// it and closes the connection.
+fn recv_response_raw(raw: Vec<u8>, method: Method) -> Response {
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(&raw).unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut conn = Connection::try_from(stream).unwrap();
+    let res = conn.recv_response_to(&method).unwrap();
+    handle.join().unwrap();
+    res
+}
+
 #[cfg(test)]
 mod method {
     use super::*;
@@ -94,6 +149,67 @@ mod method {
         BAD_INPUT: b"Foo";
         BAD_INPUT: b"get";
     }
+
+    #[test]
+    fn canonical_sort_order() {
+        let methods = BTreeSet::from([
+            Method::Any,
+            Method::Shutdown,
+            Method::Connect,
+            Method::Trace,
+            Method::Options,
+            Method::Delete,
+            Method::Patch,
+            Method::Put,
+            Method::Post,
+            Method::Head,
+            Method::Get,
+        ]);
+
+        let expected = [
+            Method::Get,
+            Method::Head,
+            Method::Post,
+            Method::Put,
+            Method::Patch,
+            Method::Delete,
+            Method::Options,
+            Method::Trace,
+            Method::Connect,
+            Method::Shutdown,
+            Method::Any,
+        ];
+
+        assert!(methods.into_iter().eq(expected));
+    }
+
+    #[test]
+    fn route_set_order() {
+        let mut router = Router::new();
+        let about = Path::new("static/about.html");
+        let index = Path::new("static/index.html");
+        let _ = router
+            .not_found(Path::new("static/error.html"))
+            .post("/about", about)
+            .get("/about", about)
+            .get("/", index)
+            .head("/", index)
+            .delete("/about", about);
+
+        let order = router
+            .routes()
+            .map(|route| (route.method, route.path.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(order, vec![
+            (Method::Get, Some("/".into())),
+            (Method::Get, Some("/about".into())),
+            (Method::Head, Some("/".into())),
+            (Method::Post, Some("/about".into())),
+            (Method::Delete, Some("/about".into())),
+            (Method::Any, None),
+        ]);
+    }
 }
 
 #[cfg(test)]
@@ -133,6 +249,72 @@ mod status {
         BAD_INPUT: 99u16;
         BAD_INPUT: 1000u16;
     }
+
+    #[test]
+    fn classes() {
+        let class_of = |code: u16| Status::try_from(code).unwrap().class();
+        assert_eq!(class_of(100), 1);
+        assert_eq!(class_of(199), 1);
+        assert_eq!(class_of(200), 2);
+        assert_eq!(class_of(299), 2);
+        assert_eq!(class_of(300), 3);
+        assert_eq!(class_of(404), 4);
+        assert_eq!(class_of(599), 5);
+        assert_eq!(class_of(999), 9);
+    }
+
+    #[test]
+    fn reason_phrases() {
+        let mut known = 0;
+
+        for code in 100..=999u16 {
+            let status = Status::try_from(code).unwrap();
+
+            match status.msg() {
+                Some(phrase) => {
+                    known += 1;
+                    assert!(!phrase.is_empty());
+                    assert_eq!(status.as_str(), format!("{code} {phrase}"));
+                },
+                None => assert_eq!(status.as_str(), code.to_string()),
+            }
+        }
+
+        assert_eq!(known, 95);
+
+        let phrase = |code: u16| Status::try_from(code).unwrap().msg();
+        assert_eq!(phrase(418), Some("I'm a Teapot"));
+        assert_eq!(phrase(451), Some("Unavailable For Legal Reasons"));
+        assert_eq!(phrase(600), None);
+    }
+
+    #[test]
+    fn class_predicates() {
+        let status = |code: u16| Status::try_from(code).unwrap();
+
+        assert!(status(199).is_informational());
+        assert!(!status(200).is_informational());
+        assert!(status(200).is_success());
+        assert!(!status(299).is_redirect());
+        assert!(status(300).is_redirect());
+        assert!(status(399).is_redirect());
+        assert!(!status(399).is_client_error());
+        assert!(status(400).is_client_error());
+        assert!(status(499).is_client_error());
+        assert!(!status(499).is_server_error());
+        assert!(status(500).is_server_error());
+        assert!(status(599).is_server_error());
+        assert!(!status(600).is_server_error());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn is_redirection_alias() {
+        let status = |code: u16| Status::try_from(code).unwrap();
+
+        assert!(status(302).is_redirection());
+        assert!(!status(200).is_redirection());
+    }
 }
 
 #[cfg(test)]
@@ -188,6 +370,106 @@ mod standard_headers {
             assert_eq!(titlecase_test, titlecase_expected);
         }
     }
+
+    #[test]
+    fn get_standard() {
+        let mut headers = Headers::new();
+
+        for (num, &(_, titlecase)) in STD_HEADER_NAMES.iter().enumerate() {
+            let lowercase = titlecase.to_ascii_lowercase();
+            headers.header(&lowercase, num.to_string().as_bytes());
+        }
+
+        for &(std, titlecase) in STD_HEADER_NAMES {
+            let parsed = HeaderName::from(titlecase);
+
+            assert!(headers.contains_standard(std));
+            assert!(headers.get_standard(std).is_some());
+            assert_eq!(headers.get_standard(std), headers.get(&parsed));
+            assert_eq!(HeaderName::standard(std), parsed);
+        }
+
+        headers.header("X-Custom", b"custom");
+        assert_eq!(headers.len(), STD_HEADER_NAMES.len() + 1);
+    }
+
+    #[test]
+    fn standard_classification() {
+        use crate::headers::{
+            ACCEPT, AUTHORIZATION, CONTENT_TYPE, ETAG, HOST, SET_COOKIE,
+            WWW_AUTHENTICATE, X_FORWARDED_FOR,
+        };
+
+        // Equality compares the names as strings, so check the variants.
+        let is_standard = |name: &HeaderName, expected| matches!(
+            name.inner,
+            HeaderNameInner::Standard(std) if std == expected
+        );
+
+        for &(std, titlecase) in STD_HEADER_NAMES {
+            let lowercase = titlecase.to_ascii_lowercase();
+            assert!(is_standard(&HeaderName::from(titlecase), std));
+            assert!(is_standard(&HeaderName::from(lowercase.as_str()), std));
+        }
+
+        let exported = [
+            (ACCEPT, "accept"),
+            (AUTHORIZATION, "AUTHORIZATION"),
+            (CONTENT_TYPE, "content-type"),
+            (ETAG, "ETag"),
+            (HOST, "host"),
+            (SET_COOKIE, "set-cookie"),
+            (WWW_AUTHENTICATE, "WWW-Authenticate"),
+            (X_FORWARDED_FOR, "x-forwarded-for"),
+        ];
+
+        for (constant, name) in exported {
+            let HeaderNameInner::Standard(std) = constant.inner else {
+                panic!("{constant} is not a standard header name");
+            };
+            assert!(is_standard(&HeaderName::from(name), std));
+            assert_eq!(constant, HeaderName::from(name));
+        }
+
+        let custom = HeaderName::from("x-custom-header");
+        assert!(matches!(custom.inner, HeaderNameInner::Custom(_)));
+    }
+
+    #[test]
+    fn custom_names_ignore_case() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let hash = |name: &HeaderName| {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let mut headers = Headers::new();
+        headers.header("X-My-Header", b"first");
+
+        for lookup in ["X-My-Header", "x-my-header", "X-MY-HEADER"] {
+            let name = HeaderName::from(lookup);
+            assert_eq!(name, HeaderName::from("X-My-Header"));
+            assert_eq!(hash(&name), hash(&HeaderName::from("x-MY-header")));
+            assert_eq!(headers.get(&name).unwrap().as_str(), "first");
+            assert!(headers.contains(&name));
+        }
+
+        // Another casing replaces the value but keeps the original name.
+        headers.header("x-my-header", b"second");
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.to_string(), "X-My-Header: second\n");
+
+        // The casing a name was given is kept for output.
+        let name = HeaderName::from("x-lowercase-name");
+        assert_eq!(name.as_str(), "x-lowercase-name");
+        assert_ne!(name, HeaderName::from("x-lowercase-names"));
+
+        headers.remove(&HeaderName::from("X-MY-HEADER"));
+        assert!(headers.is_empty());
+    }
 }
 
 #[cfg(test)]
@@ -239,12 +521,76 @@ mod multiple_headers {
 
         assert_eq!(test_headers, expected_headers);
     }
+
+    #[test]
+    fn max_headers() {
+        use crate::MAX_HEADERS;
+
+        let lines = |count: u16| {
+            (0..count)
+                .map(|num| format!("X-Header-{num}: {num}"))
+                .collect::<Vec<String>>()
+                .join("\r\n")
+                + "\r\n"
+        };
+
+        let input = lines(MAX_HEADERS);
+        let headers = Headers::from_str(&input).unwrap();
+        assert_eq!(headers.len(), usize::from(MAX_HEADERS));
+
+        let input = lines(MAX_HEADERS + 1);
+        assert_eq!(Headers::from_str(&input), Err(NetError::TooManyHeaders));
+
+        let input = format!("GET / HTTP/1.1\r\n{input}\r\n");
+        assert_eq!(Request::from_str(&input), Err(NetError::TooManyHeaders));
+
+        let input = input.replace("GET / HTTP/1.1", "HTTP/1.1 200 OK");
+        assert_eq!(Response::from_str(&input), Err(NetError::TooManyHeaders));
+    }
 }
 
 #[cfg(test)]
 mod request {
     use super::*;
 
+    #[test]
+    fn text_and_bytes() {
+        let req = Request::builder()
+            .method(Method::Post)
+            .body(Body::Text("caf\u{e9}".into()))
+            .build();
+        assert_eq!(req.text(), "caf\u{e9}");
+        assert_eq!(req.bytes(), "caf\u{e9}".as_bytes());
+
+        let req = Request::new();
+        assert_eq!(req.text(), "");
+        assert!(req.bytes().is_empty());
+    }
+
+    #[test]
+    fn query_params() {
+        let req = Request::builder()
+            .path("/search?page=2".into())
+            .query(&[("q", "foo bar"), ("lang", "en&fr")])
+            .build();
+        assert_eq!(
+            req.path.as_str(),
+            "/search?page=2&q=foo%20bar&lang=en%26fr"
+        );
+
+        let req = Request::builder()
+            .path("/search".into())
+            .query(&[("q", "caf\u{e9}")])
+            .build();
+        assert_eq!(req.path.as_str(), "/search?q=caf%C3%A9");
+
+        let req = Request::builder()
+            .path("/search?".into())
+            .query(&[("a.b", "~1")])
+            .build();
+        assert_eq!(req.path.as_str(), "/search?a.b=~1");
+    }
+
     #[test]
     fn from_str() {
         let input = "\
@@ -304,12 +650,150 @@ mod request {
 
         assert_eq!(test_req, expected_req);
     }
+
+    #[test]
+    fn clone_with_body() {
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/post".into())
+            .header("Pineapple", b"pizza")
+            .body(Body::Text("request body".into()))
+            .build();
+
+        let cloned = req.clone();
+
+        assert_eq!(cloned, req);
+        assert_eq!(cloned.body.as_bytes(), req.body.as_bytes());
+    }
+
+    #[test]
+    fn header_getters() {
+        let req = Request::from_str(
+            "POST /upload HTTP/1.1\r\n\
+            Host: example.com\r\n\
+            User-Agent: test-agent\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 2\r\n\r\n{}"
+        ).unwrap();
+        assert_eq!(req.host().as_deref(), Some("example.com"));
+        assert_eq!(req.user_agent().as_deref(), Some("test-agent"));
+        assert_eq!(req.content_type().as_deref(), Some("application/json"));
+        assert_eq!(req.content_length(), Some(2));
+
+        let req = Request::from_str("GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(req.host(), None);
+        assert_eq!(req.content_type(), None);
+        assert_eq!(req.content_length(), None);
+    }
+
+    #[test]
+    fn preferred_content_type() {
+        let offered = ["application/json", "application/xml"];
+
+        let prefer = |accept: Option<&str>, offered: &[&'static str]| {
+            let mut builder = Request::builder();
+            if let Some(accept) = accept {
+                let _ = builder.header("Accept", accept.as_bytes());
+            }
+            builder.build().preferred_content_type(offered)
+        };
+
+        // Without an Accept header, the first offered type is chosen.
+        assert_eq!(prefer(None, &offered), Some("application/json"));
+        assert_eq!(prefer(None, &[]), None);
+
+        // Quality ordering.
+        let accept = "application/json;q=0.5, application/xml";
+        assert_eq!(prefer(Some(accept), &offered), Some("application/xml"));
+        let accept = "application/xml;q=0.8,application/json;q=0.9";
+        assert_eq!(prefer(Some(accept), &offered), Some("application/json"));
+
+        // Ties go to the first offered type.
+        let accept = "application/xml, application/json";
+        assert_eq!(prefer(Some(accept), &offered), Some("application/json"));
+
+        // Wildcards, where the most specific range decides the quality.
+        assert_eq!(prefer(Some("*/*"), &offered), Some("application/json"));
+        let accept = "application/*;q=0.2, application/xml";
+        assert_eq!(prefer(Some(accept), &offered), Some("application/xml"));
+        let accept = "text/*, */*;q=0.1";
+        let html = ["application/json", "text/html; charset=utf-8"];
+        assert_eq!(prefer(Some(accept), &html), Some(html[1]));
+        let accept = "TEXT/HTML;Q=0.5, application/json;q=0";
+        assert_eq!(prefer(Some(accept), &html), Some(html[1]));
+
+        // Types that are not acceptable are never chosen.
+        let accept = "application/json;q=0, */*;q=0.5";
+        assert_eq!(prefer(Some(accept), &offered), Some("application/xml"));
+        assert_eq!(prefer(Some("text/plain"), &offered), None);
+        assert_eq!(prefer(Some("*/*;q=0"), &offered), None);
+    }
 }
 
 #[cfg(test)]
 mod response {
     use super::*;
 
+    #[test]
+    fn text_and_bytes() {
+        let res = Response::builder()
+            .body(Body::Bytes(vec![b'o', b'k', 0xff].into()))
+            .build()
+            .unwrap();
+        assert_eq!(res.text(), "ok\u{fffd}");
+        assert_eq!(res.bytes(), &[b'o', b'k', 0xff]);
+
+        let res = Response::builder()
+            .body(Body::Json("{\"a\":1}".into()))
+            .build()
+            .unwrap();
+        assert_eq!(res.text(), "{\"a\":1}");
+    }
+
+    #[test]
+    fn header_getters() {
+        let res = Response::from_str(
+            "HTTP/1.1 301 Moved Permanently\r\n\
+            Location: /new\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Encoding: gzip\r\n\
+            Content-Length: 0\r\n\r\n"
+        ).unwrap();
+        assert_eq!(res.location().as_deref(), Some("/new"));
+        assert_eq!(res.content_type().as_deref(), Some("text/plain"));
+        assert_eq!(res.content_encoding().as_deref(), Some("gzip"));
+        assert_eq!(res.content_length(), Some(0));
+
+        let res = Response::from_str("HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        assert_eq!(res.location(), None);
+        assert_eq!(res.content_length(), None);
+    }
+
+    #[test]
+    fn retry_after() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use crate::RetryAfter;
+        use crate::headers::names::RETRY_AFTER;
+
+        let mut res = Response::new();
+        assert_eq!(res.headers.retry_after(), None);
+
+        res.retry_after(RetryAfter::Seconds(120));
+        assert_eq!(res.headers.get(&RETRY_AFTER), Some(&"120".into()));
+        assert_eq!(res.headers.retry_after(), Some(RetryAfter::Seconds(120)));
+
+        let date = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        res.retry_after(RetryAfter::Date(date));
+        assert_eq!(
+            res.headers.get(&RETRY_AFTER),
+            Some(&"Sun, 06 Nov 1994 08:49:37 GMT".into())
+        );
+        assert_eq!(res.headers.retry_after(), Some(RetryAfter::Date(date)));
+
+        res.headers.insert(RETRY_AFTER, "soon".into());
+        assert_eq!(res.headers.retry_after(), None);
+    }
+
     #[test]
     fn from_str() {
         let input = "\
@@ -349,6 +833,62 @@ mod response {
 
         assert_eq!(test_res, expected_res);
     }
+
+    #[test]
+    fn clone_with_body() {
+        let res = Response::builder()
+            .status_code(201)
+            .header("Pineapple", b"pizza")
+            .body(Body::Json(r#"{"data": [1, 2, 3]}"#.into()))
+            .build()
+            .unwrap();
+
+        let cloned = res.clone();
+
+        assert_eq!(cloned, res);
+        assert_eq!(cloned.body.as_bytes(), res.body.as_bytes());
+
+        let mut changed = res.clone();
+        changed.headers.insert(CONTENT_TYPE, "text/html".into());
+        assert_ne!(changed, res);
+
+        // A streamed body shares its reader with the clone.
+        let body = Body::from_reader(std::io::empty(), None).unwrap();
+        let res = Response::builder().body(body).build().unwrap();
+        let cloned = res.clone();
+        assert_eq!(cloned, res);
+
+        let streams = (res.body, cloned.body);
+        let (Body::Stream(a), Body::Stream(b)) = streams else {
+            panic!("expected streamed bodies");
+        };
+        assert!(a.take().is_some());
+        assert!(b.take().is_none());
+    }
+
+    #[test]
+    fn clone_live_client_messages() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(Request::default())
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+
+        // Snapshots of a live client's messages do not need the connection.
+        let req = client.req.clone().unwrap();
+        let res = client.res.clone().unwrap();
+        assert_eq!(Some(&req), client.req.as_ref());
+        assert_eq!(Some(&res), client.res.as_ref());
+        assert!(client.conn.is_some());
+    }
 }
 
 #[cfg(test)]
@@ -409,13 +949,140 @@ mod utils {
             "example.com": "example.com:80", "/";
             "www.example.com:80/test": "www.example.com:80", "/test";
             "127.0.0.1:80/test": "127.0.0.1:80", "/test";
+            "http://[::1]": "[::1]:80", "/";
+            "http://[::1]:8080/path": "[::1]:8080", "/path";
+            "[::1]/": "[::1]:80", "/";
+            "[2001:db8::1]:443": "[2001:db8::1]:443", "/";
+            "http://[2001:db8::1]:443/a/b": "[2001:db8::1]:443", "/a/b";
         }
 
         test_uri_parser! {
             SHOULD_ERROR: "https://www.example.com";
             SHOULD_ERROR: "http://";
+            SHOULD_ERROR: "http://[::1";
+            SHOULD_ERROR: "http://[::1:8080/path";
+            SHOULD_ERROR: "[::1]8080";
+            SHOULD_ERROR: "http://[not-an-ip]/";
+        }
+    }
+
+    #[test]
+    fn host_from_address() {
+        assert_eq!(host_from_addr("example.com:80"), "example.com");
+        assert_eq!(host_from_addr("Example.com:8080"), "example.com:8080");
+        assert_eq!(host_from_addr("127.0.0.1:80"), "127.0.0.1");
+        assert_eq!(host_from_addr("[::1]:7878"), "[::1]:7878");
+        assert_eq!(host_from_addr("[::1]:80"), "[::1]");
+        assert_eq!(host_from_addr("example.com:8080"), "example.com:8080");
+    }
+
+    #[test]
+    fn parse_imf_fixdate() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let date = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(date, Some(expected));
+
+        let expected = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        let date = parse_http_date("Tue, 29 Feb 2000 12:34:56 GMT");
+        assert_eq!(date, Some(expected));
+
+        assert!(parse_http_date("Sun, 06 Nov 1994 08:49:37").is_none());
+        assert!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT").is_none());
+        assert!(parse_http_date("Sun, 06 Nov 1994 24:49:37 GMT").is_none());
+        assert!(parse_http_date("06 Nov 1994 08:49:37 GMT").is_none());
+    }
+
+    #[test]
+    fn parse_obsolete_dates() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let expected = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let date = parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT");
+        assert_eq!(date, Some(expected));
+        let date = parse_http_date("Sun Nov  6 08:49:37 1994");
+        assert_eq!(date, Some(expected));
+
+        let expected = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        let date = parse_http_date("Tuesday, 29-Feb-00 12:34:56 GMT");
+        assert_eq!(date, Some(expected));
+
+        assert!(parse_http_date("Sunday, 06-Nov-1994 08:49:37 GMT").is_none());
+        assert!(parse_http_date("Sunday, 06-Nov-94 08:49:37").is_none());
+        assert!(parse_http_date("Sun Nov  6 08:49:37 1994 GMT").is_none());
+    }
+
+    #[test]
+    fn format_http_date_round_trip() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use crate::utils::format_http_date;
+
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let date = format_http_date(time);
+        assert_eq!(date, "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date(&date), Some(time));
+
+        let epoch = format_http_date(UNIX_EPOCH);
+        assert_eq!(epoch, "Thu, 01 Jan 1970 00:00:00 GMT");
+    }
+
+    #[test]
+    fn format_log_dates() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use crate::utils::{format_clf_date, format_rfc3339};
+
+        let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+        assert_eq!(format_clf_date(time), "10/Oct/2000:13:55:36 +0000");
+        assert_eq!(format_rfc3339(time), "2000-10-10T13:55:36Z");
+
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_251_199);
+        assert_eq!(format_clf_date(leap_day), "29/Feb/2024:23:59:59 +0000");
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn pretty_print_json() {
+        use crate::utils::pretty_json;
+
+        let json = r#"{"slides":[{"title":"Wake up","items":[]},
+            {"title":"A \"quote\"","n":-1.5e3}],"ok":true,"none":null,
+            "empty":{}}"#;
+
+        let expected = r#"{
+  "slides": [
+    {
+      "title": "Wake up",
+      "items": []
+    },
+    {
+      "title": "A \"quote\"",
+      "n": -1.5e3
+    }
+  ],
+  "ok": true,
+  "none": null,
+  "empty": {}
+}"#;
+
+        assert_eq!(pretty_json(json).as_deref(), Some(expected));
+        assert_eq!(pretty_json(" 42 ").as_deref(), Some("42"));
+        assert_eq!(pretty_json("[]").as_deref(), Some("[]"));
+
+        for bad in ["", "{", "[1,]", "{\"a\" 1}", "{1: 2}", "nul", "[1] 2"] {
+            assert_eq!(pretty_json(bad), None, "{bad}");
         }
     }
+
+    #[test]
+    fn pretty_json_depth_is_bounded() {
+        use crate::utils::{pretty_json, MAX_JSON_DEPTH};
+
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(pretty_json(&nested(MAX_JSON_DEPTH)).is_some());
+        assert_eq!(pretty_json(&nested(MAX_JSON_DEPTH + 1)), None);
+        assert_eq!(pretty_json(&"[".repeat(1_000_000)), None);
+    }
 }
 
 mod style {
@@ -430,7 +1097,8 @@ mod style {
                 $(
                     let expected_style = Style {
                         req: $req_style,
-                        res: $res_style
+                        res: $res_style,
+                        pretty: false
                     };
 
                     let mut test_style = Style::default();
@@ -465,6 +1133,28 @@ mod style {
         "RHB": Color(All), Color(None);
         "shb": Color(None), Color(All);
         "xyz3s": Color(None), Color(Line);
+        "RRssh": Color(Line), Color(LineHdrs);
+    }
+
+    #[test]
+    fn highlight_json() {
+        use crate::style::highlight_json;
+        use crate::style::colors::{BLUE, GREEN, MAGENTA, ORANGE, RESET};
+
+        let json = r#"{"a": "b:", "c" : [-1.5e3, true, null]}"#;
+        let expected = format!(
+            "{{{BLUE}\"a\"{RESET}: {GREEN}\"b:\"{RESET}, \
+            {BLUE}\"c\"{RESET} : [{ORANGE}-1.5e3{RESET}, \
+            {MAGENTA}true{RESET}, {MAGENTA}null{RESET}]}}"
+        );
+        assert_eq!(highlight_json(json), expected);
+
+        // Escaped quotes do not end a string.
+        let json = r#"["x\"y"]"#;
+        assert_eq!(
+            highlight_json(json),
+            format!("[{GREEN}\"x\\\"y\"{RESET}]")
+        );
     }
 }
 
@@ -497,9 +1187,15 @@ mod trait_impls {
 #[cfg(test)]
 mod client_cli {
     use super::*;
+    use crate::DEFAULT_NAME;
 
     #[test]
     fn parse_args() {
+        let mut router = Router::new();
+        let _ = router.add_testing_routes();
+        let addr = start_server(Server::builder().router(&mut router));
+        let uri = format!("{addr}/echo");
+
         let mut args = VecDeque::from([
             "./client",
             "--plain",
@@ -513,14 +1209,15 @@ mod client_cli {
             "-H", "content-type:text/html; charset=utf-8",
             "-H", "pineaPPle:yum123",
             "--body", "This is a test meSSage :) in the request bOdy.",
-            "httpbin.org/json"
+            uri.as_str()
         ]);
 
-        let mut test_client = ClientCli::parse_args(&mut args).unwrap();
+        let test_client = ClientCli::parse_args(&mut args).unwrap();
 
         let style = Style {
             req: Kind::Plain(Parts::LineBody),
-            res: Kind::Plain(Parts::All)
+            res: Kind::Plain(Parts::All),
+            pretty: false
         };
 
         let mut headers = Headers::new();
@@ -533,30 +1230,126 @@ mod client_cli {
 
         let expected_req = Request {
             method: Method::Post,
-            path: "/json".into(),
+            path: "/echo".into(),
             version: Version::default(),
             headers,
-            body: Body::Text(body_text.into())
+            body: Body::Text(body_text.into()),
+            authority: None
         };
 
-        let mut expected_client = Client::builder()
+        let expected_client = Client::builder()
             .do_debug(true)
             .no_dates(true)
             .style(style)
             .req(expected_req)
-            .addr("httpbin.org:80")
+            .addr(addr)
+            .host(&addr.to_string())
             .build()
             .unwrap();
 
-        if let Some(req) = test_client.req.as_mut() {
-            req.headers.insert(HOST, "httpbin.org".into());
-        }
+        assert_eq!(test_client, expected_client);
+    }
 
-        if let Some(req) = expected_client.req.as_mut() {
-            req.headers.insert(HOST, "httpbin.org".into());
-        }
+    #[test]
+    fn pretty() {
+        let mut args = VecDeque::from([
+            "./client", "--dry-run", "--pretty", "example.com/json"
+        ]);
 
-        assert_eq!(test_client, expected_client);
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        assert!(client.style.pretty);
+
+        let body = Body::Json(r#"{"a":[1,2]}"#.into());
+        assert_eq!(
+            client.body_to_string(&body),
+            "{\n  \"a\": [\n    1,\n    2\n  ]\n}"
+        );
+
+        // Other bodies and invalid JSON are printed unchanged.
+        let text = Body::Text(r#"{"a":1}"#.into());
+        assert_eq!(client.body_to_string(&text), r#"{"a":1}"#);
+        let bad = Body::Json("{oops".into());
+        assert_eq!(client.body_to_string(&bad), "{oops");
+    }
+
+    #[test]
+    fn body_file() {
+        use std::env;
+        use std::fs;
+
+        let file_path = env::temp_dir().join("rustnet_body_file_test.json");
+        fs::write(&file_path, br#"{"name": "rustnet"}"#).unwrap();
+        let path = file_path.to_str().unwrap();
+
+        let mut args = VecDeque::from([
+            "./client", "--dry-run", "-M", "POST", "--body-file", path,
+            "example.com/post"
+        ]);
+
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        let req = client.req.unwrap();
+        assert_eq!(req.body.as_bytes(), br#"{"name": "rustnet"}"#);
+        assert_eq!(
+            req.headers.get(&CONTENT_TYPE),
+            Some(&"application/json".into())
+        );
+
+        // An explicit Content-Type is not replaced.
+        let mut args = VecDeque::from([
+            "./client", "--dry-run", "--body-file", path,
+            "-H", "Content-Type: text/plain", "example.com/post"
+        ]);
+
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        let req = client.req.unwrap();
+        assert_eq!(
+            req.headers.get(&CONTENT_TYPE),
+            Some(&"text/plain".into())
+        );
+
+        let _ = fs::remove_file(&file_path);
+    }
+
+    #[test]
+    fn dry_run() {
+        let mut args = VecDeque::from([
+            "./client",
+            "--dry-run",
+            "--plain",
+            "--no-dates",
+            "--method", "put",
+            "-H", "pineaPPle:yum123",
+            "--body", "Dry run body.",
+            "example.com:8080/dry/run"
+        ]);
+
+        let mut client = ClientCli::parse_args(&mut args).unwrap();
+        assert!(client.dry_run);
+        assert!(!client.do_send);
+        assert!(client.conn.is_none());
+
+        let wire = client.wire_bytes().unwrap();
+        assert!(wire.starts_with(b"PUT /dry/run HTTP/1.1\r\n"));
+        assert!(wire.ends_with(b"\r\n\r\nDry run body."));
+
+        let mut headers = Headers::new();
+        headers.insert(ACCEPT, "*/*".into());
+        headers.insert(CONTENT_LENGTH, 13.into());
+        headers.insert(CONTENT_TYPE, "text/plain; charset=utf-8".into());
+        headers.insert(HOST, "example.com:8080".into());
+        headers.insert("Pineapple".into(), "yum123".into());
+        headers.insert(USER_AGENT, DEFAULT_NAME.into());
+
+        let expected_req = Request {
+            method: Method::Put,
+            path: "/dry/run".into(),
+            version: Version::OneDotOne,
+            headers,
+            body: Body::Text("Dry run body.".into()),
+            authority: None
+        };
+
+        assert_eq!(Request::try_from(&wire[..]).unwrap(), expected_req);
     }
 }
 
@@ -582,51 +1375,62 @@ mod server_cli {
 
         let test_cli = ServerCli::parse_args(&mut args);
 
-        let router = Router(BTreeSet::from([
-            Route {
-                method: Method::Shutdown,
-                path: None,
-                target: Target::Shutdown
-            },
+        let router = Router::from(BTreeSet::from([
             Route {
                 method: Method::Get,
                 path: Some("/favicon.ico".into()),
-                target: Target::Favicon(Path::new("./favicon.ico").into())
+                target: Target::Favicon(Path::new("./favicon.ico").into()),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Any,
                 path: None,
-                target: Path::new("./error_404.html").into()
+                target: Path::new("./error_404.html").into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Get,
                 path: Some("/get".into()),
-                target: Path::new("./static/get.html").into()
+                target: Path::new("./static/get.html").into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Post,
                 path: Some("/post".into()),
-                target: Path::new("./static/post.html").into()
+                target: Path::new("./static/post.html").into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Head,
                 path: Some("/head".into()),
-                target: Path::new("./static/head.html").into()
+                target: Path::new("./static/head.html").into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Put,
                 path: Some("/put".into()),
-                target: "test message1.".into()
+                target: "test message1.".into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Patch,
                 path: Some("/patch".into()),
-                target: "test message2.".into()
+                target: "test message2.".into(),
+                max_age: None,
+                accepts: Vec::new()
             },
             Route {
                 method: Method::Delete,
                 path: Some("/delete".into()),
-                target: "test message3.".into()
+                target: "test message3.".into(),
+                max_age: None,
+                accepts: Vec::new()
             }
         ]));
 
@@ -642,3 +1446,6664 @@ mod server_cli {
         assert_eq!(test_cli, expected_cli);
     }
 }
+
+#[cfg(test)]
+mod cache_control {
+    use super::*;
+
+    fn cache_control(router: &Router, method: Method, path: &str) -> String {
+        let req = Request::builder()
+            .method(method)
+            .path(path.to_string().into())
+            .build();
+        let res = router.resolve(&req).unwrap();
+        res.headers.get(&CACHE_CONTROL).unwrap().to_string()
+    }
+
+    #[test]
+    fn get_cached_sets_max_age() {
+        let mut router = Router::new();
+        let _ = router
+            .get("/about", Path::new("static/about.html"))
+            .get_cached("/cached", Path::new("static/about.html"), 86_400);
+
+        let cached = "public, max-age=86400";
+        assert_eq!(cache_control(&router, Method::Get, "/cached"), cached);
+        assert_eq!(cache_control(&router, Method::Head, "/cached"), cached);
+        assert_eq!(cache_control(&router, Method::Get, "/about"), "no-cache");
+        assert_eq!(cache_control(&router, Method::Get, "/none"), "no-cache");
+    }
+}
+
+#[cfg(test)]
+mod preconditions {
+    use super::*;
+    use crate::utils::file_etag;
+
+    const FILE: &str = "static/about.html";
+
+    fn resolve_with(method: Method, name: &str, value: &str) -> u16 {
+        let mut router = Router::new();
+        let _ = router
+            .get("/about", Path::new(FILE))
+            .put("/about", Path::new(FILE))
+            .delete("/about", Path::new(FILE));
+
+        let req = Request::builder()
+            .method(method)
+            .path("/about".into())
+            .header(name, value.as_bytes())
+            .build();
+
+        router.resolve(&req).unwrap().status.code()
+    }
+
+    #[test]
+    fn if_match() {
+        let etag = file_etag(Path::new(FILE)).unwrap();
+        let other = format!("\"stale\", {etag}");
+
+        assert_eq!(resolve_with(Method::Put, "If-Match", "\"stale\""), 412);
+        assert_eq!(resolve_with(Method::Delete, "If-Match", "\"0-0\""), 412);
+        assert_eq!(resolve_with(Method::Put, "If-Match", &etag), 200);
+        assert_eq!(resolve_with(Method::Put, "If-Match", &other), 200);
+        assert_eq!(resolve_with(Method::Put, "If-Match", "*"), 200);
+        // Weak tags never match.
+        let weak = format!("W/{etag}");
+        assert_eq!(resolve_with(Method::Put, "If-Match", &weak), 412);
+        // Safe methods are not checked.
+        assert_eq!(resolve_with(Method::Get, "If-Match", "\"stale\""), 200);
+    }
+
+    #[test]
+    fn if_unmodified_since() {
+        let old = "Sun, 06 Nov 1994 08:49:37 GMT";
+        let future = "Fri, 01 Jan 2100 00:00:00 GMT";
+        let name = "If-Unmodified-Since";
+
+        assert_eq!(resolve_with(Method::Put, name, old), 412);
+        assert_eq!(resolve_with(Method::Delete, name, old), 412);
+        assert_eq!(resolve_with(Method::Put, name, future), 200);
+        // Invalid dates are ignored.
+        assert_eq!(resolve_with(Method::Put, name, "yesterday"), 200);
+    }
+}
+
+#[cfg(test)]
+mod encoding {
+    use super::*;
+    use std::fs;
+    use crate::encoding::{
+        decode, gunzip, inflate, inflate_raw, MAX_DECODED_LEN as MAX,
+    };
+
+    const TEXT: &[u8] = b"Hello, hello, hello! This text is compressed. ";
+
+    // `TEXT` repeated 4 times and compressed with zlib.
+    const ZLIB: [u8; 47] = [
+        120, 156, 243, 72, 205, 201, 201, 215, 81, 200, 64, 162, 20, 21, 66,
+        50, 50, 139, 21, 74, 82, 43, 74, 20, 128, 116, 114, 126, 110, 65, 81,
+        106, 113, 113, 106, 138, 158, 130, 199, 160, 81, 13, 0, 45, 37, 64,
+        69,
+    ];
+
+    #[test]
+    fn gunzip_dynamic_block() {
+        let gzipped = fs::read("static/index.html.gz").unwrap();
+        let expected = fs::read("static/index.html").unwrap();
+        assert_eq!(gunzip(&gzipped, MAX).unwrap(), expected);
+        assert_eq!(decode("gzip", &gzipped, MAX).unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn inflate_fixed_and_stored_blocks() {
+        let expected = TEXT.repeat(4);
+        assert_eq!(inflate(&ZLIB, MAX).unwrap(), expected);
+
+        // Raw deflate data without the zlib wrapper.
+        let raw = &ZLIB[2..ZLIB.len() - 4];
+        assert_eq!(inflate(raw, MAX).unwrap(), expected);
+
+        let stored = b"\x01\x05\x00\xfa\xffhello";
+        assert_eq!(inflate_raw(stored, MAX).unwrap(), (b"hello".to_vec(), 10));
+    }
+
+    #[test]
+    fn reject_corrupt_data() {
+        let mut gzipped = fs::read("static/index.html.gz").unwrap();
+        let len = gzipped.len();
+        gzipped[len - 8] ^= 0xff;
+        assert_eq!(gunzip(&gzipped, MAX), Err(NetError::BadEncoding));
+        assert_eq!(gunzip(&gzipped[..len / 2], MAX), Err(NetError::BadEncoding));
+
+        let mut zlib = ZLIB;
+        zlib[46] ^= 0xff;
+        assert_eq!(inflate(&zlib, MAX), Err(NetError::BadEncoding));
+    }
+
+    #[test]
+    fn unsupported_encoding() {
+        #[cfg(not(feature = "brotli"))]
+        assert_eq!(decode("br", TEXT, MAX), Ok(None));
+        assert_eq!(decode("zstd", TEXT, MAX), Ok(None));
+        assert_eq!(decode("identity", TEXT, MAX), Ok(Some(TEXT.to_vec())));
+    }
+
+    #[test]
+    fn decoded_length_is_capped() {
+        let gzipped = fs::read("static/index.html.gz").unwrap();
+        let len = fs::read("static/index.html").unwrap().len();
+        assert!(gunzip(&gzipped, len).is_ok());
+        assert_eq!(gunzip(&gzipped, len - 1), Err(NetError::BodyTooLarge));
+        assert_eq!(
+            decode("gzip", &gzipped, len - 1),
+            Err(NetError::BodyTooLarge)
+        );
+
+        let len = TEXT.len() * 4;
+        assert!(inflate(&ZLIB, len).is_ok());
+        assert_eq!(inflate(&ZLIB, len - 1), Err(NetError::BodyTooLarge));
+
+        let stored = b"\x01\x05\x00\xfa\xffhello";
+        assert_eq!(inflate_raw(stored, 4), Err(NetError::BodyTooLarge));
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn unbrotli_known_payload() {
+        use crate::encoding::unbrotli;
+
+        // "The quick brown fox jumps over the lazy dog" repeated 4096 times.
+        const BROTLI: [u8; 58] = [
+            0x5b, 0xff, 0xaf, 0x02, 0xc0, 0x22, 0x79, 0x5c, 0xfb, 0x5a, 0x8c,
+            0x42, 0x3b, 0xf4, 0x25, 0x55, 0x19, 0x5a, 0x92, 0x99, 0xb1, 0x35,
+            0xc8, 0x19, 0x9e, 0x9e, 0x0a, 0x7b, 0x4b, 0x90, 0xb9, 0x3c, 0x98,
+            0xc8, 0x09, 0x40, 0xf3, 0xe6, 0xd9, 0x4d, 0xe4, 0x6d, 0x65, 0x1b,
+            0x27, 0x87, 0x13, 0x5f, 0xa6, 0xe9, 0x30, 0x96, 0x7b, 0x3c, 0x15,
+            0xd8, 0x53, 0x1c,
+        ];
+
+        let expected =
+            b"The quick brown fox jumps over the lazy dog".repeat(4096);
+        assert_eq!(unbrotli(&BROTLI, MAX).unwrap(), expected);
+        assert_eq!(decode("br", &BROTLI, MAX).unwrap(), Some(expected));
+        assert_eq!(unbrotli(&BROTLI[..20], MAX), Err(NetError::BadEncoding));
+
+        let len = 43 * 4096;
+        assert!(unbrotli(&BROTLI, len).is_ok());
+        assert_eq!(unbrotli(&BROTLI, len - 1), Err(NetError::BodyTooLarge));
+    }
+
+    #[test]
+    fn sha1_digests() {
+        use std::fmt::Write as _;
+        use crate::encoding::sha1;
+
+        let hex = |digest: [u8; 20]| {
+            digest.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+        };
+
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(sha1(&[b'a'; 1000])),
+            "291e9a6c66994949b57ba5e650361e98fc36b1ba"
+        );
+    }
+
+    #[test]
+    fn md5_and_sha256_digests() {
+        use std::fmt::Write as _;
+        use crate::encoding::{md5, sha256};
+
+        let hex = |digest: &[u8]| {
+            digest.iter().fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+        };
+
+        assert_eq!(hex(&md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hex(&md5(&[b'a'; 1000])),
+            "cabe45dcc9ae5b66ba86600cca6b8ba8"
+        );
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(&[b'a'; 1000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn base64() {
+        use crate::encoding::base64_encode;
+
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "+/8=");
+    }
+}
+
+#[cfg(test)]
+mod compressed {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::fs;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+    use crate::encoding::SUPPORTED_ENCODINGS;
+    use crate::headers::names::CONTENT_ENCODING;
+
+    fn serve_gzipped(listener: TcpListener) -> thread::JoinHandle<Request> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let req = conn.recv_request().unwrap();
+
+            let body = fs::read("static/index.html.gz").unwrap();
+            let head = format!(
+                "HTTP/1.1 200 OK\r\n\
+                Content-Encoding: gzip\r\n\
+                Content-Length: {}\r\n\
+                Content-Type: text/html; charset=utf-8\r\n\r\n",
+                body.len()
+            );
+            conn.write_all(head.as_bytes()).unwrap();
+            conn.write_all(&body).unwrap();
+            conn.flush().unwrap();
+            req
+        })
+    }
+
+    #[test]
+    fn parse_compressed_flag() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("{}/gzip", listener.local_addr().unwrap());
+
+        let mut args = VecDeque::from([
+            "./http_client", "--compressed", uri.as_str(),
+        ]);
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        assert!(client.auto_decompress);
+        let req = client.req.unwrap();
+        assert_eq!(
+            req.headers.get(&ACCEPT_ENCODING),
+            Some(&SUPPORTED_ENCODINGS.into())
+        );
+
+        let mut args = VecDeque::from(["./http_client", uri.as_str()]);
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        assert!(!client.auto_decompress);
+        assert!(!client.req.unwrap().headers.contains(&ACCEPT_ENCODING));
+    }
+
+    #[test]
+    fn decode_gzipped_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("{}/gzip", listener.local_addr().unwrap());
+        let handle = serve_gzipped(listener);
+
+        let mut args = VecDeque::from([
+            "./http_client", "--compressed", uri.as_str(),
+        ]);
+        let mut client = ClientCli::parse_args(&mut args).unwrap();
+        client.send_request().unwrap();
+        client.recv_response().unwrap();
+
+        let req = handle.join().unwrap();
+        assert!(req.headers.contains(&ACCEPT_ENCODING));
+
+        let expected = fs::read_to_string("static/index.html").unwrap();
+        let res = client.res.unwrap();
+        assert_eq!(res.body, Body::Html(expected.clone().into()));
+        assert!(!res.headers.contains(&CONTENT_ENCODING));
+        assert_eq!(
+            res.headers.get(&CONTENT_LENGTH),
+            Some(&expected.len().into())
+        );
+        assert!(res.to_string().contains(&expected));
+    }
+
+    // Serves a single response with the given Content-Encoding and body.
+    fn serve_encoded(
+        listener: TcpListener,
+        coding: &'static str,
+        body: &'static [u8]
+    ) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request().unwrap();
+
+            let head = format!(
+                "HTTP/1.1 200 OK\r\n\
+                Content-Encoding: {coding}\r\n\
+                Content-Length: {}\r\n\
+                Content-Type: text/plain\r\n\r\n",
+                body.len()
+            );
+            conn.write_all(head.as_bytes()).unwrap();
+            conn.write_all(body).unwrap();
+            conn.flush().unwrap();
+        })
+    }
+
+    #[test]
+    fn builder_sets_accept_encoding() {
+        let client = Client::builder()
+            .dry_run(true)
+            .auto_decompress(true)
+            .req(Request::new())
+            .build()
+            .unwrap();
+        assert_eq!(
+            client.req.unwrap().headers.get(&ACCEPT_ENCODING),
+            Some(&SUPPORTED_ENCODINGS.into())
+        );
+
+        let mut req = Request::new();
+        req.headers.insert(ACCEPT_ENCODING, "gzip".into());
+        let client = Client::builder()
+            .dry_run(true)
+            .auto_decompress(true)
+            .req(req)
+            .build()
+            .unwrap();
+        assert_eq!(
+            client.req.unwrap().headers.get(&ACCEPT_ENCODING),
+            Some(&"gzip".into())
+        );
+
+        let client = Client::builder()
+            .dry_run(true)
+            .req(Request::new())
+            .build()
+            .unwrap();
+        assert!(!client.req.unwrap().headers.contains(&ACCEPT_ENCODING));
+    }
+
+    #[test]
+    fn unsupported_encoding_passthrough() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = serve_encoded(listener, "zstd", b"not really zstd");
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .auto_decompress(true)
+            .req(Request::new())
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.body.as_bytes(), b"not really zstd");
+        assert_eq!(res.headers.get(&CONTENT_ENCODING), Some(&"zstd".into()));
+    }
+
+    #[test]
+    fn unrequested_encoding_passthrough() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gzipped: &'static [u8] =
+            fs::read("static/index.html.gz").unwrap().leak();
+        let handle = serve_encoded(listener, "gzip", gzipped);
+
+        let mut req = Request::new();
+        req.headers.insert(ACCEPT_ENCODING, "deflate".into());
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .auto_decompress(true)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.body.as_bytes(), gzipped);
+        assert_eq!(res.headers.get(&CONTENT_ENCODING), Some(&"gzip".into()));
+    }
+}
+
+#[cfg(test)]
+mod chunked {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use crate::headers::names::TRANSFER_ENCODING;
+
+    fn chunked_response(body: &str) -> Response {
+        Response::builder()
+            .header("Content-Length", b"999")
+            .chunked()
+            .body(body.to_string().into())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn builder_sets_headers() {
+        let res = chunked_response("test");
+        assert!(res.is_chunked());
+        assert!(!res.headers.contains(&CONTENT_LENGTH));
+        assert_eq!(
+            res.headers.get(&TRANSFER_ENCODING),
+            Some(&"chunked".into())
+        );
+    }
+
+    #[test]
+    fn write_chunk_framing() {
+        let body = "abcdefghij".repeat(500);
+        let (raw, _) = send_response_raw(chunked_response(&body));
+        let raw = String::from_utf8(raw).unwrap();
+
+        let (head, framed) = raw.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(!head.contains("Content-Length"));
+
+        let expected = format!(
+            "800\r\n{}\r\n800\r\n{}\r\n388\r\n{}\r\n0\r\n\r\n",
+            &body[..2048],
+            &body[2048..4096],
+            &body[4096..]
+        );
+        assert_eq!(framed, expected);
+    }
+
+    #[test]
+    fn decode_chunked_body() {
+        let body = "abcdefghij".repeat(500);
+        let (raw, _) = send_response_raw(chunked_response(&body));
+
+        let res = recv_response_raw(raw, Method::Get);
+        assert_eq!(res.body, Body::Text(body.into()));
+        assert!(res.is_chunked());
+    }
+
+    #[test]
+    fn write_chunks_round_trip() {
+        use std::io::Write;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            conn.writer
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\
+                    Transfer-Encoding: chunked\r\n\r\n")
+                .unwrap();
+            conn.write_chunk(b"first,").unwrap();
+            conn.write_chunk(b"").unwrap();
+            conn.write_chunk(b"second,").unwrap();
+            conn.write_chunk(b"third").unwrap();
+            conn.finish_chunked().unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        let res = conn.recv_response_to(&Method::Get).unwrap();
+        handle.join().unwrap();
+
+        assert!(res.is_chunked());
+        assert_eq!(res.text(), "first,second,third");
+    }
+}
+
+#[cfg(test)]
+mod close_delimited {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    // Sends the raw response and then closes the connection.
+    fn recv_raw(raw: &[u8], method: Method) -> Response {
+        recv_response_raw(raw.to_vec(), method)
+    }
+
+    #[test]
+    fn read_body_to_eof() {
+        let res = recv_raw(b"HTTP/1.1 200 OK\r\n\
+            Connection: close\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            Body that ends at EOF.", Method::Get);
+        assert_eq!(res.body, Body::Text("Body that ends at EOF.".into()));
+
+        // HTTP/1.0 connections close by default.
+        let res = recv_raw(b"HTTP/1.0 200 OK\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            Old body.", Method::Get);
+        assert_eq!(res.body, Body::Text("Old body.".into()));
+    }
+
+    #[test]
+    fn no_body_read_when_not_permitted() {
+        let raw = b"HTTP/1.1 200 OK\r\n\
+            Connection: close\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            Unexpected.";
+        assert_eq!(recv_raw(raw, Method::Head).body, Body::Empty);
+
+        let raw = b"HTTP/1.1 204 No Content\r\n\
+            Connection: close\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            Unexpected.";
+        assert_eq!(recv_raw(raw, Method::Get).body, Body::Empty);
+
+        let raw = b"HTTP/1.1 304 Not Modified\r\n\
+            Connection: close\r\n\
+            Content-Type: text/plain\r\n\r\n\
+            Unexpected.";
+        assert_eq!(recv_raw(raw, Method::Get).body, Body::Empty);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn recv_response_shim_assumes_get() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\
+                Connection: close\r\n\
+                Content-Type: text/plain\r\n\r\n\
+                Old API.").unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        let res = conn.recv_response().unwrap();
+        handle.join().unwrap();
+        assert_eq!(res.body, Body::Text("Old API.".into()));
+    }
+
+    #[test]
+    fn keep_alive_without_length_has_no_body() {
+        let raw = b"HTTP/1.0 200 OK\r\n\
+            Connection: keep-alive\r\n\
+            Content-Type: text/plain\r\n\r\n";
+        assert_eq!(recv_raw(raw, Method::Get).body, Body::Empty);
+    }
+}
+
+#[cfg(test)]
+mod host {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn host_header_uses_hostname() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let addr = format!("localhost:{port}");
+
+        let client = Client::new(Method::Get, &format!("{addr}/test")).unwrap();
+        let req = client.req.unwrap();
+        assert_eq!(req.headers.get(&HOST), Some(&addr.as_str().into()));
+
+        // A user-provided Host header is not replaced.
+        let req = Request::builder()
+            .header("Host", b"example.com")
+            .build();
+        let client = Client::builder()
+            .addr(&addr)
+            .host(&addr)
+            .req(req)
+            .build()
+            .unwrap();
+        let req = client.req.unwrap();
+        assert_eq!(req.headers.get(&HOST), Some(&"example.com".into()));
+    }
+
+    #[test]
+    fn host_header_falls_back_to_ip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(Request::new())
+            .send()
+            .unwrap();
+
+        let req = client.req.take().unwrap();
+        let expected = addr.to_string();
+        assert_eq!(req.headers.get(&HOST), Some(&expected.as_str().into()));
+    }
+}
+
+#[cfg(test)]
+mod retries {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    // Accepts connections and drops the first `num_drops` of them after
+    // reading a request, then responds to the next one. Returns the number
+    // of requests received.
+    fn flaky_server(num_drops: usize) -> (SocketAddr, JoinHandle<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut num_reqs = 0;
+
+            for stream in listener.incoming() {
+                let mut conn = Connection::try_from(stream.unwrap()).unwrap();
+
+                let Ok(req) = conn.recv_request() else {
+                    break;
+                };
+
+                num_reqs += 1;
+
+                // Close the connection before responding.
+                if num_reqs <= num_drops {
+                    continue;
+                }
+
+                // A shutdown request stops the server.
+                if matches!(req.method, Method::Shutdown) {
+                    break;
+                }
+
+                let mut res = Response::builder().status_code(200).build()
+                    .unwrap();
+                conn.send_response(&mut res).unwrap();
+            }
+
+            num_reqs
+        });
+
+        (addr, handle)
+    }
+
+    fn send(addr: SocketAddr, method: Method, retries: u8) -> Client {
+        let req = Request::builder().method(method).build();
+        Client::builder().addr(addr).retries(retries).req(req).send().unwrap()
+    }
+
+    fn stop(addr: SocketAddr, handle: JoinHandle<usize>) -> usize {
+        let mut client = send(addr, Method::Shutdown, 0);
+        assert!(client.recv_response().is_err());
+        handle.join().unwrap() - 1
+    }
+
+    #[test]
+    fn retry_idempotent_request() {
+        let (addr, handle) = flaky_server(2);
+
+        let mut client = send(addr, Method::Get, 2);
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 200);
+
+        assert_eq!(stop(addr, handle), 3);
+    }
+
+    #[test]
+    fn return_last_error_when_retries_run_out() {
+        let (addr, handle) = flaky_server(3);
+
+        let mut client = send(addr, Method::Put, 2);
+        assert_eq!(client.recv_response(), Err(NetError::UnexpectedEof));
+
+        assert_eq!(stop(addr, handle), 3);
+    }
+
+    #[test]
+    fn never_retry_non_idempotent_request() {
+        let (addr, handle) = flaky_server(2);
+
+        let mut client = send(addr, Method::Post, 2);
+        assert_eq!(client.recv_response(), Err(NetError::UnexpectedEof));
+
+        let mut client = send(addr, Method::Patch, 2);
+        assert_eq!(client.recv_response(), Err(NetError::UnexpectedEof));
+
+        assert_eq!(stop(addr, handle), 2);
+    }
+}
+
+#[cfg(test)]
+mod retry_after {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+    use crate::RetryAfter;
+
+    // Responds to the first `num_busy` requests with a 503 status and a
+    // Retry-After header of `secs` seconds, then responds with a 200
+    // status. Returns the number of requests received.
+    fn busy_server(
+        num_busy: usize,
+        secs: u64
+    ) -> (SocketAddr, JoinHandle<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut num_reqs = 0;
+
+            for stream in listener.incoming() {
+                let mut conn = Connection::try_from(stream.unwrap()).unwrap();
+
+                let Ok(req) = conn.recv_request() else {
+                    continue;
+                };
+
+                num_reqs += 1;
+
+                let mut res = if num_reqs <= num_busy {
+                    let mut res = Response::builder()
+                        .status_code(503)
+                        .build()
+                        .unwrap();
+                    res.retry_after(RetryAfter::Seconds(secs));
+                    res
+                } else {
+                    Response::builder().status_code(200).build().unwrap()
+                };
+
+                conn.send_response(&mut res).unwrap();
+
+                if matches!(req.method, Method::Shutdown) {
+                    break;
+                }
+            }
+
+            num_reqs
+        });
+
+        (addr, handle)
+    }
+
+    fn send(addr: SocketAddr, method: Method, any_method: bool) -> Client {
+        let req = Request::builder().method(method).build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .retries(2)
+            .respect_retry_after(true)
+            .retry_any_method(any_method)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        client
+    }
+
+    fn stop(addr: SocketAddr, handle: JoinHandle<usize>) -> usize {
+        let _ = send(addr, Method::Shutdown, false);
+        handle.join().unwrap() - 1
+    }
+
+    #[test]
+    fn retry_after_delay() {
+        let (addr, handle) = busy_server(1, 1);
+
+        let start = Instant::now();
+        let client = send(addr, Method::Get, false);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(client.res.unwrap().status.code(), 200);
+
+        assert_eq!(stop(addr, handle), 2);
+    }
+
+    #[test]
+    fn non_idempotent_not_retried_by_default() {
+        let (addr, handle) = busy_server(1, 1);
+
+        let client = send(addr, Method::Post, false);
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 503);
+        assert_eq!(res.headers.retry_after(), Some(RetryAfter::Seconds(1)));
+
+        assert_eq!(stop(addr, handle), 1);
+    }
+
+    #[test]
+    fn non_idempotent_retried_when_allowed() {
+        let (addr, handle) = busy_server(1, 1);
+
+        let client = send(addr, Method::Post, true);
+        assert_eq!(client.res.unwrap().status.code(), 200);
+
+        assert_eq!(stop(addr, handle), 2);
+    }
+
+    #[test]
+    fn retry_after_past_deadline() {
+        let (addr, handle) = busy_server(1, 1);
+
+        let req = Request::builder().method(Method::Get).build();
+        let mut client = Client::builder()
+            .timeout(Duration::from_millis(500))
+            .addr(addr)
+            .retries(2)
+            .respect_retry_after(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 503);
+
+        assert_eq!(stop(addr, handle), 1);
+    }
+
+    #[test]
+    fn retry_after_longer_than_max() {
+        let secs = crate::client::MAX_RETRY_AFTER.as_secs() + 1;
+        let (addr, handle) = busy_server(1, secs);
+
+        let start = Instant::now();
+        let client = send(addr, Method::Get, false);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 503);
+        assert_eq!(res.headers.retry_after(), Some(RetryAfter::Seconds(secs)));
+
+        assert_eq!(stop(addr, handle), 1);
+    }
+}
+
+#[cfg(test)]
+mod upload {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Cursor;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+    use crate::headers::names::{CONTENT_LENGTH, CONTENT_TYPE, HOST};
+
+    #[test]
+    fn upload_file_with_progress() {
+        let file_path = env::temp_dir().join("rustnet_upload_test.dat");
+        let contents = (0..10_000u32)
+            .map(|n| (n % 251) as u8)
+            .collect::<Vec<u8>>();
+        fs::write(&file_path, &contents).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let req = conn.recv_request().unwrap();
+
+            let mut res = Response::builder().status_code(201).build().unwrap();
+            conn.send_response(&mut res).unwrap();
+            req
+        });
+
+        let mut calls = Vec::new();
+        let uri = format!("{addr}/upload");
+        let mut client = Client::upload_file(&uri, &file_path, |sent, total| {
+            calls.push((sent, total));
+        }).unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 201);
+
+        let total = contents.len() as u64;
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(calls.iter().all(|&(_, call_total)| call_total == Some(total)));
+        assert_eq!(calls.last(), Some(&(total, Some(total))));
+
+        let req = handle.join().unwrap();
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.body.as_bytes(), &contents[..]);
+
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn upload_file_uses_resolve_and_timeout() {
+        let file_path = env::temp_dir().join("rustnet_upload_resolve.txt");
+        fs::write(&file_path, "uploaded").unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let req = conn.recv_request().unwrap();
+
+            let mut res = Response::builder().status_code(201).build().unwrap();
+            conn.send_response(&mut res).unwrap();
+            req
+        });
+
+        let uri = format!("http://upload.test:{}/upload", addr.port());
+        let req = Request::builder().method(Method::Post).build();
+        let mut client = Client::builder()
+            .req(req)
+            .timeout(Duration::from_secs(5))
+            .resolve("upload.test", addr)
+            .uri(&uri.parse().unwrap())
+            .upload_file(&file_path, |_, _| {})
+            .unwrap();
+        assert!(client.deadline.is_some());
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 201);
+
+        let req = handle.join().unwrap();
+        let host = format!("upload.test:{}", addr.port());
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.headers.get(&HOST), Some(&host.as_str().into()));
+        assert!(req.headers.get(&CONTENT_TYPE).is_some());
+        assert_eq!(req.body.as_bytes(), b"uploaded");
+
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn short_reader_is_incomplete() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap());
+        let mut conn = Connection::try_from(stream.unwrap()).unwrap();
+
+        let mut req = Request::builder().method(Method::Post).build();
+        req.headers.insert(CONTENT_LENGTH, "5".into());
+
+        let mut reader = Cursor::new(b"abc");
+        let total = Some(5);
+        let sent = conn.stream_request(&mut req, &mut reader, total, |_, _| {});
+        assert!(matches!(sent, Err(NetError::IncompleteBody)), "{sent:?}");
+    }
+}
+
+#[cfg(test)]
+mod http_one_zero {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn send_raw(request: &str) -> Response {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        Response::try_from(raw.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn defaults_to_close() {
+        let res = send_raw("GET /about HTTP/1.0\r\n\r\n");
+        assert_eq!(res.version, Version::OneDotZero);
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+
+    #[test]
+    fn keep_alive_requested() {
+        let res = send_raw(
+            "GET /about HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n"
+        );
+        assert_eq!(res.version, Version::OneDotZero);
+        assert_eq!(
+            res.headers.get(&CONNECTION),
+            Some(&"keep-alive".into())
+        );
+    }
+
+    #[test]
+    fn not_found_matches_version() {
+        let res = send_raw("GET /missing HTTP/1.0\r\n\r\n");
+        assert_eq!(res.version, Version::OneDotZero);
+        assert_eq!(res.status.code(), 404);
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+
+    #[test]
+    fn http_one_one_unchanged() {
+        let res = send_raw("GET /about HTTP/1.1\r\n\r\n");
+        assert_eq!(res.version, Version::OneDotOne);
+        assert!(!res.headers.contains(&CONNECTION));
+    }
+}
+
+#[cfg(test)]
+mod shutdown_route {
+    use super::*;
+
+    fn router_with_user_shutdown_route() -> Router {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+        router.mount(Route::new(Method::Shutdown, "/".into(), "user".into()));
+        router
+    }
+
+    fn send(addr: SocketAddr, method: Method, path: &str) -> Response {
+        let uri = format!("{addr}{path}");
+        let mut client = Client::send(method, &uri).unwrap();
+        client.recv_response().unwrap();
+        client.res.unwrap()
+    }
+
+    #[test]
+    fn user_route_is_not_shadowed() {
+        let mut router = router_with_user_shutdown_route();
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let res = send(addr, Method::Shutdown, "/");
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body, Body::Text("user".into()));
+
+        // The server is still running.
+        let res = send(addr, Method::Get, "/about");
+        assert_eq!(res.status.code(), 200);
+    }
+
+    #[test]
+    fn test_server_reserves_shutdown() {
+        let mut router = router_with_user_shutdown_route();
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).is_test_server(true);
+        let addr = start_server(&mut builder);
+
+        let res = send(addr, Method::Shutdown, "/");
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body, Body::Text("Server is shutting down.".into()));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn shutdown_shim_leaves_router_unchanged() {
+        let mut router = router_with_user_shutdown_route();
+        let before = router.clone();
+        let _ = router.shutdown();
+        assert_eq!(router, before);
+    }
+
+    #[test]
+    fn idle_server_stops_promptly() {
+        use std::net::TcpStream;
+        use std::time::{Duration, Instant};
+
+        let handle = Server::builder()
+            .addr("127.0.0.1:0")
+            .start()
+            .unwrap();
+        let addr = handle.local_addr();
+
+        let start = Instant::now();
+        handle.shutdown();
+        handle.join().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // The listener was closed along with its thread.
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
+
+#[cfg(test)]
+mod rate_limit {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::thread;
+    use std::time::Duration;
+    use crate::RateLimit;
+    use crate::headers::names::RETRY_AFTER;
+
+    #[test]
+    fn limits_each_client() {
+        let limit = RateLimit::new(2, Duration::from_millis(200));
+        let client1 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let client2 = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+        assert!(limit.check(client1).is_ok());
+        assert!(limit.check(client1).is_ok());
+        assert!(limit.check(client1).is_err());
+        assert!(limit.check(client2).is_ok());
+        assert_eq!(limit.num_clients(), 2);
+
+        // Both windows expire and the stale entries are pruned.
+        thread::sleep(Duration::from_millis(250));
+        assert!(limit.check(client1).is_ok());
+        assert_eq!(limit.num_clients(), 1);
+    }
+
+    #[test]
+    fn server_responds_with_429() {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+
+        let mut builder = Server::builder();
+        let _ = builder
+            .router(&mut router)
+            .rate_limit(1, Duration::from_secs(30));
+
+        let uri = format!("{}/about", start_server(&mut builder));
+
+        let mut client = Client::send(Method::Get, &uri).unwrap();
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 200);
+
+        let mut client = Client::send(Method::Get, &uri).unwrap();
+        client.recv_response().unwrap();
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 429);
+        assert_eq!(res.headers.get(&RETRY_AFTER), Some(&"30".into()));
+    }
+}
+
+#[cfg(test)]
+mod forwarded {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+
+    const CLIENT1: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    fn forwarded_request(uri: &str, value: &str) -> Client {
+        let (addr, path) = crate::utils::parse_uri(uri).unwrap();
+        let req = Request::builder()
+            .path(path.into())
+            .header("X-Forwarded-For", value.as_bytes())
+            .build();
+        Client::builder().addr(&addr).req(req).send().unwrap()
+    }
+
+    fn status_code(mut client: Client) -> u16 {
+        client.recv_response().unwrap();
+        client.res.unwrap().status.code()
+    }
+
+    #[test]
+    fn parse_single_address() {
+        let req = Request::builder()
+            .header("X-Forwarded-For", b"203.0.113.7")
+            .build();
+        assert_eq!(req.forwarded_for(), Some(CLIENT1));
+
+        let req = Request::builder()
+            .header("X-Forwarded-For", b"203.0.113.7:41234")
+            .build();
+        assert_eq!(req.forwarded_for(), Some(CLIENT1));
+
+        let req = Request::builder()
+            .header("X-Forwarded-For", b"unknown")
+            .build();
+        assert_eq!(req.forwarded_for(), None);
+        assert_eq!(Request::new().forwarded_for(), None);
+    }
+
+    #[test]
+    fn parse_proxy_chain() {
+        let req = Request::builder()
+            .header("X-Forwarded-For", b"203.0.113.7, 10.0.0.1, 10.0.0.2")
+            .build();
+        assert_eq!(req.forwarded_for(), Some(CLIENT1));
+
+        let req = Request::builder()
+            .header("X-Forwarded-For", b"2001:db8::1,10.0.0.1")
+            .build();
+        assert_eq!(req.forwarded_for(), "2001:db8::1".parse().ok());
+    }
+
+    #[test]
+    fn rate_limit_trusts_forwarded_for() {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+
+        let mut builder = Server::builder();
+        let _ = builder
+            .router(&mut router)
+            .trust_forwarded(true)
+            .rate_limit(1, Duration::from_secs(30));
+
+        let uri = format!("{}/about", start_server(&mut builder));
+
+        let client = forwarded_request(&uri, "203.0.113.7, 10.0.0.1");
+        assert_eq!(status_code(client), 200);
+        let client = forwarded_request(&uri, "203.0.113.8, 10.0.0.1");
+        assert_eq!(status_code(client), 200);
+        let client = forwarded_request(&uri, "203.0.113.7");
+        assert_eq!(status_code(client), 429);
+    }
+
+    #[test]
+    fn rate_limit_ignores_untrusted_forwarded_for() {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+
+        let mut builder = Server::builder();
+        let _ = builder
+            .router(&mut router)
+            .rate_limit(1, Duration::from_secs(30));
+
+        let uri = format!("{}/about", start_server(&mut builder));
+
+        let client = forwarded_request(&uri, "203.0.113.7");
+        assert_eq!(status_code(client), 200);
+        let client = forwarded_request(&uri, "203.0.113.8");
+        assert_eq!(status_code(client), 429);
+    }
+}
+
+#[cfg(test)]
+mod tui {
+    use super::*;
+    use crate::Tui;
+
+    #[test]
+    fn style_persists_across_requests() {
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut expected = Style::default();
+        expected.from_format_str("Rs");
+
+        let mut tui = Tui::new();
+        tui.handle_user_input("style Rs").unwrap();
+        assert_eq!(tui.style, expected);
+
+        // Request using a full URI.
+        tui.handle_user_input(&format!("{addr}/about")).unwrap();
+        assert_eq!(tui.client.style, expected);
+        assert_eq!(tui.last_code, Some(200));
+
+        // Request using only a path to the last address.
+        tui.handle_user_input("/about").unwrap();
+        assert_eq!(tui.client.style, expected);
+        assert_eq!(tui.last_code, Some(200));
+        assert_eq!(tui.style, expected);
+    }
+}
+
+#[cfg(test)]
+mod expect_continue {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+    use crate::headers::names::EXPECT;
+
+    fn start_echo_server() -> SocketAddr {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Post, "/upload".into(), "ok".into()));
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        start_server(&mut builder)
+    }
+
+    fn read_head(reader: &mut BufReader<TcpStream>) -> String {
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            assert!(reader.read_line(&mut head).unwrap() > 0);
+        }
+        head
+    }
+
+    #[test]
+    fn server_sends_interim_response() {
+        let addr = start_echo_server();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        stream.write_all(b"POST /upload HTTP/1.1\r\n\
+            Content-Length: 4\r\nExpect: 100-continue\r\n\r\n").unwrap();
+        assert_eq!(read_head(&mut reader), "HTTP/1.1 100 Continue\r\n\r\n");
+
+        stream.write_all(b"test").unwrap();
+        let mut raw = Vec::new();
+        reader.read_to_end(&mut raw).unwrap();
+
+        let res = Response::try_from(raw.as_slice()).unwrap();
+        assert_eq!(res.status.code(), 201);
+    }
+
+    #[test]
+    fn server_skips_interim_response_without_route() {
+        let addr = start_echo_server();
+        let mut stream = TcpStream::connect(addr).unwrap();
+
+        stream.write_all(b"POST /missing HTTP/1.1\r\n\
+            Content-Length: 4\r\nExpect: 100-continue\r\n\r\n").unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+
+        let res = Response::try_from(raw.as_slice()).unwrap();
+        assert_eq!(res.status.code(), 404);
+    }
+
+    #[test]
+    fn late_body_is_not_parsed_as_request() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/one".into(), "one".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        // The body is sent without waiting for an interim response, and it
+        // looks like a request of its own.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"POST /missing HTTP/1.1\r\n\
+            Content-Length: 21\r\nExpect: 100-continue\r\n\r\n\
+            GET /one HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+
+        let res = Response::try_from(raw.as_slice()).unwrap();
+        assert_eq!(res.status.code(), 404);
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+        assert_eq!(raw.windows(8).filter(|w| w == b"HTTP/1.1").count(), 1);
+    }
+
+    #[test]
+    fn close_decision_leaves_request_headers_alone() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"POST /missing HTTP/1.1\r\n\
+            Content-Length: 4\r\nExpect: 100-continue\r\n\r\n\
+            GET /one HTTP/1.1\r\n\r\n").unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        let server = Server::builder().build().unwrap();
+
+        let req = server.recv_request(&mut conn).unwrap();
+        assert!(conn.close_after_response);
+        assert!(!conn.body_pending);
+        assert_eq!(req.headers.get(&CONNECTION), None);
+
+        let next = conn.recv_request_head().unwrap();
+        assert_eq!(next.path.as_str(), "/one");
+        assert!(!conn.close_after_response);
+    }
+
+    #[test]
+    fn client_waits_for_interim_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let mut req = conn.recv_request_head().unwrap();
+            assert!(req.expects_continue());
+
+            // No body bytes arrive before the interim response.
+            conn.reader.get_ref()
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            assert!(conn.fill_buf().is_err());
+            conn.reader.get_ref().set_read_timeout(None).unwrap();
+
+            conn.send_continue().unwrap();
+            let mut buf = Vec::new();
+            req.body = conn.recv_body(&mut buf, &req.headers).unwrap();
+
+            let mut res = Response::builder().status_code(201).build().unwrap();
+            conn.send_response(&mut res).unwrap();
+            req
+        });
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/upload".into())
+            .body("test".into())
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .expect_continue(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 201);
+
+        let req = handle.join().unwrap();
+        assert_eq!(req.headers.get(&EXPECT), Some(&"100-continue".into()));
+        assert_eq!(req.body, Body::Text("test".into()));
+    }
+
+    #[test]
+    fn client_skips_body_on_final_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request_head().unwrap();
+
+            let mut res = Response::builder().status_code(417).build().unwrap();
+            conn.send_response(&mut res).unwrap();
+
+            // The client closes the connection without sending the body.
+            let mut rest = Vec::new();
+            conn.read_to_end(&mut rest).unwrap();
+            rest
+        });
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/upload".into())
+            .body("test".into())
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .expect_continue(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.take().unwrap().status.code(), 417);
+        drop(client);
+
+        assert!(handle.join().unwrap().is_empty());
+    }
+
+    #[test]
+    fn client_reads_split_interim_status_line() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let _ = read_head(&mut reader);
+
+            // The status code is cut in two across separate writes.
+            stream.write_all(b"HTTP/1.1 10").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            stream.write_all(b"0 Continue\r\n\r\n").unwrap();
+
+            let mut body = [0; 4];
+            reader.read_exact(&mut body).unwrap();
+            stream.write_all(b"HTTP/1.1 201 Created\r\n\
+                Content-Length: 0\r\n\r\n").unwrap();
+            body
+        });
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/upload".into())
+            .body("test".into())
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .expect_continue(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().status.code(), 201);
+        assert_eq!(&handle.join().unwrap(), b"test");
+    }
+
+    #[test]
+    fn read_timeout_is_restored() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let _ = read_head(&mut reader);
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+
+            let mut body = [0; 4];
+            reader.read_exact(&mut body).unwrap();
+        });
+
+        let mut conn = Connection::try_from(TcpStream::connect(addr).unwrap())
+            .unwrap();
+        let timeout = Some(Duration::from_secs(5));
+        conn.set_read_timeout(timeout).unwrap();
+
+        let mut req = Request::builder()
+            .method(Method::Post)
+            .body("test".into())
+            .build();
+        conn.send_request_expect_continue(&mut req, Duration::from_secs(1))
+            .unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(conn.reader.get_ref().read_timeout().unwrap(), timeout);
+    }
+
+    #[test]
+    fn ordered_headers_are_kept() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let head = read_head(&mut reader);
+            stream.write_all(b"HTTP/1.1 417 Expectation Failed\r\n\
+                Content-Length: 0\r\n\r\n").unwrap();
+            head
+        });
+
+        let mut conn = Connection::try_from(TcpStream::connect(addr).unwrap())
+            .unwrap();
+        conn.ordered_headers = true;
+
+        let mut req = Request::builder()
+            .method(Method::Post)
+            .header("Zebra", b"1")
+            .header("Apple", b"2")
+            .body("test".into())
+            .build();
+        conn.send_request_expect_continue(&mut req, Duration::from_secs(1))
+            .unwrap();
+
+        let head = handle.join().unwrap();
+        let zebra = head.find("Zebra: 1\r\n").unwrap();
+        let apple = head.find("Apple: 2\r\n").unwrap();
+        assert!(zebra < apple, "{head}");
+
+        // The final response is kept for the next read.
+        let res = conn.recv_response_to(&Method::Post).unwrap();
+        assert_eq!(res.status.code(), 417);
+    }
+}
+
+#[cfg(test)]
+mod accepts {
+    use super::*;
+
+    fn start_json_server() -> SocketAddr {
+        let mut router = Router::new()
+            .route("/api")
+            .accepts(&["application/json"])
+            .post(Target::Json("{\"ok\":true}".into()))
+            .apply();
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        start_server(&mut builder)
+    }
+
+    fn post(addr: SocketAddr, content_type: &str, body: &str) -> Response {
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/api".into())
+            .header("Content-Type", content_type.as_bytes())
+            .body(Body::Text(body.to_string().into()))
+            .build();
+
+        let mut client = Client::builder().addr(addr).req(req).send().unwrap();
+        client.recv_response().unwrap();
+        client.res.unwrap()
+    }
+
+    #[test]
+    fn rejects_unlisted_content_type() {
+        let addr = start_json_server();
+        let res = post(addr, "text/plain", "hello");
+        assert_eq!(res.status.code(), 415);
+    }
+
+    #[test]
+    fn accepts_listed_content_type() {
+        let addr = start_json_server();
+        let res = post(addr, "application/json; charset=utf-8", "{}");
+        assert_eq!(res.status.code(), 201);
+        assert_eq!(res.body, Body::Json("{\"ok\":true}".into()));
+    }
+
+    #[test]
+    fn route_debug_lists_content_types() {
+        let router = Router::new()
+            .route("/api")
+            .accepts(&["application/json", "text/xml"])
+            .post(Target::Empty)
+            .apply();
+
+        let route = router.routes().next().unwrap();
+        assert_eq!(route.accepts(), ["application/json", "text/xml"]);
+        assert_eq!(
+            format!("{route:?}"),
+            "POST /api -> Target::Empty (accepts=application/json, text/xml)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod access_log {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, UNIX_EPOCH};
+    use crate::{LogEntry, LogFormat};
+
+    fn entry() -> LogEntry {
+        LogEntry {
+            client_ip: "127.0.0.1".parse().unwrap(),
+            method: Method::Get,
+            path: "/a \"b\"".to_string(),
+            version: Version::OneDotOne,
+            status: 200,
+            bytes: 2326,
+            elapsed: Duration::from_micros(1500),
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+        }
+    }
+
+    #[test]
+    fn simple_format() {
+        assert_eq!(
+            LogFormat::Simple.format(&entry()),
+            "[127.0.0.1|200] GET /a \"b\" (2326 bytes, 1.500 ms)"
+        );
+    }
+
+    #[test]
+    fn common_format() {
+        assert_eq!(
+            LogFormat::Common.format(&entry()),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \
+            \"GET /a \"b\" HTTP/1.1\" 200 2326"
+        );
+
+        let empty = LogEntry { bytes: 0, ..entry() };
+        assert!(LogFormat::Common.format(&empty).ends_with(" 200 -"));
+    }
+
+    #[test]
+    fn json_format() {
+        assert_eq!(
+            LogFormat::Json.format(&entry()),
+            "{\"time\":\"2000-10-10T13:55:36Z\",\"client_ip\":\"127.0.0.1\",\
+            \"method\":\"GET\",\"path\":\"/a \\\"b\\\"\",\
+            \"version\":\"HTTP/1.1\",\"status\":200,\"bytes\":2326,\
+            \"elapsed_ms\":1.500}"
+        );
+    }
+
+    #[test]
+    fn writes_to_log_file() {
+        let log_path = env::temp_dir().join("rustnet_access_log_test.txt");
+        let _ = fs::remove_file(&log_path);
+
+        let mut router = Router::new();
+        let _ = router.get("/about", Path::new("static/about.html"));
+        let mut builder = Server::builder();
+        let _ = builder
+            .router(&mut router)
+            .log_file(&log_path)
+            .log_format(LogFormat::Json);
+        let addr = start_server(&mut builder);
+
+        let uri = format!("{addr}/about");
+        let mut client = Client::send(Method::Get, &uri).unwrap();
+        client.recv_response().unwrap();
+        let bytes = client.res.unwrap().body.len();
+
+        // The access log line is written after the response is sent.
+        let mut contents = String::new();
+        for _ in 0..50 {
+            contents = fs::read_to_string(&log_path).unwrap_or_default();
+            if contents.contains("elapsed_ms") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let _ = fs::remove_file(&log_path);
+        let line = contents.lines().last().unwrap();
+        assert!(line.starts_with("{\"time\":\""));
+        assert!(line.contains(
+            "\"client_ip\":\"127.0.0.1\",\"method\":\"GET\",\"path\":\"/about\""
+        ));
+        assert!(line.contains(&format!("\"status\":200,\"bytes\":{bytes},")));
+        assert!(line.contains("\"elapsed_ms\":"));
+    }
+}
+
+#[cfg(test)]
+mod log_rotate {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn log_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let dir = log_dir("rustnet_log_rotate_test");
+        let log_path = dir.join("server.log");
+
+        let server = Server::builder()
+            .log_file(&log_path)
+            .log_rotate(30, 2)
+            .build()
+            .unwrap();
+
+        // Each line is 10 bytes, so the file rotates after three messages.
+        for num in 0..9 {
+            server.log(&format!("message {num}"));
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("server.log"), "message 6\nmessage 7\nmessage 8\n");
+        assert_eq!(read("server.log.1"), "message 3\nmessage 4\nmessage 5\n");
+        assert_eq!(read("server.log.2"), "message 0\nmessage 1\nmessage 2\n");
+
+        server.log("message 9");
+        assert_eq!(read("server.log"), "message 9\n");
+        assert_eq!(read("server.log.1"), "message 6\nmessage 7\nmessage 8\n");
+        assert_eq!(read("server.log.2"), "message 3\nmessage 4\nmessage 5\n");
+        assert!(!dir.join("server.log.3").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keep_zero_truncates() {
+        let dir = log_dir("rustnet_log_rotate_keep_zero_test");
+        let log_path = dir.join("server.log");
+
+        let server = Server::builder()
+            .log_file(&log_path)
+            .log_rotate(10, 0)
+            .build()
+            .unwrap();
+
+        server.log("message 0");
+        server.log("message 1");
+
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), "message 1\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[cfg(test)]
+mod ping {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use crate::server::PING_PATH;
+    use crate::utils::check_server_is_ready;
+
+    fn get_ping(addr: SocketAddr) -> Response {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let req = format!("GET {PING_PATH} HTTP/1.1\r\n\r\n");
+        stream.write_all(req.as_bytes()).unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        Response::try_from(raw.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn registered_in_test_mode() {
+        let addr = start_server(Server::builder().is_test_server(true));
+        let res = get_ping(addr);
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.text(), "pong");
+        assert!(check_server_is_ready(&addr.to_string()));
+    }
+
+    #[test]
+    fn absent_in_normal_mode() {
+        let addr = start_server(&mut Server::builder());
+        assert_eq!(get_ping(addr).status.code(), 404);
+        assert!(!check_server_is_ready(&addr.to_string()));
+    }
+
+    #[test]
+    fn reports_ephemeral_port() {
+        let handle = Server::http("127.0.0.1:0")
+            .is_test_server(true)
+            .start()
+            .unwrap();
+
+        let addr = handle.local_addr();
+        assert_eq!(addr.ip().to_string(), "127.0.0.1");
+        assert_ne!(addr.port(), 0);
+
+        // The reported address is immediately reachable.
+        assert_eq!(get_ping(addr).text(), "pong");
+
+        let req = Request::builder().method(Method::Shutdown).build();
+        let mut client = Client::builder().addr(addr).req(req).send().unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod normalize_path {
+    use super::*;
+
+    fn normalize(path: &str) -> String {
+        UriPath::from(path).normalize().to_string()
+    }
+
+    #[test]
+    fn removes_dot_segments() {
+        // Examples from RFC 3986, sections 5.2.4 and 5.4.
+        assert_eq!(normalize("/a/b/c/./../../g"), "/a/g");
+        assert_eq!(normalize("/b/c/./g"), "/b/c/g");
+        assert_eq!(normalize("/b/c/."), "/b/c/");
+        assert_eq!(normalize("/b/c/./"), "/b/c/");
+        assert_eq!(normalize("/b/c/.."), "/b/");
+        assert_eq!(normalize("/b/c/../"), "/b/");
+        assert_eq!(normalize("/b/c/../g"), "/b/g");
+        assert_eq!(normalize("/b/c/../../g"), "/g");
+        assert_eq!(normalize("/b/c/../../../g"), "/g");
+        assert_eq!(normalize("/b/c/g."), "/b/c/g.");
+        assert_eq!(normalize("/b/c/.g"), "/b/c/.g");
+        assert_eq!(normalize("/b/c/g.."), "/b/c/g..");
+        assert_eq!(normalize("/b/c/..g"), "/b/c/..g");
+        assert_eq!(normalize("/b/c/./../g"), "/b/g");
+        assert_eq!(normalize("/b/c/g/./h"), "/b/c/g/h");
+        assert_eq!(normalize("/b/c/g/../h"), "/b/c/h");
+    }
+
+    #[test]
+    fn collapses_slashes() {
+        assert_eq!(normalize("//a///b"), "/a/b");
+        assert_eq!(normalize("/a//b/"), "/a/b/");
+        assert_eq!(normalize("/"), "/");
+        assert_eq!(normalize("/.."), "/");
+        assert_eq!(normalize("*"), "*");
+    }
+
+    #[test]
+    fn keeps_query() {
+        assert_eq!(normalize("/a/../b?x=../y"), "/b?x=../y");
+    }
+
+    #[test]
+    fn router_matches_normalized_path() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/about".into(), "ok".into()));
+
+        for path in ["/about", "//about", "/a/../about", "/./about"] {
+            let req = Request::builder().path(path.into()).build();
+            assert_eq!(router.get_target(&req), Target::Text("ok".into()));
+        }
+
+        let req = Request::builder().path("/../../etc/passwd".into()).build();
+        assert_eq!(router.get_target(&req), Target::NotFound);
+    }
+}
+
+#[cfg(test)]
+mod default_accept {
+    use super::*;
+    use crate::HeaderValue;
+
+    fn build_client(req: Request, accept: Option<&str>) -> Client {
+        let mut builder = Client::builder();
+        let _ = builder.dry_run(true).req(req);
+
+        if let Some(accept) = accept {
+            let _ = builder.default_accept(accept.into());
+        }
+
+        builder.build().unwrap()
+    }
+
+    fn accept_header(client: &Client) -> Option<&HeaderValue> {
+        client.req.as_ref().and_then(|req| req.headers.get(&ACCEPT))
+    }
+
+    #[test]
+    fn applied_when_unset() {
+        let client = build_client(Request::new(), None);
+        assert_eq!(accept_header(&client), Some(&"*/*".into()));
+
+        let req = Request::builder().path("/api".into()).build();
+        let client = build_client(req, Some("application/json"));
+        assert_eq!(accept_header(&client), Some(&"application/json".into()));
+    }
+
+    #[test]
+    fn explicit_accept_preserved() {
+        let req = Request::builder()
+            .path("/api".into())
+            .header("Accept", b"text/html")
+            .build();
+        let client = build_client(req, Some("application/json"));
+        assert_eq!(accept_header(&client), Some(&"text/html".into()));
+    }
+}
+
+#[cfg(test)]
+mod path_match {
+    use super::*;
+    use crate::PathMatch;
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/about".into(), "a".into()));
+        router.mount(Route::new(Method::Get, "/Docs/".into(), "d".into()));
+        router.mount(Route::new(Method::Get, "/".into(), "r".into()));
+        router
+    }
+
+    fn is_found(path: &str, ignore_slash: bool, no_case: bool) -> bool {
+        let opts = PathMatch {
+            ignore_trailing_slash: ignore_slash,
+            case_insensitive: no_case,
+        };
+        let req = Request::builder().path(path.into()).build();
+        router().get_route_with(&req, opts).is_some()
+    }
+
+    #[test]
+    fn exact_by_default() {
+        assert!(is_found("/about", false, false));
+        assert!(!is_found("/about/", false, false));
+        assert!(!is_found("/About", false, false));
+        assert!(!is_found("/docs/", false, false));
+    }
+
+    #[test]
+    fn trailing_slash() {
+        assert!(is_found("/about/", true, false));
+        assert!(is_found("/Docs", true, false));
+        assert!(!is_found("/ABOUT/", true, false));
+
+        // The root path keeps its slash.
+        let opts = PathMatch {
+            ignore_trailing_slash: true,
+            ..PathMatch::default()
+        };
+        assert!(opts.matches("/", "/"));
+        assert!(!opts.matches("/", ""));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        assert!(is_found("/About", false, true));
+        assert!(is_found("/docs/", false, true));
+        assert!(!is_found("/About/", false, true));
+        assert!(!is_found("/docs", false, true));
+    }
+
+    #[test]
+    fn both_options() {
+        assert!(is_found("/ABOUT/", true, true));
+        assert!(is_found("/docs", true, true));
+        assert!(is_found("/", true, true));
+        assert!(!is_found("/abouts", true, true));
+    }
+
+    #[test]
+    fn server_options() {
+        let mut router = router();
+        let mut builder = Server::builder();
+        let _ = builder
+            .router(&mut router)
+            .ignore_trailing_slash(true)
+            .case_insensitive_paths(true);
+
+        let addr = start_server(&mut builder);
+
+        let mut client = Client::send(Method::Get, &format!("{addr}/About/"))
+            .unwrap();
+        client.recv_response().unwrap();
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.text(), "a");
+    }
+}
+
+#[cfg(test)]
+mod not_found_for {
+    use super::*;
+    use crate::NotFoundFor;
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        let _ = router
+            .not_found_for("text/html", Target::Html("<p>404</p>".into()))
+            .not_found_for("application/json", Target::Json("{}".into()))
+            .not_found_for(Method::Post, Target::Text("no post".into()));
+        router
+    }
+
+    fn not_found(method: Method, accept: Option<&str>) -> Target {
+        let mut req = Request::builder().method(method).build();
+        req.headers.remove(&ACCEPT);
+
+        if let Some(accept) = accept {
+            req.headers.insert(ACCEPT, accept.into());
+        }
+
+        router().not_found_target(&req)
+    }
+
+    #[test]
+    fn negotiates_by_accept() {
+        let browser = "text/html,application/xhtml+xml,\
+            application/xml;q=0.9,*/*;q=0.8";
+        assert_eq!(
+            not_found(Method::Get, Some(browser)),
+            Target::Html("<p>404</p>".into())
+        );
+        assert_eq!(
+            not_found(Method::Get, Some("application/json")),
+            Target::Json("{}".into())
+        );
+        assert_eq!(
+            not_found(Method::Get, Some("text/html;q=0.5, application/json")),
+            Target::Json("{}".into())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_method_then_default() {
+        assert_eq!(
+            not_found(Method::Post, Some("*/*")),
+            Target::Text("no post".into())
+        );
+        assert_eq!(not_found(Method::Get, None), Target::NotFound);
+
+        let mut router = router();
+        router.mount(Route {
+            method: Method::Any,
+            path: None,
+            target: Target::Text("default".into()),
+            max_age: None,
+            accepts: Vec::new()
+        });
+        let req = Request::builder().path("/missing".into()).build();
+        let target = router.not_found_target(&req);
+        assert_eq!(target, Target::Text("default".into()));
+        assert!(router
+            .not_found_targets()
+            .any(|(key, _)| *key == NotFoundFor::Method(Method::Post)));
+    }
+
+    #[test]
+    fn server_responds_with_404() {
+        let mut router = router();
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let req = Request::builder()
+            .path("/missing".into())
+            .header("Accept", b"application/json")
+            .build();
+        let mut client = Client::builder().addr(addr).req(req).send().unwrap();
+        client.recv_response().unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 404);
+        assert_eq!(res.text(), "{}");
+        assert_eq!(
+            res.headers.get(&CONTENT_TYPE),
+            Some(&"application/json".into())
+        );
+    }
+}
+
+#[cfg(test)]
+mod parse_errors {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn send_raw(raw: &[u8]) -> Response {
+        let addr = start_server(&mut Server::builder());
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(raw).unwrap();
+
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).unwrap();
+        Response::try_from(res.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn garbage_request_line() {
+        let res = send_raw(b"GARBAGE\r\n\r\n");
+        assert_eq!(res.status.code(), 400);
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+
+    #[test]
+    fn too_many_headers() {
+        let mut raw = b"GET / HTTP/1.1\r\n".to_vec();
+        for num in 0..500 {
+            let header = format!("X-Header-{num}: {num}\r\n");
+            raw.extend_from_slice(header.as_bytes());
+        }
+        raw.extend_from_slice(b"\r\n");
+
+        let res = send_raw(&raw);
+        assert_eq!(res.status.code(), 431);
+    }
+
+    #[test]
+    fn uri_length_limit() {
+        let mut builder = Server::builder();
+        let _ = builder.max_uri_len(64);
+        let addr = start_server(&mut builder);
+
+        let send = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let req = format!("GET {path} HTTP/1.1\r\n\r\n");
+            stream.write_all(req.as_bytes()).unwrap();
+
+            let mut res = Vec::new();
+            stream.read_to_end(&mut res).unwrap();
+            Response::try_from(res.as_slice()).unwrap().status.code()
+        };
+
+        // Just under and at the limit.
+        assert_eq!(send(&format!("/{}", "a".repeat(62))), 404);
+        assert_eq!(send(&format!("/{}", "a".repeat(63))), 404);
+
+        // Just over the limit and far over the read limit for the line.
+        assert_eq!(send(&format!("/{}", "a".repeat(64))), 414);
+        assert_eq!(send(&format!("/{}", "a".repeat(10_000))), 414);
+    }
+
+    #[test]
+    fn error_status_codes() {
+        assert_eq!(NetError::BadMethod.status_code(), 400);
+        assert_eq!(NetError::UriTooLong.status_code(), 414);
+        assert_eq!(NetError::TooManyHeaders.status_code(), 431);
+        assert_eq!(NetError::HeadersTooLarge.status_code(), 431);
+        assert_eq!(NetError::UnexpectedEof.status_code(), 500);
+    }
+}
+
+#[cfg(test)]
+mod vectored_write {
+    use super::*;
+
+    fn check_bytes(body: Body) {
+        let res = Response::builder().body(body).build().unwrap();
+        let (raw, res) = send_response_raw(res);
+
+        let mut expected = vec![format!("{} {}", res.version, res.status)];
+        for (name, value) in res.headers.iter() {
+            expected.push(format!("{name}: {value}"));
+        }
+        expected.push(String::new());
+
+        let mut expected = (expected.join("\r\n") + "\r\n").into_bytes();
+        expected.extend_from_slice(res.body.as_bytes());
+
+        assert_eq!(raw, expected);
+        assert_eq!(res.head_bytes(), &expected[..raw.len() - res.body.len()]);
+    }
+
+    #[test]
+    fn serialized_bytes_unchanged() {
+        check_bytes(Body::Empty);
+        check_bytes(Body::Text("small body".into()));
+
+        // Larger than the writer's buffer.
+        check_bytes(Body::Text("abcdefghij".repeat(1000).into()));
+    }
+}
+
+#[cfg(test)]
+mod client_disconnect {
+    use super::*;
+    use std::io::{ErrorKind, Write};
+    use std::net::TcpStream;
+    use crate::server::NUM_WORKERS;
+
+    #[test]
+    fn server_keeps_serving() {
+        let mut router = Router::new();
+        let body = vec![b'a'; 4 * 1024 * 1024];
+        router.mount(Route::new(Method::Get, "/big".into(), body.into()));
+        router.mount(Route::new(Method::Get, "/small".into(), "ok".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        // Close each connection without reading the large response, more
+        // times than there are workers.
+        for _ in 0..(2 * NUM_WORKERS) {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET /big HTTP/1.1\r\n\r\n").unwrap();
+        }
+
+        let mut client = Client::send(Method::Get, &format!("{addr}/small"))
+            .unwrap();
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().text(), "ok");
+    }
+
+    #[test]
+    fn disconnect_errors() {
+        assert!(NetError::UnexpectedEof.is_disconnect());
+        let err = NetError::Write(ErrorKind::BrokenPipe.into());
+        assert!(err.is_disconnect());
+        let err = NetError::IoError(ErrorKind::ConnectionReset.into());
+        assert!(err.is_disconnect());
+        let err = NetError::Write(ErrorKind::TimedOut.into());
+        assert!(!err.is_disconnect());
+        assert!(!NetError::BadRequest.is_disconnect());
+    }
+}
+
+#[cfg(test)]
+mod keep_alive {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[test]
+    fn pipelined_requests() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/one".into(), "one".into()));
+        router.mount(Route::new(Method::Get, "/two".into(), "two".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /one HTTP/1.1\r\n\r\n\
+                GET /two HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let first = conn.recv_response_to(&Method::Get).unwrap();
+        assert_eq!(first.text(), "one");
+        assert!(!first.headers.contains(&CONNECTION));
+
+        let second = conn.recv_response_to(&Method::Get).unwrap();
+        assert_eq!(second.text(), "two");
+        assert_eq!(second.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+
+    #[test]
+    fn pipelined_requests_in_one_write() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/one".into(), "one".into()));
+        router.mount(Route::new(Method::Post, "/two".into(), "two".into()));
+        router.mount(Route::new(Method::Get, "/three".into(), "three".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        // All three requests arrive in the reader's buffer at once.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /one HTTP/1.1\r\n\r\n\
+                POST /two HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc\
+                GET /three HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let first = conn.recv_response_to(&Method::Get).unwrap();
+        assert_eq!(first.text(), "one");
+
+        let second = conn.recv_response_to(&Method::Post).unwrap();
+        assert_eq!(second.text(), "two");
+
+        let third = conn.recv_response_to(&Method::Get).unwrap();
+        assert_eq!(third.text(), "three");
+        assert_eq!(third.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/one".into(), "one".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /one HTTP/1.1\r\n\r\nGET /one HTTP/1.1\r\n\r\n")
+            .unwrap();
+
+        let mut conn = Connection::try_from(stream).unwrap();
+        assert_eq!(conn.recv_response_to(&Method::Get).unwrap().text(), "one");
+        assert!(conn.recv_response_to(&Method::Get).is_err());
+    }
+
+    fn no_content(_req: &Request, res: &mut Response) {
+        res.body = Body::Empty;
+    }
+
+    #[test]
+    fn empty_bodies_do_not_stall() {
+        let mut router = Router::new();
+        let _ = router.redirect("/old", "/new", false);
+        router.mount(Route::new(
+            Method::Get,
+            "/empty".into(),
+            Target::Handler(crate::Handler(no_content)),
+        ));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        // A response without a length would only end after the server's
+        // keep-alive timeout, so fail well before that.
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        for (path, code) in [("/old", 302), ("/empty", 200), ("/none", 404)] {
+            let raw = format!("GET {path} HTTP/1.1\r\n\r\n");
+            conn.write_all(raw.as_bytes()).unwrap();
+            conn.flush().unwrap();
+
+            let res = conn.recv_response_to(&Method::Get).unwrap();
+            assert_eq!(res.status.code(), code, "{path}");
+            assert_eq!(res.headers.get(&CONTENT_LENGTH), Some(&"0".into()));
+            assert!(res.body.is_empty(), "{path}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod trace_echo {
+    use super::*;
+
+    #[test]
+    fn echoes_request_head() {
+        let mut builder = Server::builder();
+        let _ = builder.enable_trace_echo(true);
+        let addr = start_server(&mut builder);
+
+        let req = Request::builder()
+            .method(Method::Trace)
+            .path("/some/path".into())
+            .header("X-Test", b"yes")
+            .header("Authorization", b"Basic c2VjcmV0")
+            .header("Cookie", b"id=1")
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(
+            res.headers.get(&CONTENT_TYPE),
+            Some(&"message/http".into())
+        );
+
+        let text = res.text();
+        assert!(text.starts_with("TRACE /some/path HTTP/1.1\r\n"));
+        assert!(text.contains("X-Test: yes\r\n"));
+        assert!(!text.contains("Authorization"));
+        assert!(!text.contains("Cookie"));
+        assert!(text.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn omits_credentials() {
+        let req = Request::builder()
+            .method(Method::Trace)
+            .header("Proxy-Authorization", b"Basic c2VjcmV0")
+            .header("Accept", b"*/*")
+            .build();
+
+        let bytes = req.trace_bytes();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.starts_with("TRACE / HTTP/1.1\r\n"));
+        assert!(text.contains("Accept: */*\r\n"));
+        assert!(!text.contains("Proxy-Authorization"));
+    }
+}
+
+#[cfg(test)]
+mod connect_tunnel {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn read_head(stream: &mut TcpStream) -> String {
+        let mut head = Vec::new();
+        let mut byte = [0; 1];
+
+        while !head.ends_with(b"\r\n\r\n") {
+            stream.read_exact(&mut byte).unwrap();
+            head.push(byte[0]);
+        }
+
+        String::from_utf8(head).unwrap()
+    }
+
+    #[test]
+    fn copies_both_directions() {
+        // An upstream server that echoes everything back until EOF.
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = upstream.local_addr().unwrap();
+        let echo = thread::spawn(move || {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut reader = stream.try_clone().unwrap();
+            std::io::copy(&mut reader, &mut stream).unwrap();
+        });
+
+        let mut builder = Server::builder();
+        let _ = builder.connect_tunnel(&[&target.to_string()]);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let connect = format!("CONNECT {target} HTTP/1.1\r\n\r\n");
+        stream.write_all(connect.as_bytes()).unwrap();
+
+        let head = read_head(&mut stream);
+        assert_eq!(head, "HTTP/1.1 200 Connection Established\r\n\r\n");
+
+        stream.write_all(b"hello through the tunnel").unwrap();
+        let mut buf = [0; 24];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello through the tunnel");
+
+        // Closing the client side ends the tunnel.
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        echo.join().unwrap();
+    }
+
+    #[test]
+    fn unreachable_target() {
+        // Bind and drop a listener to find a port with nothing behind it.
+        let target = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut builder = Server::builder();
+        let _ = builder.connect_tunnel(&[&target.to_string()]);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let connect = format!("CONNECT {target} HTTP/1.1\r\n\r\n");
+        stream.write_all(connect.as_bytes()).unwrap();
+
+        let head = read_head(&mut stream);
+        assert!(head.starts_with("HTTP/1.1 502 Bad Gateway\r\n"));
+    }
+
+    #[test]
+    fn target_not_allowed() {
+        let mut builder = Server::builder();
+        let _ = builder.connect_tunnel(&["Example.com:443"]);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"CONNECT 127.0.0.1:22 HTTP/1.1\r\n\r\n")
+            .unwrap();
+
+        let head = read_head(&mut stream);
+        assert!(head.starts_with("HTTP/1.1 403 Forbidden\r\n"), "{head}");
+    }
+
+    #[test]
+    fn tunnels_are_rate_limited() {
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = upstream.local_addr().unwrap().to_string();
+
+        let mut builder = Server::builder();
+        let _ = builder
+            .connect_tunnel(&[&target])
+            .rate_limit(1, std::time::Duration::from_secs(30));
+        let addr = start_server(&mut builder);
+
+        let connect = format!("CONNECT {target} HTTP/1.1\r\n\r\n");
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(connect.as_bytes()).unwrap();
+        let head = read_head(&mut first);
+        assert_eq!(head, "HTTP/1.1 200 Connection Established\r\n\r\n");
+
+        let mut second = TcpStream::connect(addr).unwrap();
+        second.write_all(connect.as_bytes()).unwrap();
+        let head = read_head(&mut second);
+        assert!(head.starts_with("HTTP/1.1 429 "), "{head}");
+    }
+
+    #[test]
+    fn idle_tunnel_is_closed() {
+        use std::time::{Duration, Instant};
+
+        // An upstream server that never sends anything.
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = upstream.local_addr().unwrap().to_string();
+        let idle = thread::spawn(move || {
+            let (mut stream, _) = upstream.accept().unwrap();
+            let mut rest = Vec::new();
+            let _ = stream.read_to_end(&mut rest);
+        });
+
+        let mut builder = Server::builder();
+        let _ = builder
+            .connect_tunnel(&[&target])
+            .tunnel_idle_timeout(Duration::from_millis(200));
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let connect = format!("CONNECT {target} HTTP/1.1\r\n\r\n");
+        stream.write_all(connect.as_bytes()).unwrap();
+        let _ = read_head(&mut stream);
+
+        let start = Instant::now();
+        let mut rest = Vec::new();
+        let _ = stream.read_to_end(&mut rest);
+        assert!(rest.is_empty());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        idle.join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod parse_bytes {
+    use super::*;
+
+    #[test]
+    fn response_with_body() {
+        let input = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 5\r\n\r\n\
+            hello";
+
+        let res = Response::try_from(&input[..]).unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body, Body::Text("hello".into()));
+    }
+
+    #[test]
+    fn request_with_body() {
+        let input = b"\
+            POST /submit HTTP/1.1\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: 11\r\n\r\n\
+            {\"a\": true}";
+
+        let req = Request::try_from(&input[..]).unwrap();
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.body, Body::Json("{\"a\": true}".into()));
+    }
+
+    #[test]
+    fn without_body() {
+        let input = b"HTTP/1.1 204 No Content\r\nServer: test\r\n\r\n";
+        let res = Response::try_from(&input[..]).unwrap();
+        assert!(res.body.is_empty());
+
+        let input = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let req = Request::try_from(&input[..]).unwrap();
+        assert!(req.body.is_empty());
+    }
+
+    #[test]
+    fn extra_whitespace_in_headers() {
+        let input = b"\
+            GET / HTTP/1.1\r\n\
+            Host :   example.com  \r\n\
+            \tX-Padded:\tvalue\t\r\n\r\n";
+
+        let req = Request::try_from(&input[..]).unwrap();
+        assert_eq!(req.headers.get(&HOST), Some(&"example.com".into()));
+        assert_eq!(
+            req.headers.get(&"X-Padded".into()),
+            Some(&"value".into())
+        );
+    }
+
+    #[test]
+    fn content_length_mismatch() {
+        // Bytes past the Content-Length are not part of the body.
+        let input = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 2\r\n\r\n\
+            hello";
+        let res = Response::try_from(&input[..]).unwrap();
+        assert_eq!(res.body, Body::Text("he".into()));
+
+        let input = b"\
+            PUT /data HTTP/1.1\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: 10\r\n\r\n\
+            hello";
+        assert_eq!(
+            Request::try_from(&input[..]),
+            Err(NetError::IncompleteBody)
+        );
+
+        let input = b"\
+            HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            Content-Length: five\r\n\r\n\
+            hello";
+        assert_eq!(
+            Response::try_from(&input[..]),
+            Err(NetError::BadHeaderValue)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Request::try_from(&b"FETCH / HTTP/1.1\r\n\r\n"[..]),
+            Err(NetError::BadMethod)
+        );
+        assert_eq!(
+            Request::try_from(&b"GET / HTTP/9.9\r\n\r\n"[..]),
+            Err(NetError::BadVersion)
+        );
+        assert_eq!(
+            Response::try_from(&b"HTTP/1.1 2OO OK\r\n\r\n"[..]),
+            Err(NetError::BadStatusCode)
+        );
+        assert_eq!(
+            Request::try_from(&b"GET / HTTP/1.1\r\n: no-name\r\n\r\n"[..]),
+            Err(NetError::BadHeaderName)
+        );
+        assert_eq!(
+            Request::try_from(&b"GET / HTTP/1.1\r\nno-colon\r\n\r\n"[..]),
+            Err(NetError::BadHeader)
+        );
+    }
+}
+
+#[cfg(test)]
+mod error_pages {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    const PAGE: &str = "./static/error.html";
+
+    fn get(router: &Router, path: &str) -> Response {
+        let req = Request::builder().path(path.into()).build();
+        router.resolve(&req).unwrap()
+    }
+
+    #[test]
+    fn serves_registered_page() {
+        let mut router = Router::new()
+            .route("/api")
+            .accepts(&["application/json"])
+            .post(Target::Json("{}".into()))
+            .apply();
+        let _ = router
+            .error_page(404, Path::new(PAGE))
+            .error_page(415, Path::new(PAGE));
+
+        let res = get(&router, "/missing");
+        assert_eq!(res.status.code(), 404);
+        assert_eq!(
+            res.headers.get(&CONTENT_TYPE),
+            Some(&"text/html; charset=utf-8".into())
+        );
+        assert!(res.text().contains("<title>Not Found</title>"));
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/api".into())
+            .header("Content-Type", b"text/plain")
+            .body(Body::Text("hi".into()))
+            .build();
+        let res = router.resolve(&req).unwrap();
+        assert_eq!(res.status.code(), 415);
+        assert!(res.text().contains("<title>Not Found</title>"));
+    }
+
+    #[test]
+    fn falls_back_without_page() {
+        let mut router = Router::new();
+        let _ = router.error_page(500, Path::new(PAGE));
+
+        let res = get(&router, "/missing");
+        assert_eq!(res.status.code(), 404);
+        assert_eq!(
+            res.body,
+            Body::try_from(Target::NotFound).unwrap()
+        );
+    }
+
+    #[test]
+    fn not_found_takes_precedence() {
+        let mut router = Router::new();
+        let _ = router
+            .error_page(404, Path::new(PAGE))
+            .not_found_for(Method::Get, Target::Text("custom".into()));
+
+        assert_eq!(get(&router, "/missing").text(), "custom");
+    }
+
+    #[test]
+    fn server_errors_use_page() {
+        let mut router = Router::new();
+        let _ = router.error_page(400, Path::new(PAGE));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"NOT A REQUEST\r\n\r\n").unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(res.contains("<title>Not Found</title>"));
+    }
+}
+
+#[cfg(test)]
+mod event_stream {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use crate::{EventHandler, EventStream, NetResult};
+
+    fn ticks(stream: &mut EventStream<'_>) -> NetResult<()> {
+        stream.send("tick", "1")?;
+        stream.send("tick", "2")?;
+        stream.send("", "first line\nsecond line")
+    }
+
+    #[test]
+    fn streams_events() {
+        let mut router = Router::new()
+            .route("/events")
+            .get(Target::EventStream(EventHandler(ticks)))
+            .apply();
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /events HTTP/1.1\r\n\r\n").unwrap();
+
+        // The stream ends when the handler returns and the server closes
+        // the connection.
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+
+        let (head, body) = res.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Content-Type: text/event-stream\r\n"));
+        assert!(head.contains("Cache-Control: no-cache\r\n"));
+        assert!(!head.contains("Content-Length"));
+        assert_eq!(
+            body,
+            "event: tick\ndata: 1\n\n\
+            event: tick\ndata: 2\n\n\
+            data: first line\ndata: second line\n\n"
+        );
+    }
+
+    #[test]
+    fn line_breaks_cannot_inject_fields() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        let mut events = EventStream { conn: &mut conn };
+
+        for event in ["tick\nid: 7", "tick\r", "\n"] {
+            assert!(events.send(event, "1").is_err(), "{event:?}");
+        }
+
+        events.send("tick", "a\rretry: 1\r\nb").unwrap();
+        drop(conn);
+
+        let mut body = String::new();
+        client.read_to_string(&mut body).unwrap();
+        assert_eq!(
+            body,
+            "event: tick\ndata: a\ndata: retry: 1\ndata: b\n\n"
+        );
+    }
+
+    #[test]
+    fn event_stream_targets() {
+        let target = Target::EventStream(EventHandler(ticks));
+        assert!(target.is_event_stream());
+        assert_eq!(target.as_content_type(), Some("text/event-stream"));
+
+        let body = Body::try_from(target).unwrap();
+        assert!(body.is_event_stream());
+        assert!(!body.is_empty());
+        assert_eq!(body.len(), 0);
+    }
+}
+
+#[cfg(test)]
+mod websocket {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use crate::{NetResult, Stream};
+
+    const HANDSHAKE: &[u8] = b"\
+        GET /chat HTTP/1.1\r\n\
+        Host: example.com\r\n\
+        Upgrade: websocket\r\n\
+        Connection: keep-alive, Upgrade\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        Sec-WebSocket-Version: 13\r\n\r\n";
+
+    #[test]
+    fn accept_key() {
+        assert_eq!(
+            crate::utils::websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    fn upgrade(request: &'static [u8]) -> (NetResult<Stream>, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(request).unwrap();
+            stream
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        let req = conn.recv_request().unwrap();
+        (conn.accept_websocket(&req), client.join().unwrap())
+    }
+
+    #[test]
+    fn handshake_hands_off_stream() {
+        let (server, mut client) = upgrade(HANDSHAKE);
+        let mut server = TcpStream::try_from(server.unwrap()).unwrap();
+        server.write_all(b"frames").unwrap();
+        drop(server);
+
+        let mut res = String::new();
+        client.read_to_string(&mut res).unwrap();
+        assert_eq!(
+            res,
+            "HTTP/1.1 101 Switching Protocols\r\n\
+            Connection: Upgrade\r\n\
+            Sec-Websocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+            Upgrade: websocket\r\n\r\n\
+            frames"
+        );
+    }
+
+    #[test]
+    fn rejects_non_upgrade_requests() {
+        let (server, _client) = upgrade(b"GET /chat HTTP/1.1\r\n\r\n");
+        assert_eq!(server.unwrap_err(), NetError::BadRequest);
+
+        let (server, _client) = upgrade(b"\
+            GET /chat HTTP/1.1\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 8\r\n\r\n");
+        assert_eq!(server.unwrap_err(), NetError::BadRequest);
+    }
+}
+
+#[cfg(test)]
+mod ws {
+    use crate::NetError;
+    use crate::ws::{
+        read_frame, read_message, write_frame, Frame, Message, Opcode,
+    };
+
+    fn round_trip(frame: &Frame) -> Frame {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, frame).unwrap();
+        read_frame(&mut buf.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn masked_text_frame() {
+        let frame = Frame::text("Hello").masked([0x37, 0xfa, 0x21, 0x3d]);
+
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &frame).unwrap();
+        assert_eq!(
+            buf,
+            [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]
+        );
+
+        let parsed = read_frame(&mut buf.as_slice()).unwrap();
+        assert_eq!(parsed, frame);
+        assert_eq!(
+            Message::try_from(parsed).unwrap(),
+            Message::Text("Hello".to_string())
+        );
+    }
+
+    #[test]
+    fn close_frame() {
+        let frame = Frame::close(1000, "done");
+        let parsed = round_trip(&frame);
+        assert_eq!(parsed, frame);
+        assert_eq!(parsed.opcode, Opcode::Close);
+        assert_eq!(
+            Message::try_from(parsed).unwrap(),
+            Message::Close(Some((1000, "done".to_string())))
+        );
+    }
+
+    #[test]
+    fn extended_lengths() {
+        for len in [125, 126, 65_535, 65_536] {
+            let frame = Frame::binary(&vec![7; len]).masked([1, 2, 3, 4]);
+            assert_eq!(round_trip(&frame), frame);
+        }
+    }
+
+    #[test]
+    fn fragmented_message() {
+        let mut first = Frame::text("Hel");
+        first.fin = false;
+        let last = Frame::new(Opcode::Continuation, b"lo".to_vec());
+
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &first).unwrap();
+        write_frame(&mut buf, &Frame::ping(b"hi")).unwrap();
+        write_frame(&mut buf, &last).unwrap();
+        write_frame(&mut buf, &Frame::pong(b"hi")).unwrap();
+
+        let mut reader = buf.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Message::Text("Hello".to_string())
+        );
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Message::Pong(b"hi".to_vec())
+        );
+    }
+
+    #[test]
+    fn bad_frames() {
+        // Reserved bit set.
+        assert_eq!(
+            read_frame(&mut [0xc1, 0x00].as_slice()),
+            Err(NetError::BadFrame)
+        );
+        // Unknown opcode.
+        assert_eq!(
+            read_frame(&mut [0x83, 0x00].as_slice()),
+            Err(NetError::BadFrame)
+        );
+        // Fragmented ping.
+        assert_eq!(
+            read_frame(&mut [0x09, 0x00].as_slice()),
+            Err(NetError::BadFrame)
+        );
+        // Continuation without a preceding fragment.
+        assert_eq!(
+            read_message(&mut [0x80, 0x00].as_slice()),
+            Err(NetError::BadFrame)
+        );
+    }
+}
+
+#[cfg(test)]
+mod ordered_headers {
+    use super::*;
+    use std::io::{BufRead, Read};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn names(headers: &Headers) -> Vec<String> {
+        headers
+            .iter_ordered()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut headers = Headers::new();
+        headers.header("X-Zeta", b"1");
+        headers.header("X-Alpha", b"2");
+        headers.header("X-Mid", b"3");
+        assert_eq!(names(&headers), ["X-Zeta", "X-Alpha", "X-Mid"]);
+
+        // Updating a value keeps the original position.
+        headers.header("X-Zeta", b"4");
+        assert_eq!(names(&headers), ["X-Zeta", "X-Alpha", "X-Mid"]);
+        assert_eq!(headers.get(&"X-Zeta".into()), Some(&"4".into()));
+
+        headers.remove(&"X-Alpha".into());
+        assert_eq!(names(&headers), ["X-Zeta", "X-Mid"]);
+
+        let mut other = Headers::new();
+        other.header("X-Omega", b"5");
+        other.header("X-Beta", b"6");
+        headers.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(
+            names(&headers),
+            ["X-Zeta", "X-Mid", "X-Omega", "X-Beta"]
+        );
+    }
+
+    #[test]
+    fn equality_ignores_order() {
+        let first = Headers::try_from(&b"B: 2\r\nA: 1\r\n\r\n"[..]).unwrap();
+        let second = Headers::try_from(&b"A: 1\r\nB: 2\r\n\r\n"[..]).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(names(&first), ["B", "A"]);
+        assert_eq!(names(&second), ["A", "B"]);
+    }
+
+    // Returns the header lines written by `send_response`.
+    fn sent_header_lines(ordered: bool) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            conn.ordered_headers = ordered;
+
+            let mut res = Response::builder().build().unwrap();
+            res.headers.header("X-Zeta", b"1");
+            res.headers.header("X-Alpha", b"2");
+            conn.send_response(&mut res).unwrap();
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut raw = String::new();
+        handle.join().unwrap();
+        stream.read_to_string(&mut raw).unwrap();
+
+        raw.lines()
+            .filter(|line| line.starts_with("X-"))
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn response_headers() {
+        assert_eq!(sent_header_lines(false), ["X-Alpha: 2", "X-Zeta: 1"]);
+        assert_eq!(sent_header_lines(true), ["X-Zeta: 1", "X-Alpha: 2"]);
+    }
+
+    #[test]
+    fn client_request_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let mut line = String::new();
+            let mut lines = Vec::new();
+
+            while conn.read_line(&mut line).unwrap() > 2 {
+                lines.push(line.trim_end().to_string());
+                line.clear();
+            }
+
+            lines
+        });
+
+        let mut req = Request::builder().build();
+        req.headers.header("X-Zeta", b"1");
+        req.headers.header("X-Alpha", b"2");
+
+        let _client = Client::builder()
+            .addr(addr)
+            .ordered_headers(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        let lines = handle.join().unwrap();
+        let zeta = lines.iter().position(|line| line == "X-Zeta: 1");
+        let alpha = lines.iter().position(|line| line == "X-Alpha: 2");
+        assert!(zeta.unwrap() < alpha.unwrap());
+    }
+}
+
+#[cfg(test)]
+mod header_values {
+    use super::*;
+    use crate::HeaderValue;
+
+    #[test]
+    fn as_u64() {
+        assert_eq!(HeaderValue::from("1234").as_u64(), Some(1234));
+        assert_eq!(HeaderValue::from(" 0 ").as_u64(), Some(0));
+        assert_eq!(HeaderValue::from(42_usize).as_u64(), Some(42));
+
+        for bad in ["", "abc", "-1", "+5", "1.5", "12 34", "0x10"] {
+            assert_eq!(HeaderValue::from(bad).as_u64(), None, "{bad}");
+        }
+
+        // Larger than u64::MAX.
+        let too_big = HeaderValue::from("18446744073709551616");
+        assert_eq!(too_big.as_u64(), None);
+    }
+
+    #[test]
+    fn split_list() {
+        let value = HeaderValue::from(" gzip, ,deflate ,, br,");
+        assert_eq!(value.split_list(), ["gzip", "deflate", "br"]);
+
+        assert!(HeaderValue::from(" , ,").split_list().is_empty());
+        assert_eq!(HeaderValue::from("identity").split_list(), ["identity"]);
+    }
+
+    #[test]
+    fn invalid_content_length() {
+        let input = b"POST / HTTP/1.1\r\nContent-Length: +3\r\n\r\nabc";
+        assert_eq!(
+            Request::try_from(&input[..]),
+            Err(NetError::BadHeaderValue)
+        );
+    }
+}
+
+#[cfg(test)]
+mod content_length {
+    use super::*;
+    use std::io::{BufRead, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn content_length(raw: &str) -> Option<usize> {
+        raw.lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map(|len| len.parse().unwrap())
+    }
+
+    fn body_len(raw: &str) -> usize {
+        raw.split_once("\r\n\r\n").unwrap().1.len()
+    }
+
+    #[test]
+    fn stale_response_length() {
+        for body in ["", "hello", "a longer response body"] {
+            let mut res = Response::builder()
+                .body(Body::Text("something else".into()))
+                .build()
+                .unwrap();
+            res.body = Body::Text(body.to_string().into());
+
+            let raw = String::from_utf8(send_response_raw(res).0).unwrap();
+            assert_eq!(content_length(&raw).unwrap_or(0), body_len(&raw));
+            assert_eq!(body_len(&raw), body.len());
+        }
+    }
+
+    #[test]
+    fn no_body_statuses() {
+        for code in [101, 204, 304] {
+            let res = Response::builder()
+                .status_code(code)
+                .header("Content-Length", b"12")
+                .build()
+                .unwrap();
+
+            let raw = String::from_utf8(send_response_raw(res).0).unwrap();
+            assert_eq!(content_length(&raw), None, "{code}");
+        }
+    }
+
+    #[test]
+    fn empty_body_has_zero_length() {
+        for code in [200, 302, 404, 500] {
+            let res = Response::builder()
+                .status_code(code)
+                .body(Body::Empty)
+                .build()
+                .unwrap();
+
+            let raw = String::from_utf8(send_response_raw(res).0).unwrap();
+            assert_eq!(content_length(&raw), Some(0), "{code}");
+            assert_eq!(body_len(&raw), 0, "{code}");
+        }
+    }
+
+    #[test]
+    fn head_response() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"HEAD / HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert_eq!(content_length(&raw), None);
+        assert_eq!(body_len(&raw), 0);
+    }
+
+    #[test]
+    fn stale_request_length() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let mut head = String::new();
+
+            // Read up to and including the blank line after the headers.
+            while conn.read_line(&mut head).unwrap() > 2 {}
+
+            head
+        });
+
+        let mut req = Request::builder()
+            .method(Method::Post)
+            .header("Content-Length", b"100")
+            .body(Body::Text("abc".into()))
+            .build();
+
+        let mut conn = Connection::try_from(TcpStream::connect(addr).unwrap())
+            .unwrap();
+        conn.send_request(&mut req).unwrap();
+
+        let head = handle.join().unwrap();
+        assert_eq!(content_length(&head), Some(3));
+    }
+}
+
+#[cfg(test)]
+mod deadline {
+    use super::*;
+    use crate::Stream;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // Responds to a single request, sending the body one byte at a time
+    // with `delay` between bytes.
+    fn drip_server(delay: Duration) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request().unwrap();
+
+            let _ = conn.write_all(b"HTTP/1.1 200 OK\r\n\
+                Content-Type: text/plain\r\n\
+                Content-Length: 5\r\n\r\n");
+            let _ = conn.flush();
+
+            for byte in b"hello" {
+                thread::sleep(delay);
+                let sent = conn.write_all(&[*byte]).and_then(|()| conn.flush());
+
+                if sent.is_err() {
+                    break;
+                }
+            }
+        });
+
+        addr
+    }
+
+    fn send(addr: SocketAddr, timeout: Duration) -> Client {
+        let req = Request::builder().build();
+        Client::builder()
+            .timeout(timeout)
+            .addr(addr)
+            .req(req)
+            .send()
+            .unwrap()
+    }
+
+    #[test]
+    fn slow_response_times_out() {
+        let addr = drip_server(Duration::from_millis(100));
+        let start = Instant::now();
+
+        let mut client = send(addr, Duration::from_millis(250));
+        assert_eq!(client.recv_response(), Err(NetError::Timeout));
+        assert!(start.elapsed() < Duration::from_millis(450));
+    }
+
+    #[test]
+    fn fast_response_succeeds() {
+        let addr = drip_server(Duration::from_millis(1));
+
+        let mut client = send(addr, Duration::from_secs(5));
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().text(), "hello");
+    }
+
+    #[test]
+    fn deadline_includes_time_before_sending() {
+        let addr = drip_server(Duration::from_millis(1));
+        let req = Request::builder().build();
+
+        let mut client = Client::builder()
+            .timeout(Duration::from_millis(50))
+            .addr(addr)
+            .req(req)
+            .build()
+            .unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(client.send_request(), Err(NetError::Timeout));
+    }
+
+    #[test]
+    fn deadline_uses_socket_timeouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Accepts the connection but never responds.
+        let handle = thread::spawn(move || listener.accept().unwrap());
+
+        let mut client = send(addr, Duration::from_millis(200));
+        let conn = client.conn.as_ref().unwrap();
+        let Stream::Tcp(ref stream) = *conn.reader.get_ref() else {
+            panic!("expected a TCP stream");
+        };
+        let timeout = stream.read_timeout().unwrap().unwrap();
+        assert!(timeout <= Duration::from_millis(200));
+
+        let start = Instant::now();
+        assert_eq!(client.recv_response(), Err(NetError::Timeout));
+        assert!(start.elapsed() < Duration::from_millis(400));
+        drop(handle.join().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod download {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn save_body_to_file() {
+        let contents = [0x89, b'P', b'N', b'G', 0x00, 0xff, b'\r', b'\n'];
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request().unwrap();
+
+            let _ = conn.write_all(b"HTTP/1.1 200 OK\r\n\
+                Content-Type: image/png\r\n\
+                Content-Length: 8\r\n\r\n");
+            let _ = conn.write_all(&contents);
+            let _ = conn.flush();
+        });
+
+        let file_path = env::temp_dir().join("rustnet_download_test.png");
+        let req = Request::builder().path("/image/png".into()).build();
+        let mut client = Client::builder()
+            .addr(addr)
+            .download(&file_path)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        client.save_body().unwrap();
+
+        assert_eq!(fs::read(&file_path).unwrap(), contents);
+        let _ = fs::remove_file(&file_path);
+    }
+
+    #[test]
+    fn progress_callback() {
+        use std::sync::mpsc;
+
+        let contents = vec![b'x'; 100_000];
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let body = contents.clone();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request().unwrap();
+
+            let _ = conn.write_all(b"HTTP/1.1 200 OK\r\n\
+                Content-Type: application/octet-stream\r\n\
+                Content-Length: 100000\r\n\r\n");
+
+            for chunk in body.chunks(10_000) {
+                let _ = conn.write_all(chunk);
+                let _ = conn.flush();
+            }
+        });
+
+        let (tx, rx) = mpsc::channel();
+        let req = Request::builder().build();
+        let mut client = Client::builder()
+            .addr(addr)
+            .on_progress(move |bytes_read, total| {
+                tx.send((bytes_read, total)).unwrap();
+            })
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().body.as_bytes(), &contents[..]);
+
+        let calls = rx.try_iter().collect::<Vec<_>>();
+        assert!(calls.len() > 1);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(calls.iter().all(|&(_, total)| total == Some(100_000)));
+        assert_eq!(calls.last(), Some(&(100_000, Some(100_000))));
+    }
+
+    #[test]
+    fn cli_option() {
+        let mut args = VecDeque::from([
+            "./client", "--dry-run", "--download", "out.png",
+            "example.com/image/png"
+        ]);
+
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        assert_eq!(client.download, Some(PathBuf::from("out.png")));
+
+        // Saving without a response fails.
+        assert!(client.save_body().is_err());
+
+        // The path is optional.
+        for args in [
+            ["./client", "--dry-run", "--download", "example.com/a.png"],
+            ["./client", "--download", "--dry-run", "example.com/a.png"],
+        ] {
+            let mut args = VecDeque::from(args);
+            let client = ClientCli::parse_args(&mut args).unwrap();
+            assert_eq!(client.download, Some(PathBuf::from(".")));
+            assert_eq!(client.req.unwrap().path.as_str(), "/a.png");
+        }
+    }
+
+    #[test]
+    fn content_disposition_filename() {
+        let tests = [
+            ("attachment; filename=report.pdf", Some("report.pdf")),
+            (
+                r#"attachment; filename="a \"b\"; c.txt""#,
+                Some(r#"a "b"; c.txt"#)
+            ),
+            (
+                "attachment; filename=\"euro.txt\"; \
+                filename*=UTF-8''%E2%82%AC%20rates.txt",
+                Some("\u{20ac} rates.txt")
+            ),
+            ("attachment; FILENAME*=iso-8859-1'en'caf%E9", Some("caf\u{e9}")),
+            ("attachment; filename*=utf-16''x; filename=y", Some("y")),
+            ("inline", None),
+        ];
+
+        for (value, expected) in tests {
+            let mut headers = Headers::new();
+            headers.header("Content-Disposition", value.as_bytes());
+            assert_eq!(
+                headers.content_disposition_filename().as_deref(),
+                expected,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn infer_filename() {
+        use crate::utils::{percent_decode, sanitize_filename};
+
+        assert_eq!(percent_decode("a%20b%zz%4"), b"a b%zz%4");
+        assert_eq!(percent_decode("%+f"), b"%+f");
+        assert_eq!(sanitize_filename("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitize_filename("C:\\x\\y.txt").unwrap(), "y.txt");
+        assert_eq!(sanitize_filename("dir/.."), None);
+        assert_eq!(sanitize_filename(""), None);
+
+        let mut client = Client::default();
+        assert_eq!(client.download_filename(), "index.html");
+
+        let path = "/files/my%20photo.jpg?size=large";
+        client.req = Some(Request::builder().path(path.into()).build());
+        assert_eq!(client.download_filename(), "my photo.jpg");
+
+        client.req = Some(Request::builder().path("/files/".into()).build());
+        assert_eq!(client.download_filename(), "index.html");
+
+        let mut res = Response::builder().build().unwrap();
+        res.headers.header(
+            "Content-Disposition",
+            b"attachment; filename=\"../../.bashrc\""
+        );
+        client.res = Some(res);
+        assert_eq!(client.download_filename(), ".bashrc");
+    }
+
+    #[test]
+    fn save_body_to_directory() {
+        let dir = env::temp_dir().join("rustnet_download_dir_test");
+        let _ = fs::create_dir_all(&dir);
+
+        let mut res = Response::builder()
+            .body(Body::Bytes(b"data".to_vec().into()))
+            .build()
+            .unwrap();
+        res.headers.header(
+            "Content-Disposition",
+            b"attachment; filename=saved.bin"
+        );
+
+        let client = Client {
+            download: Some(dir.clone()),
+            res: Some(res),
+            ..Client::default()
+        };
+
+        client.save_body().unwrap();
+        assert_eq!(fs::read(dir.join("saved.bin")).unwrap(), b"data");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_body_keeps_existing_files() {
+        let dir = env::temp_dir().join("rustnet_download_keep_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("saved.bin"), b"mine").unwrap();
+
+        let mut res = Response::builder()
+            .body(Body::Bytes(b"data".to_vec().into()))
+            .build()
+            .unwrap();
+        res.headers.header(
+            "Content-Disposition",
+            b"attachment; filename=saved.bin"
+        );
+
+        let client = Client {
+            download: Some(dir.clone()),
+            res: Some(res),
+            ..Client::default()
+        };
+
+        client.save_body().unwrap();
+        client.save_body().unwrap();
+        assert_eq!(fs::read(dir.join("saved.bin")).unwrap(), b"mine");
+        assert_eq!(fs::read(dir.join("saved.bin.1")).unwrap(), b"data");
+        assert_eq!(fs::read(dir.join("saved.bin.2")).unwrap(), b"data");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[cfg(test)]
+mod timing {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn elapsed_is_recorded() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let _ = conn.recv_request().unwrap();
+
+            thread::sleep(Duration::from_millis(50));
+            let _ = conn.write_all(b"HTTP/1.1 204 No Content\r\n\r\n");
+            let _ = conn.flush();
+        });
+
+        let req = Request::builder().build();
+        let mut client = Client::builder()
+            .addr(addr)
+            .timing(true)
+            .req(req)
+            .send()
+            .unwrap();
+
+        assert!(client.sent_at.is_some());
+        assert!(client.elapsed.is_none());
+
+        client.recv_response().unwrap();
+        let elapsed = client.elapsed.unwrap();
+        assert!(elapsed >= Duration::from_millis(50), "{elapsed:?}");
+    }
+
+    #[test]
+    fn cli_option() {
+        let mut args = VecDeque::from([
+            "./client", "--dry-run", "--timing", "example.com"
+        ]);
+
+        let client = ClientCli::parse_args(&mut args).unwrap();
+        assert!(client.timing);
+        assert!(client.elapsed.is_none());
+    }
+}
+
+#[cfg(test)]
+mod resolve {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    // Answers one request with a 204 status and returns the request.
+    fn serve_one() -> (SocketAddr, JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let req = conn.recv_request().unwrap();
+
+            let mut res = Response::builder().status_code(204).build().unwrap();
+            conn.send_response(&mut res).unwrap();
+            req
+        });
+
+        (addr, handle)
+    }
+
+    #[test]
+    fn connect_to_override() {
+        let (addr, handle) = serve_one();
+
+        let req = Request::builder().build();
+        let mut client = Client::builder()
+            .resolve("Staging.Example.TEST", addr)
+            .req(req)
+            .uri(&"http://staging.example.test/".parse().unwrap())
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.conn.unwrap().remote_addr, addr);
+
+        let req = handle.join().unwrap();
+        assert_eq!(
+            req.headers.get(&HOST),
+            Some(&"staging.example.test".into())
+        );
+    }
+
+    #[test]
+    fn ipv6_host_override() {
+        let (addr, handle) = serve_one();
+
+        let req = Request::builder().build();
+        let mut client = Client::builder()
+            .resolve("2001:DB8::1", addr)
+            .req(req)
+            .uri(&"http://[2001:db8::1]:8080/".parse().unwrap())
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        assert_eq!(client.conn.unwrap().remote_addr, addr);
+
+        let req = handle.join().unwrap();
+        assert_eq!(
+            req.headers.get(&HOST),
+            Some(&"[2001:db8::1]:8080".into())
+        );
+    }
+}
+
+#[cfg(all(test, unix))]
+mod unix_socket {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+    use crate::server::PING_PATH;
+    use crate::UNIX_SOCKET_ADDR;
+
+    #[test]
+    fn request_over_unix_socket() {
+        let path = env::temp_dir().join("rustnet_unix_socket_test.sock");
+        let _ = fs::remove_file(&path);
+
+        let mut router = Router::new();
+        router.mount(Route::new(
+            Method::Get,
+            "/hello".into(),
+            "Hello over a Unix socket.".into()
+        ));
+
+        let server = Server::unix(&path)
+            .router(&mut router)
+            .is_test_server(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(server.unix_path.as_deref(), Some(path.as_path()));
+        assert_eq!(
+            server.listener.as_ref().unwrap().to_string(),
+            path.display().to_string()
+        );
+        let handle = server.start().unwrap();
+
+        let req = Request::builder().path("/hello".into()).build();
+        let mut client = Client::builder()
+            .unix(&path)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        let res = client.res.as_ref().unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body.to_string(), "Hello over a Unix socket.");
+
+        let req = client.req.as_ref().unwrap();
+        assert_eq!(req.headers.get(&HOST), Some(&"localhost".into()));
+
+        let conn = client.conn.as_ref().unwrap();
+        assert!(conn.writer.get_ref().is_unix());
+        assert_eq!(conn.remote_addr, UNIX_SOCKET_ADDR);
+
+        // The shutdown request wakes the listener through the socket file.
+        let req = Request::builder().method(Method::Shutdown).build();
+        let mut client = Client::builder()
+            .unix(&path)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+
+        // The socket file is removed along with the listener.
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn unix_clients_are_not_rate_limited_together() {
+        let path = env::temp_dir().join("rustnet_unix_rate_limit_test.sock");
+        let _ = fs::remove_file(&path);
+
+        let handle = Server::unix(&path)
+            .rate_limit(1, Duration::from_secs(30))
+            .is_test_server(true)
+            .start()
+            .unwrap();
+
+        for _ in 0..3 {
+            let req = Request::builder().path(PING_PATH.into()).build();
+            let mut client = Client::builder()
+                .unix(&path)
+                .req(req)
+                .send()
+                .unwrap();
+            client.recv_response().unwrap();
+            assert_eq!(client.res.unwrap().status.code(), 200);
+        }
+
+        let req = Request::builder().method(Method::Shutdown).build();
+        let mut client = Client::builder()
+            .unix(&path)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+        assert!(!path.exists());
+    }
+}
+
+#[cfg(test)]
+mod ipv6 {
+    use super::*;
+    use crate::server::PING_PATH;
+
+    #[test]
+    fn serve_and_connect() {
+        let addr = Server::builder()
+            .addr("[::1]:0")
+            .is_test_server(true)
+            .start()
+            .unwrap()
+            .local_addr();
+        assert!(addr.is_ipv6());
+        assert_ne!(addr.port(), 0);
+
+        let uri = format!("http://[::1]:{}{PING_PATH}", addr.port());
+        let mut client = Client::send(Method::Get, &uri).unwrap();
+        client.recv_response().unwrap();
+
+        let host = client.req.as_ref().unwrap().headers.get(&HOST).unwrap();
+        assert_eq!(host.to_string(), format!("[::1]:{}", addr.port()));
+
+        let res = client.res.as_ref().unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.text(), "pong");
+    }
+}
+
+#[cfg(test)]
+mod default_headers {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn merged_unless_set_on_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            tx.send(conn.recv_request().unwrap()).unwrap();
+        });
+
+        let mut defaults = Headers::new();
+        defaults.header("X-Api-Key", b"default-key");
+        defaults.header("X-Trace", b"on");
+        defaults.insert(ACCEPT, "application/json".into());
+
+        let mut req = Request::builder().build();
+        req.headers.header("X-Api-Key", b"explicit-key");
+
+        let _ = Client::builder()
+            .addr(addr)
+            .default_headers(defaults)
+            .req(req)
+            .send()
+            .unwrap();
+
+        let received = rx.recv().unwrap();
+        let get = |name: &str| {
+            received.headers.get(&name.into()).map(ToString::to_string)
+        };
+
+        assert_eq!(get("X-Api-Key").as_deref(), Some("explicit-key"));
+        assert_eq!(get("X-Trace").as_deref(), Some("on"));
+        assert_eq!(get("Accept").as_deref(), Some("application/json"));
+    }
+}
+
+#[cfg(test)]
+mod user_agent {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use crate::DEFAULT_NAME;
+
+    fn dry_run(opts: &[&'static str]) -> Option<String> {
+        let mut args = VecDeque::from(["./client", "--dry-run"]);
+        args.extend(opts);
+        args.push_back("example.com/");
+
+        let mut client = ClientCli::parse_args(&mut args).unwrap();
+        let _ = client.wire_bytes().unwrap();
+
+        let req = client.req.as_ref().unwrap();
+        req.headers.get(&USER_AGENT).map(ToString::to_string)
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(dry_run(&[]).as_deref(), Some(DEFAULT_NAME));
+    }
+
+    #[test]
+    fn custom() {
+        let user_agent = dry_run(&["--user-agent", "my-agent/1.0"]);
+        assert_eq!(user_agent.as_deref(), Some("my-agent/1.0"));
+    }
+
+    #[test]
+    fn suppressed() {
+        assert!(dry_run(&["--no-user-agent"]).is_none());
+    }
+
+    #[test]
+    fn explicit_header_wins() {
+        let opts = ["--no-user-agent", "-H", "User-Agent:explicit/2.0"];
+        assert_eq!(dry_run(&opts).as_deref(), Some("explicit/2.0"));
+
+        let opts = ["-H", "User-Agent:explicit/2.0", "--user-agent", "other"];
+        assert_eq!(dry_run(&opts).as_deref(), Some("explicit/2.0"));
+    }
+
+    #[test]
+    fn suppressed_on_the_wire() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            tx.send(conn.recv_request().unwrap()).unwrap();
+        });
+
+        // A built `Request` starts out with the default User-Agent header.
+        let req = Request::builder().build();
+        assert!(req.headers.contains(&USER_AGENT));
+
+        let _ = Client::builder()
+            .addr(addr)
+            .user_agent(None)
+            .req(req)
+            .send()
+            .unwrap();
+
+        let received = rx.recv().unwrap();
+        assert!(!received.headers.contains(&USER_AGENT));
+    }
+}
+
+#[cfg(test)]
+mod run_blocking {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn returns_after_shutdown_route() {
+        let server = Server::http("127.0.0.1:0")
+            .is_test_server(true)
+            .build()
+            .unwrap();
+
+        let addr = server.listener.as_ref().unwrap().local_addr;
+        let (tx, rx) = channel();
+
+        thread::spawn(move || tx.send(server.run()).unwrap());
+
+        // Still running until the shutdown route is requested.
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        let req = Request::builder().method(Method::Shutdown).build();
+        let mut client = Client::builder().addr(addr).req(req).send().unwrap();
+        client.recv_response().unwrap();
+
+        let result = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(result.is_ok());
+    }
+}
+
+#[cfg(test)]
+mod redirect {
+    use super::*;
+    use crate::headers::names::LOCATION;
+
+    #[test]
+    fn response_builder() {
+        let res = Response::builder()
+            .body("ignored".into())
+            .redirect(307, "/new/place")
+            .build()
+            .unwrap();
+
+        assert_eq!(res.status.code(), 307);
+        assert_eq!(res.headers.get(&LOCATION).unwrap().as_str(), "/new/place");
+        assert!(res.body.is_empty());
+
+        let err = Response::builder().redirect(200, "/x").build();
+        assert_eq!(err, Err(NetError::BadStatusCode));
+
+        let err = Response::builder().redirect(404, "/x").build();
+        assert_eq!(err, Err(NetError::BadStatusCode));
+    }
+
+    #[test]
+    fn router_routes() {
+        let mut router = Router::new();
+        let _ = router
+            .redirect("/old", "/new", true)
+            .redirect("/temp", "http://example.com/", false);
+
+        let get = |method: Method, path: &str| {
+            let req = Request::builder()
+                .method(method)
+                .path(path.to_string().into())
+                .build();
+            router.resolve(&req).unwrap()
+        };
+
+        let res = get(Method::Get, "/old");
+        assert_eq!(res.status.code(), 301);
+        assert_eq!(res.headers.get(&LOCATION).unwrap().as_str(), "/new");
+        assert!(res.body.is_empty());
+
+        let res = get(Method::Head, "/temp");
+        assert_eq!(res.status.code(), 302);
+        let location = res.headers.get(&LOCATION).unwrap();
+        assert_eq!(location.as_str(), "http://example.com/");
+
+        assert_eq!(get(Method::Get, "/new").status.code(), 404);
+    }
+}
+
+#[cfg(test)]
+mod cors {
+    use super::*;
+    use crate::{Cors, CorsConfig};
+    use crate::headers::names::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS as ACAC,
+        ACCESS_CONTROL_ALLOW_HEADERS as ACAH,
+        ACCESS_CONTROL_ALLOW_METHODS as ACAM,
+        ACCESS_CONTROL_ALLOW_ORIGIN as ACAO, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD,
+        ORIGIN, VARY,
+    };
+
+    fn header(res: &Response, name: &HeaderName) -> Option<String> {
+        res.headers.get(name).map(ToString::to_string)
+    }
+
+    fn preflight(origin: &str, method: &str) -> Request {
+        let mut req = Request::builder().method(Method::Options).build();
+        req.headers.insert(ORIGIN, origin.into());
+        req.headers.insert(ACCESS_CONTROL_REQUEST_METHOD, method.into());
+        req.headers.insert(ACCESS_CONTROL_REQUEST_HEADERS, "X-Token".into());
+        req
+    }
+
+    fn listed() -> Cors {
+        let mut config = CorsConfig::new();
+        let _ = config
+            .allow_origin("https://app.example.com")
+            .allow_origin("https://admin.example.com")
+            .allow_methods(&[Method::Get, Method::Post])
+            .allow_headers(&["Content-Type", "X-Token"])
+            .allow_credentials(true)
+            .max_age(600);
+        Cors::from(config)
+    }
+
+    #[test]
+    fn preflight_allowed() {
+        let req = preflight("https://app.example.com", "POST");
+        assert!(Cors::is_preflight(&req));
+
+        let res = listed().preflight(&req).unwrap();
+        assert_eq!(res.status.code(), 204);
+        assert!(res.body.is_empty());
+        assert_eq!(
+            header(&res, &ACAO).as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(header(&res, &ACAM).as_deref(), Some("GET, POST"));
+        assert_eq!(
+            header(&res, &ACAH).as_deref(),
+            Some("Content-Type, X-Token")
+        );
+        assert_eq!(header(&res, &ACAC).as_deref(), Some("true"));
+        assert_eq!(
+            header(&res, &ACCESS_CONTROL_MAX_AGE).as_deref(),
+            Some("600")
+        );
+        assert_eq!(header(&res, &VARY).as_deref(), Some("Origin"));
+    }
+
+    #[test]
+    fn preflight_rejected() {
+        let cors = listed();
+
+        let req = preflight("https://evil.com", "GET");
+        let res = cors.preflight(&req).unwrap();
+        assert_eq!(res.status.code(), 204);
+        assert!(header(&res, &ACAO).is_none());
+        assert!(header(&res, &ACAM).is_none());
+
+        let req = preflight("https://app.example.com", "DELETE");
+        let res = cors.preflight(&req).unwrap();
+        assert!(header(&res, &ACAO).is_none());
+    }
+
+    #[test]
+    fn any_origin() {
+        let cors = Cors::default();
+
+        let req = preflight("https://a.test", "PUT");
+        let res = cors.preflight(&req).unwrap();
+        assert_eq!(header(&res, &ACAO).as_deref(), Some("*"));
+        assert_eq!(header(&res, &ACAH).as_deref(), Some("X-Token"));
+        assert!(header(&res, &ACAC).is_none());
+        assert!(header(&res, &VARY).is_none());
+
+        // Credentials are never allowed for any origin.
+        let mut config = CorsConfig::new();
+        let _ = config.allow_credentials(true);
+        let res = Cors::from(config).preflight(&req).unwrap();
+        assert_eq!(header(&res, &ACAO).as_deref(), Some("*"));
+        assert!(header(&res, &ACAC).is_none());
+    }
+
+    #[test]
+    fn vary_is_parsed_as_a_list() {
+        let cors = listed();
+        let mut req = Request::builder().build();
+        req.headers.insert(ORIGIN, "https://app.example.com".into());
+
+        for (vary, expected) in [
+            ("X-Origin-Id", "X-Origin-Id, Origin"),
+            ("Accept, origin", "Accept, origin"),
+            ("*", "*"),
+        ] {
+            let mut res = Response::builder()
+                .header("Vary", vary.as_bytes())
+                .build()
+                .unwrap();
+            cors.apply(&req, &mut res);
+            assert_eq!(header(&res, &VARY).as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn preflight_is_rate_limited() {
+        let mut router = Router::new();
+        let server = Server::builder()
+            .router(&mut router)
+            .cors(CorsConfig::new())
+            .rate_limit(1, std::time::Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::net::TcpStream::connect(addr).unwrap();
+        let conn = Connection::try_from(client).unwrap();
+
+        let req = preflight("https://a.test", "GET");
+        assert_eq!(server.resolve(&req, &conn).unwrap().status.code(), 204);
+
+        let res = server.resolve(&req, &conn).unwrap();
+        assert_eq!(res.status.code(), 429);
+        assert_eq!(header(&res, &ACAO).as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn server_responses() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/api".into(), "data".into()));
+
+        let mut config = CorsConfig::new();
+        let _ = config.allow_origin("https://app.example.com");
+
+        let server = Server::builder()
+            .router(&mut router)
+            .cors(config)
+            .build()
+            .unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::net::TcpStream::connect(addr).unwrap();
+        let conn = Connection::try_from(client).unwrap();
+
+        let mut req = Request::builder().path("/api".into()).build();
+        let res = server.resolve(&req, &conn).unwrap();
+        assert_eq!(res.text(), "data");
+        assert!(header(&res, &ACAO).is_none());
+
+        req.headers.insert(ORIGIN, "https://app.example.com".into());
+        let res = server.resolve(&req, &conn).unwrap();
+        assert_eq!(res.text(), "data");
+        assert_eq!(
+            header(&res, &ACAO).as_deref(),
+            Some("https://app.example.com")
+        );
+
+        let req = preflight("https://app.example.com", "GET");
+        let res = server.resolve(&req, &conn).unwrap();
+        assert_eq!(res.status.code(), 204);
+        assert_eq!(header(&res, &ACAM).as_deref(), Some(
+            "GET, HEAD, POST, PUT, PATCH, DELETE"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod deferred_body {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use crate::server::MAX_DISCARD_LEN;
+
+    #[test]
+    fn read_on_demand() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        client
+            .write_all(
+                b"POST /x HTTP/1.1\r\nContent-Type: text/plain\r\n\
+                Content-Length: 5\r\n\r\nhello"
+            )
+            .unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let mut req = conn.recv_request_head().unwrap();
+        assert!(req.has_body());
+        assert!(req.body.is_empty());
+        assert!(conn.body_pending);
+
+        assert_eq!(req.read_body(&mut conn).unwrap().as_bytes(), b"hello");
+        assert!(!conn.body_pending);
+
+        // A second call does not read from the connection again.
+        assert_eq!(req.read_body(&mut conn).unwrap().as_bytes(), b"hello");
+    }
+
+    #[test]
+    fn unread_body_is_discarded() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Post, "/one".into(), "one".into()));
+        router.mount(Route::new(Method::Get, "/two".into(), "two".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST /one HTTP/1.1\r\nContent-Length: 11\r\n\r\n\
+                ignored bodGET /two HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let first = conn.recv_response_to(&Method::Post).unwrap();
+        assert_eq!(first.status.code(), 201);
+        assert_eq!(first.text(), "one");
+        assert!(!first.headers.contains(&CONNECTION));
+
+        let second = conn.recv_response_to(&Method::Get).unwrap();
+        assert_eq!(second.text(), "two");
+    }
+
+    #[test]
+    fn large_body_closes_connection() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Post, "/one".into(), "one".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).keep_alive(true);
+        let addr = start_server(&mut builder);
+
+        // The body is never sent, so the response must not wait for it.
+        let head = format!(
+            "POST /one HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_DISCARD_LEN + 1
+        );
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+
+        let mut conn = Connection::try_from(stream).unwrap();
+        let res = conn.recv_response_to(&Method::Post).unwrap();
+        assert_eq!(res.text(), "one");
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+    }
+}
+
+#[cfg(test)]
+mod error_kinds {
+    use super::*;
+    use std::io::{Error as IoError, ErrorKind};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    // Returns an address on which nothing is listening.
+    fn closed_addr() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[test]
+    fn refused_connection() {
+        let addr = closed_addr();
+
+        let res = Client::builder().addr(addr).build();
+        assert_eq!(res.err(), Some(NetError::ConnectionRefused));
+
+        let res = Client::builder()
+            .addr(addr)
+            .timeout(Duration::from_secs(1))
+            .build();
+        assert_eq!(res.err(), Some(NetError::ConnectionRefused));
+
+        let res = Connection::try_from(addr.to_string().as_str());
+        assert_eq!(res.err(), Some(NetError::ConnectionRefused));
+    }
+
+    #[test]
+    fn unresolvable_host() {
+        use crate::utils::resolve;
+
+        assert_eq!(resolve("host.invalid:80"), Err(NetError::Dns));
+        assert_eq!(resolve("127.0.0.1:x"), Err(NetError::BadAddress));
+    }
+
+    #[test]
+    fn io_error_mapping() {
+        let err = NetError::from(IoError::from(ErrorKind::TimedOut));
+        assert_eq!(err, NetError::Timeout);
+        assert!(err.is_timeout());
+        assert_eq!(err.io_kind(), Some(ErrorKind::TimedOut));
+
+        let err = NetError::from(ErrorKind::ConnectionRefused);
+        assert_eq!(err, NetError::ConnectionRefused);
+        assert_eq!(err.io_kind(), Some(ErrorKind::ConnectionRefused));
+
+        let err = NetError::from(ErrorKind::PermissionDenied);
+        assert_eq!(err, NetError::IoError(ErrorKind::PermissionDenied.into()));
+        assert_eq!(err.io_kind(), Some(ErrorKind::PermissionDenied));
+
+        let err = NetError::from(IoError::other("disk on fire"));
+        let source = std::error::Error::source(&err).unwrap();
+        assert_eq!(source.to_string(), "disk on fire");
+        assert!(std::error::Error::source(&NetError::Timeout).is_none());
+
+        assert_eq!(NetError::BodyTooLarge.io_kind(), None);
+        assert_eq!(NetError::BodyTooLarge.status_code(), 413);
+        assert_eq!(
+            IoError::from(NetError::ConnectionRefused).kind(),
+            ErrorKind::ConnectionRefused
+        );
+    }
+}
+
+#[cfg(test)]
+mod handler_panics {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use crate::{EventHandler, EventStream, NetResult};
+    use crate::server::NUM_WORKERS;
+
+    fn explode(stream: &mut EventStream<'_>) -> NetResult<()> {
+        stream.send("tick", "1")?;
+        panic!("handler exploded");
+    }
+
+    #[test]
+    fn workers_survive_panics() {
+        let mut router = Router::new()
+            .route("/explode")
+            .get(Target::EventStream(EventHandler(explode)))
+            .apply();
+        router.mount(Route::new(Method::Get, "/ok".into(), "ok".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        // Panic in more handlers than there are workers.
+        for _ in 0..=NUM_WORKERS {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET /explode HTTP/1.1\r\n\r\n").unwrap();
+
+            // The connection is closed after the partial event stream.
+            let mut res = String::new();
+            stream.read_to_string(&mut res).unwrap();
+            assert!(res.starts_with("HTTP/1.1 200 OK"));
+            assert!(res.ends_with("event: tick\ndata: 1\n\n"));
+        }
+
+        let mut client = Client::send(Method::Get, &format!("{addr}/ok"))
+            .unwrap();
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().text(), "ok");
+    }
+}
+
+#[cfg(test)]
+mod backpressure {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+    use crate::server::NUM_WORKERS;
+
+    fn get(addr: SocketAddr) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /ok HTTP/1.1\r\n\r\n").unwrap();
+
+        let mut res = String::new();
+        let _ = stream.read_to_string(&mut res);
+        res
+    }
+
+    // Opens connections that never send a request, one at a time so that
+    // each is taken off the queue before the next one arrives.
+    fn connect_idle(addr: SocketAddr, count: usize) -> Vec<TcpStream> {
+        (0..count)
+            .map(|_| {
+                let stream = TcpStream::connect(addr).unwrap();
+                thread::sleep(Duration::from_millis(50));
+                stream
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_queue_responds_with_503() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/ok".into(), "ok".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).max_queued(1);
+        let addr = start_server(&mut builder);
+
+        // Occupy every worker and the single queue slot with connections
+        // that never send a request.
+        let idle = connect_idle(addr, NUM_WORKERS + 1);
+
+        for _ in 0..3 {
+            let res = get(addr);
+            assert!(res.starts_with("HTTP/1.1 503 Service Unavailable"));
+            assert!(res.ends_with("Server is busy."));
+        }
+
+        drop(idle);
+
+        // Capacity returns once the idle connections have been closed.
+        let mut res = String::new();
+        for _ in 0..50 {
+            res = get(addr);
+            if res.starts_with("HTTP/1.1 200 OK") {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(res.ends_with("ok"));
+    }
+
+    #[test]
+    fn slow_rejected_client_does_not_block_accept() {
+        let mut builder = Server::builder();
+        let _ = builder.max_queued(0);
+        let addr = start_server(&mut builder);
+
+        let idle = connect_idle(addr, NUM_WORKERS);
+
+        // A rejected client that keeps sending a byte at a time.
+        let mut slow = TcpStream::connect(addr).unwrap();
+        let dripper = thread::spawn(move || {
+            for _ in 0..40 {
+                if slow.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        let res = get(addr);
+        assert!(res.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        dripper.join().unwrap();
+        drop(idle);
+    }
+}
+
+#[cfg(test)]
+mod auto_headers {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    // Sends a new `Request` with the given builder settings and returns the
+    // request as received by the server.
+    fn send_received(auto_accept: bool, auto_host: bool) -> Request {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = channel();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            tx.send(conn.recv_request().unwrap()).unwrap();
+        });
+
+        let _ = Client::builder()
+            .addr(addr)
+            .auto_accept(auto_accept)
+            .auto_host(auto_host)
+            .req(Request::new())
+            .send()
+            .unwrap();
+
+        rx.recv().unwrap()
+    }
+
+    #[test]
+    fn inserted_by_default() {
+        let received = send_received(true, true);
+        assert_eq!(received.headers.get(&ACCEPT), Some(&"*/*".into()));
+        assert!(received.headers.contains(&HOST));
+    }
+
+    #[test]
+    fn accept_omitted() {
+        let received = send_received(false, true);
+        assert!(!received.headers.contains(&ACCEPT));
+        assert!(received.headers.contains(&HOST));
+    }
+
+    #[test]
+    fn host_omitted() {
+        let received = send_received(true, false);
+        assert!(received.headers.contains(&ACCEPT));
+        assert!(!received.headers.contains(&HOST));
+    }
+}
+
+#[cfg(test)]
+mod request_target {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+
+    // Parses the request head as received over a `Connection`.
+    fn recv_over_connection(wire: &[u8]) -> Request {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        stream.write_all(wire).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        conn.recv_request().unwrap()
+    }
+
+    #[test]
+    fn origin_form() {
+        let wire = b"GET /a/b?c=1 HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+        for req in [
+            Request::try_from(&wire[..]).unwrap(),
+            recv_over_connection(wire),
+        ] {
+            assert_eq!(req.path.as_str(), "/a/b?c=1");
+            assert_eq!(req.authority, None);
+            assert_eq!(req.host().as_deref(), Some("example.com"));
+        }
+    }
+
+    #[test]
+    fn absolute_form() {
+        let wire = b"GET http://example.com:8080/a/b?c=1 HTTP/1.1\r\n\
+            Host: other.com\r\n\r\n";
+
+        for req in [
+            Request::try_from(&wire[..]).unwrap(),
+            recv_over_connection(wire),
+        ] {
+            assert_eq!(req.method, Method::Get);
+            assert_eq!(req.path.as_str(), "/a/b?c=1");
+            assert_eq!(req.authority.as_deref(), Some("example.com:8080"));
+            assert_eq!(req.host().as_deref(), Some("example.com:8080"));
+        }
+    }
+
+    #[test]
+    fn absolute_form_targets() {
+        let targets = [
+            ("http://example.com", "/", "example.com"),
+            ("HTTPS://example.com/", "/", "example.com"),
+            ("http://example.com?q=1", "/?q=1", "example.com"),
+            ("http://[::1]:80/x", "/x", "[::1]:80"),
+        ];
+
+        for (target, path, authority) in targets {
+            let (parsed, parsed_authority) =
+                UriPath::parse_target(Some(target.as_bytes())).unwrap();
+            assert_eq!(parsed.as_str(), path);
+            assert_eq!(parsed_authority.as_deref(), Some(authority));
+        }
+
+        for target in ["http://", "http:///x", "http://user@example.com/"] {
+            assert_eq!(
+                UriPath::parse_target(Some(target.as_bytes())),
+                Err(NetError::BadPath)
+            );
+        }
+    }
+
+    #[test]
+    fn asterisk_form() {
+        let wire = b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+        for req in [
+            Request::try_from(&wire[..]).unwrap(),
+            recv_over_connection(wire),
+        ] {
+            assert_eq!(req.method, Method::Options);
+            assert_eq!(req.path.as_str(), "*");
+            assert_eq!(req.authority, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod route_listing {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::path::Path;
+    use crate::server::ROUTES_PATH;
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        let _ = router
+            .get("/about", Path::new("static/about.html"))
+            .post("/post", Path::new("static/index.html"))
+            .redirect("/old", "/new", true)
+            .not_found(Path::new("static/error.html"));
+        router
+    }
+
+    fn get_routes(addr: SocketAddr) -> Response {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let req = format!("GET {ROUTES_PATH} HTTP/1.1\r\n\r\n");
+        stream.write_all(req.as_bytes()).unwrap();
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+        Response::try_from(raw.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn routes() {
+        let router = router();
+        let paths = router
+            .routes()
+            .map(|route| (route.method, route.path().map(UriPath::as_str)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(paths, [
+            (Method::Get, Some("/about")),
+            (Method::Get, Some("/old")),
+            (Method::Post, Some("/post")),
+            (Method::Any, None),
+        ]);
+    }
+
+    #[test]
+    fn describe() {
+        let router = router();
+        assert_eq!(router.describe(), "\
+            GET /about -> File\n\
+            GET /old -> Redirect\n\
+            POST /post -> File\n\
+            ANY -> File\n");
+
+        assert_eq!(router.to_string(), "Router(\n\
+            \x20   GET /about -> File,\n\
+            \x20   GET /old -> Redirect,\n\
+            \x20   POST /post -> File,\n\
+            \x20   ANY -> File,\n\
+            )");
+
+        assert_eq!(Router::new().describe(), "");
+    }
+
+    #[test]
+    fn exposed() {
+        let addr = start_server(
+            Server::builder().router(&mut router()).expose_routes(true)
+        );
+
+        let res = get_routes(addr);
+        assert_eq!(res.status.code(), 200);
+
+        let listing = res.text();
+        assert!(listing.contains("GET /about -> File\n"));
+        assert!(listing.contains(&format!("GET {ROUTES_PATH} -> Text\n")));
+        assert!(listing.ends_with("ANY -> File\n"));
+    }
+
+    #[test]
+    fn hidden_by_default() {
+        let addr = start_server(&mut Server::builder());
+        assert_eq!(get_routes(addr).status.code(), 404);
+    }
+
+    #[test]
+    fn existing_route_kept() {
+        let mut router = Router::new();
+        let mut router = router.route(ROUTES_PATH).get("mine").apply();
+
+        let addr = start_server(
+            Server::builder().router(&mut router).expose_routes(true)
+        );
+        assert_eq!(get_routes(addr).text(), "mine");
+    }
+}
+
+#[cfg(all(test, feature = "http2"))]
+mod http2 {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+    use crate::h2::{
+        self, ACK, END_HEADERS, END_STREAM, Frame, FrameKind, HpackDecoder,
+        PREFACE,
+    };
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s = s.replace(' ', "");
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..(i + 2)], 16).unwrap())
+            .collect()
+    }
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn hpack_rfc_request_examples() {
+        let mut decoder = HpackDecoder::new();
+
+        let block = hex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff");
+        assert_eq!(decoder.decode(&block).unwrap(), fields(&[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ]));
+
+        let block = hex("8286 84be 5886 a8eb 1064 9cbf");
+        assert_eq!(decoder.decode(&block).unwrap(), fields(&[
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+            ("cache-control", "no-cache"),
+        ]));
+        assert_eq!(decoder.size, 110);
+    }
+
+    #[test]
+    fn hpack_rfc_response_example() {
+        let mut decoder = HpackDecoder::new();
+        let block = hex("4882 6402 5885 aec3 771a 4b61 96d0 7abe 9410 54d4 \
+            44a8 2005 9504 0b81 66e0 82a6 2d1b ff6e 919d 29ad 1718 63c7 8f0b \
+            97c8 e9ae 82ae 43d3");
+
+        assert_eq!(decoder.decode(&block).unwrap(), fields(&[
+            (":status", "302"),
+            ("cache-control", "private"),
+            ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+            ("location", "https://www.example.com"),
+        ]));
+        assert_eq!(decoder.size, 222);
+    }
+
+    #[test]
+    fn hpack_round_trip() {
+        let long_value = "x".repeat(300);
+        let pairs = [
+            (":method", "GET"),
+            (":path", "/a/b?c=1"),
+            ("content-type", "text/plain"),
+            ("x-custom", long_value.as_str()),
+        ];
+
+        let block = h2::encode_headers(&pairs);
+        let decoded = HpackDecoder::new().decode(&block).unwrap();
+        assert_eq!(decoded, fields(&pairs));
+    }
+
+    #[test]
+    fn hpack_rejects_bad_input() {
+        let mut decoder = HpackDecoder::new();
+        assert_eq!(decoder.decode(&[0x80]), Err(NetError::Http2(0x9)));
+        assert_eq!(decoder.decode(&[0xbe]), Err(NetError::Http2(0x9)));
+        assert_eq!(decoder.decode(&[0x04, 0x05]), Err(NetError::Http2(0x9)));
+
+        // Padding must be made of one bits.
+        assert!(h2::huffman_decode(&[0xf1, 0xe3, 0xc2, 0xe5, 0xf2]).is_err());
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = Frame::new(FrameKind::Data, END_STREAM, 1, b"abc".to_vec());
+        let mut wire = Vec::new();
+        h2::write_frame(&mut wire, &frame).unwrap();
+        assert_eq!(&wire[..9], &[0, 0, 3, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(h2::read_frame(&mut &wire[..]).unwrap(), frame);
+
+        let oversized = [0x00, 0x40, 0x01, 0, 0, 0, 0, 0, 1];
+        let err = h2::read_frame(&mut &oversized[..]);
+        assert_eq!(err, Err(NetError::Http2(h2::FRAME_SIZE_ERROR)));
+    }
+
+    #[test]
+    fn prior_knowledge_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut preface = [0u8; 24];
+            stream.read_exact(&mut preface).unwrap();
+            assert_eq!(&preface[..], PREFACE);
+
+            let mut decoder = HpackDecoder::new();
+            let mut req_fields = Vec::new();
+            let mut req_body = Vec::<u8>::new();
+
+            loop {
+                let frame = h2::read_frame(&mut stream).unwrap();
+
+                match frame.kind {
+                    FrameKind::Headers => {
+                        assert!(frame.has_flag(END_HEADERS));
+                        req_fields = decoder.decode(&frame.payload).unwrap();
+                    },
+                    FrameKind::Data => req_body.extend(&frame.payload),
+                    _ => continue,
+                }
+
+                if frame.has_flag(END_STREAM) {
+                    break;
+                }
+            }
+
+            let settings = Frame::settings(&[]);
+            h2::write_frame(&mut stream, &settings).unwrap();
+
+            let block = h2::encode_headers(&[
+                (":status", "200"),
+                ("content-type", "text/plain"),
+            ]);
+            let headers =
+                Frame::new(FrameKind::Headers, END_HEADERS, 1, block);
+            h2::write_frame(&mut stream, &headers).unwrap();
+
+            let data = b"hello over h2".to_vec();
+            let data = Frame::new(FrameKind::Data, END_STREAM, 1, data);
+            h2::write_frame(&mut stream, &data).unwrap();
+
+            // The client acknowledges the server's settings.
+            let got_ack = (0..4).any(|_| {
+                h2::read_frame(&mut stream).is_ok_and(|frame| {
+                    frame.kind == FrameKind::Settings && frame.has_flag(ACK)
+                })
+            });
+
+            (req_fields, req_body, got_ack)
+        });
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/submit".into())
+            .body("ping".into())
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .host(&format!("example.com:{}", addr.port()))
+            .version(Version::TwoDotZero)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+
+        let res = client.res.take().unwrap();
+        assert_eq!(res.version, Version::TwoDotZero);
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.text(), "hello over h2");
+
+        let (req_fields, req_body, got_ack) = handle.join().unwrap();
+        let authority = format!("example.com:{}", addr.port());
+        assert!(req_fields.starts_with(&fields(&[
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":authority", &authority),
+            (":path", "/submit"),
+        ])));
+        assert!(req_fields.iter().all(|(name, _)| name != "host"));
+        assert_eq!(req_body, b"ping");
+        assert!(got_ack);
+    }
+}
+
+#[cfg(test)]
+mod digest_auth {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+    use crate::DigestChallenge;
+    use crate::headers::names::{AUTHORIZATION, WWW_AUTHENTICATE};
+
+    const CHALLENGE: &str = "Digest realm=\"http-auth@example.org\", \
+        qop=\"auth, auth-int\", algorithm=SHA-256, \
+        nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+        opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+
+    // Returns the unquoted value of the `name` parameter in an
+    // Authorization header.
+    fn param(auth: &str, name: &str) -> String {
+        auth.trim_start_matches("Digest ")
+            .split(", ")
+            .find_map(|item| item.strip_prefix(&format!("{name}=")))
+            .unwrap()
+            .trim_matches('"')
+            .to_string()
+    }
+
+    // Challenges each request on a single connection until one carries
+    // the correct credentials. Returns the number of requests received.
+    fn protected_server() -> (SocketAddr, JoinHandle<usize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            let challenge = CHALLENGE.parse::<DigestChallenge>().unwrap();
+            let mut num_reqs = 0;
+
+            while let Ok(req) = conn.recv_request() {
+                num_reqs += 1;
+
+                let authorized = req.headers.get(&AUTHORIZATION).is_some_and(
+                    |auth| {
+                        let auth = auth.as_str();
+                        let cnonce = param(&auth, "cnonce");
+                        let expected = challenge.authorization(
+                            "Mufasa",
+                            "Circle of Life",
+                            req.method,
+                            "/dir/index.html",
+                            &cnonce,
+                            1,
+                        );
+                        auth == expected
+                    },
+                );
+
+                let mut res = if authorized {
+                    Response::builder().status_code(200).build().unwrap()
+                } else {
+                    let mut res = Response::builder()
+                        .status_code(401)
+                        .build()
+                        .unwrap();
+                    res.headers.insert(WWW_AUTHENTICATE, CHALLENGE.into());
+                    res
+                };
+
+                conn.send_response(&mut res).unwrap();
+            }
+
+            num_reqs
+        });
+
+        (addr, handle)
+    }
+
+    fn send(addr: SocketAddr, pass: &str) -> Client {
+        let req = Request::builder()
+            .path("/dir/index.html".into())
+            .build();
+
+        let mut client = Client::builder()
+            .addr(addr)
+            .digest_auth("Mufasa", pass)
+            .req(req)
+            .send()
+            .unwrap();
+
+        client.recv_response().unwrap();
+        client
+    }
+
+    #[test]
+    fn rfc7616_example() {
+        let md5 = CHALLENGE.replace("SHA-256", "MD5");
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+        let expected = [
+            (md5.as_str(), "8ca523f5e9506fed4657c9700eebdbec"),
+            (
+                CHALLENGE,
+                concat!(
+                    "753927fa0e85d155564e2e272a28d180",
+                    "2ca10daf4496794697cf8db5856cb6c1"
+                ),
+            ),
+        ];
+
+        for (challenge, response) in expected {
+            let auth = challenge
+                .parse::<DigestChallenge>()
+                .unwrap()
+                .authorization(
+                    "Mufasa",
+                    "Circle of Life",
+                    Method::Get,
+                    "/dir/index.html",
+                    cnonce,
+                    1,
+                );
+
+            assert_eq!(param(&auth, "response"), response);
+            assert_eq!(param(&auth, "qop"), "auth");
+            assert_eq!(param(&auth, "nc"), "00000001");
+            assert_eq!(param(&auth, "cnonce"), cnonce);
+            assert_eq!(param(&auth, "uri"), "/dir/index.html");
+            assert_eq!(
+                param(&auth, "opaque"),
+                "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS"
+            );
+        }
+    }
+
+    #[test]
+    fn debug_redacts_password() {
+        let mut builder = Client::builder();
+        let _ = builder.digest_auth("Mufasa", "Circle of Life");
+
+        let debug = format!("{builder:?}");
+        assert!(debug.contains("Mufasa"), "{debug}");
+        assert!(!debug.contains("Circle of Life"), "{debug}");
+
+        let client = builder.dry_run(true).build().unwrap();
+        assert!(!format!("{client:?}").contains("Circle of Life"));
+    }
+
+    #[test]
+    fn rfc2069_challenge() {
+        // The RFC 2617 example without the qop parameter.
+        let challenge = "Digest realm=\"testrealm@host.com\", \
+            nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\""
+            .parse::<DigestChallenge>()
+            .unwrap();
+
+        assert!(!challenge.qop_auth);
+        assert_eq!(challenge.algorithm, "MD5");
+
+        let auth = challenge.authorization(
+            "Mufasa",
+            "Circle Of Life",
+            Method::Get,
+            "/dir/index.html",
+            "unused",
+            1,
+        );
+
+        assert_eq!(
+            param(&auth, "response"),
+            "670fd8c2df070c60b045671b8b24ff02"
+        );
+        assert!(!auth.contains("cnonce"));
+    }
+
+    #[test]
+    fn parse_challenges() {
+        let challenge = "Basic realm=\"basic\", Digest realm=\"a, b\", \
+            nonce=\"xyz\", algorithm=sha-256-sess, Bearer realm=\"other\""
+            .parse::<DigestChallenge>()
+            .unwrap();
+
+        assert_eq!(challenge.realm, "a, b");
+        assert_eq!(challenge.nonce, "xyz");
+        assert_eq!(challenge.algorithm, "SHA-256-sess");
+        assert_eq!(challenge.opaque, None);
+
+        let mut headers = Headers::new();
+        assert_eq!(headers.digest_challenge(), None);
+        headers.insert(WWW_AUTHENTICATE, "Basic realm=\"basic\"".into());
+        assert_eq!(headers.digest_challenge(), None);
+
+        for bad in [
+            "Digest realm=\"r\"",
+            "Digest nonce=\"n\"",
+            "Digest realm=\"r\", nonce=\"n\", algorithm=SHA-512",
+            "Digest realm=\"r\", nonce=\"n\", qop=\"auth-int\"",
+        ] {
+            assert!(bad.parse::<DigestChallenge>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn answers_challenge_once() {
+        let (addr, handle) = protected_server();
+        let mut client = send(addr, "Circle of Life");
+        assert_eq!(client.res.take().unwrap().status.code(), 200);
+
+        let auth = client.req.as_ref().unwrap().headers.get(&AUTHORIZATION);
+        assert!(auth.unwrap().as_str().starts_with("Digest "));
+
+        drop(client);
+        assert_eq!(handle.join().unwrap(), 2);
+
+        let (addr, handle) = protected_server();
+        let mut client = send(addr, "wrong password");
+        assert_eq!(client.res.take().unwrap().status.code(), 401);
+
+        drop(client);
+        assert_eq!(handle.join().unwrap(), 2);
+    }
+}
+
+#[cfg(test)]
+mod split_connection {
+    use super::*;
+    use std::io::{BufRead, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[test]
+    fn full_duplex_halves() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Echoes each line back in upper case until the client stops
+        // writing.
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (mut reader, mut writer) =
+                Connection::try_from(stream).unwrap().split();
+
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                writer.write_all(line.to_uppercase().as_bytes()).unwrap();
+                writer.flush().unwrap();
+                line.clear();
+            }
+
+            writer.shutdown().unwrap();
+        });
+
+        let mut conn = Connection::try_from(TcpStream::connect(addr).unwrap())
+            .unwrap();
+        conn.write_all(b"first\n").unwrap();
+        conn.flush().unwrap();
+
+        // Buffer the first echoed line before splitting.
+        assert!(!conn.fill_buf().unwrap().is_empty());
+
+        let (mut reader, mut writer) = conn.split();
+        assert_eq!(reader.remote_addr, addr);
+        assert_eq!(writer.remote_addr, addr);
+
+        let sender = thread::spawn(move || {
+            writer.write_all(b"second\nthird\n").unwrap();
+            writer.shutdown().unwrap();
+        });
+
+        let mut echoed = String::new();
+        reader.read_to_string(&mut echoed).unwrap();
+        assert_eq!(echoed, "FIRST\nSECOND\nTHIRD\n");
+
+        sender.join().unwrap();
+        handle.join().unwrap();
+    }
+}
+
+#[cfg(test)]
+mod fixed_clock {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn exchange(addr: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn date_header_uses_clock() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).clock(move || now);
+        let addr = start_server(&mut builder);
+
+        let date_line = "\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\n";
+
+        let res = exchange(addr, b"GET / HTTP/1.1\r\nHost: test\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+        assert!(res.contains(date_line), "{res}");
+
+        // Error responses use the clock as well.
+        let res = exchange(addr, b"BAD\r\n\r\n");
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        assert!(res.contains(date_line), "{res}");
+    }
+}
+
+#[cfg(test)]
+mod streamed_body {
+    use super::*;
+    use std::io::Cursor;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use crate::headers::names::TRANSFER_ENCODING;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| u8::try_from(i % 251).unwrap()).collect()
+    }
+
+    fn stream_response(body: Vec<u8>, len: Option<u64>) -> Response {
+        let body = Body::from_reader(Cursor::new(body), len).unwrap();
+        assert!(body.is_stream());
+        Response::builder().body(body).build().unwrap()
+    }
+
+    #[test]
+    fn small_body_is_buffered() {
+        let body = Body::from_reader(Cursor::new(b"hello world"), Some(5));
+        assert_eq!(body.unwrap(), Body::Bytes(b"hello"[..].into()));
+
+        let body = Body::from_reader(Cursor::new(b""), Some(0));
+        assert_eq!(body.unwrap(), Body::Empty);
+
+        let body = Body::from_reader(Cursor::new(b"short"), Some(10));
+        assert_eq!(body, Err(NetError::IncompleteBody));
+    }
+
+    #[test]
+    fn large_or_unknown_body_is_streamed() {
+        let large = Body::from_reader(
+            Cursor::new(data(10)),
+            Some(crate::MAX_BUFFERED_BODY + 1)
+        );
+        assert!(large.unwrap().is_stream());
+
+        let unknown = Body::from_reader(Cursor::new(data(10)), None).unwrap();
+        assert!(unknown.is_stream());
+        assert!(unknown.get_ref().is_none());
+        assert_eq!(unknown.clone(), unknown);
+    }
+
+    #[test]
+    fn stream_with_content_length() {
+        let body = data(200_000);
+        let res = stream_response(body.clone(), Some(200_000));
+        let (raw, _) = send_response_raw(res);
+        let res = recv_response_raw(raw, Method::Get);
+
+        assert!(!res.is_chunked());
+        assert_eq!(
+            res.headers.get(&CONTENT_LENGTH),
+            Some(&"200000".into())
+        );
+        assert_eq!(res.body.as_bytes(), &body[..]);
+    }
+
+    #[test]
+    fn stream_with_chunked_encoding() {
+        let body = data(100_000);
+        let res = stream_response(body.clone(), None);
+        let (raw, _) = send_response_raw(res);
+        let res = recv_response_raw(raw, Method::Get);
+
+        assert!(res.is_chunked());
+        assert!(!res.headers.contains(&CONTENT_LENGTH));
+        assert_eq!(
+            res.headers.get(&TRANSFER_ENCODING),
+            Some(&"chunked".into())
+        );
+        assert_eq!(res.body.as_bytes(), &body[..]);
+    }
+
+    #[test]
+    fn stream_request_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::try_from(stream).unwrap();
+            conn.recv_request().unwrap()
+        });
+
+        let body = data(100_000);
+        let mut req = Request {
+            method: Method::Post,
+            body: Body::from_reader(Cursor::new(body.clone()), None).unwrap(),
+            ..Request::default()
+        };
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+        conn.send_request(&mut req).unwrap();
+
+        let received = handle.join().unwrap();
+        assert!(crate::utils::is_chunked(&received.headers));
+        assert_eq!(received.body.as_bytes(), &body[..]);
+
+        // The reader was used up by the first send.
+        assert!(conn.send_request(&mut req).is_err());
+    }
+}
+
+#[cfg(test)]
+mod reverse_proxy {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    fn send(addr: SocketAddr, req: Request) -> Response {
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        client.res.unwrap()
+    }
+
+    fn get(addr: SocketAddr, path: &'static str) -> Response {
+        send(addr, Request::builder().path(path.into()).build())
+    }
+
+    #[test]
+    fn forwards_get_to_upstream() {
+        let large: Vec<u8> =
+            (0..100_000).map(|i| b"0123456789"[i % 10]).collect();
+
+        let mut upstream_router = Router::new();
+        upstream_router.mount(Route::new(
+            Method::Get,
+            "/api/hello".into(),
+            "hello from upstream".into()
+        ));
+        upstream_router.mount(Route::new(
+            Method::Get,
+            "/api/large".into(),
+            large.into()
+        ));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut upstream_router);
+        let upstream = start_server(&mut builder);
+
+        let mut router = Router::new();
+        let _ = router.proxy("/api", &format!("http://{upstream}")).unwrap();
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let res = get(addr, "/api/hello");
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.text(), "hello from upstream");
+
+        // Bodies longer than `MAX_BUFFERED_BODY` are streamed.
+        let res = get(addr, "/api/large");
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body.len(), 100_000);
+        assert!(res.body.as_bytes().starts_with(b"0123456789"));
+
+        // Missing upstream routes are passed through.
+        assert_eq!(get(addr, "/api/missing").status.code(), 404);
+
+        // The prefix only matches whole path segments.
+        assert_eq!(get(addr, "/apis").status.code(), 404);
+    }
+
+    #[test]
+    fn forwards_body_without_hop_by_hop_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut head = String::new();
+            while !head.ends_with("\r\n\r\n") {
+                reader.read_line(&mut head).unwrap();
+            }
+
+            let mut body = [0; 5];
+            reader.read_exact(&mut body).unwrap();
+
+            (&stream)
+                .write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 7\r\n\
+                    Connection: close, X-Hop\r\nX-Hop: 1\r\n\
+                    Keep-Alive: timeout=5\r\nX-Upstream: yes\r\n\r\ncreated")
+                .unwrap();
+
+            (head, body)
+        });
+
+        let mut router = Router::new();
+        let _ = router.proxy("/", &format!("http://{upstream}/base")).unwrap();
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/items?id=1".into())
+            .header("Keep-Alive", b"timeout=5")
+            .header("Proxy-Authorization", b"Basic c2VjcmV0")
+            .header("X-Custom", b"kept")
+            .body("hello".into())
+            .build();
+
+        let res = send(addr, req);
+        let (head, body) = handle.join().unwrap();
+
+        assert!(head.starts_with("POST /base/items?id=1 HTTP/1.1\r\n"));
+        assert!(head.contains(&format!("Host: {upstream}\r\n")), "{head}");
+        assert!(head.contains("X-Custom: kept\r\n"), "{head}");
+        assert!(!head.contains("Keep-Alive"), "{head}");
+        assert!(!head.contains("Proxy-Authorization"), "{head}");
+        assert_eq!(&body, b"hello");
+
+        assert_eq!(res.status.code(), 201);
+        assert_eq!(res.text(), "created");
+        assert!(res.headers.contains(&"X-Upstream".into()));
+        assert!(!res.headers.contains(&"X-Hop".into()));
+        assert!(!res.headers.contains(&"Keep-Alive".into()));
+    }
+
+    #[test]
+    fn unreachable_upstream_is_bad_gateway() {
+        // Reserve a port and then close it so that connecting fails.
+        let upstream = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut router = Router::new();
+        let _ = router.proxy("/", &format!("http://{upstream}")).unwrap();
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router);
+        let addr = start_server(&mut builder);
+
+        assert_eq!(get(addr, "/anything").status.code(), 502);
+        assert!(TcpStream::connect(upstream).is_err());
+    }
+
+    #[test]
+    fn invalid_upstream_is_rejected_when_configured() {
+        let mut router = Router::new();
+        assert_eq!(
+            router.proxy("/", "ftp://127.0.0.1:21").err(),
+            Some(NetError::BadScheme)
+        );
+        assert_eq!(
+            router.proxy("/", "https://example.com").err(),
+            Some(NetError::HttpsNotImplemented)
+        );
+        assert!(router.proxy("/", "http://").is_err());
+        assert_eq!(router.routes().count(), 0);
+
+        let _ = router.proxy("/api", "http://127.0.0.1:8080/v1").unwrap();
+        let route = router.routes().next().unwrap();
+        let Target::Proxy(ref upstream) = route.target else {
+            panic!("{route:?}");
+        };
+        assert_eq!(upstream.addr(), "127.0.0.1:8080");
+        assert_eq!(upstream.path_and_query(), "/v1");
+    }
+
+    #[test]
+    fn chunked_body_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        client
+            .write_all(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n")
+            .unwrap();
+
+        let mut headers = Headers::new();
+        headers.insert("Transfer-Encoding".into(), "chunked".into());
+
+        let mut buf = Vec::new();
+        assert_eq!(
+            conn.recv_chunked_body_with_limit(&mut buf, &headers, 10),
+            Err(NetError::BodyTooLarge)
+        );
+        assert_eq!(buf, b"hello");
+    }
+}
+
+#[cfg(test)]
+mod response_cache {
+    use super::*;
+    use std::{env, fs};
+    use std::time::Duration;
+    use crate::{CacheConfig, ResponseCache};
+    use crate::headers::names::{AGE, DATE};
+
+    fn get(addr: SocketAddr, req: Request) -> Response {
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        client.res.unwrap()
+    }
+
+    fn text_response(text: &'static str) -> Response {
+        Response::builder().body(text.into()).build().unwrap()
+    }
+
+    fn req(path: &'static str) -> Request {
+        Request::builder().path(path.into()).build()
+    }
+
+    #[test]
+    fn second_request_skips_route() {
+        let file_path = env::temp_dir().join("rustnet_cache_test.txt");
+        fs::write(&file_path, "first").unwrap();
+
+        let mut router = Router::new();
+        let _ = router.get("/file", file_path.clone());
+
+        let config = CacheConfig {
+            ttl: Duration::from_secs(60),
+            max_entries: 8
+        };
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).cache(config);
+        let addr = start_server(&mut builder);
+
+        let res = get(addr, req("/file"));
+        assert_eq!(res.text(), "first");
+        assert!(!res.headers.contains(&AGE));
+
+        // The route would now serve the new contents.
+        fs::write(&file_path, "second").unwrap();
+
+        let res = get(addr, req("/file"));
+        assert_eq!(res.text(), "first");
+        assert_eq!(res.headers.get(&AGE), Some(&"0".into()));
+
+        let no_store = Request::builder()
+            .path("/file".into())
+            .header("Cache-Control", b"no-store")
+            .build();
+        assert_eq!(get(addr, no_store).text(), "second");
+
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn cached_response_matches_request_version() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).cache(CacheConfig::default());
+        let addr = start_server(&mut builder);
+
+        // An HTTP/1.1 client fills the cache.
+        assert_eq!(get(addr, req("/")).version, Version::OneDotOne);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+
+        let res = Response::try_from(raw.as_slice()).unwrap();
+        assert_eq!(res.version, Version::OneDotZero);
+        assert_eq!(res.headers.get(&AGE), Some(&"0".into()));
+        assert_eq!(res.headers.get(&CONNECTION), Some(&"close".into()));
+        assert_eq!(res.text(), "hello");
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = ResponseCache::new(CacheConfig {
+            ttl: Duration::from_millis(50),
+            max_entries: 8
+        });
+
+        cache.store(&req("/"), &text_response("hello"));
+        assert_eq!(cache.get(&req("/")).unwrap().text(), "hello");
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&req("/")), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn honors_no_store_and_method() {
+        let cache = ResponseCache::new(CacheConfig::default());
+
+        let mut res = text_response("secret");
+        res.headers.insert(CACHE_CONTROL, "private, No-Store".into());
+        cache.store(&req("/"), &res);
+
+        let post = Request::builder()
+            .method(Method::Post)
+            .path("/".into())
+            .build();
+        cache.store(&post, &text_response("created"));
+
+        let missing = Response::builder().status_code(404).build().unwrap();
+        cache.store(&req("/missing"), &missing);
+
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn skips_private_and_personal_responses() {
+        let cache = ResponseCache::new(CacheConfig::default());
+
+        for (name, value) in [
+            ("Cache-Control", "Private"),
+            ("Cache-Control", "private=\"Set-Cookie\", max-age=60"),
+            ("Set-Cookie", "session=abc"),
+            ("Vary", "Accept-Encoding"),
+        ] {
+            let mut res = text_response("user data");
+            res.headers.header(name, value.as_bytes());
+            cache.store(&req("/"), &res);
+            assert!(cache.is_empty(), "{name}: {value}");
+        }
+    }
+
+    #[test]
+    fn credentialed_and_conditional_requests_bypass_cache() {
+        let cache = ResponseCache::new(CacheConfig::default());
+
+        for (name, value) in [
+            ("Authorization", "Basic dXNlcjpwYXNz"),
+            ("Cookie", "session=abc"),
+        ] {
+            let personal = Request::builder()
+                .path("/".into())
+                .header(name, value.as_bytes())
+                .build();
+            cache.store(&personal, &text_response("user data"));
+            assert!(cache.is_empty(), "{name}");
+        }
+
+        cache.store(&req("/"), &text_response("hello"));
+
+        for (name, value) in [
+            ("Authorization", "Basic dXNlcjpwYXNz"),
+            ("If-None-Match", "\"abc\""),
+            ("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ] {
+            let bypass = Request::builder()
+                .path("/".into())
+                .header(name, value.as_bytes())
+                .build();
+            assert_eq!(cache.get(&bypass), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn replay_refreshes_date() {
+        let cache = ResponseCache::new(CacheConfig::default());
+
+        let stale = "Sun, 06 Nov 1994 08:49:37 GMT";
+        let mut res = text_response("hello");
+        res.headers.insert(DATE, stale.into());
+        cache.store(&req("/"), &res);
+
+        let replayed = cache.get(&req("/")).unwrap();
+        let date = replayed.headers.get(&DATE).unwrap().to_string();
+        assert_ne!(date, stale);
+        assert!(parse_http_date(&date).is_some());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ResponseCache::new(CacheConfig {
+            ttl: Duration::from_secs(60),
+            max_entries: 2
+        });
+
+        cache.store(&req("/a"), &text_response("a"));
+        cache.store(&req("/b"), &text_response("b"));
+
+        // Using "/a" leaves "/b" as the least recently used entry.
+        assert!(cache.get(&req("/a")).is_some());
+        cache.store(&req("/c"), &text_response("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&req("/a")).is_some());
+        assert!(cache.get(&req("/b")).is_none());
+        assert!(cache.get(&req("/c")).is_some());
+    }
+}
+
+#[cfg(test)]
+mod server_ready {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::mpsc;
+
+    #[test]
+    fn accepts_as_soon_as_start_returns() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let (tx, rx) = mpsc::channel();
+        let mut builder = Server::builder();
+        let _ = builder.router(&mut router).on_ready(move |addr| {
+            tx.send(addr).unwrap();
+        });
+        let addr = start_server(&mut builder);
+
+        // The callback has already run by the time `start` returns.
+        assert_eq!(rx.try_recv(), Ok(addr));
+
+        // A single attempt, without sleeping or retrying.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+        assert!(res.ends_with("hello"), "{res}");
+    }
+}
+
+#[cfg(test)]
+mod uri {
+    use super::*;
+    use crate::Uri;
+
+    #[test]
+    fn parses_components() {
+        let uri = "HTTP://Example.com:8080/a/b?x=1&y=2#top"
+            .parse::<Uri>()
+            .unwrap();
+        assert_eq!(uri.scheme, "http");
+        assert_eq!(uri.host, "example.com");
+        assert_eq!(uri.port, 8080);
+        assert_eq!(uri.path, "/a/b");
+        assert_eq!(uri.query.as_deref(), Some("x=1&y=2"));
+        assert_eq!(uri.addr(), "example.com:8080");
+        assert_eq!(uri.authority(), "example.com:8080");
+        assert_eq!(uri.path_and_query(), "/a/b?x=1&y=2");
+
+        let uri = "https://[2001:db8::1]?q".parse::<Uri>().unwrap();
+        assert_eq!(uri.host, "2001:db8::1");
+        assert_eq!(uri.port, 443);
+        assert_eq!(uri.path, "/");
+        assert_eq!(uri.addr(), "[2001:db8::1]:443");
+        assert_eq!(uri.authority(), "[2001:db8::1]");
+        assert!(uri.is_https());
+
+        let uri = "localhost/".parse::<Uri>().unwrap();
+        assert_eq!(uri.scheme, "http");
+        assert_eq!(uri.port, 80);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for input in [
+            "http://example.com/",
+            "http://example.com:8080/a?b=c",
+            "https://example.com/path",
+            "https://example.com:80/",
+            "http://[::1]/",
+            "http://[::1]:7878/x?",
+        ] {
+            let uri = input.parse::<Uri>().unwrap();
+            assert_eq!(uri.to_string(), input);
+            assert_eq!(uri.to_string().parse::<Uri>().unwrap(), uri);
+        }
+
+        let uri = "example.com".parse::<Uri>().unwrap();
+        assert_eq!(uri.to_string(), "http://example.com/");
+    }
+
+    #[test]
+    fn rejects_bad_uris() {
+        for input in [
+            "ftp://example.com/",
+            "://example.com",
+            "localhost",
+            "http://:8080/",
+            "http://example.com:http/",
+            "http://user@example.com/",
+            "http://::1/",
+        ] {
+            assert!(input.parse::<Uri>().is_err(), "{input}");
+        }
+
+        assert_eq!(
+            parse_uri("https://example.com/"),
+            Err(NetError::HttpsNotImplemented)
+        );
+    }
+
+    #[test]
+    fn client_builder_uri() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/a".into(), "a".into()));
+
+        let addr = start_server(Server::builder().router(&mut router));
+        let uri = format!("http://{addr}/a#top").parse::<Uri>().unwrap();
+
+        let req = Request::builder().method(Method::Get).build();
+        let mut client = Client::builder().req(req).uri(&uri).send().unwrap();
+        let req = client.req.as_ref().unwrap();
+        assert_eq!(req.path.as_str(), "/a");
+        assert_eq!(req.headers.get(&HOST).unwrap().as_str(), addr.to_string());
+
+        client.recv_response().unwrap();
+        let res = client.res.as_ref().unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body.as_bytes(), b"a");
+
+        let uri = "https://example.com/".parse::<Uri>().unwrap();
+        assert!(matches!(
+            Client::builder().uri(&uri).build(),
+            Err(NetError::HttpsNotImplemented)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod handler_target {
+    use super::*;
+    use crate::Handler;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn echo(req: &Request, res: &mut Response) {
+        let name = HeaderName::from("X-Echo");
+
+        if let Some(value) = req.headers.get(&name) {
+            res.headers.insert(name.clone(), value.clone());
+        }
+
+        res.body = req.body.clone();
+    }
+
+    #[test]
+    fn handler_reads_request() {
+        let mut router = Router::new();
+        router.mount(Route::new(
+            Method::Post,
+            "/echo".into(),
+            Target::Handler(Handler(echo)),
+        ));
+
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nX-Echo: hi\r\n\
+                Content-Type: text/plain\r\nContent-Length: 5\r\n\
+                Connection: close\r\n\r\nhello")
+            .unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 201 Created\r\n"), "{res}");
+        assert!(res.contains("\r\nX-Echo: hi\r\n"), "{res}");
+        assert!(res.contains("\r\nContent-Length: 5\r\n"), "{res}");
+        assert!(res.ends_with("\r\n\r\nhello"), "{res}");
+    }
+
+    #[test]
+    fn handler_target_traits() {
+        let target = Target::Handler(Handler(echo));
+        assert!(target.is_handler());
+        assert_eq!(target.kind(), "Handler");
+        assert_eq!(target, Target::Handler(Handler(echo)));
+        assert_eq!(Body::try_from(target).unwrap(), Body::Empty);
+    }
+}
+
+#[cfg(test)]
+mod testing_routes {
+    use super::*;
+
+    fn send(addr: SocketAddr, req: Request) -> Response {
+        let mut client = Client::builder()
+            .addr(addr)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        client.res.take().unwrap()
+    }
+
+    #[test]
+    fn echo_headers_and_status() {
+        let mut router = Router::new();
+        let _ = router.add_testing_routes();
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let req = Request::builder()
+            .method(Method::Put)
+            .path("/echo".into())
+            .body(Body::Json(r#"{"a":1}"#.into()))
+            .build();
+        let res = send(addr, req);
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.body, Body::Json(r#"{"a":1}"#.into()));
+        assert_eq!(
+            res.headers.get(&CONTENT_TYPE).unwrap().as_str(),
+            "application/json"
+        );
+
+        let mut req = Request::builder().path("/headers".into()).build();
+        req.headers.insert("X-Quote".into(), "say \"hi\"".into());
+        let res = send(addr, req);
+        assert_eq!(res.status.code(), 200);
+        let json = res.body.to_string();
+        assert!(json.starts_with("{\"headers\":{"), "{json}");
+        assert!(json.contains(r#""X-Quote":"say \"hi\"""#), "{json}");
+        assert!(json.contains(&format!("\"Host\":\"{addr}\"")), "{json}");
+
+        let req = Request::builder().path("/status/418".into()).build();
+        assert_eq!(send(addr, req).status.code(), 418);
+
+        let req = Request::builder().path("/status/204".into()).build();
+        let res = send(addr, req);
+        assert_eq!(res.status.code(), 204);
+        assert!(res.body.is_empty());
+
+        let req = Request::builder().path("/status/299".into()).build();
+        assert_eq!(send(addr, req).status.code(), 404);
+    }
+}
+
+#[cfg(test)]
+mod body_framing {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{Shutdown, TcpListener, TcpStream};
+
+    /// Returns a server-side `Connection` that has already received `raw`,
+    /// along with the client's end of the connection.
+    fn recv_conn(raw: &[u8], close: bool) -> (Connection, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        client.write_all(raw).unwrap();
+
+        if close {
+            client.shutdown(Shutdown::Write).unwrap();
+        }
+
+        let (stream, _) = listener.accept().unwrap();
+        (Connection::try_from(stream).unwrap(), client)
+    }
+
+    #[test]
+    fn zero_content_length_is_empty() {
+        let (mut conn, _client) = recv_conn(b"POST /a HTTP/1.1\r\n\
+            Content-Length: 0\r\nContent-Type: text/plain\r\n\r\n\
+            GET /b HTTP/1.1\r\n\r\n", false);
+
+        let req = conn.recv_request().unwrap();
+        assert_eq!(req.path.as_str(), "/a");
+        assert_eq!(req.body, Body::Empty);
+        assert!(!conn.body_pending);
+
+        let next = conn.recv_request().unwrap();
+        assert_eq!(next.method, Method::Get);
+        assert_eq!(next.path.as_str(), "/b");
+    }
+
+    #[test]
+    fn bodyless_get_leaves_pipelined_data() {
+        let (mut conn, _client) = recv_conn(b"GET /a HTTP/1.1\r\n\r\n\
+            GET /b HTTP/1.1\r\nContent-Type: text/plain\r\n\r\n", false);
+
+        let req = conn.recv_request().unwrap();
+        assert_eq!(req.path.as_str(), "/a");
+        assert_eq!(req.body, Body::Empty);
+
+        let next = conn.recv_request().unwrap();
+        assert_eq!(next.path.as_str(), "/b");
+        assert_eq!(next.body, Body::Empty);
+    }
+
+    #[test]
+    fn strict_content_length() {
+        let (mut conn, _client) = recv_conn(b"POST /a HTTP/1.1\r\n\
+            Content-Length: nope\r\n\r\nbody", false);
+        assert_eq!(conn.recv_request(), Err(NetError::BadHeaderValue));
+
+        let (mut conn, _client) = recv_conn(b"POST /a HTTP/1.1\r\n\
+            Content-Type: text/plain\r\nContent-Length: 10\r\n\r\nshort", true);
+        assert_eq!(conn.recv_request(), Err(NetError::IncompleteBody));
+    }
+
+    #[test]
+    fn expect_without_body_gets_final_response() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Post, "/a".into(), "ok".into()));
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST /a HTTP/1.1\r\nContent-Length: 0\r\n\
+                Expect: 100-continue\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 201 Created\r\n"), "{res}");
+        assert!(!res.contains("100 Continue"), "{res}");
+    }
+}
+
+#[cfg(test)]
+mod socket_options {
+    use super::*;
+    use crate::Stream;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    #[test]
+    fn client_tcp_nodelay() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let client = Client::builder()
+            .tcp_nodelay(true)
+            .addr(addr)
+            .req(Request::default())
+            .build()
+            .unwrap();
+
+        let conn = client.conn.as_ref().unwrap();
+        let Stream::Tcp(ref stream) = *conn.reader.get_ref() else {
+            panic!("expected a TCP stream");
+        };
+        assert!(stream.nodelay().unwrap());
+    }
+
+    #[test]
+    fn client_tcp_nodelay_survives_retry() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Drops the first connection after reading its request.
+        let handle = thread::spawn(move || {
+            for (num, stream) in listener.incoming().take(2).enumerate() {
+                let mut conn = Connection::try_from(stream.unwrap()).unwrap();
+                conn.recv_request().unwrap();
+
+                if num > 0 {
+                    let mut res = Response::builder().status_code(200)
+                        .build().unwrap();
+                    conn.send_response(&mut res).unwrap();
+                }
+            }
+        });
+
+        let mut client = Client::builder()
+            .tcp_nodelay(true)
+            .retries(1)
+            .addr(addr)
+            .req(Request::default())
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        handle.join().unwrap();
+
+        let conn = client.conn.as_ref().unwrap();
+        let Stream::Tcp(ref stream) = *conn.reader.get_ref() else {
+            panic!("expected a TCP stream");
+        };
+        assert!(stream.nodelay().unwrap());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn rapid_rebind_after_shutdown() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+
+        let handle = Server::builder()
+            .router(&mut router)
+            .tcp_nodelay(true)
+            .addr("127.0.0.1:0")
+            .start()
+            .unwrap();
+        let addr = handle.local_addr();
+
+        // The server closes this connection first, leaving it in TIME_WAIT.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.ends_with("hello"), "{res}");
+
+        handle.shutdown();
+        handle.join().unwrap();
+
+        // Binding on Unix sets `SO_REUSEADDR`, so the address is free again.
+        let server = Server::builder().addr(addr).build();
+        assert!(server.is_ok(), "{server:?}");
+    }
+
+}
+
+#[cfg(test)]
+mod header_validation {
+    use super::*;
+    use crate::{Handler, Header, HeaderValue};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    const INJECTED: &str = "a\r\nSet-Cookie: evil=1\r\n\r\n<html>";
+
+    #[test]
+    fn try_insert_rejects_invalid_headers() {
+        let mut headers = Headers::new();
+
+        assert_eq!(
+            headers.try_insert("X-Test".into(), INJECTED.into()),
+            Err(NetError::BadHeaderValue)
+        );
+        assert_eq!(
+            headers.try_insert("X-Test".into(), HeaderValue(b"b\0".to_vec())),
+            Err(NetError::BadHeaderValue)
+        );
+        assert_eq!(
+            headers.try_insert("Bad Name".into(), "ok".into()),
+            Err(NetError::BadHeaderName)
+        );
+        assert_eq!(
+            headers.try_insert("Bad:Name".into(), "ok".into()),
+            Err(NetError::BadHeaderName)
+        );
+        assert!(headers.is_empty());
+
+        assert!(headers.try_insert("X-Test".into(), "ok".into()).is_ok());
+        assert_eq!(headers.get(&"x-test".into()), Some(&"ok".into()));
+    }
+
+    #[test]
+    fn insert_sanitizes_values_and_skips_bad_names() {
+        let mut headers = Headers::new();
+        headers.insert("X-Test".into(), INJECTED.into());
+        headers.header("X-Bytes", b"one\r\ntwo\0");
+        headers.insert("Bad\r\nName".into(), "ok".into());
+
+        assert_eq!(headers.len(), 2);
+        assert_eq!(
+            headers.get(&"X-Test".into()).unwrap().as_str(),
+            "aSet-Cookie: evil=1<html>"
+        );
+        assert_eq!(headers.get(&"X-Bytes".into()).unwrap().as_str(), "onetwo");
+    }
+
+    #[test]
+    fn parsed_header_names_must_be_tokens() {
+        assert_eq!(
+            Header::try_from(&b"Bad Name: value"[..]),
+            Err(NetError::BadHeaderName)
+        );
+        assert!(Header::try_from(&b"X-Good_Name~1: value"[..]).is_ok());
+    }
+
+    fn inject(_req: &Request, res: &mut Response) {
+        res.headers.insert("X-Test".into(), INJECTED.into());
+    }
+
+    #[test]
+    fn crlf_value_does_not_reach_the_wire() {
+        let mut router = Router::new();
+        router.mount(Route::new(
+            Method::Get,
+            "/".into(),
+            Target::Handler(Handler(inject)),
+        ));
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut raw = String::new();
+        stream.read_to_string(&mut raw).unwrap();
+
+        assert!(raw.contains("\r\nX-Test: aSet-Cookie: evil=1<html>\r\n"));
+        assert!(!raw.contains("\r\nSet-Cookie"), "{raw}");
+    }
+}
+
+#[cfg(test)]
+mod body_file {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use crate::WRITER_BUFSIZE;
+
+    #[test]
+    fn streams_file_larger_than_writer_buffer() {
+        let path = env::temp_dir()
+            .join(format!("rustnet_body_file_{}.bin", process::id()));
+        let contents = (0..WRITER_BUFSIZE * 50)
+            .map(|i| u8::try_from(i % 251).unwrap())
+            .collect::<Vec<u8>>();
+        fs::write(&path, &contents).unwrap();
+
+        let mut router = Router::new();
+        let _ = router.add_testing_routes();
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let req = Request::builder()
+            .method(Method::Post)
+            .path("/echo".into())
+            .body_file(&path)
+            .unwrap()
+            .build();
+        let expected_len = contents.len().to_string();
+        assert_eq!(
+            req.headers.get(&CONTENT_LENGTH),
+            Some(&expected_len.as_str().into())
+        );
+        assert!(matches!(req.body, Body::Stream(_)));
+
+        let mut client =
+            Client::builder().addr(addr).req(req).send().unwrap();
+        client.recv_response().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 201);
+        assert_eq!(res.body.len(), contents.len());
+        assert_eq!(res.body.as_bytes(), &contents[..]);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let path = env::temp_dir().join("rustnet_no_such_file.bin");
+        assert!(Request::builder().body_file(path).is_err());
+    }
+}
+
+#[cfg(test)]
+mod reason_phrase {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn custom_reason_replaces_canonical_phrase() {
+        let res = Response::builder()
+            .status_code(200)
+            .reason("Totally Fine")
+            .build()
+            .unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert_eq!(res.reason(), Some("Totally Fine"));
+        assert_eq!(
+            res.status_line_to_plain_string(),
+            "HTTP/1.1 200 Totally Fine"
+        );
+        assert!(res.head_bytes().starts_with(b"HTTP/1.1 200 Totally Fine\r\n"));
+
+        let res = Response::builder()
+            .status_code(404)
+            .reason("Gone\r\nX-Evil: 1")
+            .build()
+            .unwrap();
+        assert_eq!(res.reason(), Some("GoneX-Evil: 1"));
+    }
+
+    #[test]
+    fn canonical_reason_without_override() {
+        let res = Response::builder().status_code(200).build().unwrap();
+        assert_eq!(res.reason, None);
+        assert_eq!(res.reason(), Some("OK"));
+        assert_eq!(res.status_line_to_plain_string(), "HTTP/1.1 200 OK");
+
+        let res = Response::builder().status_code(299).build().unwrap();
+        assert_eq!(res.reason(), None);
+        assert_eq!(res.status_line_to_plain_string(), "HTTP/1.1 299");
+    }
+
+    #[test]
+    fn custom_reason_is_sent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let mut res = Response::builder()
+            .status_code(200)
+            .reason("Totally Fine")
+            .body(Body::Text("ok".into()))
+            .build()
+            .unwrap();
+        conn.send_response(&mut res).unwrap();
+        drop(conn);
+
+        let mut raw = String::new();
+        client.read_to_string(&mut raw).unwrap();
+        assert!(raw.starts_with("HTTP/1.1 200 Totally Fine\r\n"), "{raw}");
+        assert!(raw.ends_with("\r\n\r\nok"), "{raw}");
+    }
+
+    #[test]
+    fn reason_with_line_break_is_not_sent() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        let mut res = Response::builder().status_code(200).build().unwrap();
+        res.reason = Some("OK\r\nX-Evil: 1".into());
+        assert!(matches!(
+            conn.send_response(&mut res),
+            Err(NetError::BadResponse)
+        ));
+        drop(conn);
+
+        let mut raw = Vec::new();
+        client.read_to_end(&mut raw).unwrap();
+        assert!(raw.is_empty());
+    }
+
+    #[test]
+    fn received_reason_is_parsed() {
+        let res = Response::try_from(
+            &b"HTTP/1.1 200 Totally Fine\r\nContent-Length: 0\r\n\r\n"[..]
+        ).unwrap();
+        assert_eq!(res.reason.as_deref(), Some("Totally Fine"));
+        assert_eq!(res.reason(), Some("Totally Fine"));
+
+        let res = Response::try_from(&b"HTTP/1.1 404 Not Found\r\n\r\n"[..])
+            .unwrap();
+        assert_eq!(res.reason, None);
+        assert_eq!(res.reason(), Some("Not Found"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut conn = Connection::try_from(stream).unwrap();
+
+        client.write_all(b"HTTP/1.1 299 Odd\r\n\r\n").unwrap();
+        let res = conn.recv_response_head().unwrap();
+        assert_eq!(res.status.code(), 299);
+        assert_eq!(res.reason(), Some("Odd"));
+    }
+}
+
+#[cfg(test)]
+mod client_from_stream {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::ClientBuilder;
+
+    #[test]
+    fn sends_request_over_given_stream() {
+        let mut router = Router::new();
+        let _ = router.add_testing_routes();
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let req = Request::builder().path("/headers".into()).build();
+        let mut client = ClientBuilder::from_stream(stream)
+            .host("example.test")
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+
+        let res = client.res.unwrap();
+        assert_eq!(res.status.code(), 200);
+        assert!(res.text().contains("\"example.test\""), "{}", res.text());
+    }
+
+    #[test]
+    fn host_defaults_to_remote_address() {
+        let mut router = Router::new();
+        let _ = router.add_testing_routes();
+        let addr = start_server(Server::builder().router(&mut router));
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let req = Request::builder().path("/headers".into()).build();
+        let mut client = ClientBuilder::from_stream(stream)
+            .req(req)
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+
+        let host = format!("\"{addr}\"");
+        assert!(client.res.unwrap().text().contains(&host));
+    }
+
+    #[test]
+    fn never_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        // Answers the first request on the given stream with a 503 that asks
+        // for a retry, then drops the stream without answering the second.
+        let handle = thread::spawn(move || {
+            let mut num_conns = 0;
+            let start = Instant::now();
+
+            while start.elapsed() < Duration::from_secs(1) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        num_conns += 1;
+                        stream.set_nonblocking(false).unwrap();
+                        let mut conn = Connection::try_from(stream).unwrap();
+                        conn.recv_request().unwrap();
+
+                        let mut res = Response::builder()
+                            .status_code(503)
+                            .header("Retry-After", b"0")
+                            .build()
+                            .unwrap();
+                        conn.send_response(&mut res).unwrap();
+                        conn.recv_request().unwrap();
+                    },
+                    Err(_) => thread::sleep(Duration::from_millis(10)),
+                }
+            }
+
+            num_conns
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut client = ClientBuilder::from_stream(stream)
+            .retries(2)
+            .respect_retry_after(true)
+            .req(Request::new())
+            .send()
+            .unwrap();
+        client.recv_response().unwrap();
+        assert_eq!(client.res.take().unwrap().status.code(), 503);
+
+        client.send_request().unwrap();
+        assert_eq!(client.recv_response(), Err(NetError::UnexpectedEof));
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod signal_scope {
+    use super::*;
+    use std::process::{self, Command};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::signal;
+
+    #[test]
+    fn signal_does_not_stop_started_servers() {
+        let mut router = Router::new();
+        router.mount(Route::new(Method::Get, "/".into(), "hello".into()));
+        let addr = start_server(Server::builder().router(&mut router));
+
+        // With the handler installed, SIGTERM is only recorded.
+        signal::install();
+        let pid = process::id().to_string();
+        let killed = Command::new("kill").args(["-TERM", &pid]).status();
+        assert!(killed.unwrap().success());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !signal::received() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(signal::take());
+        assert!(!signal::received());
+
+        let uri = format!("{addr}/");
+        let mut client = Client::send(Method::Get, &uri).unwrap();
+        client.recv_response().unwrap();
+        assert_eq!(client.res.unwrap().text(), "hello");
+    }
+}
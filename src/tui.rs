@@ -4,7 +4,7 @@ use std::str::FromStr;
 
 use crate::{
     Client, Connection, HeaderValue, Method, NetError, NetResult, Request,
-    TEST_SERVER_ADDR, TUI_NAME, utils,
+    Status, Style, TEST_SERVER_ADDR, TUI_NAME, utils,
 };
 use crate::headers::names::{CONNECTION, HOST};
 use crate::style::colors::{
@@ -19,6 +19,7 @@ pub struct Tui {
     pub running: bool,
     pub last_addr: Option<String>,
     pub last_code: Option<u16>,
+    pub style: Style,
     pub client: Client,
     pub server: Option<Child>,
 }
@@ -31,6 +32,7 @@ impl Default for Tui {
             running: true,
             last_addr: None,
             last_code: None,
+            style: Style::default(),
             client: Client::default(),
             server: None
         }
@@ -102,22 +104,19 @@ impl Tui {
                 | "request"
                 | "response"
                 | "verbose" => self.output_style(input),
+            _ if input.starts_with("style ") => {
+                let format = input.trim_start_matches("style ").trim();
+                self.set_style(format);
+            },
             "builder" => {
                 self.client.get_request_from_user()?;
 
-                // Set output style to "verbose".
-                let old_style = self.client.style;
-                self.client.style.from_format_str("*");
-
                 if let Some(conn) = self.client.conn.as_ref() {
                     self.last_addr = Some(conn.remote_addr.to_string());
                 }
 
                 println!();
                 self.send_request_and_print_output()?;
-
-                // Restore old output style.
-                self.client.style = old_style;
             },
             "start-server" => {
                 if self.server.is_some() {
@@ -181,7 +180,9 @@ impl Tui {
 
         match utils::parse_uri(uri).ok() {
             Some((addr, path)) => {
-                let req = builder.path(path.into()).build();
+                let mut req = builder.path(path.into()).build();
+                let host = utils::host_from_addr(&addr);
+                req.headers.insert(HOST, host.as_str().into());
                 self.client.req = Some(req);
                 self.client.conn = Some(Connection::try_from(addr.as_str())?);
                 self.last_addr = Some(addr);
@@ -193,7 +194,9 @@ impl Tui {
                     return Err(NetError::BadPath);
                 };
 
-                let req = builder.path(uri.to_string().into()).build();
+                let mut req = builder.path(uri.to_string().into()).build();
+                let host = utils::host_from_addr(addr);
+                req.headers.insert(HOST, host.as_str().into());
                 self.client.req = Some(req);
                 self.client.conn = Some(Connection::try_from(addr.as_str())?);
                 Ok(())
@@ -211,20 +214,33 @@ impl Tui {
         self.do_send = true;
 
         match style {
-            "body" => self.client.style.from_format_str("b"),
-            "response" => self.client.style.from_format_str("shb"),
-            "minimal" => self.client.style.from_format_str("Rs"),
-            "verbose" => self.client.style.from_format_str("*"),
+            "body" => self.style.from_format_str("b"),
+            "response" => self.style.from_format_str("shb"),
+            "minimal" => self.style.from_format_str("Rs"),
+            "verbose" => self.style.from_format_str("*"),
             "request" => {
                 self.do_send = false;
-                self.client.style.from_format_str("RHB");
+                self.style.from_format_str("RHB");
             },
             _ => unreachable!(),
         }
 
+        self.client.style = self.style;
         println!("Output style set to {MAGENTA}{style}{RESET}.\n");
     }
 
+    /// Sets the output style from a format string, using the same format as
+    /// the client's `--output` option, and prints a message to stdout.
+    pub fn set_style(&mut self, format: &str) {
+        // Reset the do_send option on style change.
+        self.do_send = true;
+
+        self.style.from_format_str(format);
+        self.client.style = self.style;
+
+        println!("Output style set to {MAGENTA}{format}{RESET}.\n");
+    }
+
     /// Clears the screen and moves the cursor to the top left.
     ///
     /// # Errors
@@ -281,12 +297,14 @@ impl Tui {
         match self.last_code.take() {
             None => write!(&mut stdout, "{CYAN}${RESET} ")?,
             Some(code) => {
-                let color = match code {
-                    100..=199 => BLUE,
-                    200..=299 => GREEN,
-                    300..=399 => YELLOW,
-                    400..=499 => ORANGE,
-                    500..=599 => RED,
+                let class = Status::try_from(code).map(|status| status.class());
+
+                let color = match class {
+                    Ok(1) => BLUE,
+                    Ok(2) => GREEN,
+                    Ok(3) => YELLOW,
+                    Ok(4) => ORANGE,
+                    Ok(5) => RED,
                     _ => MAGENTA,
                 };
 
@@ -322,7 +340,12 @@ Additional requests to the same address can be entered {MAGENTA}/PATH{RESET}.
     request       Only print the requests (but do not send them).
     response      Only print the responses (default).
     start-server  Start a test server at localhost:7878.
-    verbose       Print both requests and responses.\n"
+    style FORMAT  Set the output style using FORMAT, see below.
+    verbose       Print both requests and responses.\n
+{MAGENTA}FORMAT OPTIONS:{RESET}
+    R = request line        s = status line
+    H = request headers     h = response headers
+    B = request body        b = response body\n"
         );
     }
 
@@ -360,7 +383,7 @@ Additional requests to the same address can be entered {MAGENTA}/PATH{RESET}.
             },
         };
 
-        if utils::check_server_is_live(TEST_SERVER_ADDR) {
+        if utils::check_server_is_ready(TEST_SERVER_ADDR) {
             println!(
                 "{GREEN}Server is listening at {TEST_SERVER_ADDR}{RESET}\n"
             );
@@ -457,6 +480,8 @@ Additional requests to the same address can be entered {MAGENTA}/PATH{RESET}.
 
     /// Handles printing output to stdout.
     pub fn print_output(&mut self) {
+        // Apply the persistent output style for every request.
+        self.client.style = self.style;
         self.client.print();
 
         // Store status code for prompt.
@@ -1,13 +1,21 @@
 use std::env;
+use std::fmt::Write as _;
 use std::fs;
-use std::net::{SocketAddr, TcpStream};
+use std::iter::Peekable;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
 use std::path::Path;
 use std::process::{Command, Stdio};
-use std::str::FromStr;
+use std::str::{self, Chars, FromStr};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::{HeaderValue, NetError, NetResult};
+use crate::{
+    Connection, HeaderValue, Headers, NetError, NetResult, Request, Uri,
+    DEFAULT_NAME, encoding,
+};
+use crate::server::PING_PATH;
+use crate::headers::names::{TRANSFER_ENCODING, USER_AGENT};
 use crate::style::colors::{RED, RESET};
 
 /// Trim whitespace from the beginning of a bytes slice.
@@ -39,76 +47,63 @@ pub fn trim(bytes: &[u8]) -> &[u8] {
 }
 
 /// Parses a string slice into a host address and a URI path.
+///
+/// This is a shim over parsing a `Uri` for callers that only need the
+/// "host:port" address to connect to and the path and query to request.
+/// The port defaults to 80 if the address does not include one. IPv6
+/// literals must be enclosed in brackets, as in `http://[::1]:8080/path`.
 /// 
 /// # Errors
 /// 
-/// Returns an error if the `uri` argument cannot be parsed into an address
-/// `String` and a path `String`.
+/// Returns an error if the `uri` argument cannot be parsed into a `Uri` or
+/// if its scheme is "https".
 pub fn parse_uri(uri: &str) -> NetResult<(String, String)> {
-    match uri.trim().split_once("://") {
-        Some((scheme, rest)) => {
-            if scheme.is_empty() || rest.is_empty() {
-                return Err(NetError::BadUri);
-            }
+    let uri = uri.parse::<Uri>()?;
 
-            match scheme {
-                "http" => match rest.split_once('/') {
-                    Some((addr, path)) => {
-                        if path.is_empty() && addr.contains(':') {
-                            // http://httpbin.org:80/
-                            Ok((addr.to_string(), String::from("/")))
-                        } else if path.is_empty() {
-                            // http://httpbin.org/
-                            Ok((format!("{addr}:80"), String::from("/")))
-                        } else if addr.contains(':') {
-                            // http://httpbin.org:80/json
-                            Ok((addr.to_string(), format!("/{path}")))
-                        } else {
-                            // http://httpbin.org/json
-                            Ok((format!("{addr}:80"), format!("/{path}")))
-                        }
-                    },
-                    None if rest.contains(':') => {
-                        // http://httpbin.org:80
-                        Ok((rest.to_string(), String::from("/")))
-                    },
-                    // http://httpbin.org
-                    None => Ok((format!("{rest}:80"), String::from("/"))),
-                },
-                "https" => Err(NetError::HttpsNotImplemented),
-                _ => Err(NetError::BadScheme),
-            }
-        },
-        // No scheme present.
-        None => match uri.split_once('/') {
-            Some((addr, _)) if addr.is_empty() => Err(NetError::BadAddress),
-            Some((addr, path)) if addr.contains(':') && path.is_empty() => {
-                // httpbin.org:80/
-                Ok((addr.to_string(), String::from("/")))
-            },
-            Some((addr, path)) if addr.contains(':') => {
-                // httpbin.org:80/json
-                Ok((addr.to_string(), format!("/{path}")))
-            },
-            Some((addr, path)) if path.is_empty() => {
-                // httpbin.org/
-                Ok((format!("{addr}:80"), String::from("/")))
-            },
-            Some((addr, path)) => {
-                // httpbin.org/json
-                Ok((format!("{addr}:80"), format!("/{path}")))
-            },
-            None if uri.contains(':') => {
-                // httpbin.org:80
-                Ok((uri.to_string(), String::from("/")))
-            },
-            None if uri.contains('.') => {
-                // httpbin.org
-                Ok((format!("{uri}:80"), String::from("/")))
-            },
-            _ => Err(NetError::BadUri),
-        },
+    if uri.is_https() {
+        return Err(NetError::HttpsNotImplemented);
     }
+
+    Ok((uri.addr(), uri.path_and_query()))
+}
+
+/// Returns true if the bytes form a non-empty token, which consists of the
+/// characters that RFC 9110 allows in header names and methods.
+#[must_use]
+pub fn is_token(bytes: &[u8]) -> bool {
+    !bytes.is_empty()
+        && bytes.iter().all(|&b| {
+            b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+        })
+}
+
+/// Returns true if the final transfer coding in the `Headers` is chunked.
+#[must_use]
+pub fn is_chunked(headers: &Headers) -> bool {
+    headers.get(&TRANSFER_ENCODING).is_some_and(|value| {
+        value
+            .as_str()
+            .rsplit(',')
+            .next()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+    })
+}
+
+/// Returns true if the User-Agent header in the `Headers` is the default
+/// `DEFAULT_NAME` value.
+#[must_use]
+pub fn is_default_user_agent(headers: &Headers) -> bool {
+    headers
+        .get(&USER_AGENT)
+        .is_some_and(|value| value.as_str() == DEFAULT_NAME)
+}
+
+/// Returns a Host header value from a "host:port" address, omitting the
+/// port if it is the default HTTP port.
+#[must_use]
+pub fn host_from_addr(addr: &str) -> String {
+    let addr = addr.trim();
+    addr.strip_suffix(":80").unwrap_or(addr).to_ascii_lowercase()
 }
 
 /// Converts the given string slice to a new titlecase `String`.
@@ -150,6 +145,85 @@ pub fn to_titlecase(input: &str) -> String {
     output
 }
 
+/// Percent-encodes every byte of `input` other than the unreserved URI
+/// characters (ALPHA, DIGIT, "-", ".", "_", and "~").
+#[must_use]
+pub fn percent_encode(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    for &byte in input.as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            output.push(char::from(byte));
+        } else {
+            let _ = write!(output, "%{byte:02X}");
+        }
+    }
+
+    output
+}
+
+/// Decodes the percent-encoded bytes in `input`. A "%" that is not followed
+/// by two hexadecimal digits is kept as is.
+#[must_use]
+pub fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let decoded = bytes
+            .get((i + 1)..(i + 3))
+            .filter(|hex| {
+                bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit)
+            })
+            .and_then(|hex| str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if let Some(byte) = decoded {
+            output.push(byte);
+            i += 3;
+        } else {
+            output.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    output
+}
+
+/// Returns a filename that is safe to join onto a directory path.
+///
+/// Everything up to the last "/" or "\\" is removed, along with any control
+/// characters. Returns `None` if nothing usable remains, such as for "..".
+#[must_use]
+pub fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+
+    let name = name.trim();
+
+    match name {
+        "" | "." | ".." => None,
+        _ => Some(name.to_string()),
+    }
+}
+
+/// Returns the Sec-WebSocket-Accept value for a Sec-WebSocket-Key, which is
+/// the base64-encoded SHA-1 digest of the key and the WebSocket GUID.
+#[must_use]
+pub fn websocket_accept(key: &str) -> String {
+    const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+    let mut input = key.trim().as_bytes().to_vec();
+    input.extend_from_slice(GUID.as_bytes());
+    encoding::base64_encode(&encoding::sha1(&input))
+}
+
 /// Builds the server binary using `cargo`.
 /// 
 /// # Errors
@@ -218,6 +292,41 @@ pub fn date_command_exists() -> Option<String> {
     None
 }
 
+/// Resolves `addr` to one or more socket addresses.
+///
+/// # Errors
+///
+/// Returns `NetError::BadAddress` if `addr` is not a valid address, or
+/// `NetError::Dns` if the host name could not be resolved.
+pub fn resolve<A: ToSocketAddrs>(addr: A) -> NetResult<Vec<SocketAddr>> {
+    let addrs = addr
+        .to_socket_addrs()
+        .map_err(|e| match e.kind() {
+            ErrorKind::InvalidInput => NetError::BadAddress,
+            _ => NetError::Dns,
+        })?
+        .collect::<Vec<_>>();
+
+    if addrs.is_empty() {
+        return Err(NetError::Dns);
+    }
+
+    Ok(addrs)
+}
+
+/// Opens a TCP connection to `addr`, trying each address it resolves to
+/// in turn.
+///
+/// # Errors
+///
+/// Returns an error if resolving `addr` fails, or the error from the last
+/// address if no connection could be established. A refused connection is
+/// returned as `NetError::ConnectionRefused`.
+pub fn connect<A: ToSocketAddrs>(addr: A) -> NetResult<TcpStream> {
+    let addrs = resolve(addr)?;
+    Ok(TcpStream::connect(addrs.as_slice())?)
+}
+
 /// Returns true if a TCP connection can be established with the provided
 /// server address.
 #[must_use]
@@ -240,6 +349,45 @@ pub fn check_server_is_live(addr: &str) -> bool {
     false
 }
 
+/// Returns true if the test server at the provided address responds to a
+/// request for its `PING_PATH` readiness route.
+///
+/// Unlike `check_server_is_live`, this confirms that the server is routing
+/// requests rather than only accepting TCP connections.
+#[must_use]
+pub fn check_server_is_ready(addr: &str) -> bool {
+    let Ok(socket) = SocketAddr::from_str(addr) else {
+        return false;
+    };
+
+    let timeout = Duration::from_millis(200);
+
+    // Attempt to connect a maximum of five times.
+    for _ in 0..5 {
+        if ping(&socket, timeout).is_ok_and(|is_pong| is_pong) {
+            return true;
+        }
+
+        thread::sleep(timeout);
+    }
+
+    false
+}
+
+/// Sends a request for the `PING_PATH` route and returns true if the
+/// response is a 200 with a "pong" body.
+fn ping(socket: &SocketAddr, timeout: Duration) -> NetResult<bool> {
+    let stream = TcpStream::connect_timeout(socket, timeout)?;
+    stream.set_read_timeout(Some(timeout * 5))?;
+
+    let mut conn = Connection::try_from(stream)?;
+    let mut req = Request::builder().path(PING_PATH.into()).build();
+    conn.send_request(&mut req)?;
+
+    let res = conn.recv_response_to(&req.method)?;
+    Ok(res.status.code() == 200 && res.body.as_bytes() == b"pong")
+}
+
 /// Returns the file extension, if present, of a `Path` value.
 #[must_use]
 pub fn get_extension(path: &Path) -> Option<&str> {
@@ -262,3 +410,392 @@ pub fn content_type_from_ext(path: &Path) -> Option<&'static str> {
         _ => None,
     }
 }
+
+/// Returns an entity tag for the file at `path` derived from its size and
+/// last modification time, or `None` if the file's metadata is unavailable.
+#[must_use]
+pub fn file_etag(path: &Path) -> Option<String> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("\"{:x}-{:x}\"", meta.len(), mtime.as_secs()))
+}
+
+/// Parses an HTTP date in the IMF-fixdate format (for example,
+/// "Sun, 06 Nov 1994 08:49:37 GMT") into a `SystemTime`.
+///
+/// The obsolete RFC 850 ("Sunday, 06-Nov-94 08:49:37 GMT") and asctime
+/// ("Sun Nov  6 08:49:37 1994") formats are also accepted, as recipients
+/// are required to do by RFC 9110. Two-digit RFC 850 years from 70 to 99
+/// are taken to be in the 1900s and all others in the 2000s.
+#[must_use]
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let (year, month, day, secs) = parse_imf_fixdate(date)
+        .or_else(|| parse_rfc850_date(date))
+        .or_else(|| parse_asctime_date(date))?;
+
+    if !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+
+    let days = days_since_epoch(year, month, day);
+    let secs = days.checked_mul(86_400)?.checked_add(secs)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Parses an IMF-fixdate into its year, month, day, and the number of
+/// seconds since midnight.
+fn parse_imf_fixdate(date: &str) -> Option<(u64, u64, u64, u64)> {
+    let mut parts = date.split_whitespace();
+
+    let _weekday = parts.next().filter(|wd| wd.ends_with(','))?;
+    let day = parts.next().and_then(|d| d.parse::<u64>().ok())?;
+    let month = parts.next().and_then(month_from_abbr)?;
+    let year = parts.next().and_then(|y| y.parse::<u64>().ok())?;
+    let secs = parts.next().and_then(seconds_from_time)?;
+
+    if parts.next() != Some("GMT") || parts.next().is_some() {
+        return None;
+    }
+
+    Some((year, month, day, secs))
+}
+
+/// Parses an obsolete RFC 850 date into its year, month, day, and the
+/// number of seconds since midnight.
+fn parse_rfc850_date(date: &str) -> Option<(u64, u64, u64, u64)> {
+    let mut parts = date.split_whitespace();
+
+    let _weekday = parts.next().filter(|wd| wd.ends_with(','))?;
+    let mut dmy = parts.next()?.splitn(3, '-');
+    let secs = parts.next().and_then(seconds_from_time)?;
+
+    if parts.next() != Some("GMT") || parts.next().is_some() {
+        return None;
+    }
+
+    let day = dmy.next().and_then(|d| d.parse::<u64>().ok())?;
+    let month = dmy.next().and_then(month_from_abbr)?;
+    let year = dmy
+        .next()
+        .filter(|y| y.len() == 2)
+        .and_then(|y| y.parse::<u64>().ok())?;
+    let year = if year >= 70 { 1900 + year } else { 2000 + year };
+
+    Some((year, month, day, secs))
+}
+
+/// Parses an obsolete asctime date into its year, month, day, and the
+/// number of seconds since midnight.
+fn parse_asctime_date(date: &str) -> Option<(u64, u64, u64, u64)> {
+    let mut parts = date.split_whitespace();
+
+    let _weekday = parts.next().filter(|wd| wd.len() == 3)?;
+    let month = parts.next().and_then(month_from_abbr)?;
+    let day = parts.next().and_then(|d| d.parse::<u64>().ok())?;
+    let secs = parts.next().and_then(seconds_from_time)?;
+    let year = parts.next().and_then(|y| y.parse::<u64>().ok())?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((year, month, day, secs))
+}
+
+/// Formats a `SystemTime` as an HTTP date in the IMF-fixdate format (for
+/// example, "Sun, 06 Nov 1994 08:49:37 GMT").
+#[must_use]
+pub fn format_http_date(time: SystemTime) -> String {
+    // The Unix epoch fell on a Thursday.
+    const WEEKDAYS: [&str; 7] = [
+        "Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed",
+    ];
+
+    let (year, month, day, secs) = utc_date(time);
+    let days = days_since_epoch(year, month, day);
+    let weekday = WEEKDAYS[usize::try_from(days % 7).unwrap_or_default()];
+    let month = MONTHS[usize::try_from(month - 1).unwrap_or_default()];
+
+    format!(
+        "{weekday}, {day:02} {month} {year} {:02}:{:02}:{:02} GMT",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Formats a `SystemTime` as a Common Log Format timestamp in UTC (for
+/// example, "10/Oct/2000:13:55:36 +0000").
+#[must_use]
+pub fn format_clf_date(time: SystemTime) -> String {
+    let (year, month, day, secs) = utc_date(time);
+    let month = MONTHS[usize::try_from(month - 1).unwrap_or_default()];
+
+    format!(
+        "{day:02}/{month}/{year}:{:02}:{:02}:{:02} +0000",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Formats a `SystemTime` as an RFC 3339 timestamp in UTC (for example,
+/// "2000-10-10T13:55:36Z").
+#[must_use]
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let (year, month, day, secs) = utc_date(time);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Month abbreviations as used in HTTP and log dates.
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Splits a `SystemTime` into its UTC year, month, day, and the number of
+/// seconds since midnight. Times before the Unix epoch are clamped to it.
+fn utc_date(time: SystemTime) -> (u64, u64, u64, u64) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let (year, month, day) = date_from_days(secs / 86_400);
+    (year, month, day, secs % 86_400)
+}
+
+/// Returns the month number (1 through 12) from its three-letter
+/// abbreviation.
+fn month_from_abbr(month: &str) -> Option<u64> {
+    MONTHS.iter()
+        .position(|&abbr| abbr == month)
+        .map(|idx| idx as u64 + 1)
+}
+
+/// Returns the number of seconds since midnight from an "HH:MM:SS" string.
+fn seconds_from_time(time: &str) -> Option<u64> {
+    let mut parts = time.splitn(3, ':').map(|p| p.parse::<u64>().ok());
+
+    let hours = parts.next()??;
+    let mins = parts.next()??;
+    let secs = parts.next()??;
+
+    if hours > 23 || mins > 59 || secs > 60 {
+        return None;
+    }
+
+    Some(hours * 3600 + mins * 60 + secs)
+}
+
+/// Returns the number of days between the Unix epoch and the given date in
+/// the proleptic Gregorian calendar.
+const fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // Count years starting in March so leap days fall at the end.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_idx = (month + 9) % 12;
+    let day_of_year = (153 * month_idx + 2) / 5 + day - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    // 719,468 days separate 0000-03-01 from 1970-01-01.
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the (year, month, day) date that is the given number of days
+/// after the Unix epoch. This is the inverse of `days_since_epoch`.
+fn date_from_days(days: u64) -> (u64, u64, u64) {
+    // Count from 0000-03-01 so leap days fall at the end of each year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year =
+        day_of_era - (year_of_era * 365 + year_of_era / 4 - year_of_era / 100);
+    let month_idx = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_idx + 2) / 5 + 1;
+    let month = if month_idx < 10 { month_idx + 3 } else { month_idx - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Escapes a string for inclusion in a JSON string literal.
+#[must_use]
+pub fn escape_json(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+
+    for ch in input.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            ch if ch.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", u32::from(ch));
+            },
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// The deepest nesting of JSON objects and arrays that `pretty_json` will
+/// print, which bounds its recursion.
+pub const MAX_JSON_DEPTH: usize = 128;
+
+/// Returns the JSON text pretty-printed with two-space indentation, or
+/// `None` if it is not valid JSON or nests objects and arrays more than
+/// `MAX_JSON_DEPTH` levels deep.
+#[must_use]
+pub fn pretty_json(json: &str) -> Option<String> {
+    let mut chars = json.chars().peekable();
+    let mut pretty = String::with_capacity(json.len() * 2);
+
+    write_json_value(&mut chars, &mut pretty, 0)?;
+    skip_json_whitespace(&mut chars);
+
+    chars.peek().is_none().then_some(pretty)
+}
+
+/// Skips insignificant whitespace between JSON tokens.
+fn skip_json_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars
+        .next_if(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        .is_some()
+    {}
+}
+
+/// Writes a newline followed by the indentation for the given depth.
+fn write_json_newline(pretty: &mut String, depth: usize) {
+    pretty.push('\n');
+
+    for _ in 0..depth {
+        pretty.push_str("  ");
+    }
+}
+
+/// Writes the next JSON value, indenting nested values below `depth`.
+fn write_json_value(
+    chars: &mut Peekable<Chars<'_>>,
+    pretty: &mut String,
+    depth: usize
+) -> Option<()> {
+    skip_json_whitespace(chars);
+
+    match chars.peek()? {
+        '{' => write_json_container(chars, pretty, depth, '}'),
+        '[' => write_json_container(chars, pretty, depth, ']'),
+        '"' => write_json_string(chars, pretty),
+        _ => write_json_scalar(chars, pretty),
+    }
+}
+
+/// Writes a JSON object or array with one member or element per line.
+fn write_json_container(
+    chars: &mut Peekable<Chars<'_>>,
+    pretty: &mut String,
+    depth: usize,
+    close: char
+) -> Option<()> {
+    if depth >= MAX_JSON_DEPTH {
+        return None;
+    }
+
+    pretty.push(chars.next()?);
+    skip_json_whitespace(chars);
+
+    if chars.next_if_eq(&close).is_some() {
+        pretty.push(close);
+        return Some(());
+    }
+
+    loop {
+        write_json_newline(pretty, depth + 1);
+
+        // Object members start with a string key.
+        if close == '}' {
+            skip_json_whitespace(chars);
+
+            if chars.peek() != Some(&'"') {
+                return None;
+            }
+
+            write_json_string(chars, pretty)?;
+            skip_json_whitespace(chars);
+            chars.next_if_eq(&':')?;
+            pretty.push_str(": ");
+        }
+
+        write_json_value(chars, pretty, depth + 1)?;
+        skip_json_whitespace(chars);
+
+        match chars.next()? {
+            ',' => pretty.push(','),
+            c if c == close => break,
+            _ => return None,
+        }
+    }
+
+    write_json_newline(pretty, depth);
+    pretty.push(close);
+    Some(())
+}
+
+/// Writes a JSON string, including its quotes, without changing escapes.
+fn write_json_string(
+    chars: &mut Peekable<Chars<'_>>,
+    pretty: &mut String
+) -> Option<()> {
+    pretty.push(chars.next()?);
+
+    loop {
+        let c = chars.next()?;
+        pretty.push(c);
+
+        match c {
+            '"' => return Some(()),
+            '\\' => pretty.push(chars.next()?),
+            // Control characters must be escaped.
+            '\0'..='\x1f' => return None,
+            _ => {},
+        }
+    }
+}
+
+/// Writes a JSON number or one of the literals true, false, and null.
+fn write_json_scalar(
+    chars: &mut Peekable<Chars<'_>>,
+    pretty: &mut String
+) -> Option<()> {
+    let mut scalar = String::new();
+
+    while let Some(c) = chars.next_if(|c| {
+        c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')
+    }) {
+        scalar.push(c);
+    }
+
+    let is_number_char = |c: char| {
+        c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')
+    };
+
+    let is_number = scalar
+        .starts_with(|c: char| c == '-' || c.is_ascii_digit())
+        && scalar.chars().all(is_number_char)
+        && scalar.parse::<f64>().is_ok();
+
+    if !is_number && !matches!(scalar.as_str(), "true" | "false" | "null") {
+        return None;
+    }
+
+    pretty.push_str(&scalar);
+    Some(())
+}
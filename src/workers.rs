@@ -1,8 +1,24 @@
-use std::sync::{Arc, Mutex};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::mpsc::{
+    channel, sync_channel, Receiver, Sender, SyncSender, TrySendError,
+};
 use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-use crate::{Connection, Method, Server};
+use crate::{Connection, NetError, NetResult, Request, Response, Server};
+use crate::headers::names::CONNECTION;
+use crate::server::KEEP_ALIVE_TIMEOUT;
+
+/// Counts the connections that are waiting for a worker and the ones that
+/// are being served.
+#[derive(Debug, Default)]
+pub struct PoolStats {
+    pub active: AtomicUsize,
+    pub queued: AtomicUsize,
+}
 
 /// Contains the ID and handle for a single worker thread.
 #[derive(Debug)]
@@ -14,70 +30,203 @@ pub struct Worker {
 impl Worker {
     /// Spawns a worker thread that receives and handles new connections.
     ///
-    /// # Panics
-    ///
-    /// Panics if there is a problem receiving a `Connection`.
+    /// A panic while serving a connection closes that connection but does
+    /// not stop the worker.
     #[allow(clippy::similar_names)]
     pub fn new(
         id: usize,
         server: Arc<Server>,
-        receiver: Arc<Mutex<Receiver<Connection>>>
+        receiver: Arc<Mutex<Receiver<Connection>>>,
+        stats: Arc<PoolStats>
     ) -> Self {
-        let handle = thread::spawn(move || {
-            while let Ok(mut conn) = receiver.lock().unwrap().recv() {
-                let (req, mut res) = match conn.recv_request() {
-                    Ok(req) => match server.router.resolve(&req) {
-                        Ok(res) => (req, res),
-                        Err(ref err) => {
-                            server.send_error(500, err.to_string(), &mut conn);
-                            continue;
-                        },
+        let handle = thread::spawn(move || loop {
+            // Release the lock before serving so other workers can receive.
+            let received = receiver
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .recv();
+
+            let Ok(mut conn) = received else {
+                break;
+            };
+
+            let _ = stats.active.fetch_add(1, Ordering::SeqCst);
+            let _ = stats.queued.fetch_sub(1, Ordering::SeqCst);
+
+            let served = panic::catch_unwind(AssertUnwindSafe(|| {
+                Self::serve(&server, &mut conn)
+            }));
+
+            let _ = stats.active.fetch_sub(1, Ordering::SeqCst);
+
+            match served {
+                Ok(true) => break,
+                Ok(false) => {},
+                Err(payload) => server.log(&format!(
+                    "[SERVER] Worker {id} recovered from a panic: {}",
+                    panic_message(&*payload)
+                )),
+            }
+        });
+
+        Self { id, handle: Some(handle) }
+    }
+
+    /// Serves requests on a single `Connection`, returning true if the
+    /// server received a shutdown request.
+    ///
+    /// With keep-alive enabled, requests are read until the client asks to
+    /// close the connection or the connection is idle for
+    /// `KEEP_ALIVE_TIMEOUT`.
+    fn serve(server: &Server, conn: &mut Connection) -> bool {
+        conn.ordered_headers = server.ordered_headers;
+        conn.options.clock.clone_from(&server.clock);
+
+        if server.keep_alive
+            && conn.set_read_timeout(Some(KEEP_ALIVE_TIMEOUT)).is_err()
+        {
+            return false;
+        }
+
+        let mut is_first = true;
+
+        loop {
+            let start = Instant::now();
+
+            let (req, mut res) = match server.recv_request(conn) {
+                Ok(req) => match Self::resolve(server, &req, conn) {
+                    Ok(res)
+                        if server.is_tunnel_request(&req)
+                            && res.status.is_success() =>
+                    {
+                        server.tunnel(&req, &res, conn, start);
+                        return false;
                     },
+                    Ok(res) => (req, res),
                     Err(ref err) => {
-                        server.send_error(500, err.to_string(), &mut conn);
-                        continue;
+                        server.send_error(500, err.to_string(), conn);
+                        return false;
                     },
-                };
+                },
+                // An idle or closed kept-alive connection is not an error.
+                Err(ref err)
+                    if !is_first && (err.is_disconnect() || err.is_timeout()) =>
+                {
+                    return false;
+                },
+                Err(ref err) => {
+                    let code = err.status_code();
+                    server.send_error(code, err.to_string(), conn);
+                    return false;
+                },
+            };
 
-                if let Err(ref err) = conn.send_response(&mut res) {
-                    server.send_error(500, err.to_string(), &mut conn);
-                    continue;
-                }
+            is_first = false;
 
-                // Check for server shutdown signal
-                if server.is_test_server
-                    && matches!(req.method, Method::Shutdown)
-                {
-                    server.shutdown(&conn);
-                    break;
-                }
+            let can_discard = Server::can_discard_body(&req, conn);
 
-                if server.do_log {
-                    server.log(&format!(
-                        "[{}|{}] {} {}",
-                        conn.remote_addr.ip(),
-                        res.status.code(),
-                        req.method,
-                        &req.path
-                    ));
+            // Event streams have no length, so they end with the connection.
+            let keep_alive = server.keep_alive
+                && can_discard
+                && !conn.close_after_response
+                && !res.body.is_event_stream()
+                && !Connection::closes_after(req.version, &req.headers)
+                && !Connection::closes_after(res.version, &res.headers);
+
+            if server.keep_alive && !keep_alive {
+                res.headers.insert(CONNECTION, "close".into());
+            }
+
+            if let Err(ref err) = conn.send_response(&mut res) {
+                // A client that has gone away is not a server error.
+                if err.is_disconnect() {
+                    server.log_debug(
+                        &format!("[SERVER] Client disconnected: {err}")
+                    );
+                } else if res.body.is_event_stream() {
+                    // The response head has already been sent.
+                    server.log(&format!("[SERVER] Event stream error: {err}"));
+                } else {
+                    server.send_error(500, err.to_string(), conn);
                 }
+
+                return false;
             }
-        });
 
-        Self { id, handle: Some(handle) }
+            // Check for server shutdown signal
+            if server.is_shutdown_request(&req) {
+                server.shutdown(conn);
+                return true;
+            }
+
+            server.log_request(&req, &res, conn, start.elapsed());
+
+            // Skip over a body that was never read, so that the next request
+            // starts in the right place and a closing connection is not
+            // reset while the client is still sending.
+            if can_discard && conn.discard_body(&req.headers).is_err() {
+                return false;
+            }
+
+            if !keep_alive {
+                return false;
+            }
+        }
+    }
+
+    /// Resolves the `Request` into a `Response`, turning a panic during
+    /// resolution into an error so that a 500 response can be sent.
+    fn resolve(
+        server: &Server,
+        req: &Request,
+        conn: &Connection
+    ) -> NetResult<Response> {
+        panic::catch_unwind(AssertUnwindSafe(|| server.resolve(req, conn)))
+            .unwrap_or_else(|payload| {
+                server.log(&format!(
+                    "[SERVER] Panic while resolving {} {}: {}",
+                    req.method,
+                    req.path,
+                    panic_message(&*payload)
+                ));
+
+                Err(NetError::Other("Handler panicked.".into()))
+            })
     }
 }
 
+/// Returns the message of a panic payload, if it has one.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// The sending half of the job queue, which is bounded when the server has
+/// a `max_queued` limit.
+#[derive(Debug)]
+pub enum JobSender {
+    Unbounded(Sender<Connection>),
+    Bounded(SyncSender<Connection>),
+}
+
 /// Holds the pool of `Worker` threads.
 #[derive(Debug)]
 pub struct ThreadPool {
     pub workers: Vec<Worker>,
-    pub sender: Option<Sender<Connection>>,
+    pub sender: Option<JobSender>,
+    pub stats: Arc<PoolStats>,
 }
 
 impl ThreadPool {
     /// Create a new `ThreadPool` with the given number of worker threads.
     ///
+    /// The pool takes the server's `max_queued` limit, if any, as the
+    /// capacity of its job queue. Idle workers take connections off the
+    /// queue as soon as they arrive, so only the waiting ones count.
+    ///
     /// # Panics
     ///
     /// Panics if the `size` argument is less than one.
@@ -85,33 +234,80 @@ impl ThreadPool {
     pub fn new(num_workers: usize, server: &Arc<Server>) -> Self {
         assert!(num_workers > 0);
 
-        let (tx, rx) = channel();
-        let sender = Some(tx);
+        let (sender, rx) = server.max_queued.map_or_else(
+            || {
+                let (tx, rx) = channel();
+                (JobSender::Unbounded(tx), rx)
+            },
+            |max_queued| {
+                let (tx, rx) = sync_channel(max_queued);
+                (JobSender::Bounded(tx), rx)
+            },
+        );
+        let sender = Some(sender);
         let receiver = Arc::new(Mutex::new(rx));
+        let stats = Arc::new(PoolStats::default());
 
         let mut workers = Vec::with_capacity(num_workers);
 
         for id in 0..num_workers {
             let server_clone = server.clone();
             let worker_rx = Arc::clone(&receiver);
-            let worker = Worker::new(id, server_clone, worker_rx);
+            let worker_stats = Arc::clone(&stats);
+            let worker = Worker::new(id, server_clone, worker_rx, worker_stats);
 
             workers.push(worker);
         }
 
-        Self { workers, sender }
+        Self { workers, sender, stats }
+    }
+
+    /// Returns the number of connections being served by a worker.
+    #[must_use]
+    pub fn active_count(&self) -> usize {
+        self.stats.active.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of connections waiting for a worker.
+    #[must_use]
+    pub fn queued_count(&self) -> usize {
+        self.stats.queued.load(Ordering::SeqCst)
     }
 
     /// Sends a `Connection` to a worker thread for handling.
     ///
+    /// Returns the `Connection` back if the job queue is bounded and already
+    /// holds `max_queued` connections.
+    ///
     /// # Panics
     ///
     /// Panics if there is a problem sending the `Connection` to the worker
     /// thread.
-    pub fn handle_connection(&self, conn: Connection) {
-        if let Some(tx) = self.sender.as_ref() {
-            tx.send(conn).unwrap();
+    #[must_use]
+    pub fn handle_connection(&self, conn: Connection) -> Option<Connection> {
+        let tx = self.sender.as_ref()?;
+
+        let _ = self.stats.queued.fetch_add(1, Ordering::SeqCst);
+
+        let rejected = match tx {
+            JobSender::Unbounded(tx) => {
+                tx.send(conn).unwrap();
+                None
+            },
+            JobSender::Bounded(tx) => match tx.try_send(conn) {
+                Ok(()) => None,
+                Err(TrySendError::Full(conn)) => Some(conn),
+                Err(TrySendError::Disconnected(_)) => {
+                    panic!("Worker threads have stopped.")
+                },
+            },
+        };
+
+        if rejected.is_some() {
+            let _ = self.stats.queued.fetch_sub(1, Ordering::SeqCst);
         }
+
+        rejected
     }
 }
 
@@ -0,0 +1,302 @@
+use std::io::{Read, Write};
+
+use crate::{NetError, NetResult};
+
+/// The largest frame payload, in bytes, that `read_frame` accepts.
+pub const MAX_PAYLOAD_LEN: u64 = 16 * 1024 * 1024;
+
+/// The opcode of a WebSocket frame.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl TryFrom<u8> for Opcode {
+    type Error = NetError;
+
+    fn try_from(opcode: u8) -> NetResult<Self> {
+        match opcode {
+            0x0 => Ok(Self::Continuation),
+            0x1 => Ok(Self::Text),
+            0x2 => Ok(Self::Binary),
+            0x8 => Ok(Self::Close),
+            0x9 => Ok(Self::Ping),
+            0xa => Ok(Self::Pong),
+            _ => Err(NetError::BadFrame),
+        }
+    }
+}
+
+impl Opcode {
+    /// Returns the opcode as the four-bit value used on the wire.
+    #[must_use]
+    pub const fn as_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xa,
+        }
+    }
+
+    /// Returns true if this is the opcode of a control frame.
+    #[must_use]
+    pub const fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+/// A single WebSocket frame.
+///
+/// The payload is always held unmasked. Frames sent by a client must have a
+/// masking key, and frames sent by a server must not.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub mask: Option<[u8; 4]>,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Returns a new unmasked, final `Frame`.
+    #[must_use]
+    pub const fn new(opcode: Opcode, payload: Vec<u8>) -> Self {
+        Self { fin: true, opcode, mask: None, payload }
+    }
+
+    /// Returns a text `Frame`.
+    #[must_use]
+    pub fn text(text: &str) -> Self {
+        Self::new(Opcode::Text, text.as_bytes().to_vec())
+    }
+
+    /// Returns a binary `Frame`.
+    #[must_use]
+    pub fn binary(data: &[u8]) -> Self {
+        Self::new(Opcode::Binary, data.to_vec())
+    }
+
+    /// Returns a ping `Frame`.
+    #[must_use]
+    pub fn ping(data: &[u8]) -> Self {
+        Self::new(Opcode::Ping, data.to_vec())
+    }
+
+    /// Returns a pong `Frame`, which should echo the data of a ping.
+    #[must_use]
+    pub fn pong(data: &[u8]) -> Self {
+        Self::new(Opcode::Pong, data.to_vec())
+    }
+
+    /// Returns a close `Frame` with the given status code and reason.
+    #[must_use]
+    pub fn close(code: u16, reason: &str) -> Self {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        Self::new(Opcode::Close, payload)
+    }
+
+    /// Sets the masking key that is applied when the frame is written.
+    #[must_use]
+    pub const fn masked(mut self, key: [u8; 4]) -> Self {
+        self.mask = Some(key);
+        self
+    }
+}
+
+/// A complete WebSocket message.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<(u16, String)>),
+}
+
+impl TryFrom<Frame> for Message {
+    type Error = NetError;
+
+    fn try_from(frame: Frame) -> NetResult<Self> {
+        match frame.opcode {
+            Opcode::Text => String::from_utf8(frame.payload)
+                .map(Self::Text)
+                .map_err(|_| NetError::BadFrame),
+            Opcode::Binary => Ok(Self::Binary(frame.payload)),
+            Opcode::Ping => Ok(Self::Ping(frame.payload)),
+            Opcode::Pong => Ok(Self::Pong(frame.payload)),
+            Opcode::Close => match frame.payload.as_slice() {
+                [] => Ok(Self::Close(None)),
+                [hi, lo, reason @ ..] => {
+                    let code = u16::from_be_bytes([*hi, *lo]);
+                    let reason = String::from_utf8(reason.to_vec())
+                        .map_err(|_| NetError::BadFrame)?;
+                    Ok(Self::Close(Some((code, reason))))
+                },
+                [_] => Err(NetError::BadFrame),
+            },
+            Opcode::Continuation => Err(NetError::BadFrame),
+        }
+    }
+}
+
+/// Reads a single frame and unmasks its payload.
+///
+/// # Errors
+///
+/// Returns `NetError::BadFrame` if a reserved bit is set, the opcode is
+/// unknown, or a control frame is fragmented or has more than 125 bytes of
+/// payload. Returns `NetError::BodyTooLarge` if the payload is longer than
+/// `MAX_PAYLOAD_LEN`. An error is also returned if reading fails.
+pub fn read_frame<R: Read>(reader: &mut R) -> NetResult<Frame> {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+
+    if head[0] & 0x70 != 0 {
+        return Err(NetError::BadFrame);
+    }
+
+    let fin = head[0] & 0x80 != 0;
+    let opcode = Opcode::try_from(head[0] & 0x0f)?;
+
+    let len = match head[1] & 0x7f {
+        0x7e => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        },
+        0x7f => {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        },
+        len => u64::from(len),
+    };
+
+    if opcode.is_control() && (!fin || len > 125) {
+        return Err(NetError::BadFrame);
+    }
+
+    if len > MAX_PAYLOAD_LEN {
+        return Err(NetError::BodyTooLarge);
+    }
+
+    let mask = if head[1] & 0x80 == 0 {
+        None
+    } else {
+        let mut key = [0u8; 4];
+        reader.read_exact(&mut key)?;
+        Some(key)
+    };
+
+    let len = usize::try_from(len).map_err(|_| NetError::BadFrame)?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+
+    if let Some(key) = mask {
+        apply_mask(&mut payload, key);
+    }
+
+    Ok(Frame { fin, opcode, mask, payload })
+}
+
+/// Writes a single frame, masking its payload if the frame has a masking
+/// key, and flushes the writer.
+///
+/// # Errors
+///
+/// An error is returned if writing fails.
+pub fn write_frame<W: Write>(writer: &mut W, frame: &Frame) -> NetResult<()> {
+    let mut buf = Vec::with_capacity(frame.payload.len() + 14);
+    buf.push((u8::from(frame.fin) << 7) | frame.opcode.as_u8());
+
+    let mask_bit = if frame.mask.is_some() { 0x80 } else { 0 };
+    let len = frame.payload.len();
+
+    match (u8::try_from(len), u16::try_from(len)) {
+        (Ok(len @ 0..=125), _) => buf.push(mask_bit | len),
+        (_, Ok(len)) => {
+            buf.push(mask_bit | 0x7e);
+            buf.extend_from_slice(&len.to_be_bytes());
+        },
+        _ => {
+            buf.push(mask_bit | 0x7f);
+            buf.extend_from_slice(&(len as u64).to_be_bytes());
+        },
+    }
+
+    let start = buf.len();
+    buf.extend_from_slice(&frame.payload);
+
+    if let Some(key) = frame.mask {
+        buf.splice(start..start, key);
+        apply_mask(&mut buf[(start + 4)..], key);
+    }
+
+    writer.write_all(&buf)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads frames until a complete message has been received, reassembling
+/// the fragments of text and binary messages.
+///
+/// A control frame that arrives on its own is returned as a message. Ping
+/// and pong frames that arrive between the fragments of a message are
+/// discarded, while a close frame is returned in place of the unfinished
+/// message.
+///
+/// # Errors
+///
+/// Returns `NetError::BadFrame` if the frames do not form a valid message,
+/// such as a continuation frame without a preceding fragment or text that
+/// is not valid UTF-8, or `NetError::BodyTooLarge` if the reassembled
+/// payload is longer than `MAX_PAYLOAD_LEN`. An error is also returned if
+/// reading fails.
+pub fn read_message<R: Read>(reader: &mut R) -> NetResult<Message> {
+    let first = read_frame(reader)?;
+
+    if first.fin || first.opcode.is_control() {
+        return Message::try_from(first);
+    }
+
+    let Frame { opcode, mut payload, .. } = first;
+
+    loop {
+        let frame = read_frame(reader)?;
+
+        match frame.opcode {
+            Opcode::Continuation => {
+                if (payload.len() + frame.payload.len()) as u64
+                    > MAX_PAYLOAD_LEN
+                {
+                    return Err(NetError::BodyTooLarge);
+                }
+
+                payload.extend_from_slice(&frame.payload);
+
+                if frame.fin {
+                    return Message::try_from(Frame::new(opcode, payload));
+                }
+            },
+            Opcode::Close => return Message::try_from(frame),
+            Opcode::Ping | Opcode::Pong => {},
+            Opcode::Text | Opcode::Binary => return Err(NetError::BadFrame),
+        }
+    }
+}
+
+/// Applies a masking key to a payload, which both masks and unmasks it.
+fn apply_mask(payload: &mut [u8], key: [u8; 4]) {
+    for (byte, mask) in payload.iter_mut().zip(key.iter().cycle()) {
+        *byte ^= mask;
+    }
+}
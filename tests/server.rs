@@ -92,6 +92,51 @@ mod connect {
     run_test!(SERVER: CONNECT many_methods);
 }
 
+// Confirm that the server binary shuts down gracefully on SIGTERM.
+#[cfg(all(test, unix))]
+mod signal {
+    #[test]
+    fn shutdown_on_sigterm() {
+        use std::process::{Command, Stdio};
+        use std::thread;
+        use std::time::{Duration, Instant};
+        use rustnet::utils;
+
+        let addr = "127.0.0.1:7879";
+
+        let mut server = Command::new(env!("CARGO_BIN_EXE_server"))
+            .args(["--test", "--", addr])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+
+        assert!(utils::check_server_is_ready(addr));
+
+        let pid = server.id().to_string();
+        let killed = Command::new("kill").args(["-TERM", &pid]).status();
+        assert!(killed.unwrap().success());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+
+        let status = loop {
+            if let Some(status) = server.try_wait().unwrap() {
+                break status;
+            }
+
+            if Instant::now() > deadline {
+                let _ = server.kill();
+                panic!("Server did not exit after SIGTERM.");
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        assert!(status.success());
+        assert!(!utils::check_server_is_live(addr));
+    }
+}
+
 // Use alphabetical module naming to shut down the test server at the end.
 #[cfg(test)]
 mod z {